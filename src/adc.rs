@@ -4,11 +4,10 @@
 //! Most options relating to regular conversions are implemented. One-shot and sequences of conversions
 //! have been tested and work as expected.
 //!
-//! GPIO to channel mapping should be correct for all supported F4 devices. The mappings were taken from
-//! CubeMX. The mappings are feature gated per 4xx device but there are actually sub variants for some
-//! devices and some pins may be missing on some variants. The implementation has been split up and commented
-//! to show which pins are available on certain device variants but currently the library doesn't enforce this.
-//! To fully support the right pins would require 10+ more features for the various variants.
+//! The GPIO to channel mapping is shared by all N32G4 devices. Channels on ports E, F and G only exist
+//! with the N32G45x/N32G4FR features. Smaller packages don't bond out every pin, which isn't visible to
+//! the type system; set [config::AdcConfig::package] to have channels whose pin is missing rejected
+//! at configuration time.
 //! ## Todo
 //! * Injected conversions
 //! * Analog watchdog config
@@ -24,7 +23,7 @@
 //!   },
 //! };
 //!
-//! let mut adc = Adc::adc1(device.ADC1, true, AdcConfig::default());
+//! let mut adc = Adc::adc1(device.ADC1, true, AdcConfig::default()).unwrap();
 //! let pa3 = gpioa.pa3.into_analog();
 //! let sample = adc.convert(&pa3, SampleTime::Cycles_480);
 //! let millivolts = adc.sample_to_millivolts(sample);
@@ -53,7 +52,7 @@
 //!     //the interrupt, good luck... try setting pclk2 really low.
 //!     //(Better yet use DMA)
 //!     .clock(Clock::Pclk2_div_8);
-//! let mut adc = Adc::adc1(device.ADC1, true, config);
+//! let mut adc = Adc::adc1(device.ADC1, true, config).unwrap();
 //! let pa0 = gpioa.pa0.into_analog();
 //! let pa3 = gpioa.pa3.into_analog();
 //! adc.configure_channel(&pa0, Sequence::One, SampleTime::Cycles_112);
@@ -85,7 +84,7 @@
 //!  let config = AdcConfig::default()
 //!      //Set the trigger you want
 //!      .external_trigger(TriggerMode::RisingEdge, ExternalTrigger::Tim_1_cc_1);
-//!  let mut adc = Adc::adc1(device.ADC1, true, config);
+//!  let mut adc = Adc::adc1(device.ADC1, true, config).unwrap();
 //!  let pa0 = gpioa.pa0.into_analog();
 //!  adc.configure_channel(&pa0, Sequence::One, SampleTime::Cycles_112);
 //!  //Make sure it's enabled but don't start the conversion
@@ -120,19 +119,24 @@
 //!     tim.bdtr.modify(|_, w| w.moe().set_bit());
 //! }
 //! ```
+//!
+//! ## Timestamps
+//!
+//! [Timestamps] records the counter of a free running timer on each trigger of a regular
+//! sequence read by DMA, see the [timestamp] module.
 
 #![deny(missing_docs)]
 
-/*
-    Currently unused but this is the formula for using temperature calibration:
-    Temperature in °C = (110-30) * (adc_sample - VtempCal30::get().read()) / (VtempCal110::get().read()-VtempCal30::get().read()) + 30
-*/
 
 
 use crate::rcc::{Enable, Reset};
 use crate::{
     pac};
 use core::fmt;
+use embedded_hal::delay::DelayNs;
+
+pub mod timestamp;
+pub use timestamp::{Timestamps, TriggerRequest};
 
 /// Vref internal signal, used for calibration
 pub struct Vref;
@@ -326,43 +330,121 @@ pub mod config {
         }
     }
 
-    /// Possible external triggers the ADC can listen to
+    /// Possible external triggers for the regular channels
+    ///
+    /// ADC1/ADC2 and ADC3/ADC4 share the `EXTRSEL` field but route different sources to it,
+    /// so not every trigger is available on every ADC. Selecting a trigger that isn't routed to
+    /// the ADC panics instead of silently selecting another source.
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-    #[repr(u8)]
     pub enum ExternalTrigger {
-        /// TIM1 compare channel 1
-        Tim_1_cc_1 = 0b0000,
-        /// TIM1 compare channel 2
-        Tim_1_cc_2 = 0b0001,
-        /// TIM1 compare channel 3
-        Tim_1_cc_3 = 0b0010,
-        /// TIM2 compare channel 2
-        Tim_2_cc_2 = 0b0011,
-        /// TIM2 compare channel 3
-        Tim_2_cc_3 = 0b0100,
-        /// TIM2 compare channel 4
-        Tim_2_cc_4 = 0b0101,
-        /// TIM2 trigger out
-        Tim_2_trgo = 0b0110,
-        /// TIM3 compare channel 1
-        Tim_3_cc_1 = 0b0111,
-        /// TIM3 trigger out
-        Tim_3_trgo = 0b1000,
-        /// TIM4 compare channel 4
-        Tim_4_cc_4 = 0b1001,
-        /// TIM5 compare channel 1
-        Tim_5_cc_1 = 0b1010,
-        /// TIM5 compare channel 2
-        Tim_5_cc_2 = 0b1011,
-        /// TIM5 compare channel 3
-        Tim_5_cc_3 = 0b1100,
-        /// External interrupt line 11
-        Exti_11 = 0b1111,
+        /// TIM1 compare channel 1 (ADC1/ADC2)
+        Tim_1_cc_1,
+        /// TIM1 compare channel 2 (ADC1/ADC2)
+        Tim_1_cc_2,
+        /// TIM1 compare channel 3 (all ADCs)
+        Tim_1_cc_3,
+        /// TIM2 compare channel 2 (ADC1/ADC2)
+        Tim_2_cc_2,
+        /// TIM2 compare channel 3 (ADC3/ADC4)
+        Tim_2_cc_3,
+        /// TIM3 compare channel 1 (ADC3/ADC4)
+        Tim_3_cc_1,
+        /// TIM3 trigger out (ADC1/ADC2)
+        Tim_3_trgo,
+        /// TIM4 compare channel 4 (ADC1/ADC2)
+        Tim_4_cc_4,
+        /// TIM5 compare channel 1 (ADC3/ADC4)
+        Tim_5_cc_1,
+        /// TIM5 compare channel 3 (ADC3/ADC4)
+        Tim_5_cc_3,
+        /// TIM8 compare channel 1 (ADC3/ADC4)
+        Tim_8_cc_1,
+        /// TIM8 trigger out (ADC3/ADC4)
+        Tim_8_trgo,
+        /// External interrupt line 11, or TIM8 trigger out if remapped in AFIO (ADC1/ADC2)
+        Exti_11,
+        /// Software trigger through `SWSTRRCH` (all ADCs)
+        Software,
+    }
+    impl ExternalTrigger {
+        /// Returns the `EXTRSEL` encoding of the trigger for ADC1/ADC2 or ADC3/ADC4
+        pub fn bits(self, adc34: bool) -> Option<u8> {
+            use ExternalTrigger::*;
+            match (self, adc34) {
+                (Tim_1_cc_1, false) => Some(0b000),
+                (Tim_1_cc_2, false) => Some(0b001),
+                (Tim_1_cc_3, _) => Some(0b010),
+                (Tim_2_cc_2, false) => Some(0b011),
+                (Tim_3_trgo, false) => Some(0b100),
+                (Tim_4_cc_4, false) => Some(0b101),
+                (Exti_11, false) => Some(0b110),
+                (Tim_3_cc_1, true) => Some(0b000),
+                (Tim_2_cc_3, true) => Some(0b001),
+                (Tim_8_cc_1, true) => Some(0b011),
+                (Tim_8_trgo, true) => Some(0b100),
+                (Tim_5_cc_1, true) => Some(0b101),
+                (Tim_5_cc_3, true) => Some(0b110),
+                (Software, _) => Some(0b111),
+                _ => None,
+            }
+        }
+    }
+
+    /// Possible external triggers for the injected channels
+    ///
+    /// Like [ExternalTrigger], the sources routed to `EXTJSEL` differ between ADC1/ADC2
+    /// and ADC3/ADC4.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub enum InjectedTrigger {
+        /// TIM1 trigger out (all ADCs)
+        Tim_1_trgo,
+        /// TIM1 compare channel 4 (all ADCs)
+        Tim_1_cc_4,
+        /// TIM2 trigger out (ADC1/ADC2)
+        Tim_2_trgo,
+        /// TIM2 compare channel 1 (ADC1/ADC2)
+        Tim_2_cc_1,
+        /// TIM3 compare channel 4 (ADC1/ADC2)
+        Tim_3_cc_4,
+        /// TIM4 trigger out (ADC1/ADC2)
+        Tim_4_trgo,
+        /// TIM4 compare channel 3 (ADC3/ADC4)
+        Tim_4_cc_3,
+        /// TIM5 trigger out (ADC3/ADC4)
+        Tim_5_trgo,
+        /// TIM5 compare channel 4 (ADC3/ADC4)
+        Tim_5_cc_4,
+        /// TIM8 compare channel 2 (ADC3/ADC4)
+        Tim_8_cc_2,
+        /// TIM8 compare channel 4 (ADC3/ADC4)
+        Tim_8_cc_4,
+        /// External interrupt line 15, or TIM8 compare channel 4 if remapped in AFIO (ADC1/ADC2)
+        Exti_15,
+        /// Software trigger through `SWSTRJCH` (all ADCs)
+        Software,
     }
-    impl From<ExternalTrigger> for u8 {
-        fn from(et: ExternalTrigger) -> u8 {
-            et as _
+    impl InjectedTrigger {
+        /// Returns the `EXTJSEL` encoding of the trigger for ADC1/ADC2 or ADC3/ADC4
+        pub fn bits(self, adc34: bool) -> Option<u8> {
+            use InjectedTrigger::*;
+            match (self, adc34) {
+                (Tim_1_trgo, _) => Some(0b000),
+                (Tim_1_cc_4, _) => Some(0b001),
+                (Tim_2_trgo, false) => Some(0b010),
+                (Tim_2_cc_1, false) => Some(0b011),
+                (Tim_3_cc_4, false) => Some(0b100),
+                (Tim_4_trgo, false) => Some(0b101),
+                (Exti_15, false) => Some(0b110),
+                (Tim_4_cc_3, true) => Some(0b010),
+                (Tim_8_cc_2, true) => Some(0b011),
+                (Tim_8_cc_4, true) => Some(0b100),
+                (Tim_5_trgo, true) => Some(0b101),
+                (Tim_5_cc_4, true) => Some(0b110),
+                (Software, _) => Some(0b111),
+                _ => None,
+            }
         }
     }
 
@@ -382,6 +464,11 @@ pub mod config {
         }
     }
 
+    /// An external trigger isn't routed to the ADC it was configured for
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub struct InvalidTrigger;
+
     /// Data register alignment
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -458,6 +545,77 @@ pub mod config {
         Sequence,
     }
 
+    /// Device package, used to check that the pins of configured channels are bonded out
+    ///
+    /// The channel tables are shared by all N32G4 devices, so without a package the ADC
+    /// accepts channels whose pin doesn't exist on the part. With a package set through
+    /// [AdcConfig::package], configuring such a channel panics.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub enum Package {
+        /// 48 pins: PA0-PA15, PB0-PB15, PC13-PC15, PD0-PD1
+        Lqfp48,
+        /// 64 pins: PA0-PA15, PB0-PB15, PC0-PC15, PD0-PD2
+        Lqfp64,
+        /// 100 pins: ports A to E
+        Lqfp100,
+        /// 128 pins: ports A to G
+        Lqfp128,
+    }
+
+    /// Pin of every external channel, per ADC. Index 0 is unused.
+    const CHANNEL_PINS: [[Option<(char, u8)>; 16]; 4] = [
+        [
+            None, Some(('A', 0)), Some(('A', 1)), Some(('A', 6)), Some(('A', 3)), Some(('F', 4)),
+            Some(('C', 0)), Some(('C', 1)), Some(('C', 2)), Some(('C', 3)), Some(('F', 2)),
+            Some(('A', 2)), None, None, None, None,
+        ],
+        [
+            None, Some(('A', 4)), Some(('A', 5)), Some(('B', 1)), Some(('A', 7)), Some(('C', 4)),
+            Some(('C', 0)), Some(('C', 1)), Some(('C', 2)), Some(('C', 3)), Some(('F', 2)),
+            Some(('A', 2)), Some(('C', 5)), Some(('B', 2)), None, None,
+        ],
+        [
+            None, Some(('B', 11)), Some(('E', 9)), Some(('E', 13)), Some(('E', 12)), Some(('B', 13)),
+            Some(('E', 8)), Some(('D', 10)), Some(('D', 11)), Some(('D', 12)), Some(('D', 13)),
+            Some(('D', 14)), Some(('B', 0)), Some(('E', 7)), Some(('E', 10)), Some(('E', 11)),
+        ],
+        [
+            None, Some(('E', 14)), Some(('E', 15)), Some(('B', 12)), Some(('B', 14)), Some(('B', 15)),
+            Some(('E', 8)), Some(('D', 10)), Some(('D', 11)), Some(('D', 12)), Some(('D', 13)),
+            Some(('D', 14)), Some(('D', 8)), Some(('D', 9)), None, None,
+        ],
+    ];
+
+    impl Package {
+        /// Returns true if pin `n` of port `port` is bonded out in this package
+        pub const fn has_pin(self, port: char, n: u8) -> bool {
+            match (self, port) {
+                (_, 'A' | 'B') => true,
+                (Package::Lqfp48, 'C') => n >= 13,
+                (Package::Lqfp48, 'D') => n <= 1,
+                (_, 'C') => true,
+                (Package::Lqfp64, 'D') => n <= 2,
+                (_, 'D' | 'E') => true,
+                (Package::Lqfp128, 'F' | 'G') => true,
+                _ => false,
+            }
+        }
+
+        /// Returns true if `channel` of ADC `adc` (1 to 4) is usable in this package
+        ///
+        /// Internal channels (16 and up) are always usable.
+        pub const fn has_channel(self, adc: u8, channel: u8) -> bool {
+            if channel >= 16 {
+                return true;
+            }
+            match CHANNEL_PINS[adc as usize - 1][channel as usize] {
+                Some((port, n)) => self.has_pin(port, n),
+                None => false,
+            }
+        }
+    }
+
     /// Configuration for the adc.
     /// There are some additional parameters on the adc peripheral that can be
     /// added here when needed but this covers several basic usecases.
@@ -469,11 +627,13 @@ pub mod config {
         pub(crate) align: Align,
         pub(crate) scan: Scan,
         pub(crate) external_trigger: (TriggerMode, ExternalTrigger),
+        pub(crate) injected_trigger: (TriggerMode, InjectedTrigger),
         pub(crate) continuous: Continuous,
         pub(crate) dma: Dma,
         pub(crate) end_of_conversion_interrupt: Eoc,
         pub(crate) default_sample_time: SampleTime,
         pub(crate) vdda: Option<u32>,
+        pub(crate) package: Option<Package>,
     }
 
     impl AdcConfig {
@@ -506,6 +666,15 @@ pub mod config {
             self.external_trigger = (trigger_mode, trigger);
             self
         }
+        /// change the injected_trigger field
+        pub fn injected_trigger(
+            mut self,
+            trigger_mode: TriggerMode,
+            trigger: InjectedTrigger,
+        ) -> Self {
+            self.injected_trigger = (trigger_mode, trigger);
+            self
+        }
         /// change the continuous field
         pub fn continuous(mut self, continuous: Continuous) -> Self {
             self.continuous = continuous;
@@ -535,6 +704,12 @@ pub mod config {
             self.vdda = Some(vdda_mv);
             self
         }
+
+        /// Specify the device package, channels whose pin isn't bonded out are then rejected.
+        pub fn package(mut self, package: Package) -> Self {
+            self.package = Some(package);
+            self
+        }
     }
 
     impl Default for AdcConfig {
@@ -544,17 +719,25 @@ pub mod config {
                 resolution: Resolution::Twelve,
                 align: Align::Right,
                 scan: Scan::Disabled,
-                external_trigger: (TriggerMode::Disabled, ExternalTrigger::Tim_1_cc_1),
+                external_trigger: (TriggerMode::Disabled, ExternalTrigger::Software),
+                injected_trigger: (TriggerMode::Disabled, InjectedTrigger::Software),
                 continuous: Continuous::Single,
                 dma: Dma::Disabled,
                 end_of_conversion_interrupt: Eoc::Disabled,
                 default_sample_time: SampleTime::Cycles_239p5,
                 vdda: None,
+                package: None,
             }
         }
     }
 }
 
+/// Time the ADC needs after being switched on before the first conversion, in microseconds
+const ADC_STABILIZATION_US: u32 = 1;
+
+/// Start-up time of the temperature sensor and internal reference, in microseconds
+const TEMP_VREF_STARTUP_US: u32 = 10;
+
 /// Analog to Digital Converter
 #[derive(Clone, Copy)]
 pub struct Adc<ADC> {
@@ -576,15 +759,26 @@ impl<ADC> fmt::Debug for Adc<ADC> {
 }
 
 macro_rules! adc {
-    ($($adc_type:ident => ($constructor_fn_name:ident)),+ $(,)*) => {
+    ($($adc_type:ident => ($constructor_fn_name:ident, $adc34:expr, $index:expr)),+ $(,)*) => {
         $(
 
             impl Adc<pac::$adc_type> {
 
                 /// Enables the ADC clock, resets the peripheral (optionally), runs calibration and applies the supplied config
+                ///
+                /// Returns [InvalidTrigger](config::InvalidTrigger) along with the ADC, before enabling
+                /// it, if a trigger of `config` isn't routed to this ADC.
                 /// # Arguments
                 /// * `reset` - should a reset be performed. This is provided because on some devices multiple ADCs share the same common reset
-                pub fn $constructor_fn_name(adc: pac::$adc_type, reset: bool, config: config::AdcConfig) -> Adc<pac::$adc_type> {
+                pub fn $constructor_fn_name(
+                    adc: pac::$adc_type,
+                    reset: bool,
+                    config: config::AdcConfig,
+                ) -> Result<Adc<pac::$adc_type>, (config::InvalidTrigger, pac::$adc_type)> {
+                    if let Err(error) = Self::check_triggers(&config) {
+                        return Err((error, adc));
+                    }
+
                     unsafe {
                         // All ADCs share the same reset interface.
                         // NOTE(unsafe) this reference will only be used for atomic writes with no side effects.
@@ -607,23 +801,45 @@ macro_rules! adc {
 
                     //Probably unnecessary to disable the ADC in most cases but it shouldn't do any harm either
                     s.disable();
-                    s.apply_config(config);
+                    // Can't fail, the triggers were checked above
+                    s.apply_config(config).ok();
 
                     s.enable();
-                    s
+                    Ok(s)
+                }
+
+                /// Checks that both triggers of `config` are routed to this ADC
+                fn check_triggers(config: &config::AdcConfig) -> Result<(), config::InvalidTrigger> {
+                    if config.external_trigger.1.bits($adc34).is_none() || config.injected_trigger.1.bits($adc34).is_none() {
+                        return Err(config::InvalidTrigger);
+                    }
+                    Ok(())
+                }
+
+                /// Panics if `channel` isn't usable in the configured package
+                fn check_channel(&self, channel: u8) {
+                    if let Some(package) = self.config.package {
+                        assert!(package.has_channel($index, channel), "ADC channel not available in this package");
+                    }
                 }
 
                 /// Applies all fields in AdcConfig
-                pub fn apply_config(&mut self, config: config::AdcConfig) {
+                ///
+                /// Returns [InvalidTrigger](config::InvalidTrigger) without changing anything if a
+                /// trigger of `config` isn't routed to this ADC.
+                pub fn apply_config(&mut self, config: config::AdcConfig) -> Result<(), config::InvalidTrigger> {
+                    Self::check_triggers(&config)?;
                     self.set_resolution(config.resolution);
                     self.set_align(config.align);
                     self.set_scan(config.scan);
-                    self.set_regular_channel_external_trigger(config.external_trigger);
+                    self.set_regular_channel_external_trigger(config.external_trigger)?;
+                    self.set_injected_channel_external_trigger(config.injected_trigger)?;
 
                     self.set_continuous(config.continuous);
                     self.set_dma(config.dma);
                     self.set_end_of_regular_conversion_interrupt(config.end_of_conversion_interrupt);
                     self.set_default_sample_time(config.default_sample_time);
+                    Ok(())
                 }
 
                 /// Returns if the adc is enabled
@@ -636,6 +852,29 @@ macro_rules! adc {
                     self.adc_reg.ctrl2().modify(|_, w| w.on().set_bit());
                 }
                 
+                /// Switches the adc on and waits until it can convert
+                ///
+                /// Unlike [enable](Self::enable), this waits for the analog part to stabilize, and for the
+                /// temperature sensor and internal reference to start if they are enabled.
+                pub fn power_up(&mut self, delay: &mut impl DelayNs) {
+                    if self.is_enabled() {
+                        return;
+                    }
+                    self.enable();
+                    if self.adc_reg.ctrl2().read().tempen().bit_is_set() {
+                        delay.delay_us(TEMP_VREF_STARTUP_US);
+                    } else {
+                        delay.delay_us(ADC_STABILIZATION_US);
+                    }
+                }
+
+                /// Switches the adc off to save power, the configuration is kept
+                ///
+                /// Use [power_up](Self::power_up) to resume conversions.
+                pub fn power_down(&mut self) {
+                    self.disable();
+                }
+
                 /// Calibrates the adc
                 pub fn calibrate(&mut self) {
                     self.adc_reg.ctrl2().modify(|_,w| w.encal().set_bit());
@@ -647,6 +886,16 @@ macro_rules! adc {
                     self.adc_reg.ctrl2().modify(|_,w| w.tempen().set_bit());
                 }
 
+                /// Disable Vref/Temp channels in the adc
+                pub fn disable_vref_temp(&mut self) {
+                    self.adc_reg.ctrl2().modify(|_,w| w.tempen().clear_bit());
+                }
+
+                /// Reference voltage in millivolts, as configured or 3300 mV by default
+                pub fn reference_voltage(&self) -> u32 {
+                    self.config.vdda.unwrap_or(3300)
+                }
+
                 /// Enable Vref/Temp channels in the adc
                 pub fn set_synchronous_injection_mode(&mut self) {
                     unsafe { self.adc_reg.ctrl1().modify(|_,w| w.dusel().bits(0b0101)) };
@@ -696,21 +945,37 @@ macro_rules! adc {
                     self.adc_reg.ctrl1().modify(|_, w| w.scanmd().bit(scan.into()));
                 }
 
-                /// Sets which external trigger to use and if it is disabled, rising, falling or both
-                pub fn set_regular_channel_external_trigger(&mut self, (edge, extsel): (config::TriggerMode, config::ExternalTrigger)) {
+                /// Sets which external trigger to use for the regular channels and if it is enabled
+                ///
+                /// Returns [InvalidTrigger](config::InvalidTrigger) and keeps the current trigger if
+                /// `extsel` isn't routed to this ADC.
+                pub fn set_regular_channel_external_trigger(
+                    &mut self,
+                    (edge, extsel): (config::TriggerMode, config::ExternalTrigger),
+                ) -> Result<(), config::InvalidTrigger> {
+                    let bits = extsel.bits($adc34).ok_or(config::InvalidTrigger)?;
                     self.config.external_trigger = (edge, extsel);
                     self.adc_reg.ctrl2().modify(|_, w| unsafe { w
-                        .extrsel().bits(extsel as _)
+                        .extrsel().bits(bits)
                         .extrtrig().bit(edge.into()) }
                     );
+                    Ok(())
                 }
-                /// Sets which external trigger to use and if it is disabled, rising, falling or both
-                pub fn set_injected_channel_external_trigger(&mut self, (edge, extsel): (config::TriggerMode, config::ExternalTrigger)) {
-                    self.config.external_trigger = (edge, extsel);
+                /// Sets which external trigger to use for the injected channels and if it is enabled
+                ///
+                /// Returns [InvalidTrigger](config::InvalidTrigger) and keeps the current trigger if
+                /// `extsel` isn't routed to this ADC.
+                pub fn set_injected_channel_external_trigger(
+                    &mut self,
+                    (edge, extsel): (config::TriggerMode, config::InjectedTrigger),
+                ) -> Result<(), config::InvalidTrigger> {
+                    let bits = extsel.bits($adc34).ok_or(config::InvalidTrigger)?;
+                    self.config.injected_trigger = (edge, extsel);
                     self.adc_reg.ctrl2().modify(|_, w| unsafe { w
-                        .extjsel().bits(extsel as _)
+                        .extjsel().bits(bits)
                         .extjtrig().bit(edge.into()) }
                     );
+                    Ok(())
                 }
 
                 /// Enables and disables continuous mode
@@ -819,6 +1084,7 @@ macro_rules! adc {
                     });
 
                     let channel = CHANNEL::channel();
+                    self.check_channel(channel);
 
                     //Set the channel in the right sequence field
                     match sequence {
@@ -894,6 +1160,7 @@ macro_rules! adc {
                     });
 
                     let channel = CHANNEL::channel();
+                    self.check_channel(channel);
                     let target_jseq : config::InjectedSequence = (3 - jlen + sequence as u8).into();
                     //Set the channel in the right sequence field
                     match target_jseq {
@@ -1038,8 +1305,8 @@ macro_rules! adc {
 
                     let result = self.current_sample();
 
-                    //Reset the config
-                    self.apply_config(self.config);
+                    //Reset the config, which only holds triggers routed to this ADC
+                    self.apply_config(self.config).ok();
 
                     result
                 }
@@ -1064,6 +1331,140 @@ macro_rules! adc {
                 }
             }
 
+            impl Adc<pac::$adc_type> {
+                /// Moves the ADC into DMA mode using `channel` to transfer the regular conversions
+                pub fn with_dma<RXCH>(mut self, mut channel: RXCH) -> AdcDma<pac::$adc_type, RXCH>
+                where
+                    RXCH: crate::dma::CompatibleChannel<pac::$adc_type, crate::dma::R>
+                {
+                    self.set_dma(config::Dma::Single);
+                    channel.configure_channel();
+                    crate::dma::RxDma {
+                        payload: self,
+                        channel,
+                    }
+                }
+            }
+
+            impl<RXCH: crate::dma::DMAChannel> AdcDma<pac::$adc_type, RXCH> {
+                /// Starts converting the regular sequence `M` times into `buffer`
+                ///
+                /// Each element of `buffer` holds one pass over the regular sequence, so `N` must
+                /// match the configured sequence length. Use [deinterleave] to split the result
+                /// into per-channel arrays.
+                ///
+                /// Returns [LengthMismatch](crate::dma::Error::LengthMismatch) if `N` differs
+                /// from the regular sequence length and [BufferTooLong](crate::dma::Error::BufferTooLong)
+                /// if the buffer holds more than [MAX_TRANSFER_LENGTH](crate::dma::MAX_TRANSFER_LENGTH)
+                /// samples, along with the ADC and the buffer.
+                #[allow(clippy::type_complexity)]
+                pub fn read_dma<const N: usize, const M: usize>(
+                    mut self,
+                    buffer: &'static mut [[u16; N]; M],
+                ) -> Result<
+                    crate::dma::Transfer<crate::dma::W, &'static mut [[u16; N]; M], Self>,
+                    (crate::dma::Error, Self, &'static mut [[u16; N]; M]),
+                > {
+                    if self.payload.sequence_length() as usize != N {
+                        return Err((crate::dma::Error::LengthMismatch, self, buffer));
+                    }
+                    if let Err(error) = self.channel.try_set_transfer_length(N * M) {
+                        return Err((error, self, buffer));
+                    }
+
+                    let address = self.payload.data_register_address();
+                    self.channel.set_peripheral_address(address, false);
+                    self.channel.set_memory_address(buffer.as_mut_ptr() as u32, true);
+
+                    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Release);
+                    let size = <u16 as crate::dma::DmaWord>::SIZE;
+                    self.channel.set_word_size(size, size);
+                    let priority = self.channel.priority_or(crate::dma::Priority::Medium) as u8;
+                    self.channel.st().chcfg().modify(|_, w| { w
+                        .mem2mem() .clear_bit()
+                        .priolvl() .bits(priority)
+                        .circ()    .clear_bit()
+                        .dir()     .clear_bit()
+                    });
+                    self.start();
+
+                    Ok(crate::dma::Transfer::w(buffer, self))
+                }
+
+                /// Starts converting the regular sequence into two buffers filled in turn
+                ///
+                /// Both halves of `buffers` are filled by one circular DMA transfer, so the DMA
+                /// moves on to the other buffer by itself and no conversion is lost while the
+                /// application processes a completed buffer with [DoubleBuffer::swap]. The ADC has
+                /// to run in continuous mode or be triggered externally, e.g. by a timer.
+                ///
+                /// Fails like [read_dma](Self::read_dma) if `N` differs from the regular sequence
+                /// length or both buffers together are too long for one transfer.
+                #[allow(clippy::type_complexity)]
+                pub fn start_double_buffered<const N: usize, const M: usize>(
+                    mut self,
+                    buffers: &'static mut [[[u16; N]; M]; 2],
+                ) -> Result<
+                    DoubleBuffer<pac::$adc_type, RXCH, N, M>,
+                    (crate::dma::Error, Self, &'static mut [[[u16; N]; M]; 2]),
+                > {
+                    if self.payload.sequence_length() as usize != N {
+                        return Err((crate::dma::Error::LengthMismatch, self, buffers));
+                    }
+                    if let Err(error) = self.channel.try_set_transfer_length(2 * N * M) {
+                        return Err((error, self, buffers));
+                    }
+
+                    let address = self.payload.data_register_address();
+                    self.channel.set_peripheral_address(address, false);
+                    self.channel.set_memory_address(buffers.as_mut_ptr() as u32, true);
+                    self.channel.clear_flags(crate::dma::Flag::HalfTransfer | crate::dma::Flag::TransferComplete);
+
+                    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Release);
+                    let size = <u16 as crate::dma::DmaWord>::SIZE;
+                    self.channel.set_word_size(size, size);
+                    let priority = self.channel.priority_or(crate::dma::Priority::Medium) as u8;
+                    self.channel.st().chcfg().modify(|_, w| { w
+                        .mem2mem() .clear_bit()
+                        .priolvl() .bits(priority)
+                        .circ()    .set_bit()
+                        .dir()     .clear_bit()
+                    });
+                    self.start();
+
+                    Ok(DoubleBuffer {
+                        buffers,
+                        adc: self,
+                        next: crate::dma::Half::First,
+                    })
+                }
+
+                /// Stops the DMA and returns the ADC and the channel
+                pub fn release(mut self) -> (Adc<pac::$adc_type>, RXCH) {
+                    self.stop();
+                    let crate::dma::RxDma { mut payload, channel } = self;
+                    payload.set_dma(config::Dma::Disabled);
+                    (payload, channel)
+                }
+            }
+
+            impl<RXCH: crate::dma::DMAChannel> crate::dma::TransferPayload for AdcDma<pac::$adc_type, RXCH> {
+                fn start(&mut self) {
+                    self.channel.start();
+                    // Externally triggered conversions start on their trigger, `SWSTRRCH` would
+                    // wait for a conversion that doesn't come
+                    match self.payload.config.external_trigger {
+                        (config::TriggerMode::RisingEdge, trigger) if trigger != config::ExternalTrigger::Software => {
+                            self.payload.enable()
+                        }
+                        _ => self.payload.start_conversion(),
+                    }
+                }
+                fn stop(&mut self) {
+                    self.channel.stop();
+                }
+            }
+
             impl<PIN> embedded_hal_02::adc::OneShot<pac::$adc_type, u16, PIN> for Adc<pac::$adc_type>
             where
                 PIN: embedded_hal_02::adc::Channel<pac::$adc_type, ID=u8>,
@@ -1080,18 +1481,166 @@ macro_rules! adc {
 
 
 
-adc!(Adc1 => (adc1));
+/// ADC with a DMA channel transferring the regular conversions
+pub type AdcDma<ADC, RXCH> = crate::dma::RxDma<Adc<ADC>, RXCH>;
+
+/// Regular sequence conversions into two alternating buffers
+///
+/// Created by `AdcDma::start_double_buffered`. Each buffer holds `M` passes over the
+/// regular sequence of `N` channels.
+pub struct DoubleBuffer<ADC, RXCH, const N: usize, const M: usize> {
+    buffers: &'static mut [[[u16; N]; M]; 2],
+    adc: AdcDma<ADC, RXCH>,
+    /// Buffer the DMA completes next
+    next: crate::dma::Half,
+}
+
+impl<ADC, RXCH: crate::dma::DMAChannel, const N: usize, const M: usize> DoubleBuffer<ADC, RXCH, N, M> {
+    /// Hands the buffer completed since the previous call to `f`
+    ///
+    /// Returns `WouldBlock` while the DMA still fills the next buffer, and
+    /// [Overrun](crate::dma::Error::Overrun) if a buffer was overwritten before or while it was
+    /// processed, i.e. when `f` didn't keep up with the conversions. The buffers keep being
+    /// filled in either case.
+    pub fn swap<R>(&mut self, f: impl FnOnce(&[[u16; N]; M], crate::dma::Half) -> R) -> nb::Result<R, crate::dma::Error> {
+        use crate::dma::{Flag, Half};
+
+        let (done, other) = match self.next {
+            Half::First => (Flag::HalfTransfer, Flag::TransferComplete),
+            Half::Second => (Flag::TransferComplete, Flag::HalfTransfer),
+        };
+        let flags = self.adc.channel.flags();
+        if !flags.contains(done) {
+            return Err(nb::Error::WouldBlock);
+        }
+        let half = self.next;
+        self.next = match half {
+            Half::First => Half::Second,
+            Half::Second => Half::First,
+        };
+        self.adc.channel.clear_flags(done);
+        if flags.contains(other) {
+            // Both buffers completed, the older one is being overwritten
+            self.adc.channel.clear_flags(other);
+            return Err(nb::Error::Other(crate::dma::Error::Overrun));
+        }
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Acquire);
+
+        let buffer = match half {
+            Half::First => &self.buffers[0],
+            Half::Second => &self.buffers[1],
+        };
+        let result = f(buffer, half);
+
+        // The DMA came back to this buffer while it was processed
+        if self.adc.channel.flags().contains(done) {
+            return Err(nb::Error::Other(crate::dma::Error::Overrun));
+        }
+        Ok(result)
+    }
+
+    /// Stops the DMA and returns the buffers and the ADC
+    pub fn stop(mut self) -> (&'static mut [[[u16; N]; M]; 2], AdcDma<ADC, RXCH>) {
+        self.adc.channel.stop();
+        (self.buffers, self.adc)
+    }
+}
+
+/// Splits samples of a regular sequence read with `read_dma` into per-channel arrays
+///
+/// `out[channel][pass]` holds the sample of the `channel`-th rank from pass `pass`
+pub fn deinterleave<const N: usize, const M: usize>(samples: &[[u16; N]; M], out: &mut [[u16; M]; N]) {
+    for (pass, sequence) in samples.iter().enumerate() {
+        for (rank, sample) in sequence.iter().enumerate() {
+            out[rank][pass] = *sample;
+        }
+    }
+}
+
+/// Typical temperature sensor output at 25 °C, in millivolts
+pub const TEMP_V25_MV: f32 = 1320.0;
+/// Typical temperature sensor slope, in millivolts per °C (the output falls with temperature)
+pub const TEMP_AVG_SLOPE_MV: f32 = 4.1;
+/// Typical internal reference voltage, in millivolts
+pub const VREFINT_MV: u32 = 1200;
+/// VBAT is sampled through an internal divider bridge
+pub const VBAT_DIVIDER: u32 = 2;
+
+/// Temperature sensor, internal reference and VBAT readings on ADC1
+///
+/// Enables the sensors on creation and always samples them with the longest sample time,
+/// which satisfies the minimum sampling time of the temperature sensor.
+/// The sensors need about 10 µs to settle after being enabled.
+pub struct InternalSensors<'a> {
+    adc: &'a mut Adc<pac::Adc1>,
+}
+
+impl<'a> InternalSensors<'a> {
+    /// Enables the temperature sensor and internal reference
+    pub fn new(adc: &'a mut Adc<pac::Adc1>) -> Self {
+        adc.enable_vref_temp();
+        Self { adc }
+    }
+
+    fn sample(&mut self, raw: u16) -> u32 {
+        raw as u32 * self.adc.reference_voltage() / self.adc.max_sample
+    }
+
+    /// Raw temperature sensor conversion
+    pub fn temperature_raw(&mut self) -> u16 {
+        self.adc.convert(&Temperature, config::SampleTime::Cycles_239p5)
+    }
+
+    /// Temperature in °C, using the typical sensor characteristics
+    pub fn temperature(&mut self) -> f32 {
+        let raw = self.temperature_raw();
+        let mv = self.sample(raw) as f32;
+        (TEMP_V25_MV - mv) / TEMP_AVG_SLOPE_MV + 25.0
+    }
+
+    /// Raw internal reference conversion
+    pub fn vref_raw(&mut self) -> u16 {
+        self.adc.convert(&Vref, config::SampleTime::Cycles_239p5)
+    }
+
+    /// Supply voltage of the ADC in millivolts, calculated from the internal reference
+    pub fn vdda(&mut self) -> u32 {
+        let raw = self.vref_raw().max(1) as u32;
+        VREFINT_MV * self.adc.max_sample / raw
+    }
+
+    /// Raw VBAT conversion
+    pub fn vbat_raw(&mut self) -> u16 {
+        self.adc.convert(&Vbat, config::SampleTime::Cycles_239p5)
+    }
+
+    /// Backup battery voltage in millivolts
+    pub fn vbat(&mut self) -> u32 {
+        let raw = self.vbat_raw();
+        self.sample(raw) * VBAT_DIVIDER
+    }
+
+    /// Disables the temperature sensor and internal reference
+    pub fn release(self) {
+        self.adc.disable_vref_temp();
+    }
+}
+
+adc!(Adc1 => (adc1, false, 1));
 
-adc!(Adc2 => (adc2));
+adc!(Adc2 => (adc2, false, 2));
 
-adc!(Adc3 => (adc3));
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+adc!(Adc3 => (adc3, true, 3));
 
-adc!(Adc4 => (adc4));
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+adc!(Adc4 => (adc4, true, 4));
 
 
 macro_rules! adc_map {
-    ($adc_type:ident => { $(($channel_type:ty , $channel_id:tt)),+ $(,)* }) => {
+    ($adc_type:ident => { $($(#[$attr:meta])* ($channel_type:ty , $channel_id:tt)),+ $(,)* }) => {
         $(
+            $(#[$attr])*
             impl embedded_hal_02::adc::Channel<crate::pac::$adc_type> for $channel_type {
                 type ID = u8;
 
@@ -1111,11 +1660,13 @@ mod mappings {
             (PA1<crate::gpio::Analog>, 2),
             (PA6<crate::gpio::Analog>, 3),
             (PA3<crate::gpio::Analog>, 4),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PF4<crate::gpio::Analog>, 5),
             (PC0<crate::gpio::Analog>, 6),
             (PC1<crate::gpio::Analog>, 7),
             (PC2<crate::gpio::Analog>, 8),
             (PC3<crate::gpio::Analog>, 9),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PF2<crate::gpio::Analog>, 10),
             (PA2<crate::gpio::Analog>, 11),
             (Temperature, 16),
@@ -1134,6 +1685,7 @@ mod mappings {
             (PC1<crate::gpio::Analog>, 7),
             (PC2<crate::gpio::Analog>, 8),
             (PC3<crate::gpio::Analog>, 9),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PF2<crate::gpio::Analog>, 10),
             (PA2<crate::gpio::Analog>, 11),
             (PC5<crate::gpio::Analog>, 12),
@@ -1142,13 +1694,18 @@ mod mappings {
             (Vref, 18),
         }
     }
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
     adc_map! {
         Adc3 => {
             (PB11<crate::gpio::Analog>, 1),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE9<crate::gpio::Analog>, 2),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE13<crate::gpio::Analog>, 3),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE12<crate::gpio::Analog>, 4),
             (PB13<crate::gpio::Analog>, 5),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE8<crate::gpio::Analog>, 6),
             (PD10<crate::gpio::Analog>, 7),
             (PD11<crate::gpio::Analog>, 8),
@@ -1156,20 +1713,27 @@ mod mappings {
             (PD13<crate::gpio::Analog>, 10),
             (PD14<crate::gpio::Analog>, 11),
             (PB0<crate::gpio::Analog>, 12),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE7<crate::gpio::Analog>, 13),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE10<crate::gpio::Analog>, 14),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE11<crate::gpio::Analog>, 15),
 
             (Vref, 18),
         }
     }
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
     adc_map! {
         Adc4 => {
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE14<crate::gpio::Analog>, 1),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE15<crate::gpio::Analog>, 2),
             (PB12<crate::gpio::Analog>, 3),
             (PB14<crate::gpio::Analog>, 4),
             (PB15<crate::gpio::Analog>, 5),
+            #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
             (PE8<crate::gpio::Analog>, 6),
             (PD10<crate::gpio::Analog>, 7),
             (PD11<crate::gpio::Analog>, 8),
@@ -0,0 +1,231 @@
+//! Timestamps of externally triggered regular sequences
+//!
+//! The timer event triggering the regular sequence can request a DMA transfer too. A second
+//! DMA channel serving that request copies the counter of a free running clock timer into a
+//! buffer, so every pass over the sequence gets the time of its trigger without any CPU
+//! involvement, e.g. to align samples with other sensors:
+//!
+//! ```rust
+//! n32g4xx_hal::dma::buffers! {
+//!     static SAMPLES: [[u16; 2]; 16] = [[0; 2]; 16];
+//!     static STAMPS: [u16; 16] = [0; 16];
+//! }
+//!
+//! // ADC1 is triggered by compare channel 1 of TIM1, whose DMA request copies the counter of TIM2
+//! let stamps = Timestamps::<CaptureCompareRequest<pac::Tim1, 1>, _, _, 16>::new(
+//!     dma1.2,
+//!     Timer::new(dp.Tim2, &clocks),
+//!     STAMPS.take().unwrap(),
+//!     1.MHz(),
+//! )?;
+//! let transfer = adc.with_dma(dma1.1).read_dma(SAMPLES.take().unwrap())
+//!     .map_err(|(error, ..)| error)?;
+//! // start the PWM of TIM1 here
+//! let (samples, adc) = transfer.wait();
+//! let (stamps, timer, channel) = stamps.wait()?;
+//! // stamps[pass] is the time of samples[pass], in ticks of 1 MHz
+//! ```
+//!
+//! Sequences triggered by a timer's trigger out use its [UpdateRequest], with the trigger out
+//! set to the update event. The request has to be wired to the DMA controller of the channel,
+//! see [chmap](crate::dma::chmap). The timestamps are 16-bit counter values, so only
+//! differences of less than 65536 ticks are meaningful.
+
+use core::marker::PhantomData;
+use core::sync::atomic::{self, Ordering};
+
+use crate::dma::{CompatibleChannel, DMAChannel, Error, Flag, WordSize, R};
+use crate::gpio::capture::FreeRunningTimer;
+use crate::time::Hertz;
+use crate::timer::{CaptureCompareRequest, Timer, UpdateRequest};
+
+/// Timer event that triggers a regular sequence and can request a DMA transfer
+pub trait TriggerRequest: crate::Sealed {
+    #[doc(hidden)]
+    fn enable();
+    #[doc(hidden)]
+    fn disable();
+}
+
+macro_rules! trigger_request {
+    ($($(#[$attr:meta])* $TIM:ty,)+) => {
+        $(
+            $(#[$attr])*
+            impl crate::Sealed for UpdateRequest<$TIM> {}
+            $(#[$attr])*
+            impl TriggerRequest for UpdateRequest<$TIM> {
+                fn enable() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.dinten().modify(|_, w| w.udeen().set_bit());
+                }
+
+                fn disable() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.dinten().modify(|_, w| w.udeen().clear_bit());
+                }
+            }
+
+            $(#[$attr])*
+            impl<const C: u8> crate::Sealed for CaptureCompareRequest<$TIM, C> {}
+            $(#[$attr])*
+            impl<const C: u8> TriggerRequest for CaptureCompareRequest<$TIM, C> {
+                fn enable() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // CCxDE
+                    tim.dinten().modify(|r, w| unsafe { w.bits(r.bits() | (1 << (C as u32 + 8))) });
+                }
+
+                fn disable() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.dinten().modify(|r, w| unsafe { w.bits(r.bits() & !(1 << (C as u32 + 8))) });
+                }
+            }
+        )+
+    };
+}
+
+trigger_request! {
+    crate::pac::Tim1,
+    crate::pac::Tim2,
+    crate::pac::Tim3,
+    crate::pac::Tim4,
+    crate::pac::Tim5,
+    crate::pac::Tim8,
+}
+
+/// Counter values of the clock timer `CLK` copied into a buffer of `M` timestamps on each
+/// trigger request `REQ`
+pub struct Timestamps<REQ, CH, CLK, const M: usize> {
+    channel: CH,
+    timer: Timer<CLK>,
+    buffer: &'static mut [u16; M],
+    tick: Hertz,
+    circular: bool,
+    _request: PhantomData<REQ>,
+}
+
+impl<REQ, CH, CLK, const M: usize> Timestamps<REQ, CH, CLK, M>
+where
+    REQ: TriggerRequest,
+    CH: CompatibleChannel<REQ, R> + DMAChannel,
+    CLK: FreeRunningTimer,
+{
+    /// Starts `timer` counting at `tick` and records its counter on the next `M` triggers
+    ///
+    /// Start it before the trigger timer, so the timestamp of every pass of a
+    /// [read_dma](super::AdcDma) transfer of `M` passes is recorded.
+    ///
+    /// Returns [InvalidRate](Error::InvalidRate) if `tick` is zero, above the clock of `timer`,
+    /// or below its clock divided by 65536.
+    pub fn new(channel: CH, timer: Timer<CLK>, buffer: &'static mut [u16; M], tick: Hertz) -> Result<Self, Error> {
+        Self::start(channel, timer, buffer, tick, false)
+    }
+
+    /// Like [new](Self::new), but records the triggers into `buffer` over and over
+    ///
+    /// With [start_double_buffered](super::AdcDma) the buffer holds the timestamps of both
+    /// halves, so `M` has to be twice the passes of one half. [Flag::HalfTransfer] and
+    /// [Flag::TransferComplete] of the channel follow the halves of the samples.
+    pub fn new_circular(channel: CH, timer: Timer<CLK>, buffer: &'static mut [u16; M], tick: Hertz) -> Result<Self, Error> {
+        Self::start(channel, timer, buffer, tick, true)
+    }
+
+    fn start(
+        mut channel: CH,
+        timer: Timer<CLK>,
+        buffer: &'static mut [u16; M],
+        tick: Hertz,
+        circular: bool,
+    ) -> Result<Self, Error> {
+        let div = match timer.clk.raw().checked_div(tick.raw()) {
+            Some(div) if (1..=1 << 16).contains(&div) => div,
+            _ => return Err(Error::InvalidRate),
+        };
+        let tick = Hertz::from_raw(timer.clk.raw() / div);
+
+        channel.stop();
+        channel.configure_channel();
+        channel.set_peripheral_address(CLK::counter_address(), false);
+        channel.set_memory_address(buffer.as_ptr() as u32, true);
+        channel.try_set_transfer_length(M)?;
+        channel.set_word_size(WordSize::Bits16, WordSize::Bits16);
+        let priority = channel.priority_or(crate::dma::Priority::High) as u8;
+        channel.st().chcfg().modify(|_, w| {
+            let w = w
+                // memory to memory mode disabled
+                .mem2mem()
+                .disabled()
+                // channel priority level, high unless set with DMAChannel::set_priority
+                .priolvl()
+                .bits(priority);
+            let w = if circular { w.circ().enabled() } else { w.circ().disabled() };
+            // read from the peripheral
+            w.dir().from_peripheral()
+        });
+        channel.clear_flags(Flag::TransferComplete | Flag::HalfTransfer);
+
+        CLK::start((div - 1) as u16);
+        atomic::compiler_fence(Ordering::Release);
+        channel.start();
+        REQ::enable();
+
+        Ok(Self {
+            channel,
+            timer,
+            buffer,
+            tick,
+            circular,
+            _request: PhantomData,
+        })
+    }
+
+    /// Returns `true` once `M` triggers were recorded, after the first pass if circular
+    pub fn is_done(&self) -> bool {
+        self.channel.flags().contains(Flag::TransferComplete)
+    }
+
+    /// Frequency the timestamps count at
+    pub fn tick(&self) -> Hertz {
+        self.tick
+    }
+
+    /// Timestamps recorded so far
+    ///
+    /// When circular, the whole buffer once the first pass completed, as long as
+    /// [Flag::TransferComplete] isn't cleared. The DMA keeps overwriting it while it runs.
+    pub fn buffer(&self) -> &[u16] {
+        let recorded = if self.circular && self.is_done() {
+            M
+        } else {
+            M - self.channel.get_txnum() as usize
+        };
+        atomic::compiler_fence(Ordering::Acquire);
+        &self.buffer[..recorded]
+    }
+
+    /// Waits until `M` triggers were recorded, after the first pass if circular, and releases
+    /// the buffer, the clock timer and the channel
+    #[allow(clippy::type_complexity)]
+    pub fn wait(self) -> Result<(&'static mut [u16; M], Timer<CLK>, CH), Error> {
+        let result = loop {
+            let flags = self.channel.flags();
+            if flags.contains(Flag::TransferError) {
+                break Err(Error::TransferError);
+            }
+            if flags.contains(Flag::TransferComplete) {
+                break Ok(());
+            }
+        };
+        let parts = self.stop();
+        result.map(|_| parts)
+    }
+
+    /// Stops recording and releases the buffer, the clock timer and the channel
+    pub fn stop(mut self) -> (&'static mut [u16; M], Timer<CLK>, CH) {
+        REQ::disable();
+        self.channel.stop();
+        CLK::stop();
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.buffer, self.timer, self.channel)
+    }
+}
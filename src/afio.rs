@@ -1,31 +1,33 @@
 //! # Alternate Function I/Os
 
-use crate::pac::{afio, Afio, Rcc};
+use crate::gpio::{marker, ExtiLine, PinExt};
+use crate::pac::{afio, Afio, Exti, Rcc};
 
 use crate::rcc::{Enable, Reset};
 
 
 pub trait AfioExt {
-    fn constrain(self) -> Afio;
+    fn constrain(self) -> Parts;
 }
 #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
 impl AfioExt for Afio {
-    fn constrain(self) -> Afio {
+    fn constrain(self) -> Parts {
         let rcc = unsafe { &(*Rcc::ptr()) };
         Afio::enable(rcc);
         Afio::reset(rcc);
-        self
-        // Parts {
-        //     ectrl: ECTRL { _0: () },
-        //     rmp_cfg: RMP_CFG { _0: () },
-        //     exticfg1: EXTI_CFG1 { _0: () },
-        //     exticfg2: EXTI_CFG2 { _0: () },
-        //     exticfg3: EXTI_CFG3 { _0: () },
-        //     exticfg4: EXTI_CFG4 { _0: () },
-        //     rmp_cfg3: RMP_CFG3 { _0: () },
-        //     rmp_cfg4: RMP_CFG4 { _0: () },
-        //     rmp_cfg5: RMP_CFG5 { _0: () },
-        // }
+
+        Parts {
+            ectrl: ECTRL { _0: () },
+            rmp_cfg: RMP_CFG { _0: () },
+            exticfg1: EXTI_CFG1 { _0: () },
+            exticfg2: EXTI_CFG2 { _0: () },
+            exticfg3: EXTI_CFG3 { _0: () },
+            exticfg4: EXTI_CFG4 { _0: () },
+            exticr: EXTICR { claimed: 0 },
+            rmp_cfg3: RMP_CFG3 { _0: () },
+            rmp_cfg4: RMP_CFG4 { _0: () },
+            rmp_cfg5: RMP_CFG5 { _0: () },
+        }
     }
 }
 
@@ -42,6 +44,7 @@ impl AfioExt for Afio {
             exticfg2: EXTI_CFG2 { _0: () },
             exticfg3: EXTI_CFG3 { _0: () },
             exticfg4: EXTI_CFG4 { _0: () },
+            exticr: EXTICR { claimed: 0 },
         }
     }
 }
@@ -60,6 +63,7 @@ impl AfioExt for Afio {
             exticfg2: EXTI_CFG2 { _0: () },
             exticfg3: EXTI_CFG3 { _0: () },
             exticfg4: EXTI_CFG4 { _0: () },
+            exticr: EXTICR { claimed: 0 },
             tol5vcfg: TOL5V_CFG { _0: () },
             eftcfg1: EFT_CFG1 { _0: () },
             eftcfg2: EFT_CFG2 { _0: () },
@@ -79,7 +83,10 @@ impl AfioExt for Afio {
 /// let p = pac::Peripherals::take().unwrap();
 /// let mut rcc = p.Rcc.constrain();
 /// let mut afio = p.Afio.constrain();
-/// 
+///
+/// // The remap APIs take the whole handle
+/// let spi = p.Spi1.spi((sck, miso, mosi), mode, 1.MHz(), &clocks, &mut afio);
+/// ```
 #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
 pub struct Parts {
     pub ectrl: ECTRL,
@@ -88,6 +95,7 @@ pub struct Parts {
     pub exticfg2: EXTI_CFG2,
     pub exticfg3: EXTI_CFG3,
     pub exticfg4: EXTI_CFG4,
+    pub exticr: EXTICR,
     pub rmp_cfg3 : RMP_CFG3,
     pub rmp_cfg4 : RMP_CFG4,
     pub rmp_cfg5 : RMP_CFG5,
@@ -111,6 +119,7 @@ pub struct Parts {
     pub exticfg2: EXTI_CFG2,
     pub exticfg3: EXTI_CFG3,
     pub exticfg4: EXTI_CFG4,
+    pub exticr: EXTICR,
     pub tol5vcfg: TOL5V_CFG,
     pub eftcfg1: EFT_CFG1,
     pub eftcfg2: EFT_CFG2,
@@ -126,6 +135,7 @@ pub struct Parts {
     pub exticfg2: EXTI_CFG2,
     pub exticfg3: EXTI_CFG3,
     pub exticfg4: EXTI_CFG4,
+    pub exticr: EXTICR,
 }
 
 pub enum DebugState {
@@ -135,17 +145,9 @@ pub enum DebugState {
     DebugDisabled
 }
 
-/// AF remap and debug I/O configuration register (MAPR)
+/// EXTI line 0..3 source selection register (EXTI_CFG1)
 ///
 /// Aquired through the [Parts](struct.Parts.html) struct.
-///
-/// ```rust
-/// let dp = pac::Peripherals::take().unwrap();
-/// let mut rcc = dp.Rcc.constrain();
-/// let mut afio = dp.Afio.constrain();
-/// function_using_mapr(&mut afio.mapr);
-/// ```
-
 pub struct EXTI_CFG1 {
     _0: (),
 }
@@ -181,11 +183,98 @@ pub struct EXTI_CFG4 {
 }
 
 impl EXTI_CFG4 {
-    pub fn exti_cfg3(&mut self) -> &afio::ExtiCfg4 {
+    pub fn exti_cfg4(&mut self) -> &afio::ExtiCfg4 {
         unsafe { &(*Afio::ptr()).exti_cfg4() }
     }
 }
 
+/// Owner of the 16 EXTI lines, selecting the port of each in the EXTI_CFGx registers
+///
+/// Aquired through the [Parts](struct.Parts.html) struct. EXTI line `n` takes its input from
+/// pin `n` of one port: [select_source](Self::select_source) claims the line for a pin, and
+/// fails while a pin of another port holds it.
+///
+/// ```rust
+/// let mut line = afio.exticr.select_source(&button)?;
+/// line.trigger_on_edge(&mut dp.Exti, Edge::Falling);
+/// line.enable_interrupt(&mut dp.Exti);
+///
+/// // PB0 can't take line 0 from PA0
+/// assert!(afio.exticr.select_source(&gpiob.pb0).is_err());
+/// ```
+///
+/// [ExtiPin::make_interrupt_source](crate::gpio::ExtiPin::make_interrupt_source) writes
+/// the same registers without claiming the line.
+pub struct EXTICR {
+    claimed: u16,
+}
+
+/// The EXTI line of a pin is already claimed by another pin
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineClaimed {
+    pub line: u8,
+}
+
+impl EXTICR {
+    /// Connects the EXTI line of `pin` to its port and returns the line
+    pub fn select_source<PIN>(&mut self, pin: &PIN) -> Result<ExtiLine, LineClaimed>
+    where
+        PIN: PinExt,
+        PIN::Mode: marker::Interruptible,
+    {
+        let line = pin.pin_id();
+        if self.is_claimed(line) {
+            return Err(LineClaimed { line });
+        }
+        self.claimed |= 1 << line;
+
+        let port = pin.port_id() as u32;
+        let offset = 4 * (line % 4);
+        // NOTE(unsafe) only the field of the claimed line is written
+        let afio = unsafe { &*Afio::ptr() };
+        match line {
+            0..=3 => afio.exti_cfg1().modify(|r, w| unsafe {
+                w.bits((r.bits() & !(0xf << offset)) | (port << offset))
+            }),
+            4..=7 => afio.exti_cfg2().modify(|r, w| unsafe {
+                w.bits((r.bits() & !(0xf << offset)) | (port << offset))
+            }),
+            8..=11 => afio.exti_cfg3().modify(|r, w| unsafe {
+                w.bits((r.bits() & !(0xf << offset)) | (port << offset))
+            }),
+            _ => afio.exti_cfg4().modify(|r, w| unsafe {
+                w.bits((r.bits() & !(0xf << offset)) | (port << offset))
+            }),
+        };
+        Ok(ExtiLine::new(line, pin.port_id()))
+    }
+
+    /// Disables the interrupt and the triggers of `line`, and frees it for another pin
+    pub fn release(&mut self, mut line: ExtiLine, exti: &mut Exti) {
+        line.disable_interrupt(exti);
+        let mask = !(1 << line.line());
+        exti.rt_cfg().modify(|r, w| unsafe { w.bits(r.bits() & mask) });
+        exti.ft_cfg().modify(|r, w| unsafe { w.bits(r.bits() & mask) });
+        line.clear_interrupt_pending_bit();
+        self.claimed &= !(1 << line.line());
+    }
+
+    /// Returns `true` if EXTI line `line` is claimed
+    pub fn is_claimed(&self, line: u8) -> bool {
+        self.claimed & (1 << line) != 0
+    }
+}
+
+/// AF remap register (RMP_CFG)
+///
+/// Aquired through the [Parts](struct.Parts.html) struct.
+///
+/// ```rust
+/// let dp = pac::Peripherals::take().unwrap();
+/// let mut afio = dp.Afio.constrain();
+/// function_using_rmp_cfg(&mut afio.rmp_cfg);
+/// ```
 pub struct RMP_CFG {
     _0: (),
 }
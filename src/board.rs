@@ -0,0 +1,246 @@
+//! # Board bring-up
+//!
+//! Splits the peripherals that nearly every application configures the same way
+//! (clocks, GPIO ports, AFIO and a SysTick delay) in one call.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! let dp = pac::Peripherals::take().unwrap();
+//! let cp = cortex_m::Peripherals::take().unwrap();
+//!
+//! let config = BoardConfig::default()
+//!     .use_hse(8.MHz())
+//!     .sysclk(144.MHz());
+//! let mut board = n32g4xx_hal::take_parts!(dp, cp, config);
+//!
+//! // The peripherals that weren't consumed can still be used
+//! let tx = dp.Usart1.tx(board.gpioa.pa9, Config::default(), &board.clocks, &mut board.afio);
+//! board.delay.delay_ms(100);
+//! ```
+//!
+//! `take_parts!` only moves the fields it needs out of `dp` and `cp`, so the
+//! remaining peripherals stay available without calling `steal`.
+//!
+//! ## Named signals
+//!
+//! The [board!](crate::board!) macro gives the pins of a board the names of its schematic, in a
+//! struct that configures them from a [Board]:
+//!
+//! ```rust
+//! n32g4xx_hal::board! {
+//!     pub struct Io {
+//!         pub led: PC13<Output<PushPull>> = gpioc.pc13;
+//!         pub uart_debug: (PA9<Alternate<PushPull>>, PA10<Input<Floating>>) = gpioa.pa9, gpioa.pa10;
+//!     }
+//! }
+//!
+//! let mut io = Io::new(n32g4xx_hal::take_parts!(dp, cp, config));
+//! io.led.set_high();
+//! let serial = dp.Usart1.serial(io.uart_debug, Config::default(), &io.clocks, &mut io.afio);
+//! ```
+//!
+//! The signals of the official evaluation boards are available with the `bsp` feature, see
+//! [n32g45xvl_stb].
+
+#[cfg(feature = "bsp")]
+pub mod n32g45xvl_stb;
+
+pub use cortex_m::delay::Delay;
+use cortex_m::peripheral::SYST;
+
+use crate::afio::AfioExt;
+use crate::delay::SYSTDelayExt;
+use crate::gpio::{gpioa, gpiob, gpioc, gpiod, gpioe, gpiof, gpiog, GpioExt};
+use crate::pac;
+use crate::rcc::{Clocks, RccExt};
+use crate::time::Hertz;
+
+/// Clock configuration applied by [Board::new]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BoardConfig {
+    hse: Option<Hertz>,
+    sysclk: Option<Hertz>,
+    hclk: Option<Hertz>,
+    pclk1: Option<Hertz>,
+    pclk2: Option<Hertz>,
+}
+
+impl BoardConfig {
+    /// Uses HSE (external oscillator) instead of HSI (internal RC oscillator) as the clock source.
+    pub fn use_hse(mut self, freq: Hertz) -> Self {
+        self.hse = Some(freq);
+        self
+    }
+
+    /// Sets the desired frequency for the SYSCLK clock
+    pub fn sysclk(mut self, freq: Hertz) -> Self {
+        self.sysclk = Some(freq);
+        self
+    }
+
+    /// Sets the desired frequency for the HCLK clock
+    pub fn hclk(mut self, freq: Hertz) -> Self {
+        self.hclk = Some(freq);
+        self
+    }
+
+    /// Sets the desired frequency for the PCKL1 clock
+    pub fn pclk1(mut self, freq: Hertz) -> Self {
+        self.pclk1 = Some(freq);
+        self
+    }
+
+    /// Sets the desired frequency for the PCLK2 clock
+    pub fn pclk2(mut self, freq: Hertz) -> Self {
+        self.pclk2 = Some(freq);
+        self
+    }
+}
+
+/// Peripherals consumed by [Board::new]
+pub struct BoardPeripherals {
+    pub rcc: pac::Rcc,
+    pub afio: pac::Afio,
+    pub gpioa: pac::Gpioa,
+    pub gpiob: pac::Gpiob,
+    pub gpioc: pac::Gpioc,
+    pub gpiod: pac::Gpiod,
+    pub gpioe: pac::Gpioe,
+    pub gpiof: pac::Gpiof,
+    pub gpiog: pac::Gpiog,
+    pub syst: SYST,
+}
+
+/// Configured clocks, GPIO ports, AFIO and delay
+pub struct Board {
+    pub clocks: Clocks,
+    pub afio: crate::afio::Parts,
+    pub gpioa: gpioa::Parts,
+    pub gpiob: gpiob::Parts,
+    pub gpioc: gpioc::Parts,
+    pub gpiod: gpiod::Parts,
+    pub gpioe: gpioe::Parts,
+    pub gpiof: gpiof::Parts,
+    pub gpiog: gpiog::Parts,
+    pub delay: Delay,
+}
+
+impl Board {
+    /// Freezes the clocks according to `config` and splits the GPIO ports
+    ///
+    /// Prefer the [take_parts!](crate::take_parts) macro, which builds [BoardPeripherals]
+    /// from `pac::Peripherals` and `cortex_m::Peripherals`.
+    pub fn new(p: BoardPeripherals, config: BoardConfig) -> Self {
+        let mut cfgr = p.rcc.constrain().cfgr;
+        if let Some(hse) = config.hse {
+            cfgr = cfgr.use_hse(hse);
+        }
+        if let Some(sysclk) = config.sysclk {
+            cfgr = cfgr.sysclk(sysclk);
+        }
+        if let Some(hclk) = config.hclk {
+            cfgr = cfgr.hclk(hclk);
+        }
+        if let Some(pclk1) = config.pclk1 {
+            cfgr = cfgr.pclk1(pclk1);
+        }
+        if let Some(pclk2) = config.pclk2 {
+            cfgr = cfgr.pclk2(pclk2);
+        }
+        let clocks = cfgr.freeze();
+
+        Self {
+            afio: p.afio.constrain(),
+            gpioa: p.gpioa.split(),
+            gpiob: p.gpiob.split(),
+            gpioc: p.gpioc.split(),
+            gpiod: p.gpiod.split(),
+            gpioe: p.gpioe.split(),
+            gpiof: p.gpiof.split(),
+            gpiog: p.gpiog.split(),
+            delay: p.syst.delay(&clocks),
+            clocks,
+        }
+    }
+}
+
+/// Builds a [Board](crate::board::Board) by moving the required fields out of
+/// `pac::Peripherals` and `cortex_m::Peripherals`
+///
+/// The remaining fields of both structs are left untouched and can still be used.
+#[macro_export]
+macro_rules! take_parts {
+    ($dp:ident, $cp:ident, $config:expr) => {
+        $crate::board::Board::new(
+            $crate::board::BoardPeripherals {
+                rcc: $dp.Rcc,
+                afio: $dp.Afio,
+                gpioa: $dp.Gpioa,
+                gpiob: $dp.Gpiob,
+                gpioc: $dp.Gpioc,
+                gpiod: $dp.Gpiod,
+                gpioe: $dp.Gpioe,
+                gpiof: $dp.Gpiof,
+                gpiog: $dp.Gpiog,
+                syst: $cp.SYST,
+            },
+            $config,
+        )
+    };
+}
+
+/// Declares a struct of named board signals, configured from a [Board](crate::board::Board)
+///
+/// Each signal is a pin, or a tuple of pins, with the mode it's configured into, followed by
+/// the pins of the [Board](crate::board::Board) it takes. The struct also holds the clocks,
+/// AFIO and delay of the board; the pins it doesn't name are dropped in their reset state.
+///
+/// ```rust
+/// n32g4xx_hal::board! {
+///     /// Signals of the application board
+///     pub struct Io {
+///         /// Status LED, active low
+///         pub led: PC13<Output<PushPull>> = gpioc.pc13;
+///         pub spi_flash: (PA5<Alternate<PushPull>>, PA6<Input<Floating>>, PA7<Alternate<PushPull>>) = gpioa.pa5, gpioa.pa6, gpioa.pa7;
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! board {
+    (
+        $(#[$attr:meta])*
+        $svis:vis struct $Board:ident {
+            $(
+                $(#[$fattr:meta])*
+                $vis:vis $name:ident : $ty:ty = $($port:ident . $pin:ident),+ ;
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        $svis struct $Board {
+            pub clocks: $crate::rcc::Clocks,
+            pub afio: $crate::afio::Parts,
+            pub delay: $crate::board::Delay,
+            $(
+                $(#[$fattr])*
+                $vis $name: $ty,
+            )*
+        }
+
+        impl $Board {
+            /// Configures the signals from the pins of `board`
+            pub fn new(board: $crate::board::Board) -> Self {
+                Self {
+                    $(
+                        $name: ($(board.$port.$pin.into_mode()),+),
+                    )*
+                    clocks: board.clocks,
+                    afio: board.afio,
+                    delay: board.delay,
+                }
+            }
+        }
+    };
+}
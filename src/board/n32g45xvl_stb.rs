@@ -0,0 +1,52 @@
+//! N32G45XVL-STB evaluation board
+//!
+//! The LQFP100 N32G457VEL7 board with three LEDs, three user keys next to the WAKEUP key and
+//! the USART1 of the NS-Link debugger as virtual COM port.
+//!
+//! ```rust
+//! let mut board = Stb::take(n32g4xx_hal::take_parts!(dp, cp, BoardConfig::default().use_hse(8.MHz()).sysclk(144.MHz())));
+//!
+//! board.led1.set_high();
+//! let serial = dp.Usart1.serial(board.uart_debug, Config::default(), &board.clocks, &mut board.afio);
+//! ```
+
+use crate::gpio::{
+    Alternate, Floating, Input, Output, PullDown, PullUp, PushPull, PA0, PA10, PA4, PA5, PA6, PA8, PA9, PB4, PB5,
+};
+
+crate::board! {
+    /// Signals of the N32G45XVL-STB, see [Stb::take]
+    pub struct Stb {
+        /// LED D1, active high
+        pub led1: PA8<Output<PushPull>> = gpioa.pa8;
+        /// LED D2, active high, shared with NJTRST
+        pub led2: PB4<Output<PushPull>> = gpiob.pb4;
+        /// LED D3, active high
+        pub led3: PB5<Output<PushPull>> = gpiob.pb5;
+        /// Key S1, low while pressed
+        pub key1: PA4<Input<PullUp>> = gpioa.pa4;
+        /// Key S2, low while pressed
+        pub key2: PA5<Input<PullUp>> = gpioa.pa5;
+        /// Key S3, low while pressed
+        pub key3: PA6<Input<PullUp>> = gpioa.pa6;
+        /// WAKEUP key, high while pressed
+        pub wakeup: PA0<Input<PullDown>> = gpioa.pa0;
+        /// TX and RX of USART1, wired to the virtual COM port of the NS-Link
+        pub uart_debug: (PA9<Alternate<PushPull>>, PA10<Input<Floating>>) = gpioa.pa9, gpioa.pa10;
+    }
+}
+
+impl Stb {
+    /// Configures the signals and releases PB4 from the JTAG port for LED D2
+    ///
+    /// The board is then debugged through SWD only, as it is with the NS-Link.
+    pub fn take(board: crate::board::Board) -> Self {
+        let mut stb = Self::new(board);
+        // SWJ_CFG = JTAG-DP disabled, SW-DP enabled
+        stb.afio
+            .rmp_cfg
+            .rmp_cfg()
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0b111 << 24)) | (0b010 << 24)) });
+        stb
+    }
+}
@@ -18,13 +18,22 @@
 //! |----------|---------|-------|
 //! | TX       | PB6     | PB13  |
 //! | RX       | PB5     | PB12  |
+//!
+//! ## Filters
+//!
+//! Unlike the STM32F1, where CAN2 borrows filter banks from CAN1, each controller has its own
+//! 14 banks. [Can::filter_banks] hands them out once per controller, see [filter].
 
 use crate::gpio::{self, Alternate, Input};
-use crate::pac::{self, Rcc,Afio};
+use crate::afio::Parts;
+use crate::pac::{self, Rcc};
+
+pub mod filter;
+pub use filter::{Filter, FilterBank, FilterBanks};
 
 pub trait Pins: crate::Sealed {
     type Instance;
-    fn remap(afio: &mut Afio);
+    fn remap(afio: &mut Parts);
 }
 
 impl<INMODE, OUTMODE> crate::Sealed
@@ -34,8 +43,8 @@ impl<INMODE, OUTMODE> crate::Sealed
 impl<INMODE, OUTMODE> Pins for (gpio::PA12<Alternate<OUTMODE>>, gpio::PA11<Input<INMODE>>) {
     type Instance = pac::Can1;
 
-    fn remap(afio: &mut Afio) {
-        afio.rmp_cfg().modify(|_, w| unsafe { w.can1_rmp().bits(0) });
+    fn remap(afio: &mut Parts) {
+        afio.rmp_cfg.rmp_cfg().modify(|_, w| unsafe { w.can1_rmp().bits(0) });
     }
 }
 
@@ -43,36 +52,41 @@ impl<INMODE, OUTMODE> crate::Sealed for (gpio::PB9<Alternate<OUTMODE>>, gpio::PB
 impl<INMODE, OUTMODE> Pins for (gpio::PB9<Alternate<OUTMODE>>, gpio::PB8<Input<INMODE>>) {
     type Instance = pac::Can1;
 
-    fn remap(afio: &mut Afio) {
-        afio.rmp_cfg().modify(|_, w| unsafe { w.can1_rmp().bits(0b10) });
+    fn remap(afio: &mut Parts) {
+        afio.rmp_cfg.rmp_cfg().modify(|_, w| unsafe { w.can1_rmp().bits(0b10) });
     }
 }
 
+#[cfg(any(feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 impl<INMODE, OUTMODE> crate::Sealed
     for (gpio::PB13<Alternate<OUTMODE>>, gpio::PB12<Input<INMODE>>)
 {
 }
 
+#[cfg(any(feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 impl<INMODE, OUTMODE> Pins for (gpio::PB13<Alternate<OUTMODE>>, gpio::PB12<Input<INMODE>>) {
     type Instance = pac::Can2;
 
-    fn remap(afio: &mut Afio) {
-        afio.rmp_cfg3().modify(|_, w| unsafe { w.can2_rmp().bits(0) });
+    fn remap(afio: &mut Parts) {
+        afio.rmp_cfg3.rmp_cfg3().modify(|_, w| unsafe { w.can2_rmp().bits(0) });
     }
 }
 
+#[cfg(any(feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 impl<INMODE, OUTMODE> crate::Sealed for (gpio::PB6<Alternate<OUTMODE>>, gpio::PB5<Input<INMODE>>) {}
+#[cfg(any(feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 impl<INMODE, OUTMODE> Pins for (gpio::PB6<Alternate<OUTMODE>>, gpio::PB5<Input<INMODE>>) {
     type Instance = pac::Can2;
 
-    fn remap(afio: &mut Afio) {
-        afio.rmp_cfg3().modify(|_, w| unsafe { w.can2_rmp().bits(0b01) });
+    fn remap(afio: &mut Parts) {
+        afio.rmp_cfg3.rmp_cfg3().modify(|_, w| unsafe { w.can2_rmp().bits(0b01) });
     }
 }
 
 /// Interface to the CAN peripheral.
 pub struct Can<Instance> {
     _peripheral: Instance,
+    banks_taken: bool,
 }
 
 impl<Instance> Can<Instance>
@@ -83,30 +97,41 @@ where
         let rcc = unsafe { &(*Rcc::ptr()) };
         Instance::enable(rcc);
 
-        Can { _peripheral: can }
+        Can { _peripheral: can, banks_taken: false }
     }
 
     /// Routes CAN TX signals and RX signals to pins.
-    pub fn assign_pins<P>(&self, _pins: P, afio: &mut Afio)
+    pub fn assign_pins<P>(&self, _pins: P, afio: &mut Parts)
     where
         P: Pins<Instance = Instance>,
     {
         P::remap(afio);
     }
+
+    /// Takes the filter banks of the controller, returns `None` if they were already taken
+    pub fn filter_banks(&mut self) -> Option<FilterBanks<Instance>> {
+        if self.banks_taken {
+            return None;
+        }
+        self.banks_taken = true;
+        Some(FilterBanks::new())
+    }
 }
 
 unsafe impl bxcan::Instance for Can<pac::Can1> {
     const REGISTERS: *mut bxcan::RegisterBlock = pac::Can1::ptr() as *mut bxcan::RegisterBlock;
 }
 
+#[cfg(any(feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 unsafe impl bxcan::Instance for Can<pac::Can2> {
     const REGISTERS: *mut bxcan::RegisterBlock = pac::Can2::ptr() as *mut bxcan::RegisterBlock;
 }
 
 unsafe impl bxcan::FilterOwner for Can<pac::Can1> {
-    const NUM_FILTER_BANKS: u8 = 14;
+    const NUM_FILTER_BANKS: u8 = filter::NUM_FILTER_BANKS;
 }
 
+#[cfg(any(feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 unsafe impl bxcan::FilterOwner for Can<pac::Can2> {
-    const NUM_FILTER_BANKS: u8 = 14;
+    const NUM_FILTER_BANKS: u8 = filter::NUM_FILTER_BANKS;
 }
@@ -0,0 +1,174 @@
+//! Filter bank allocation and configuration
+//!
+//! Every CAN controller has 14 filter banks. [FilterBanks] hands them out one at a time as
+//! [FilterBank]s, so independent drivers sharing a controller each own the banks they
+//! configure instead of overwriting each other's by index, and [FilterBanks::split] divides
+//! the banks between two owners up front.
+//!
+//! Each bank holds one [Filter]: a single 32-bit identifier/mask pair, two 16-bit pairs, or a
+//! list of two 32-bit or four 16-bit identifiers, and delivers the accepted frames to one of
+//! the two receive FIFOs.
+//!
+//! ```rust
+//! let mut can1 = Can::new(dp.Can1);
+//! let banks = can1.filter_banks().unwrap();
+//! let (mut sensors, mut commands) = banks.split(7);
+//! let mut can = bxcan::Can::builder(can1).set_bit_timing(0x001c_0003).enable();
+//!
+//! let bank = sensors.take().unwrap();
+//! bank.enable(&mut can, Filter::mask(StandardId::new(0x100).unwrap(), StandardId::new(0x700).unwrap()));
+//! let bank = commands.take().unwrap();
+//! bank.enable(&mut can, Filter::list16([cmd0, cmd1, cmd2, cmd3]).fifo(Fifo::Fifo1));
+//! ```
+
+use core::marker::PhantomData;
+
+use bxcan::filter::{BankConfig, ListEntry16, ListEntry32, Mask16, Mask32};
+use bxcan::{ExtendedId, Fifo, FilterOwner, Id, StandardId};
+
+use super::Can;
+
+/// Number of filter banks of each controller
+pub const NUM_FILTER_BANKS: u8 = 14;
+
+/// Acceptance filter of one bank
+#[derive(Debug, Clone, Copy)]
+pub struct Filter {
+    config: BankConfig,
+    fifo: Fifo,
+}
+
+impl Filter {
+    /// Accepts every frame
+    pub fn accept_all() -> Self {
+        Self::new(Mask32::accept_all())
+    }
+
+    /// 32-bit scale mask: accepts the standard frames whose identifier matches `id` in the bits set in `mask`
+    pub fn mask(id: StandardId, mask: StandardId) -> Self {
+        Self::new(Mask32::frames_with_std_id(id, mask))
+    }
+
+    /// 32-bit scale mask: accepts the extended frames whose identifier matches `id` in the bits set in `mask`
+    pub fn mask_extended(id: ExtendedId, mask: ExtendedId) -> Self {
+        Self::new(Mask32::frames_with_ext_id(id, mask))
+    }
+
+    /// 16-bit scale masks: accepts the standard frames matching either of the identifier/mask pairs
+    pub fn masks16(masks: [(StandardId, StandardId); 2]) -> Self {
+        Self::new(masks.map(|(id, mask)| Mask16::frames_with_std_id(id, mask)))
+    }
+
+    /// 32-bit scale list: accepts the data frames with either of the identifiers
+    pub fn list(ids: [Id; 2]) -> Self {
+        Self::new(ids.map(ListEntry32::data_frames_with_id))
+    }
+
+    /// 16-bit scale list: accepts the standard data frames with any of the four identifiers
+    pub fn list16(ids: [StandardId; 4]) -> Self {
+        Self::new(ids.map(ListEntry16::data_frames_with_id))
+    }
+
+    fn new(config: impl Into<BankConfig>) -> Self {
+        Self {
+            config: config.into(),
+            fifo: Fifo::Fifo0,
+        }
+    }
+
+    /// Delivers the accepted frames to `fifo` instead of FIFO 0
+    pub fn fifo(mut self, fifo: Fifo) -> Self {
+        self.fifo = fifo;
+        self
+    }
+}
+
+/// Filter banks of the controller `I` not handed out yet
+pub struct FilterBanks<I> {
+    /// One bit per available bank
+    free: u16,
+    _can: PhantomData<I>,
+}
+
+impl<I> FilterBanks<I> {
+    pub(crate) fn new() -> Self {
+        Self {
+            free: (1 << NUM_FILTER_BANKS) - 1,
+            _can: PhantomData,
+        }
+    }
+
+    /// Takes the lowest available bank
+    pub fn take(&mut self) -> Option<FilterBank<I>> {
+        if self.free == 0 {
+            return None;
+        }
+        let index = self.free.trailing_zeros() as u8;
+        self.free &= !(1 << index);
+        Some(FilterBank {
+            index,
+            _can: PhantomData,
+        })
+    }
+
+    /// Gives a bank back, it has to be disabled first
+    pub fn give_back(&mut self, bank: FilterBank<I>) {
+        self.free |= 1 << bank.index;
+    }
+
+    /// Number of banks still available
+    pub fn available(&self) -> u8 {
+        self.free.count_ones() as u8
+    }
+
+    /// Splits the available banks in two, the first `n` of them going to the first half
+    pub fn split(self, n: u8) -> (FilterBanks<I>, FilterBanks<I>) {
+        let mut first = 0;
+        let mut rest = self.free;
+        for _ in 0..n {
+            let lowest = rest & rest.wrapping_neg();
+            first |= lowest;
+            rest &= !lowest;
+        }
+        (
+            FilterBanks {
+                free: first,
+                _can: PhantomData,
+            },
+            FilterBanks {
+                free: rest,
+                _can: PhantomData,
+            },
+        )
+    }
+}
+
+/// Exclusively owned filter bank of the controller `I`
+pub struct FilterBank<I> {
+    index: u8,
+    _can: PhantomData<I>,
+}
+
+impl<I> FilterBank<I>
+where
+    Can<I>: FilterOwner,
+{
+    /// Index of the bank
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Configures the bank with `filter` and enables it
+    ///
+    /// The controller briefly enters filter initialization mode, which doesn't stop
+    /// reception but drops frames arriving during the update.
+    pub fn enable(&self, can: &mut bxcan::Can<Can<I>>, filter: Filter) {
+        can.modify_filters()
+            .enable_bank(self.index, filter.fifo, filter.config);
+    }
+
+    /// Disables the bank
+    pub fn disable(&self, can: &mut bxcan::Can<Can<I>>) {
+        can.modify_filters().disable_bank(self.index);
+    }
+}
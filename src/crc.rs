@@ -35,7 +35,8 @@ pub struct Crc16State {
     pub endianness: CrcEndianness
 }
 
-#[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CrcEndianness {
     StartFromMsb,
     StartFromLsb
@@ -219,4 +220,19 @@ impl Crc16Engine {
             endianness: state.endianness
         }
     }
-}
\ No newline at end of file
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF) computed in software
+///
+/// Continues from `crc`, so pass 0xFFFF for the first chunk. Used to frame serial protocols
+/// without tying up the CRC unit.
+pub fn crc16_ccitt(crc: u16, data: &[u8]) -> u16 {
+    let mut crc = crc;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
@@ -0,0 +1,260 @@
+//! # 8080 parallel display bus
+//!
+//! [ParallelBus8080] drives the data lines of an 8080 style LCD interface from the low
+//! 8 or 16 pins of a GPIO port. A timer paces the transfer: every update event requests
+//! a DMA write of the next word to the port, and a PWM channel of the same timer
+//! generates the WR strobe that latches it.
+//!
+//! Requires the `display` feature.
+//!
+//! The data port is written as a whole, so the other output pins of that port are
+//! driven low and can't be used as outputs or pulled inputs. CS has to be tied low or
+//! driven by the user, RD tied high.
+//!
+//! ```rust
+//! // PB0..PB7 as data lines
+//! let gpiob = dp.Gpiob.split();
+//! let _data = (
+//!     gpiob.pb0.into_push_pull_output(), gpiob.pb1.into_push_pull_output(),
+//!     // ...
+//! );
+//! let dc = gpioa.pa5.into_push_pull_output();
+//! // The strobe is active low: WR falls when a word is written and rises half a period later
+//! let mut wr = dp.Tim3.pwm(gpioa.pa6.into_alternate(), 4.MHz(), &clocks).into_active_low();
+//! wr.enable();
+//!
+//! let dma = dp.Dma1.split();
+//! let bus = ParallelBus8080::<'B', _, _, _, _, Width8>::new(dma.3, wr, dc);
+//! let display = mipidsi::Builder::new(ST7789, bus).init(&mut delay)?;
+//! ```
+
+use core::sync::atomic::{self, Ordering};
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_02::PwmPin;
+
+use crate::dma::{CompatibleChannel, DMAChannel, Flag, W, MAX_TRANSFER_LENGTH};
+use crate::timer::{UpdateDma, UpdateRequest};
+
+/// Number of words converted at a time for data that can't be sent as is
+const CHUNK: usize = 64;
+
+/// Width of the data bus
+pub trait BusWidth: crate::Sealed {
+    #[doc(hidden)]
+    const WIDE: bool;
+}
+
+/// 8 bit bus on pins 0 to 7 of the port
+pub struct Width8;
+/// 16 bit bus on pins 0 to 15 of the port
+pub struct Width16;
+
+impl crate::Sealed for Width8 {}
+impl crate::Sealed for Width16 {}
+
+impl BusWidth for Width8 {
+    const WIDE: bool = false;
+}
+
+impl BusWidth for Width16 {
+    const WIDE: bool = true;
+}
+
+/// 8080 parallel bus on port `P`, paced by `TIM`
+///
+/// `WR` is a PWM channel of `TIM` configured active low, its frequency is the word rate.
+/// After the last word the strobe is disabled from software within one WR period, so
+/// the WR period must be longer than the interrupt latency of the calling context.
+pub struct ParallelBus8080<const P: char, TIM, CH, WR, DC, WIDTH> {
+    channel: CH,
+    wr: WR,
+    dc: DC,
+    scratch: [u16; CHUNK],
+    _tim: core::marker::PhantomData<(TIM, WIDTH)>,
+}
+
+impl<const P: char, TIM, CH, WR, DC, WIDTH> ParallelBus8080<P, TIM, CH, WR, DC, WIDTH>
+where
+    TIM: UpdateDma,
+    CH: CompatibleChannel<UpdateRequest<TIM>, W> + DMAChannel,
+    WR: PwmPin<Duty = u16>,
+    DC: OutputPin,
+    WIDTH: BusWidth,
+{
+    /// Creates the bus, the data pins have to be configured as push-pull outputs
+    pub fn new(channel: CH, mut wr: WR, dc: DC) -> Self {
+        wr.set_duty(0);
+        Self {
+            channel,
+            wr,
+            dc,
+            scratch: [0; CHUNK],
+            _tim: core::marker::PhantomData,
+        }
+    }
+
+    /// Releases the DMA channel, the WR strobe and the DC pin
+    pub fn release(self) -> (CH, WR, DC) {
+        (self.channel, self.wr, self.dc)
+    }
+
+    /// Writes `len` words starting at `address` to the port and waits for completion
+    fn transfer(&mut self, address: u32, len: usize, bits16: bool) -> Result<(), DisplayError> {
+        if len == 0 {
+            return Ok(());
+        }
+        let pod = unsafe { (*crate::gpio::gpiox::<P>()).pod().as_ptr() } as u32;
+
+        self.channel.stop();
+        self.channel.configure_channel();
+        self.channel.set_peripheral_address(pod, false);
+        self.channel.set_memory_address(address, true);
+        self.channel
+            .try_set_transfer_length(len)
+            .map_err(|_| DisplayError::BusWriteError)?;
+        let priority = self.channel.priority_or(crate::dma::Priority::High) as u8;
+        self.channel.st().chcfg().modify(|_, w| {
+            let w = w
+                // memory to memory mode disabled
+                .mem2mem()
+                .disabled()
+                // channel priority level, high unless set with DMAChannel::set_priority
+                .priolvl()
+                .bits(priority);
+            // words are zero extended to the 32-bit port register
+            let w = if bits16 { w.msize().bits16() } else { w.msize().bits8() };
+            w.psize()
+                .bits32()
+                // circular mode disabled
+                .circ()
+                .disabled()
+                // read from memory
+                .dir()
+                .from_memory()
+        });
+
+        // The first strobe has to wait for the first word, which is written on the first update
+        self.wr.set_duty(0);
+        TIM::prepare();
+        self.wr.set_duty(self.wr.get_max_duty() / 2);
+
+        atomic::compiler_fence(Ordering::Release);
+        self.channel.start();
+        TIM::start();
+
+        let result = loop {
+            let flags = self.channel.flags();
+            if flags.contains(Flag::TransferError) {
+                break Err(DisplayError::BusWriteError);
+            }
+            if flags.contains(Flag::TransferComplete) {
+                break Ok(());
+            }
+        };
+
+        // The last word was written on the latest update, suppress the strobe of the next one
+        cortex_m::interrupt::free(|_| {
+            self.wr.set_duty(0);
+            TIM::clear_update();
+        });
+        while !TIM::is_updated() {}
+        TIM::stop();
+        self.channel.stop();
+        atomic::compiler_fence(Ordering::Acquire);
+        result
+    }
+
+    fn send_u8(&mut self, data: &[u8]) -> Result<(), DisplayError> {
+        if WIDTH::WIDE {
+            for chunk in data.chunks(CHUNK) {
+                for (dst, &src) in self.scratch.iter_mut().zip(chunk) {
+                    *dst = src as u16;
+                }
+                self.transfer(self.scratch.as_ptr() as u32, chunk.len(), true)?;
+            }
+            Ok(())
+        } else {
+            for chunk in data.chunks(MAX_TRANSFER_LENGTH) {
+                self.transfer(chunk.as_ptr() as u32, chunk.len(), false)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn send_u16(&mut self, data: &[u16]) -> Result<(), DisplayError> {
+        if WIDTH::WIDE {
+            for chunk in data.chunks(MAX_TRANSFER_LENGTH) {
+                self.transfer(chunk.as_ptr() as u32, chunk.len(), true)?;
+            }
+            Ok(())
+        } else {
+            // High byte first
+            self.send_iter(&mut data.iter().flat_map(|w| w.to_be_bytes()))
+        }
+    }
+
+    fn send_iter(&mut self, iter: &mut dyn Iterator<Item = u8>) -> Result<(), DisplayError> {
+        self.send_words(&mut iter.map(u16::from))
+    }
+
+    fn send_words(&mut self, iter: &mut dyn Iterator<Item = u16>) -> Result<(), DisplayError> {
+        loop {
+            let mut len = 0;
+            for (dst, src) in self.scratch.iter_mut().zip(&mut *iter) {
+                *dst = src;
+                len += 1;
+            }
+            if len == 0 {
+                return Ok(());
+            }
+            self.transfer(self.scratch.as_ptr() as u32, len, true)?;
+        }
+    }
+
+    fn send(&mut self, data: DataFormat<'_>) -> Result<(), DisplayError> {
+        match data {
+            DataFormat::U8(data) => self.send_u8(data),
+            DataFormat::U16(data) => self.send_u16(data),
+            DataFormat::U16BE(data) => {
+                for word in data.iter_mut() {
+                    *word = u16::from_be(*word);
+                }
+                self.send_u16(data)
+            }
+            DataFormat::U16LE(data) => {
+                for word in data.iter_mut() {
+                    *word = u16::from_le(*word);
+                }
+                self.send_u16(data)
+            }
+            DataFormat::U8Iter(iter) => self.send_iter(iter),
+            DataFormat::U16BEIter(iter) | DataFormat::U16LEIter(iter) if WIDTH::WIDE => {
+                self.send_words(iter)
+            }
+            DataFormat::U16BEIter(iter) => self.send_iter(&mut iter.flat_map(|w| w.to_be_bytes())),
+            DataFormat::U16LEIter(iter) => self.send_iter(&mut iter.flat_map(|w| w.to_le_bytes())),
+            _ => Err(DisplayError::DataFormatNotImplemented),
+        }
+    }
+}
+
+impl<const P: char, TIM, CH, WR, DC, WIDTH> WriteOnlyDataCommand for ParallelBus8080<P, TIM, CH, WR, DC, WIDTH>
+where
+    TIM: UpdateDma,
+    CH: CompatibleChannel<UpdateRequest<TIM>, W> + DMAChannel,
+    WR: PwmPin<Duty = u16>,
+    DC: OutputPin,
+    WIDTH: BusWidth,
+{
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.dc.set_low().map_err(|_| DisplayError::DCError)?;
+        self.send(cmd)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.dc.set_high().map_err(|_| DisplayError::DCError)?;
+        self.send(buf)
+    }
+}
@@ -0,0 +1,104 @@
+//! Static DMA buffers
+//!
+//! DMA transfers borrow their buffers for `'static`, which usually means a `static mut`
+//! and an `unsafe` block at each use, where nothing stops a second `&mut` to the same buffer.
+//! A [DmaBuffer] is a plain `static` handing out its `&'static mut` once:
+//!
+//! ```rust
+//! n32g4xx_hal::dma::buffers! {
+//!     static RX: [u8; 64] = [0; 64];
+//!     static SAMPLES: [[[u16; 2]; 32]; 2] = [[[0; 2]; 32]; 2];
+//! }
+//!
+//! let rx = RX.take().unwrap();
+//! let transfer = serial.rx.with_dma(dma.5).read(rx);
+//!
+//! assert!(RX.take().is_none());
+//! ```
+//!
+//! The buffers are word aligned and linked into `.uninit.dma_buffers`, which startup neither
+//! copies nor zeroes, so large buffers cost no flash and no boot time. A buffer is set to its
+//! initial value when it is taken. A linker script can place them in a specific SRAM region
+//! by that name.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Uninitialized storage of a [DmaBuffer]
+#[repr(C, align(4))]
+pub struct UninitBuffer<T>(UnsafeCell<MaybeUninit<T>>);
+
+// NOTE(unsafe) only the DmaBuffer owning it accesses it, once
+unsafe impl<T: Send> Sync for UninitBuffer<T> {}
+
+impl<T> UninitBuffer<T> {
+    pub const fn new() -> Self {
+        Self(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+}
+
+impl<T> Default for UninitBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Buffer of type `T` whose `&'static mut` can be taken once, see [buffers](macro@crate::dma::buffers)
+///
+/// The buffer lives in an [UninitBuffer] and is initialized with `init` when it is taken.
+pub struct DmaBuffer<T: 'static, F = fn() -> T> {
+    buffer: &'static UninitBuffer<T>,
+    init: F,
+    taken: AtomicBool,
+}
+
+impl<T, F> DmaBuffer<T, F> {
+    /// Creates a buffer stored in `buffer`, holding `init()` once taken
+    pub const fn new(buffer: &'static UninitBuffer<T>, init: F) -> Self {
+        Self {
+            buffer,
+            init,
+            taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns `true` if the buffer was taken
+    pub fn is_taken(&self) -> bool {
+        self.taken.load(Ordering::Relaxed)
+    }
+}
+
+impl<T, F: Fn() -> T> DmaBuffer<T, F> {
+    /// Initializes the buffer and returns it the first time, then `None`
+    pub fn take(&'static self) -> Option<&'static mut T> {
+        if self.taken.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            // NOTE(unsafe) the flag was clear, so no other reference exists
+            let buffer = unsafe { &mut *self.buffer.0.get() };
+            Some(buffer.write((self.init)()))
+        }
+    }
+}
+
+/// Declares [DmaBuffer] statics
+///
+/// Each `static NAME: T = init;` becomes a `static NAME: DmaBuffer<T>` whose storage is
+/// linked into `.uninit.dma_buffers`, see the [module documentation](mod@crate::dma::buffers).
+#[macro_export]
+macro_rules! dma_buffers {
+    ($($(#[$attr:meta])* $vis:vis static $NAME:ident : $T:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $NAME: $crate::dma::DmaBuffer<$T> = {
+                #[link_section = ".uninit.dma_buffers"]
+                static BUFFER: $crate::dma::UninitBuffer<$T> = $crate::dma::UninitBuffer::new();
+                fn init() -> $T {
+                    $init
+                }
+                $crate::dma::DmaBuffer::new(&BUFFER, init as fn() -> $T)
+            };
+        )*
+    };
+}
@@ -1,60 +1,161 @@
+//! # DMA request mapping
+//!
+//! Every DMA channel can serve any request of its controller, selected by the channel's
+//! `CHSEL` register. [CompatibleChannel] is implemented for all 8 channels of the controller
+//! a request is wired to, and `configure_channel` writes the request number, so picking a
+//! channel of the wrong controller fails to compile instead of silently never triggering.
+//!
+//! Each request is only wired to one controller:
+//!
+//! | Peripheral            | DMA1                      | DMA2                      |
+//! |-----------------------|---------------------------|---------------------------|
+//! | ADC1, ADC2            | R                         |                           |
+//! | ADC3, ADC4            |                           | R                         |
+//! | USART1-3, UART5       | R, W                      |                           |
+//! | UART4, UART6, UART7   |                           | R, W                      |
+//! | I2C1-3                | R, W                      |                           |
+//! | SPI1, SPI2            | R, W                      |                           |
+//! | SPI3                  |                           | R, W                      |
+//! | SDIO                  |                           | R, W                      |
+//! | DAC channel 1, 2      |                           | W                         |
+//! | TIM1-4 update         | R, W                      |                           |
+//! | TIM5-8 update         |                           | R, W                      |
+//! | TIM1, TIM2 CC1-4      | R, W                      |                           |
+//! | TIM3 CC1, CC3, CC4    | R, W                      |                           |
+//! | TIM4 CC1-3            | R, W                      |                           |
+//! | TIM5, TIM8 CC1-4      |                           | R, W                      |
+//!
+//! `R` transfers from the peripheral to memory, `W` from memory to the peripheral.
+//!
+//! ```rust
+//! use n32g4xx_hal::dma::{CompatibleChannel, DmaExt, R};
+//! use n32g4xx_hal::pac;
+//!
+//! fn usart1_rx<CH: CompatibleChannel<pac::Usart1, R>>(_ch: CH) {}
+//!
+//! let channels = dp.Dma1.split();
+//! usart1_rx(channels.5);
+//! ```
+//!
+//! ```compile_fail
+//! use n32g4xx_hal::dma::{CompatibleChannel, DmaExt, R};
+//! use n32g4xx_hal::pac;
+//!
+//! fn usart1_rx<CH: CompatibleChannel<pac::Usart1, R>>(_ch: CH) {}
+//!
+//! let dp = pac::Peripherals::take().unwrap();
+//! // USART1 isn't wired to DMA2
+//! let channels = dp.Dma2.split();
+//! usart1_rx(channels.5);
+//! ```
+
 use crate::dma::DMAChannel;
+use crate::timer::{CaptureCompareRequest, UpdateRequest};
+
+/// DMA request raised by channel `C` of the DAC
+pub struct DacRequest<const C: u8>;
+
 macro_rules! chmap_setup {
     (
         $(
-        $PER:ty: (
-            $dmaunit:tt::($($dmach:ident$(,)*)+) => (R => $rmp_rx:expr, W => $rmp_tx:expr)
-        ),
+            $PER:ty: $dmaunit:ident => ($($dir:ident => $rmp:expr),+),
         )+
     ) => {
         $(
             $(
-                impl crate::dma::CompatibleChannel<$PER,crate::dma::R> for crate::dma::$dmaunit::$dmach {
-                    fn configure_channel(&mut self) {
-                        unsafe { self.st().chsel().modify(|_,w| w.ch_sel().bits($rmp_rx)) }
-                    }
-                }
-    
-                impl crate::dma::CompatibleChannel<$PER,crate::dma::W> for crate::dma::$dmaunit::$dmach {
-                    fn configure_channel(&mut self) {
-                        unsafe { self.st().chsel().modify(|_,w| w.ch_sel().bits($rmp_tx)) }
-                    }
-                }
+                chmap_setup!(@channels $PER, $dmaunit, $dir, $rmp, C1, C2, C3, C4, C5, C6, C7, C8);
             )+
         )+
-    }
+    };
+    (@channels $PER:ty, $dmaunit:ident, $dir:ident, $rmp:expr, $($dmach:ident),+) => {
+        $(
+            impl crate::dma::CompatibleChannel<$PER, crate::dma::$dir> for crate::dma::$dmaunit::$dmach {
+                fn configure_channel(&mut self) {
+                    unsafe { self.st().chsel().modify(|_,w| w.ch_sel().bits($rmp)) }
+                }
+            }
+        )+
+    };
 }
 
 //ADCs
 chmap_setup!(
-    crate::pac::Adc1: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 10, W => 15)),
-    crate::pac::Adc2: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 10, W => 15)),
-    crate::pac::Adc3: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 10, W => 15)),
-    crate::pac::Adc4: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 10, W => 15)),
+    crate::pac::Adc1: dma1 => (R => 0),
+    crate::pac::Adc2: dma1 => (R => 39),
+    crate::pac::Adc3: dma2 => (R => 17),
+    crate::pac::Adc4: dma2 => (R => 22),
 );
 
 //US?ARTs
 chmap_setup!(
-    crate::pac::Usart1: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 23, W => 16)),
-    crate::pac::Usart2: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 29, W => 34)),
-    crate::pac::Usart3: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 11, W => 5)),
-    crate::pac::Uart4: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 14, W => 24)),
-    crate::pac::Uart5: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 40, W => 1)),
-    crate::pac::Uart6: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 14, W => 12)),
-    crate::pac::Uart7: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 27, W => 30)),
+    crate::pac::Usart1: dma1 => (R => 23, W => 16),
+    crate::pac::Usart2: dma1 => (R => 29, W => 34),
+    crate::pac::Usart3: dma1 => (R => 11, W => 5),
+    crate::pac::Uart4: dma2 => (R => 10, W => 21),
+    crate::pac::Uart5: dma1 => (R => 40, W => 1),
+    crate::pac::Uart6: dma2 => (R => 24, W => 25),
+    crate::pac::Uart7: dma2 => (R => 26, W => 27),
 );
 
 //I2Cs
 chmap_setup!(
-    crate::pac::I2c1: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 38, W => 33)),
-    crate::pac::I2c2: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 28, W => 22)),
-    crate::pac::I2c3: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 6, W => 2)),
+    crate::pac::I2c1: dma1 => (R => 38, W => 33),
+    crate::pac::I2c2: dma1 => (R => 27, W => 22),
+    crate::pac::I2c3: dma1 => (R => 6, W => 2),
 );
 
 //SPIs
 chmap_setup!(
-    crate::pac::Spi1: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 10, W => 15)),
-    crate::pac::Spi2: (dma1::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 21, W => 25)),
-    crate::pac::Spi3: (dma2::(C1,C2,C3,C4,C5,C6,C7,C8) => (R => 4, W => 11)),
+    crate::pac::Spi1: dma1 => (R => 10, W => 15),
+    crate::pac::Spi2: dma1 => (R => 21, W => 25),
+    crate::pac::Spi3: dma2 => (R => 9, W => 20),
 );
 
+//SDIO
+chmap_setup!(
+    crate::pac::Sdio: dma2 => (R => 14, W => 14),
+);
+
+//DAC
+chmap_setup!(
+    DacRequest<1>: dma2 => (W => 12),
+    DacRequest<2>: dma2 => (W => 16),
+);
+
+//Timer update events
+chmap_setup!(
+    UpdateRequest<crate::pac::Tim1>: dma1 => (R => 24, W => 24),
+    UpdateRequest<crate::pac::Tim2>: dma1 => (R => 8, W => 8),
+    UpdateRequest<crate::pac::Tim3>: dma1 => (R => 14, W => 14),
+    UpdateRequest<crate::pac::Tim4>: dma1 => (R => 37, W => 37),
+    UpdateRequest<crate::pac::Tim5>: dma2 => (R => 8, W => 8),
+    UpdateRequest<crate::pac::Tim6>: dma2 => (R => 11, W => 11),
+    UpdateRequest<crate::pac::Tim7>: dma2 => (R => 15, W => 15),
+    UpdateRequest<crate::pac::Tim8>: dma2 => (R => 3, W => 3),
+);
+
+//Timer capture/compare events
+chmap_setup!(
+    CaptureCompareRequest<crate::pac::Tim1, 1>: dma1 => (R => 7, W => 7),
+    CaptureCompareRequest<crate::pac::Tim1, 2>: dma1 => (R => 12, W => 12),
+    CaptureCompareRequest<crate::pac::Tim1, 3>: dma1 => (R => 30, W => 30),
+    CaptureCompareRequest<crate::pac::Tim1, 4>: dma1 => (R => 17, W => 17),
+    CaptureCompareRequest<crate::pac::Tim2, 1>: dma1 => (R => 28, W => 28),
+    CaptureCompareRequest<crate::pac::Tim2, 2>: dma1 => (R => 35, W => 35),
+    CaptureCompareRequest<crate::pac::Tim2, 3>: dma1 => (R => 3, W => 3),
+    CaptureCompareRequest<crate::pac::Tim2, 4>: dma1 => (R => 36, W => 36),
+    CaptureCompareRequest<crate::pac::Tim3, 1>: dma1 => (R => 31, W => 31),
+    CaptureCompareRequest<crate::pac::Tim3, 3>: dma1 => (R => 9, W => 9),
+    CaptureCompareRequest<crate::pac::Tim3, 4>: dma1 => (R => 13, W => 13),
+    CaptureCompareRequest<crate::pac::Tim4, 1>: dma1 => (R => 4, W => 4),
+    CaptureCompareRequest<crate::pac::Tim4, 2>: dma1 => (R => 20, W => 20),
+    CaptureCompareRequest<crate::pac::Tim4, 3>: dma1 => (R => 26, W => 26),
+    CaptureCompareRequest<crate::pac::Tim5, 1>: dma2 => (R => 19, W => 19),
+    CaptureCompareRequest<crate::pac::Tim5, 2>: dma2 => (R => 13, W => 13),
+    CaptureCompareRequest<crate::pac::Tim5, 3>: dma2 => (R => 7, W => 7),
+    CaptureCompareRequest<crate::pac::Tim5, 4>: dma2 => (R => 0, W => 0),
+    CaptureCompareRequest<crate::pac::Tim8, 1>: dma2 => (R => 18, W => 18),
+    CaptureCompareRequest<crate::pac::Tim8, 2>: dma2 => (R => 23, W => 23),
+    CaptureCompareRequest<crate::pac::Tim8, 3>: dma2 => (R => 2, W => 2),
+    CaptureCompareRequest<crate::pac::Tim8, 4>: dma2 => (R => 4, W => 4),
+);
@@ -0,0 +1,128 @@
+//! # DMA transfer statistics
+//!
+//! With the `dma-metrics` feature every channel counts the transfers it completed, the
+//! bytes it moved and the transfer errors it hit. The counters live in static atomics,
+//! so they can be read from anywhere with [metrics], e.g. from a diagnostics command.
+//!
+//! A transfer is accounted for when its channel is stopped. The bytes of a circular
+//! transfer are those moved in the lap it was stopped in.
+//!
+//! ```rust
+//! let snapshot = dma::metrics();
+//! let usart_rx = snapshot.dma1[4];
+//! defmt::info!("{} transfers, {} bytes, {} errors", usart_rx.transfers, usart_rx.bytes, usart_rx.errors);
+//! ```
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use enumflags2::BitFlags;
+
+use super::Flag;
+
+const CHANNELS: usize = 8;
+
+struct Counters {
+    /// Transfer length when the channel was started, 0 while idle
+    started: AtomicU32,
+    transfers: AtomicU32,
+    bytes: AtomicU32,
+    errors: AtomicU32,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            started: AtomicU32::new(0),
+            transfers: AtomicU32::new(0),
+            bytes: AtomicU32::new(0),
+            errors: AtomicU32::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> ChannelMetrics {
+        ChannelMetrics {
+            transfers: self.transfers.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    fn clear(&self) {
+        self.transfers.store(0, Ordering::Relaxed);
+        self.bytes.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const IDLE: Counters = Counters::new();
+static COUNTERS: [[Counters; CHANNELS]; 2] = [[IDLE; CHANNELS], [IDLE; CHANNELS]];
+
+/// Statistics of a single channel
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMetrics {
+    /// Transfers that ran to completion
+    pub transfers: u32,
+    /// Bytes moved, including those of incomplete transfers
+    pub bytes: u32,
+    /// Transfers stopped by a transfer error
+    pub errors: u32,
+}
+
+/// Statistics of all channels, index 0 is channel 1
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    /// Channels of DMA1
+    pub dma1: [ChannelMetrics; CHANNELS],
+    /// Channels of DMA2
+    pub dma2: [ChannelMetrics; CHANNELS],
+}
+
+/// Returns the statistics of all channels
+///
+/// A transfer is accounted for when its channel is stopped. The bytes of a circular
+/// transfer are those moved in the lap it was stopped in.
+///
+/// The counters are read one by one, so a transfer finishing meanwhile may be
+/// reflected in some counters of its channel only.
+pub fn metrics() -> Metrics {
+    let mut metrics = Metrics::default();
+    for (dst, src) in metrics.dma1.iter_mut().zip(&COUNTERS[0]) {
+        *dst = src.snapshot();
+    }
+    for (dst, src) in metrics.dma2.iter_mut().zip(&COUNTERS[1]) {
+        *dst = src.snapshot();
+    }
+    metrics
+}
+
+/// Clears the statistics of all channels
+pub fn reset_metrics() {
+    for counters in COUNTERS.iter().flatten() {
+        counters.clear();
+    }
+}
+
+/// Records the start of a transfer of `len` elements
+pub(crate) fn started(dma: usize, channel: usize, len: u32) {
+    COUNTERS[dma][channel].started.store(len, Ordering::Relaxed);
+}
+
+/// Accounts for the transfer being stopped, given its remaining length and element size
+pub(crate) fn stopped(dma: usize, channel: usize, remaining: u32, size: u32, flags: BitFlags<Flag>) {
+    let counters = &COUNTERS[dma][channel];
+    let len = counters.started.swap(0, Ordering::Relaxed);
+    if len == 0 {
+        return;
+    }
+    counters
+        .bytes
+        .fetch_add(len.saturating_sub(remaining) * size, Ordering::Relaxed);
+    if flags.contains(Flag::TransferError) {
+        counters.errors.fetch_add(1, Ordering::Relaxed);
+    } else if flags.contains(Flag::TransferComplete) {
+        counters.transfers.fetch_add(1, Ordering::Relaxed);
+    }
+}
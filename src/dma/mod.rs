@@ -6,12 +6,140 @@ use core::{
 };
 use embedded_dma::{ReadBuffer, WriteBuffer};
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
     Overrun,
+    /// The transfer didn't complete in time and was stopped
+    Timeout,
+    /// The buffer holds more than [MAX_TRANSFER_LENGTH] elements
+    BufferTooLong,
+    /// An address isn't aligned to the word size of its side of the transfer
+    Misaligned,
+    /// The buffers don't fit the transfer: the receive and transmit buffers of a [ReadWriteDma]
+    /// transfer differ in length, or the rows of an ADC buffer differ from the regular sequence
+    LengthMismatch,
+    /// The channel reported a transfer error, e.g. a bus error at one of its addresses
+    TransferError,
+    /// The timer pacing the transfer can't generate the requested rate
+    InvalidRate,
+    /// The input filter setting of the timer requesting the transfer is above 15
+    InvalidFilter,
+}
+
+/// Maximum number of elements in a single DMA transfer, limited by the 16-bit TXNUM register
+pub const MAX_TRANSFER_LENGTH: usize = u16::MAX as usize;
+
+/// Splits a buffer into chunks that each fit in a single DMA transfer
+///
+/// The chunks keep the `'static` lifetime, so they can be transferred one after another
+/// with [ReadDma::read] or [WriteDma::write].
+pub fn dma_chunks<T>(buffer: &'static mut [T]) -> core::slice::ChunksMut<'static, T> {
+    buffer.chunks_mut(MAX_TRANSFER_LENGTH)
+}
+
+/// Starts a transfer of `len` bytes between a peripheral data register and memory, or returns
+/// [Error::BufferTooLong] without touching the channel
+///
+/// Used by the drivers that run their own byte streams over a serial port. The priority is
+/// medium unless set with [DMAChannel::set_priority].
+pub(crate) fn start_byte_transfer<CH: DMAChannel>(
+    channel: &mut CH,
+    peripheral: u32,
+    address: u32,
+    len: usize,
+    from_memory: bool,
+    circular: bool,
+) -> Result<(), Error> {
+    channel.try_set_transfer_length(len)?;
+    channel.set_peripheral_address(peripheral, false);
+    channel.set_memory_address(address, true);
+    let priority = channel.priority_or(Priority::Medium) as u8;
+    channel.st().chcfg().modify(|_, w| { w
+        .mem2mem() .clear_bit()
+        .priolvl() .bits(priority)
+        .msize()   .bits8()
+        .psize()   .bits8()
+        .circ()    .bit(circular)
+        .dir()     .bit(from_memory)
+    });
+    channel.start();
+    Ok(())
+}
+
+/// Size of the words the DMA reads or writes on one side of a transfer (`MSIZE`/`PSIZE`)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WordSize {
+    Bits8 = 0,
+    Bits16 = 1,
+    Bits32 = 2,
+}
+
+impl WordSize {
+    /// Decodes the `MSIZE`/`PSIZE` field value
+    pub fn from_bits(bits: u8) -> Self {
+        match bits {
+            0 => WordSize::Bits8,
+            1 => WordSize::Bits16,
+            _ => WordSize::Bits32,
+        }
+    }
+
+    /// Size in bytes
+    pub const fn bytes(self) -> usize {
+        1 << self as usize
+    }
+
+    /// Widest word size that `address` and `len` bytes are both aligned to
+    ///
+    /// Use this to pick the word size of memory to memory copies of byte buffers of unknown
+    /// alignment, instead of programming a size the DMA would silently truncate the addresses
+    /// to. Each transfer moves one word, so both sides have to use the same size for a copy.
+    pub fn widest_for(address: u32, len: usize) -> Self {
+        match (address as usize | len) & 3 {
+            0 => WordSize::Bits32,
+            2 => WordSize::Bits16,
+            _ => WordSize::Bits8,
+        }
+    }
+
+    /// Fails with [Error::Misaligned] if `address` isn't aligned to this word size
+    pub fn check(self, address: u32) -> Result<(), Error> {
+        if address as usize % self.bytes() != 0 {
+            return Err(Error::Misaligned);
+        }
+        Ok(())
+    }
 }
 
+/// Element types of DMA buffers, each moved as one word of [SIZE](Self::SIZE)
+///
+/// The drivers program `MSIZE` and `PSIZE` from the `Word` of the buffer, and implement their
+/// DMA traits for the data width of the peripheral only: a buffer of another element type
+/// doesn't compile, instead of being truncated or padded by the DMA.
+pub trait DmaWord: crate::Sealed + Copy {
+    const SIZE: WordSize;
+}
+
+impl crate::Sealed for u8 {}
+impl crate::Sealed for u16 {}
+impl crate::Sealed for u32 {}
+
+impl DmaWord for u8 {
+    const SIZE: WordSize = WordSize::Bits8;
+}
+
+impl DmaWord for u16 {
+    const SIZE: WordSize = WordSize::Bits16;
+}
+
+impl DmaWord for u32 {
+    const SIZE: WordSize = WordSize::Bits32;
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Event {
     HalfTransfer,
@@ -19,12 +147,30 @@ pub enum Event {
     TransferError,
 }
 
+/// Per-channel DMA status flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// Global interrupt flag, set if any of the other flags is set
+    Global = 1 << 0,
+    /// Transfer complete
+    TransferComplete = 1 << 1,
+    /// Half transfer
+    HalfTransfer = 1 << 2,
+    /// Transfer error
+    TransferError = 1 << 3,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Half {
     First,
     Second,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TransferDirection {
     MemoryToMemory,
@@ -37,8 +183,9 @@ where
     BUFFER: 'static,
 {
     buffer: &'static mut [BUFFER; 2],
-    payload: PAYLOAD,
+    pub(crate) payload: PAYLOAD,
     readable_half: Half,
+    read_position: usize,
 }
 
 impl<BUFFER, PAYLOAD> CircBuffer<BUFFER, PAYLOAD>
@@ -51,6 +198,7 @@ where
             buffer: buf,
             payload,
             readable_half: Half::Second,
+            read_position: 0,
         }
     }
 }
@@ -64,6 +212,13 @@ pub trait DmaExt {
 pub trait TransferPayload {
     fn start(&mut self);
     fn stop(&mut self);
+    /// Returns `true` once the peripheral is done with the data the channel moved
+    ///
+    /// Transmitters that keep shifting data out after the last DMA request override this,
+    /// so waiting on a transfer also covers the tail of the transmission.
+    fn is_drained(&self) -> bool {
+        true
+    }
 }
 
 pub struct Transfer<MODE, BUFFER, PAYLOAD>
@@ -101,6 +256,34 @@ where
     }
 }
 
+impl<MODE, BUFFER, PAYLOAD> Transfer<MODE, BUFFER, PAYLOAD>
+where
+    PAYLOAD: TransferPayload,
+{
+    /// Stops the payload and takes the buffer and payload out of the transfer
+    fn finish(mut self) -> (BUFFER, PAYLOAD) {
+        atomic::compiler_fence(Ordering::Acquire);
+
+        self.payload.stop();
+
+        // we need a read here to make the Acquire fence effective
+        // we do *not* need this if `dma.stop` does a RMW operation
+        unsafe { ptr::read_volatile(&0); }
+
+        // we need a fence here for the same reason we need one in `Transfer.wait`
+        atomic::compiler_fence(Ordering::Acquire);
+
+        // NOTE(unsafe) There is no panic branch between getting the resources
+        // and forgetting `self`.
+        unsafe {
+            let buffer = ptr::read(&self.buffer);
+            let payload = ptr::read(&self.payload);
+            mem::forget(self);
+            (buffer, payload)
+        }
+    }
+}
+
 impl<MODE, BUFFER, PAYLOAD> Drop for Transfer<MODE, BUFFER, PAYLOAD>
 where
     PAYLOAD: TransferPayload,
@@ -118,6 +301,7 @@ pub struct R;
 pub struct W;
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelStatus {
     TransferInProgress,
     TransferComplete,
@@ -127,7 +311,9 @@ pub enum ChannelStatus {
 pub trait DMAChannel {
     fn set_peripheral_address(&mut self, address: u32, inc: bool);
     fn set_memory_address(&mut self, address: u32, inc: bool);
+    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on long transfers, use try_set_transfer_length"))]
     fn set_transfer_length(&mut self, len: usize);
+    fn try_set_transfer_length(&mut self, len: usize) -> Result<(), Error>;
     fn set_transfer_direction(&mut self, direction: TransferDirection);
     fn start(&mut self);
     fn stop(&mut self);
@@ -140,6 +326,45 @@ pub trait DMAChannel {
     fn intsts(&self) -> n32g4::raw::R<crate::pac::dma1::intsts::IntstsSpec>;
     fn intclr(&self) -> &crate::pac::dma1::Intclr;
     fn get_txnum(&self) -> u32;
+    /// Status flags of this channel only, shifted down from INTSTS
+    fn flags(&self) -> BitFlags<Flag>;
+    /// Clears status flags of this channel only
+    ///
+    /// This is a single write to the write-1-to-clear INTCLR register, so it can't
+    /// clear the flags of other channels even when called from nested interrupts
+    fn clear_flags(&self, flags: BitFlags<Flag>);
+
+    /// Sets the arbitration priority, it overrides the default of the drivers for every
+    /// transfer started on this channel, see [priority](crate::dma::priority)
+    fn set_priority(&mut self, priority: Priority);
+    /// Priority set with [set_priority](Self::set_priority), or `default`
+    fn priority_or(&self, default: Priority) -> Priority;
+    /// Priority currently programmed
+    fn priority(&mut self) -> Priority {
+        Priority::from_bits(self.st().chcfg().read().priolvl().bits())
+    }
+
+    /// Sets the memory and peripheral word sizes (`MSIZE`/`PSIZE`), with the channel disabled
+    fn set_word_size(&mut self, memory: WordSize, peripheral: WordSize) {
+        self.st().chcfg().modify(|_, w| unsafe { w.msize().bits(memory as u8).psize().bits(peripheral as u8) });
+    }
+
+    /// Like [set_memory_address](Self::set_memory_address), but fails with [Error::Misaligned]
+    /// instead of letting the DMA truncate an `address` that isn't aligned to the configured
+    /// memory word size
+    fn set_memory_address_checked(&mut self, address: u32, inc: bool) -> Result<(), Error> {
+        WordSize::from_bits(self.st().chcfg().read().msize().bits()).check(address)?;
+        self.set_memory_address(address, inc);
+        Ok(())
+    }
+
+    /// Like [set_peripheral_address](Self::set_peripheral_address), but fails with
+    /// [Error::Misaligned] if `address` isn't aligned to the configured peripheral word size
+    fn set_peripheral_address_checked(&mut self, address: u32, inc: bool) -> Result<(), Error> {
+        WordSize::from_bits(self.st().chcfg().read().psize().bits()).check(address)?;
+        self.set_peripheral_address(address, inc);
+        Ok(())
+    }
 }
 
 
@@ -187,6 +412,8 @@ where
     }
 
     pub fn wait(mut self) -> (BUFFER, RxTxDma<PAYLOAD, CX, TXC>) {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::DMA_WAIT.scope();
         while !self.is_done() {}
 
         atomic::compiler_fence(Ordering::Acquire);
@@ -214,6 +441,28 @@ where
             (buffer, payload)
         }
     }
+
+    /// Waits for the transfer to complete, giving up after `timeout`
+    ///
+    /// On timeout the channel is stopped and the buffer and payload are returned with
+    /// [Error::Timeout], so a wedged peripheral doesn't hang the caller.
+    #[allow(clippy::type_complexity)]
+    pub fn wait_with_timeout<TIM>(self, timeout: crate::time::MicroSecond, timer: &mut TIM)
+        -> Result<(BUFFER, RxTxDma<PAYLOAD, CX, TXC>), (Error, BUFFER, RxTxDma<PAYLOAD, CX, TXC>)>
+    where
+        TIM: embedded_hal_02::timer::CountDown<Time = crate::time::MicroSecond>,
+    {
+        timer.start(timeout);
+        loop {
+            if self.is_done() {
+                return Ok(self.finish());
+            }
+            if timer.wait().is_ok() {
+                let (buffer, payload) = self.finish();
+                return Err((Error::Timeout, buffer, payload));
+            }
+        }
+    }
 }
 impl<BUFFER, PAYLOAD, MODE,CX: DMAChannel> Transfer<MODE, BUFFER, RxDma<PAYLOAD, CX>>
 where
@@ -224,6 +473,8 @@ where
     }
 
     pub fn wait(mut self) -> (BUFFER, RxDma<PAYLOAD, CX>) {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::DMA_WAIT.scope();
         while !self.is_done() {}
 
         atomic::compiler_fence(Ordering::Acquire);
@@ -251,17 +502,43 @@ where
             (buffer, payload)
         }
     }
+
+    /// Waits for the transfer to complete, giving up after `timeout`
+    ///
+    /// On timeout the channel is stopped and the buffer and payload are returned with
+    /// [Error::Timeout], so a wedged peripheral doesn't hang the caller.
+    #[allow(clippy::type_complexity)]
+    pub fn wait_with_timeout<TIM>(self, timeout: crate::time::MicroSecond, timer: &mut TIM)
+        -> Result<(BUFFER, RxDma<PAYLOAD, CX>), (Error, BUFFER, RxDma<PAYLOAD, CX>)>
+    where
+        TIM: embedded_hal_02::timer::CountDown<Time = crate::time::MicroSecond>,
+    {
+        timer.start(timeout);
+        loop {
+            if self.is_done() {
+                return Ok(self.finish());
+            }
+            if timer.wait().is_ok() {
+                let (buffer, payload) = self.finish();
+                return Err((Error::Timeout, buffer, payload));
+            }
+        }
+    }
 }
 
 impl<BUFFER, PAYLOAD, MODE, CX: DMAChannel> Transfer<MODE, BUFFER, TxDma<PAYLOAD, CX>>
 where
     TxDma<PAYLOAD, CX>: TransferPayload,
 {
+    /// Returns `true` once the channel moved all the data and the payload is
+    /// [drained](TransferPayload::is_drained)
     pub fn is_done(&self) -> bool {
-        !self.payload.channel.in_progress()
+        !self.payload.channel.in_progress() && self.payload.is_drained()
     }
 
     pub fn wait(mut self) -> (BUFFER, TxDma<PAYLOAD, CX>) {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::DMA_WAIT.scope();
         while !self.is_done() {}
 
         atomic::compiler_fence(Ordering::Acquire);
@@ -289,12 +566,34 @@ where
             (buffer, payload)
         }
     }
+
+    /// Waits for the transfer to complete, giving up after `timeout`
+    ///
+    /// On timeout the channel is stopped and the buffer and payload are returned with
+    /// [Error::Timeout], so a wedged peripheral doesn't hang the caller.
+    #[allow(clippy::type_complexity)]
+    pub fn wait_with_timeout<TIM>(self, timeout: crate::time::MicroSecond, timer: &mut TIM)
+        -> Result<(BUFFER, TxDma<PAYLOAD, CX>), (Error, BUFFER, TxDma<PAYLOAD, CX>)>
+    where
+        TIM: embedded_hal_02::timer::CountDown<Time = crate::time::MicroSecond>,
+    {
+        timer.start(timeout);
+        loop {
+            if self.is_done() {
+                return Ok(self.finish());
+            }
+            if timer.wait().is_ok() {
+                let (buffer, payload) = self.finish();
+                return Err((Error::Timeout, buffer, payload));
+            }
+        }
+    }
 }
 
 macro_rules! dma {
-    ($($DMAX:ident: ($dmaX:ident, {
+    ($($DMAX:ident: ($dmaX:ident, $index:expr, {
         $($CX:ident: (
-            $chX:ident,
+            $chX:ident, $shift:expr,
             $htxfX:ident,
             $txcfX:ident,
             $errfX:ident,
@@ -310,9 +609,18 @@ macro_rules! dma {
 
                 use crate::pac::{Rcc, $DMAX, dma1};
 
-                use crate::dma::{CircBuffer, DMAChannel, DmaExt, Error, Event, Half, RxDma, TransferPayload};
+                use core::sync::atomic::{AtomicU8, Ordering};
+
+                use crate::dma::{CircBuffer, DMAChannel, DmaExt, Error, Event, Flag, Half, Priority, RxDma, TransferPayload};
+                use enumflags2::BitFlags;
                 use crate::rcc::Enable;
 
+                /// Priority set for each channel, `NO_PRIORITY` until set
+                static PRIORITIES: [AtomicU8; 8] = [UNSET; 8];
+                const NO_PRIORITY: u8 = 0xff;
+                #[allow(clippy::declare_interior_mutable_const)]
+                const UNSET: AtomicU8 = AtomicU8::new(NO_PRIORITY);
+
                 #[allow(clippy::manual_non_exhaustive)]
                 pub struct Channels((), $(pub $CX),+);
 
@@ -334,13 +642,13 @@ macro_rules! dma {
                         fn clear_flag(&mut self, event : crate::dma::Event) { 
                             match event {
                                 crate::dma::Event::HalfTransfer => {
-                                    self.intclr().modify(|_,w| w.$chtxfX().clear_bit())
+                                    self.intclr().write(|w| w.$chtxfX().set_bit())
                                 },
                                 crate::dma::Event::TransferComplete => {
-                                    self.intclr().modify(|_,w| w.$ctxcfX().clear_bit())
+                                    self.intclr().write(|w| w.$ctxcfX().set_bit())
                                 },
                                 crate::dma::Event::TransferError => {
-                                    self.intclr().modify(|_,w| w.$cerrfX().clear_bit())
+                                    self.intclr().write(|w| w.$cerrfX().set_bit())
                                 }
                             }
                         }
@@ -377,20 +685,37 @@ macro_rules! dma {
                             self.st().chcfg().modify(|_, w| w.minc().bit(inc) );
                         }
 
-                        /// Number of bytes to transfer
+                        /// Number of elements to transfer
+                        ///
+                        /// Panics if `len` exceeds [MAX_TRANSFER_LENGTH](crate::dma::MAX_TRANSFER_LENGTH),
+                        /// see [try_set_transfer_length](Self::try_set_transfer_length).
                         fn set_transfer_length(&mut self, len: usize) {
-                            self.st().txnum().write(|w| unsafe { w.ndtx().bits(u16::try_from(len).unwrap()) });
+                            self.try_set_transfer_length(len).expect("DMA transfer longer than 65535 elements");
+                        }
+
+                        /// Number of elements to transfer, or [BufferTooLong](crate::dma::Error::BufferTooLong)
+                        fn try_set_transfer_length(&mut self, len: usize) -> Result<(), crate::dma::Error> {
+                            let len = u16::try_from(len).map_err(|_| crate::dma::Error::BufferTooLong)?;
+                            self.st().txnum().write(|w| unsafe { w.ndtx().bits(len) });
+                            Ok(())
                         }
 
                         /// Starts the DMA transfer
                         fn start(&mut self) {
                             self.st().paddr().modify(|r,w| unsafe { w.addr().bits(r.addr().bits()) });
                             self.st().maddr().modify(|r,w| unsafe { w.addr().bits(r.addr().bits()) });
+                            #[cfg(feature = "dma-metrics")]
+                            crate::dma::metrics::started($index, $shift / 4, self.get_txnum());
                             self.st().chcfg().modify(|_, w| w.chen().set_bit() );
                         }
 
                         /// Stops the DMA transfer
                         fn stop(&mut self) {
+                            #[cfg(feature = "dma-metrics")]
+                            {
+                                let size = 1 << self.st().chcfg().read().msize().bits();
+                                crate::dma::metrics::stopped($index, $shift / 4, self.get_txnum(), size, self.flags());
+                            }
                             self.intclr().write(|w| w.$cglbfX().set_bit());
                             self.st().chcfg().modify(|_, w| w.chen().clear_bit() );
                         }
@@ -443,6 +768,27 @@ macro_rules! dma {
                             // NOTE(unsafe) atomic read with no side effects
                             unsafe { &(*$DMAX::ptr())}.$chX().txnum().read().bits()
                         }
+
+                        fn flags(&self) -> BitFlags<Flag> {
+                            BitFlags::from_bits_truncate(self.intsts().bits() >> $shift)
+                        }
+
+                        fn clear_flags(&self, flags: BitFlags<Flag>) {
+                            // NOTE(unsafe) INTCLR is write-1-to-clear, a single write only touches this channel's bits
+                            self.intclr().write(|w| unsafe { w.bits(flags.bits() << $shift) });
+                        }
+
+                        fn set_priority(&mut self, priority: Priority) {
+                            PRIORITIES[$shift / 4].store(priority as u8, Ordering::Relaxed);
+                            self.st().chcfg().modify(|_, w| w.priolvl().bits(priority as u8));
+                        }
+
+                        fn priority_or(&self, default: Priority) -> Priority {
+                            match PRIORITIES[$shift / 4].load(Ordering::Relaxed) {
+                                NO_PRIORITY => default,
+                                bits => Priority::from_bits(bits),
+                            }
+                        }
                     }
                     impl<B, PAYLOAD> CircBuffer<B, RxDma<PAYLOAD, $CX>>
                     where
@@ -521,6 +867,37 @@ macro_rules! dma {
                         }
                     }
 
+                    impl<T, const N: usize, PAYLOAD> CircBuffer<[T; N], RxDma<PAYLOAD, $CX>>
+                    where
+                        RxDma<PAYLOAD, $CX>: TransferPayload,
+                    {
+                        /// Hands everything received since the previous call to `f`, including a partially filled half
+                        ///
+                        /// Meant for the idle line interrupt of a receiver, where the data before the line went idle
+                        /// would otherwise wait for the half to fill up. The data is passed as two slices as it may wrap
+                        /// around the end of the buffer. Don't mix with [peek](Self::peek), which tracks halves instead.
+                        pub fn read_available<R, F>(&mut self, f: F) -> R
+                        where
+                            F: FnOnce(&[T], &[T]) -> R,
+                        {
+                            let total = 2 * N;
+                            // The half/complete flags are superseded by the read position
+                            self.payload.channel.clear_flags(Flag::HalfTransfer | Flag::TransferComplete);
+                            let write_position = (total - self.payload.channel.get_txnum() as usize) % total;
+                            core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Acquire);
+
+                            // NOTE(unsafe) both halves are contiguous, the DMA only writes outside of the returned range
+                            let data = unsafe { core::slice::from_raw_parts(self.buffer.as_ptr() as *const T, total) };
+                            let read_position = self.read_position;
+                            self.read_position = write_position;
+                            if write_position >= read_position {
+                                f(&data[read_position..write_position], &[])
+                            } else {
+                                f(&data[read_position..], &data[..write_position])
+                            }
+                        }
+                    }
+
                     
                 )+
 
@@ -545,87 +922,87 @@ macro_rules! dma {
 }
 
 dma! {
-    Dma1: (dma1, {
+    Dma1: (dma1, 0, {
         C1: (
-            st1,
+            st1, 0,
             htxf1, txcf1, errf1,
             chtxf1, ctxcf1, cglbf1, cerrf1
         ),
         C2: (
-            st2,
+            st2, 4,
             htxf2, txcf2, errf2,
             chtxf2, ctxcf2, cglbf2, cerrf2
         ),
         C3: (
-            st3,
+            st3, 8,
             htxf3, txcf3, errf3,
             chtxf3, ctxcf3, cglbf3, cerrf3
         ),
         C4: (
-            st4,
+            st4, 12,
             htxf4, txcf4, errf4,
             chtxf4, ctxcf4, cglbf4, cerrf4
         ),
         C5: (
-            st5,
+            st5, 16,
             htxf5, txcf5, errf5,
             chtxf5, ctxcf5, cglbf5, cerrf5
         ),
         C6: (
-            st6,
+            st6, 20,
             htxf6, txcf6, errf6,
             chtxf6, ctxcf6, cglbf6, cerrf6
         ),
         C7: (
-            st7,
+            st7, 24,
             htxf7, txcf7, errf7,
             chtxf7, ctxcf7, cglbf7, cerrf7
         ),
         C8: (
-            st8,
+            st8, 28,
             htxf8, txcf8, errf8,
             chtxf8, ctxcf8, cglbf8, cerrf8
         ),
     }),
 
-    Dma2: (dma2, {
+    Dma2: (dma2, 1, {
         C1: (
-            st1,
+            st1, 0,
             htxf1, txcf1, errf1,
             chtxf1, ctxcf1, cglbf1, cerrf1
         ),
         C2: (
-            st2,
+            st2, 4,
             htxf2, txcf2, errf2,
             chtxf2, ctxcf2, cglbf2, cerrf2
         ),
         C3: (
-            st3,
+            st3, 8,
             htxf3, txcf3, errf3,
             chtxf3, ctxcf3, cglbf3, cerrf3
         ),
         C4: (
-            st4,
+            st4, 12,
             htxf4, txcf4, errf4,
             chtxf4, ctxcf4, cglbf4, cerrf4
         ),
         C5: (
-            st5,
+            st5, 16,
             htxf5, txcf5, errf5,
             chtxf5, ctxcf5, cglbf5, cerrf5
         ),
         C6: (
-            st6,
+            st6, 20,
             htxf6, txcf6, errf6,
             chtxf6, ctxcf6, cglbf6, cerrf6
         ),
         C7: (
-            st7,
+            st7, 24,
             htxf7, txcf7, errf7,
             chtxf7, ctxcf7, cglbf7, cerrf7
         ),
         C8: (
-            st8,
+            st8, 28,
             htxf8, txcf8, errf8,
             chtxf8, ctxcf8, cglbf8, cerrf8
         ),
@@ -668,7 +1045,19 @@ where
     B: 'static,
     Self: core::marker::Sized,
 {
-    fn circ_read(self, buffer: &'static mut [B; 2]) -> CircBuffer<B, Self>;
+    /// Panics if the buffer exceeds [MAX_TRANSFER_LENGTH]
+    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on long buffers, use try_circ_read"))]
+    fn circ_read(self, buffer: &'static mut [B; 2]) -> CircBuffer<B, Self> {
+        match self.try_circ_read(buffer) {
+            Ok(circ_buffer) => circ_buffer,
+            Err(_) => panic!("DMA transfer longer than 65535 elements"),
+        }
+    }
+
+    /// Like [circ_read](Self::circ_read), but returns [Error::BufferTooLong] along with the
+    /// payload and the buffer instead of panicking
+    #[allow(clippy::type_complexity)]
+    fn try_circ_read(self, buffer: &'static mut [B; 2]) -> Result<CircBuffer<B, Self>, (Error, Self, &'static mut [B; 2])>;
 }
 
 /// Trait for DMA readings from peripheral to memory.
@@ -677,7 +1066,19 @@ where
     B: WriteBuffer<Word = RS>,
     Self: core::marker::Sized + TransferPayload,
 {
-    fn read(self, buffer: B) -> Transfer<W, B, Self>;
+    /// Panics if the buffer exceeds [MAX_TRANSFER_LENGTH]
+    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on long buffers, use try_read"))]
+    fn read(self, buffer: B) -> Transfer<W, B, Self> {
+        match self.try_read(buffer) {
+            Ok(transfer) => transfer,
+            Err(_) => panic!("DMA transfer longer than 65535 elements"),
+        }
+    }
+
+    /// Like [read](Self::read), but returns [Error::BufferTooLong] along with the payload and
+    /// the buffer instead of panicking
+    #[allow(clippy::type_complexity)]
+    fn try_read(self, buffer: B) -> Result<Transfer<W, B, Self>, (Error, Self, B)>;
 }
 
 /// Trait for DMA writing from memory to peripheral.
@@ -686,17 +1087,46 @@ where
     B: ReadBuffer<Word = TS>,
     Self: core::marker::Sized + TransferPayload,
 {
-    fn write(self, buffer: B) -> Transfer<R, B, Self>;
+    /// Panics if the buffer exceeds [MAX_TRANSFER_LENGTH]
+    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on long buffers, use try_write"))]
+    fn write(self, buffer: B) -> Transfer<R, B, Self> {
+        match self.try_write(buffer) {
+            Ok(transfer) => transfer,
+            Err(_) => panic!("DMA transfer longer than 65535 elements"),
+        }
+    }
+
+    /// Like [write](Self::write), but returns [Error::BufferTooLong] along with the payload and
+    /// the buffer instead of panicking
+    #[allow(clippy::type_complexity)]
+    fn try_write(self, buffer: B) -> Result<Transfer<R, B, Self>, (Error, Self, B)>;
 }
 
-/// Trait for DMA simultaneously reading and writing within one synchronous operation. Panics if both buffers are not of equal length.
+/// Trait for DMA simultaneously reading and writing within one synchronous operation.
 pub trait ReadWriteDma<RXB, TXB, TS>: Transmit
 where
     RXB: WriteBuffer<Word = TS>,
     TXB: ReadBuffer<Word = TS>,
     Self: core::marker::Sized + TransferPayload,
 {
-    fn read_write(self, rx_buffer: RXB, tx_buffer: TXB) -> Transfer<W, (RXB, TXB), Self>;
+    /// Panics if both buffers are not of equal length, or exceed [MAX_TRANSFER_LENGTH]
+    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on mismatched or long buffers, use try_read_write"))]
+    fn read_write(self, rx_buffer: RXB, tx_buffer: TXB) -> Transfer<W, (RXB, TXB), Self> {
+        match self.try_read_write(rx_buffer, tx_buffer) {
+            Ok(transfer) => transfer,
+            Err((Error::LengthMismatch, ..)) => panic!("receive and send buffer lengths do not match!"),
+            Err(_) => panic!("DMA transfer longer than 65535 elements"),
+        }
+    }
+
+    /// Like [read_write](Self::read_write), but returns [Error::LengthMismatch] or
+    /// [Error::BufferTooLong] along with the payload and the buffers instead of panicking
+    #[allow(clippy::type_complexity)]
+    fn try_read_write(
+        self,
+        rx_buffer: RXB,
+        tx_buffer: TXB,
+    ) -> Result<Transfer<W, (RXB, TXB), Self>, (Error, Self, (RXB, TXB))>;
 }
 
 pub trait DMAMode {}
@@ -708,4 +1138,19 @@ where MODE : DMAMode {
 }
 
 #[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
-pub mod chmap;
\ No newline at end of file
+pub mod chmap;
+
+pub mod buffers;
+pub use buffers::{DmaBuffer, UninitBuffer};
+pub use crate::dma_buffers as buffers;
+
+pub mod priority;
+pub use priority::{Priority, Profile, Role};
+
+pub mod scoped;
+pub use scoped::ScopedBuffer;
+
+#[cfg(feature = "dma-metrics")]
+mod metrics;
+#[cfg(feature = "dma-metrics")]
+pub use metrics::{metrics, reset_metrics, ChannelMetrics, Metrics};
\ No newline at end of file
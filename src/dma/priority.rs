@@ -0,0 +1,86 @@
+//! Channel priorities
+//!
+//! Each DMA controller arbitrates between its channels with pending requests one word at a
+//! time: the channel with the highest [Priority] wins, and among channels of the same
+//! priority the lowest channel number wins. So a busy channel of higher priority, or of a lower
+//! number at the same priority, delays the others for as long as it keeps requesting. The two
+//! controllers arbitrate independently and share the bus matrix with the CPU in round robin.
+//!
+//! The drivers program a default priority, medium for peripheral transfers and high for the
+//! timer paced ones, whenever they start a transfer. [DMAChannel::set_priority] overrides it
+//! for the channel, and a [Profile] picks consistent priorities for the channels of an
+//! application by their [Role].
+//!
+//! ```rust
+//! let mut dma = dp.Dma1.split();
+//! let profile = Profile::AudioStreaming;
+//! profile.assign(&mut dma.5, Role::Stream); // I2S samples
+//! profile.assign(&mut dma.1, Role::Control); // ADC readings
+//! profile.assign(&mut dma.4, Role::Bulk); // UART logging
+//! ```
+
+use super::DMAChannel;
+
+/// Arbitration priority of a channel (`PRIOLVL`)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low = 0,
+    Medium = 1,
+    High = 2,
+    VeryHigh = 3,
+}
+
+impl Priority {
+    pub(crate) fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Priority::Low,
+            1 => Priority::Medium,
+            2 => Priority::High,
+            _ => Priority::VeryHigh,
+        }
+    }
+}
+
+/// What a channel is used for, ranked by a [Profile]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Continuous stream that underruns or overruns if delayed, e.g. audio or DAC playback
+    Stream,
+    /// Short transfers a control loop waits on, e.g. ADC sampling or PWM duty updates
+    Control,
+    /// Transfers that only need throughput, e.g. logging, displays or memory copies
+    Bulk,
+}
+
+/// Channel priorities for a kind of application
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// Streams first, as a late sample is audible, then control transfers
+    AudioStreaming,
+    /// Control transfers first, as they bound the loop latency, then streams
+    ControlLoop,
+    /// Every channel at medium priority, the lowest channel number wins
+    Balanced,
+}
+
+impl Profile {
+    /// Priority of channels with `role`
+    pub fn priority(self, role: Role) -> Priority {
+        match (self, role) {
+            (Profile::AudioStreaming, Role::Stream) => Priority::VeryHigh,
+            (Profile::AudioStreaming, Role::Control) => Priority::High,
+            (Profile::ControlLoop, Role::Control) => Priority::VeryHigh,
+            (Profile::ControlLoop, Role::Stream) => Priority::High,
+            (Profile::Balanced, _) => Priority::Medium,
+            (_, Role::Bulk) => Priority::Low,
+        }
+    }
+
+    /// Sets the priority of `channel` for its `role`
+    pub fn assign<CH: DMAChannel>(self, channel: &mut CH, role: Role) {
+        channel.set_priority(self.priority(role));
+    }
+}
@@ -0,0 +1,153 @@
+//! # Transfers of borrowed buffers
+//!
+//! [ReadDma] and friends take ownership of their buffer, and with plain references only of
+//! `&'static mut` ones: the DMA would keep writing to a stack buffer after it went out of
+//! scope if the [Transfer] was leaked with `mem::forget`. The scoped transfers lend the
+//! transfer to a closure instead, by shared reference, so it can't be leaked, and stop it
+//! when the closure returns. The buffer is borrowed for the call only:
+//!
+//! ```rust
+//! let mut frame = [0u8; 64];
+//! let (received, rx) = Transfer::scoped_read(rx, &mut frame, |xfer| {
+//!     while !xfer.is_done() {
+//!         if idle_line() {
+//!             break;
+//!         }
+//!     }
+//!     xfer.peek::<u8>().len()
+//! })
+//! .map_err(|(e, _rx)| e)?;
+//! // `frame` is no longer borrowed here, the channel is stopped
+//! ```
+//!
+//! When the closure returns before the transfer is done, the channel is stopped and the
+//! rest of the buffer keeps its old contents. A panic in the closure stops the channel too.
+
+use core::marker::PhantomData;
+use core::slice;
+
+use embedded_dma::{ReadBuffer, WriteBuffer};
+
+use super::{Error, ReadDma, ReadWriteDma, Transfer, TransferPayload, WriteDma, R, W};
+
+/// Buffer lent to a scoped [Transfer]
+///
+/// Only the scoped constructors of [Transfer] make these, which guarantee that the DMA
+/// stopped before the borrow ends.
+pub struct ScopedBuffer<'b, T> {
+    ptr: *mut T,
+    len: usize,
+    _borrow: PhantomData<&'b mut [T]>,
+}
+
+impl<'b, T> ScopedBuffer<'b, T> {
+    fn new(buffer: &'b mut [T]) -> Self {
+        Self {
+            ptr: buffer.as_mut_ptr(),
+            len: buffer.len(),
+            _borrow: PhantomData,
+        }
+    }
+
+    /// Lent to transmit transfers, which only use it as [ReadBuffer]
+    fn shared(buffer: &'b [T]) -> Self {
+        Self {
+            ptr: buffer.as_ptr() as *mut T,
+            len: buffer.len(),
+            _borrow: PhantomData,
+        }
+    }
+}
+
+// NOTE(unsafe) the buffer is borrowed until the scoped transfer stopped the channel
+unsafe impl<'b, T> WriteBuffer for ScopedBuffer<'b, T> {
+    type Word = T;
+
+    unsafe fn write_buffer(&mut self) -> (*mut T, usize) {
+        (self.ptr, self.len)
+    }
+}
+
+unsafe impl<'b, T> ReadBuffer for ScopedBuffer<'b, T> {
+    type Word = T;
+
+    unsafe fn read_buffer(&self) -> (*const T, usize) {
+        (self.ptr, self.len)
+    }
+}
+
+impl<'b, T> AsRef<[T]> for ScopedBuffer<'b, T> {
+    fn as_ref(&self) -> &[T] {
+        // NOTE(unsafe) like `peek` on a static buffer, the DMA only writes past the part
+        // returned by `peek`
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'b, T, PAYLOAD> Transfer<W, ScopedBuffer<'b, T>, PAYLOAD>
+where
+    PAYLOAD: ReadDma<ScopedBuffer<'b, T>, T> + TransferPayload,
+{
+    /// Receives into `buffer` while `f` runs, and stops the channel when it returns
+    ///
+    /// Fails like [ReadDma::try_read] when the buffer is too long.
+    pub fn scoped_read<F, O>(payload: PAYLOAD, buffer: &'b mut [T], f: F) -> Result<(O, PAYLOAD), (Error, PAYLOAD)>
+    where
+        F: FnOnce(&Transfer<W, ScopedBuffer<'b, T>, PAYLOAD>) -> O,
+    {
+        let transfer = payload
+            .try_read(ScopedBuffer::new(buffer))
+            .map_err(|(e, payload, _)| (e, payload))?;
+        let output = f(&transfer);
+        let (_, payload) = transfer.finish();
+        Ok((output, payload))
+    }
+}
+
+impl<'b, T, PAYLOAD> Transfer<R, ScopedBuffer<'b, T>, PAYLOAD>
+where
+    PAYLOAD: WriteDma<ScopedBuffer<'b, T>, T> + TransferPayload,
+{
+    /// Transmits `buffer` while `f` runs, and stops the channel when it returns
+    ///
+    /// Fails like [WriteDma::try_write] when the buffer is too long.
+    pub fn scoped_write<F, O>(payload: PAYLOAD, buffer: &'b [T], f: F) -> Result<(O, PAYLOAD), (Error, PAYLOAD)>
+    where
+        F: FnOnce(&Transfer<R, ScopedBuffer<'b, T>, PAYLOAD>) -> O,
+    {
+        let transfer = payload
+            .try_write(ScopedBuffer::shared(buffer))
+            .map_err(|(e, payload, _)| (e, payload))?;
+        let output = f(&transfer);
+        let (_, payload) = transfer.finish();
+        Ok((output, payload))
+    }
+}
+
+impl<'b, T, PAYLOAD> Transfer<W, (ScopedBuffer<'b, T>, ScopedBuffer<'b, T>), PAYLOAD>
+where
+    PAYLOAD: ReadWriteDma<ScopedBuffer<'b, T>, ScopedBuffer<'b, T>, T> + TransferPayload,
+{
+    /// Receives into `rx_buffer` while transmitting `tx_buffer` as long as `f` runs, and stops
+    /// the channels when it returns
+    ///
+    /// Fails like [ReadWriteDma::try_read_write] when the buffers differ in length or are too
+    /// long.
+    #[allow(clippy::type_complexity)]
+    pub fn scoped_read_write<F, O>(
+        payload: PAYLOAD,
+        rx_buffer: &'b mut [T],
+        tx_buffer: &'b [T],
+        f: F,
+    ) -> Result<(O, PAYLOAD), (Error, PAYLOAD)>
+    where
+        F: FnOnce(&Transfer<W, (ScopedBuffer<'b, T>, ScopedBuffer<'b, T>), PAYLOAD>) -> O,
+    {
+        let transfer = payload
+            .try_read_write(ScopedBuffer::new(rx_buffer), ScopedBuffer::shared(tx_buffer))
+            .map_err(|(e, payload, _)| (e, payload))?;
+        let output = f(&transfer);
+        let (_, payload) = transfer.finish();
+        Ok((output, payload))
+    }
+}
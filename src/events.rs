@@ -0,0 +1,87 @@
+//! # Event latches
+//!
+//! An [EventLatch] is a static set of flags of a peripheral that interrupt handlers set and
+//! the main loop drains, without a critical section or a `Mutex<RefCell<...>>`. Setting and
+//! taking flags are single atomic operations, so any number of interrupt priorities can set
+//! flags while the main loop takes them, and no flag set in between is lost.
+//!
+//! The latch holds the same [ReadFlags](crate::ReadFlags) enums the drivers report, so an
+//! interrupt handler can copy the flags of its peripheral as they are.
+//!
+//! ```rust
+//! static USART1_EVENTS: EventLatch<serial::Flag> = EventLatch::new();
+//!
+//! // USART1 interrupt
+//! USART1_EVENTS.latch(&serial);
+//! serial.clear_flags(serial::CFlag::TransmissionComplete);
+//!
+//! // main loop
+//! if USART1_EVENTS.take().contains(serial::Flag::TransmissionComplete) {
+//!     // start the next message
+//! }
+//! ```
+
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use enumflags2::{BitFlag, BitFlags};
+
+use crate::ReadFlags;
+
+/// Flags of type `F` set from interrupt handlers and taken by the main loop
+pub struct EventLatch<F> {
+    bits: AtomicU32,
+    _flag: PhantomData<F>,
+}
+
+impl<F: BitFlag<Numeric = u32>> EventLatch<F> {
+    /// Creates a latch with no flag set
+    pub const fn new() -> Self {
+        Self {
+            bits: AtomicU32::new(0),
+            _flag: PhantomData,
+        }
+    }
+
+    /// Sets `flags`, keeping the ones already set
+    pub fn set(&self, flags: impl Into<BitFlags<F>>) {
+        self.bits.fetch_or(flags.into().bits(), Ordering::Release);
+    }
+
+    /// Sets the flags `peripheral` currently reports and returns them
+    ///
+    /// The flags of the peripheral itself are left as they are, clear them afterwards if
+    /// they would retrigger the interrupt.
+    pub fn latch<P: ReadFlags<Flag = F>>(&self, peripheral: &P) -> BitFlags<F> {
+        let flags = peripheral.flags();
+        self.set(flags);
+        flags
+    }
+
+    /// Takes all the flags set, clearing them
+    pub fn take(&self) -> BitFlags<F> {
+        BitFlags::from_bits_truncate(self.bits.swap(0, Ordering::Acquire))
+    }
+
+    /// Takes the flags among `flags` that are set, clearing only these
+    pub fn take_only(&self, flags: impl Into<BitFlags<F>>) -> BitFlags<F> {
+        let mask = flags.into().bits();
+        BitFlags::from_bits_truncate(self.bits.fetch_and(!mask, Ordering::Acquire) & mask)
+    }
+
+    /// Returns the flags set, without clearing them
+    pub fn peek(&self) -> BitFlags<F> {
+        BitFlags::from_bits_truncate(self.bits.load(Ordering::Acquire))
+    }
+
+    /// Returns `true` if any flag is set
+    pub fn is_pending(&self) -> bool {
+        self.bits.load(Ordering::Relaxed) != 0
+    }
+}
+
+impl<F: BitFlag<Numeric = u32>> Default for EventLatch<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
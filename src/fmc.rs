@@ -1,8 +1,13 @@
+use core::marker::PhantomData;
+
 use crate::pac::{flash, Flash as Fmc};
+use crate::time::Hertz;
 use embedded_storage::nor_flash::{
     ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
 };
 
+pub mod ram;
+
 pub trait FMCExt {
     /// Constrains the FLASH peripheral to play nicely with the other abstractions
     fn constrain(self) -> Flash;
@@ -17,7 +22,7 @@ impl FMCExt for Fmc {
 pub struct Flash;
 
 impl Flash {
-    const FLASH_BASE: u32 = 0x0800_0000;
+    pub(crate) const FLASH_BASE: u32 = 0x0800_0000;
     fn max_addr() -> u32 {
         Flash::FLASH_BASE + (Flash::capacity() as u32) - 1
     }
@@ -51,29 +56,20 @@ impl Flash {
 
     fn program_word(&mut self, offset: u32, word: u32) {
         let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
-        while fmc.sts().read().busy().bit_is_set() {}
-        fmc.ctrl().modify(|_, w| w.pg().set_bit());
-        let write_ptr = unsafe { core::mem::transmute::<usize,*mut u32>((Flash::FLASH_BASE + offset) as usize) };
-        unsafe { core::ptr::write_volatile(write_ptr, word); }
-        while fmc.sts().read().busy().bit_is_set() {}
-        fmc.ctrl().modify(|_, w| w.pg().clear_bit());
+        let address = (Flash::FLASH_BASE + offset) as *mut u32;
+        // NOTE(unsafe) the flash is unlocked and the offset checked by the caller
+        unsafe { ram::program_word(fmc.ctrl().as_ptr(), fmc.sts().as_ptr(), address, word) };
     }
 
     fn erase_page(&mut self, offset: u32) {
         let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
-        while fmc.sts().read().busy().bit_is_set() {}
         let erase_addr = Flash::FLASH_BASE + offset;
-
-        fmc.ctrl().modify(|_, w| w.per().set_bit());
-        unsafe { fmc.addr().write(|w| w.fadd().bits(erase_addr)); }
-        fmc.ctrl().modify(|_, w| w.start().set_bit());
-        cortex_m::asm::dsb();
-        cortex_m::asm::isb();
-        while fmc.sts().read().busy().bit_is_set() {}
-        fmc.ctrl().modify(|_, w| w.per().clear_bit());
+        // NOTE(unsafe) the flash is unlocked by the caller
+        unsafe { ram::erase_page(fmc.ctrl().as_ptr(), fmc.sts().as_ptr(), fmc.addr().as_ptr(), erase_addr) };
     }
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FlashError {
     WriteProtected,
@@ -220,4 +216,370 @@ impl embedded_storage_async::nor_flash::ReadNorFlash for Flash {
     fn capacity(&self) -> usize {
         Flash::capacity()
     }
-}
\ No newline at end of file
+}
+
+/// Read protection level
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadProtection {
+    /// No read protection
+    Level0,
+    /// Flash can't be read through the debug interface or from RAM.
+    /// Going back to [ReadProtection::Level0] mass erases the flash.
+    Level1,
+}
+
+/// Contents of the user option bytes
+///
+/// Level 2 read protection is permanent and deliberately not exposed, and this
+/// family has no brown-out level in the option bytes.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionBytes {
+    /// Read protection level
+    pub read_protection: ReadProtection,
+    /// User option byte (watchdog and reset configuration)
+    pub user: u8,
+    /// User data bytes
+    pub data: [u8; 2],
+    /// Write protection, one bit per group of pages. A cleared bit protects the group.
+    pub write_protection: u32,
+}
+
+/// Option bytes unlocked for programming, see [Flash::unlock_option_bytes]
+///
+/// Dropping it locks the option bytes and the flash again.
+pub struct OptionBytesUnlocked<'a> {
+    flash: &'a mut Flash,
+}
+
+const OPTION_BYTES_BASE: u32 = 0x1FFF_F800;
+const RDP_KEY: u16 = 0xA5;
+
+impl Flash {
+    /// Reads the option bytes currently in effect
+    pub fn option_bytes(&self) -> OptionBytes {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        let ob = fmc.ob().read();
+        OptionBytes {
+            read_protection: if ob.rdprt1().bit_is_set() {
+                ReadProtection::Level1
+            } else {
+                ReadProtection::Level0
+            },
+            // the user option bits follow RDPRT1
+            user: (ob.bits() >> 2) as u8,
+            data: [ob.data0().bits(), ob.data1().bits()],
+            write_protection: fmc.wrp().read().bits(),
+        }
+    }
+
+    /// Returns the read protection level currently in effect
+    pub fn read_protection(&self) -> ReadProtection {
+        self.option_bytes().read_protection
+    }
+
+    /// First step to change the option bytes: unlocks the flash and option byte write access
+    ///
+    /// # Warning
+    /// Wrong option bytes can make the device unusable: enabling read protection blocks the
+    /// debugger, and removing it again mass erases the flash. Double check the values passed
+    /// to [OptionBytesUnlocked::program].
+    pub fn unlock_option_bytes(&mut self) -> OptionBytesUnlocked<'_> {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        self.unlock();
+        if fmc.ctrl().read().optwe().bit_is_clear() {
+            fmc.optkey().write(|w| unsafe { w.bits(0x45670123) });
+            fmc.optkey().write(|w| unsafe { w.bits(0xCDEF89AB) });
+        }
+        OptionBytesUnlocked { flash: self }
+    }
+}
+
+impl OptionBytesUnlocked<'_> {
+    fn program_half_word(fmc: &flash::RegisterBlock, offset: u32, value: u16) -> Result<(), FlashError> {
+        fmc.ctrl().modify(|_, w| w.optpg().set_bit());
+        let ptr = (OPTION_BYTES_BASE + offset) as *mut u16;
+        unsafe { core::ptr::write_volatile(ptr, value) };
+        while fmc.sts().read().busy().bit_is_set() {}
+        fmc.ctrl().modify(|_, w| w.optpg().clear_bit());
+
+        if unsafe { core::ptr::read_volatile(ptr) } & 0xff != value {
+            return Err(FlashError::ProgramError);
+        }
+        Ok(())
+    }
+
+    /// Second step: erases and reprograms all option bytes, then locks the flash again
+    ///
+    /// The new values take effect after the next reset.
+    pub fn program(self, ob: OptionBytes) -> Result<(), FlashError> {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        if fmc.ctrl().read().optwe().bit_is_clear() {
+            return Err(FlashError::WriteProtected);
+        }
+
+        while fmc.sts().read().busy().bit_is_set() {}
+        fmc.ctrl().modify(|_, w| w.opter().set_bit());
+        fmc.ctrl().modify(|_, w| w.start().set_bit());
+        while fmc.sts().read().busy().bit_is_set() {}
+        fmc.ctrl().modify(|_, w| w.opter().clear_bit());
+
+        let rdp = match ob.read_protection {
+            ReadProtection::Level0 => RDP_KEY,
+            ReadProtection::Level1 => 0x00,
+        };
+        let result = Self::program_half_word(fmc, 0x0, rdp)
+            .and_then(|_| Self::program_half_word(fmc, 0x2, ob.user as u16))
+            .and_then(|_| Self::program_half_word(fmc, 0x4, ob.data[0] as u16))
+            .and_then(|_| Self::program_half_word(fmc, 0x6, ob.data[1] as u16))
+            .and_then(|_| {
+                (0..4).try_for_each(|i| {
+                    Self::program_half_word(fmc, 0x8 + 2 * i, ((ob.write_protection >> (8 * i)) & 0xff) as u16)
+                })
+            });
+
+        // locked again when self is dropped
+        result
+    }
+}
+
+impl Drop for OptionBytesUnlocked<'_> {
+    fn drop(&mut self) {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ctrl().modify(|_, w| w.optwe().clear_bit());
+        self.flash.lock();
+    }
+}
+
+/// System clock frequency each flash wait state allows
+const LATENCY_STEP: u32 = 24_000_000;
+
+/// Number of flash wait states needed at `sysclk`
+pub fn required_latency(sysclk: Hertz) -> u8 {
+    (sysclk.raw().saturating_sub(1) / LATENCY_STEP) as u8
+}
+
+/// Configured flash wait states are too few for the system clock
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyError {
+    /// Wait states needed at the system clock
+    pub required: u8,
+    /// Wait states configured
+    pub configured: u8,
+}
+
+/// Flash access control: wait states, prefetch buffer and instruction cache
+///
+/// [CFGR::freeze](crate::rcc::CFGR::freeze) sets the wait states for the system clock and
+/// enables the prefetch buffer and the instruction cache, unless configured otherwise with
+/// the `flash_*` methods of the [CFGR](crate::rcc::CFGR).
+pub struct Acr<'a> {
+    _flash: PhantomData<&'a mut Flash>,
+}
+
+impl Flash {
+    /// Gives access to the flash access control register
+    pub fn acr(&mut self) -> Acr<'_> {
+        Acr { _flash: PhantomData }
+    }
+}
+
+impl Acr<'_> {
+    pub(crate) fn configure(latency: u8, prefetch: bool, icache: bool) {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().modify(|_, w| unsafe {
+            w.latency().bits(latency);
+            w.prftbfe().bit(prefetch);
+            w.icahen().bit(icache)
+        });
+    }
+
+    /// Returns the number of wait states
+    pub fn latency(&self) -> u8 {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().read().latency().bits()
+    }
+
+    /// Sets the number of wait states
+    ///
+    /// Running with fewer wait states than [required_latency] reads corrupted instructions
+    /// and data, check the value with [verify_latency](Self::verify_latency).
+    pub fn set_latency(&mut self, latency: u8) {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().modify(|_, w| unsafe { w.latency().bits(latency) });
+    }
+
+    /// Checks that the configured wait states are enough for `sysclk`
+    pub fn verify_latency(&self, sysclk: Hertz) -> Result<(), LatencyError> {
+        let required = required_latency(sysclk);
+        let configured = self.latency();
+        if configured < required {
+            return Err(LatencyError { required, configured });
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the prefetch buffer is enabled
+    pub fn is_prefetch_enabled(&self) -> bool {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().read().prftbfe().bit_is_set()
+    }
+
+    /// Enables or disables the prefetch buffer
+    pub fn set_prefetch(&mut self, enable: bool) {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().modify(|_, w| w.prftbfe().bit(enable));
+    }
+
+    /// Returns `true` if the instruction cache is enabled
+    pub fn is_icache_enabled(&self) -> bool {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().read().icahen().bit_is_set()
+    }
+
+    /// Enables or disables the instruction cache
+    pub fn set_icache(&mut self, enable: bool) {
+        let fmc: &flash::RegisterBlock = unsafe { &(*Fmc::ptr()) };
+        fmc.ac().modify(|_, w| w.icahen().bit(enable));
+    }
+}
+
+/// Firmware slot of an [AbLayout]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    /// The other slot
+    pub fn other(self) -> Slot {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+}
+
+/// Marks a record of the state page, the low bit holds the slot
+const SLOT_RECORD: u32 = 0xA5A5_0000;
+
+/// A/B firmware slots over the single flash bank
+///
+/// The flash of this family is a single bank without bank swapping in the option bytes, and
+/// the CPU stalls on any flash read while a page is erased or programmed: there is no
+/// read-while-write, so keep the erase and program calls short if interrupts have deadlines.
+///
+/// The layout splits the flash in two slots of the same size and a state page recording
+/// which slot is active. A bootloader reads [active](Self::active) and jumps to that slot,
+/// the application writes the update to the [inactive](Self::inactive) slot and then calls
+/// [swap](Self::swap). Each swap appends a word to the state page, so an interrupted swap
+/// leaves the previous slot active, except every 512th swap which erases the full page first
+/// and falls back to [Slot::A] if interrupted.
+///
+/// ```rust
+/// const LAYOUT: AbLayout = AbLayout::new(0x4000, 0x3_0000, 0x2_c000, 0x7_f800);
+///
+/// let mut flash = dp.Flash.constrain();
+/// LAYOUT.erase_inactive(&mut flash)?;
+/// LAYOUT.write_inactive(&mut flash, 0, &image)?;
+/// LAYOUT.swap(&mut flash)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbLayout {
+    a: u32,
+    b: u32,
+    size: u32,
+    state: u32,
+}
+
+impl AbLayout {
+    /// Slots at the flash offsets `a` and `b` of `size` bytes, and the state page at `state`
+    ///
+    /// Panics if an offset or the size isn't a multiple of the page size, or if the slots
+    /// and the state page overlap.
+    pub const fn new(a: u32, b: u32, size: u32, state: u32) -> Self {
+        let page = Flash::ERASE_SIZE as u32;
+        assert!(a % page == 0 && b % page == 0 && size % page == 0 && state % page == 0);
+        assert!(a + size <= b || b + size <= a);
+        assert!(state + page <= a || a + size <= state);
+        assert!(state + page <= b || b + size <= state);
+        Self { a, b, size, state }
+    }
+
+    /// Flash offset of `slot`
+    pub fn offset(&self, slot: Slot) -> u32 {
+        match slot {
+            Slot::A => self.a,
+            Slot::B => self.b,
+        }
+    }
+
+    /// Size of each slot in bytes
+    pub fn slot_size(&self) -> u32 {
+        self.size
+    }
+
+    /// Index of the first free word of the state page and the slot of the last record
+    fn scan_state(&self, flash: &mut Flash) -> Result<(u32, Option<Slot>), FlashError> {
+        let mut active = None;
+        let mut index = 0;
+        while index < (Flash::ERASE_SIZE / 4) as u32 {
+            let mut word = [0; 4];
+            flash.read(self.state + index * 4, &mut word)?;
+            let word = u32::from_ne_bytes(word);
+            if word == u32::MAX {
+                break;
+            }
+            // Anything else is a record whose programming was interrupted
+            if word & !1 == SLOT_RECORD {
+                active = Some(if word & 1 == 0 { Slot::A } else { Slot::B });
+            }
+            index += 1;
+        }
+        Ok((index, active))
+    }
+
+    /// Slot to boot, [Slot::A] until the first swap
+    pub fn active(&self, flash: &mut Flash) -> Result<Slot, FlashError> {
+        Ok(self.scan_state(flash)?.1.unwrap_or(Slot::A))
+    }
+
+    /// Slot updates are written to
+    pub fn inactive(&self, flash: &mut Flash) -> Result<Slot, FlashError> {
+        Ok(self.active(flash)?.other())
+    }
+
+    /// Erases the inactive slot
+    pub fn erase_inactive(&self, flash: &mut Flash) -> Result<(), FlashError> {
+        let offset = self.offset(self.inactive(flash)?);
+        flash.erase(offset, offset + self.size)
+    }
+
+    /// Writes `bytes` at `offset` into the inactive slot
+    pub fn write_inactive(&self, flash: &mut Flash, offset: u32, bytes: &[u8]) -> Result<(), FlashError> {
+        if offset as usize + bytes.len() > self.size as usize {
+            return Err(FlashError::OutOfBounds);
+        }
+        let slot = self.offset(self.inactive(flash)?);
+        flash.write(slot + offset, bytes)
+    }
+
+    /// Makes the inactive slot active from the next boot on, and returns it
+    pub fn swap(&self, flash: &mut Flash) -> Result<Slot, FlashError> {
+        let (index, active) = self.scan_state(flash)?;
+        let slot = active.unwrap_or(Slot::A).other();
+        let index = if index == (Flash::ERASE_SIZE / 4) as u32 {
+            flash.erase(self.state, self.state + Flash::ERASE_SIZE as u32)?;
+            0
+        } else {
+            index
+        };
+        let record = SLOT_RECORD | (slot == Slot::B) as u32;
+        flash.write(self.state + index * 4, &record.to_ne_bytes())?;
+        Ok(slot)
+    }
+}
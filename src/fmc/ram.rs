@@ -0,0 +1,156 @@
+//! # Vector table relocation and RAM functions
+//!
+//! While the flash controller programs or erases a page, every fetch from the flash stalls
+//! until it's done, up to a few tens of milliseconds for a page erase. That includes the
+//! vector fetch and the code of any interrupt handler, so a single program or erase freezes
+//! interrupts as well as the caller.
+//!
+//! Interrupts keep being served during flash operations when the vector table, the handlers
+//! that must keep running and everything they call live in RAM:
+//!
+//! ```rust
+//! static mut VECTORS: VectorTable<{ ram::VECTORS }> = VectorTable::new();
+//!
+//! ram::relocate_vector_table(&mut cp.SCB, unsafe { &mut VECTORS });
+//!
+//! n32g4xx_hal::ramfunc! {
+//!     fn on_tick() {
+//!         // only touches registers and RAM
+//!     }
+//! }
+//! ```
+//!
+//! [Flash](super::Flash) itself waits for the controller from RAM, so programming works the
+//! same whether the vector table was relocated or not. RAM functions are copied with `.data`
+//! at startup, build with optimizations so that the register accesses are inlined into them.
+
+use cortex_m::peripheral::SCB;
+
+/// Entries of the vector table: the initial stack pointer, 15 exceptions and the interrupts
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+pub const VECTORS: usize = 16 + 81;
+/// Entries of the vector table: the initial stack pointer, 15 exceptions and the interrupts
+#[cfg(not(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr")))]
+pub const VECTORS: usize = 16 + 64;
+
+// FMC_CTRL
+const CTRL_PG: u32 = 1 << 0;
+const CTRL_PER: u32 = 1 << 1;
+const CTRL_START: u32 = 1 << 6;
+// FMC_STS
+const STS_BUSY: u32 = 1 << 0;
+
+/// Vector table in RAM, see [relocate_vector_table]
+///
+/// VTOR needs the table aligned to its size rounded up to a power of two, 512 bytes cover
+/// up to 128 entries.
+#[repr(C, align(512))]
+pub struct VectorTable<const N: usize>([u32; N]);
+
+impl<const N: usize> VectorTable<N> {
+    /// Fails the build of [relocate_vector_table] for a table shorter than [VECTORS]
+    const COVERS_DEVICE: () = assert!(N >= VECTORS, "the vector table needs at least ram::VECTORS entries");
+
+    /// Creates an empty table, filled by [relocate_vector_table]
+    pub const fn new() -> Self {
+        assert!(N <= 128);
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> Default for VectorTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copies the `N` entries of the active vector table into `table` and points VTOR to it
+///
+/// `N` has to be at least [VECTORS], a shorter table doesn't build.
+///
+/// The handlers stay where they are, the table only stops the vector fetch from stalling on
+/// the flash: place the handlers that must run during flash operations in RAM with
+/// [ramfunc](crate::ramfunc).
+pub fn relocate_vector_table<const N: usize>(scb: &mut SCB, table: &'static mut VectorTable<N>) {
+    #[allow(clippy::let_unit_value)]
+    let () = VectorTable::<N>::COVERS_DEVICE;
+    cortex_m::interrupt::free(|_| {
+        let current = scb.vtor.read() as *const u32;
+        for (i, entry) in table.0.iter_mut().enumerate() {
+            // NOTE(unsafe) the active table has at least the entries of the device
+            *entry = unsafe { core::ptr::read_volatile(current.add(i)) };
+        }
+        cortex_m::asm::dsb();
+        // NOTE(unsafe) the table is 'static and a copy of the active one
+        unsafe { scb.vtor.write(table.0.as_ptr() as u32) };
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    });
+}
+
+/// Places functions in RAM
+///
+/// The functions are linked into `.data`, copied to RAM at startup and never inlined into
+/// code running from the flash. Anything they call has to be in RAM too, or inlined.
+///
+/// ```rust
+/// n32g4xx_hal::ramfunc! {
+///     /// Runs while the flash is busy
+///     pub fn toggle_led() {
+///         // NOTE(unsafe) atomic write to a stateless register
+///         unsafe { (*pac::Gpioc::ptr()).pbsc().write(|w| w.bits(1 << 13)) };
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! ramfunc {
+    (@split $(#[$attr:meta])* $vis:vis unsafe fn $name:ident $args:tt $(-> $ret:ty)? $body:block $($tail:tt)*) => {
+        $(#[$attr])*
+        #[link_section = ".data.ramfunc"]
+        #[inline(never)]
+        $vis unsafe fn $name $args $(-> $ret)? $body
+        $crate::ramfunc!(@split $($tail)*);
+    };
+    (@split $(#[$attr:meta])* $vis:vis fn $name:ident $args:tt $(-> $ret:ty)? $body:block $($tail:tt)*) => {
+        $(#[$attr])*
+        #[link_section = ".data.ramfunc"]
+        #[inline(never)]
+        $vis fn $name $args $(-> $ret)? $body
+        $crate::ramfunc!(@split $($tail)*);
+    };
+    (@split) => {};
+    ($($items:tt)*) => {
+        $crate::ramfunc!(@split $($items)*);
+    };
+}
+
+crate::ramfunc! {
+    /// Programs a word and waits for the end of the operation without fetching from the flash
+    ///
+    /// # Safety
+    /// The pointers are the FMC `CTRL` and `STS` registers, the flash is unlocked and `address`
+    /// is erased and word aligned.
+    pub(crate) unsafe fn program_word(ctrl: *mut u32, sts: *const u32, address: *mut u32, word: u32) {
+        while core::ptr::read_volatile(sts) & STS_BUSY != 0 {}
+        core::ptr::write_volatile(ctrl, core::ptr::read_volatile(ctrl) | CTRL_PG);
+        core::ptr::write_volatile(address, word);
+        while core::ptr::read_volatile(sts) & STS_BUSY != 0 {}
+        core::ptr::write_volatile(ctrl, core::ptr::read_volatile(ctrl) & !CTRL_PG);
+    }
+
+    /// Erases the page at `address` and waits for the end of the operation without fetching
+    /// from the flash
+    ///
+    /// # Safety
+    /// The pointers are the FMC `CTRL`, `STS` and `ADD` registers and the flash is unlocked.
+    pub(crate) unsafe fn erase_page(ctrl: *mut u32, sts: *const u32, add: *mut u32, address: u32) {
+        while core::ptr::read_volatile(sts) & STS_BUSY != 0 {}
+        core::ptr::write_volatile(ctrl, core::ptr::read_volatile(ctrl) | CTRL_PER);
+        core::ptr::write_volatile(add, address);
+        core::ptr::write_volatile(ctrl, core::ptr::read_volatile(ctrl) | CTRL_START);
+        // dsb/isb as instructions, cortex_m::asm may call into the flash
+        core::arch::asm!("dsb", "isb");
+        while core::ptr::read_volatile(sts) & STS_BUSY != 0 {}
+        core::ptr::write_volatile(ctrl, core::ptr::read_volatile(ctrl) & !CTRL_PER);
+    }
+}
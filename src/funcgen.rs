@@ -0,0 +1,175 @@
+//! # Function generator
+//!
+//! [FuncGen] plays a sine or triangle table in a loop to a 12-bit DAC holding register, one
+//! sample per update event of a timer, with a circular DMA transfer, see
+//! [paced_write_circular]. The frequency, the amplitude and the offset can be changed while
+//! it plays.
+//!
+//! There is no DAC driver yet: enable the DAC channel through the PAC, and pass the address
+//! of its 12-bit right aligned data register. With the DAC trigger disabled each sample is
+//! converted as soon as it is written. If the DAC channel is triggered by the timer instead,
+//! select [TriggerSource::Update](crate::timer::TriggerSource::Update) with
+//! `Timer::set_trigger_source` before starting the generator: the conversions then happen on
+//! TRGO, one sample behind the DMA writes.
+//!
+//! ```rust
+//! static mut TABLE: [u16; 64] = [0; 64];
+//!
+//! let dma = dp.Dma1.split();
+//! let timer = Timer::new(dp.Tim2, &clocks);
+//! let mut sine = FuncGen::start(timer, dma.2, dac_dr12ch1, unsafe { &mut TABLE }, Shape::Sine, 1.kHz())?;
+//!
+//! sine.set_amplitude(1000);
+//! sine.set_frequency(440.Hz())?;
+//! ```
+
+use crate::dma::{CompatibleChannel, DMAChannel, Error, W};
+use crate::time::Hertz;
+use crate::timer::{paced_write_circular, PacedWrite, Timer, UpdateDma, UpdateRequest};
+
+/// Largest value of the 12-bit DAC
+pub const DAC_MAX: u16 = 4095;
+
+/// `32767 * sin(i * π / 128)`, a quarter of a sine period
+const QUARTER_SINE: [u16; 65] = [
+    0, 804, 1608, 2410, 3212, 4011, 4808, 5602,
+    6393, 7179, 7962, 8739, 9512, 10278, 11039, 11793,
+    12539, 13279, 14010, 14732, 15446, 16151, 16846, 17530,
+    18204, 18868, 19519, 20159, 20787, 21403, 22005, 22594,
+    23170, 23731, 24279, 24811, 25329, 25832, 26319, 26790,
+    27245, 27683, 28105, 28510, 28898, 29268, 29621, 29956,
+    30273, 30571, 30852, 31113, 31356, 31580, 31785, 31971,
+    32137, 32285, 32412, 32521, 32609, 32678, 32728, 32757,
+    32767,
+];
+
+/// Waveform played by [FuncGen]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Sine,
+    Triangle,
+}
+
+impl Shape {
+    /// Value of the waveform at `phase`, a full period being 65536, between -32767 and 32767
+    pub fn value(self, phase: u16) -> i32 {
+        let quadrant = phase >> 14;
+        let mut pos = (phase & 0x3fff) as u32;
+        if quadrant & 1 == 1 {
+            pos = 0x4000 - pos;
+        }
+        let value = match self {
+            Shape::Sine => {
+                let index = (pos >> 8) as usize;
+                let frac = (pos & 0xff) as i32;
+                let a = QUARTER_SINE[index] as i32;
+                let b = QUARTER_SINE[(index + 1).min(64)] as i32;
+                a + (((b - a) * frac) >> 8)
+            }
+            Shape::Triangle => ((pos * 32767) >> 14) as i32,
+        };
+        if quadrant >= 2 {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Fills `table` with one period of the waveform, swinging `amplitude` around `offset`
+    ///
+    /// The samples are clamped to the range of the 12-bit DAC.
+    pub fn fill(self, table: &mut [u16], amplitude: u16, offset: u16) {
+        let len = table.len() as u32;
+        for (i, sample) in table.iter_mut().enumerate() {
+            let value = self.sample((i as u32 * 65536 / len) as u16, amplitude, offset);
+            // NOTE(unsafe) the table may be read by a running DMA transfer
+            unsafe { core::ptr::write_volatile(sample, value) };
+        }
+    }
+
+    fn sample(self, phase: u16, amplitude: u16, offset: u16) -> u16 {
+        let value = offset as i32 + self.value(phase) * amplitude as i32 / 32767;
+        value.clamp(0, DAC_MAX as i32) as u16
+    }
+}
+
+/// Waveform of `N` samples played in a loop at the rate of `TIM`
+pub struct FuncGen<TIM, CH, const N: usize> {
+    transfer: PacedWrite<TIM, CH, &'static mut [u16; N]>,
+    shape: Shape,
+    amplitude: u16,
+    offset: u16,
+}
+
+impl<TIM, CH, const N: usize> FuncGen<TIM, CH, N>
+where
+    TIM: UpdateDma,
+    CH: CompatibleChannel<UpdateRequest<TIM>, W> + DMAChannel,
+{
+    /// Starts playing `shape` at `frequency`, full scale, to the DAC data register at `address`
+    ///
+    /// The sample rate is `N` times `frequency`, so the number of samples bounds the highest
+    /// frequency the timer and the DMA keep up with.
+    pub fn start(
+        timer: Timer<TIM>,
+        channel: CH,
+        address: u32,
+        table: &'static mut [u16; N],
+        shape: Shape,
+        frequency: Hertz,
+    ) -> Result<Self, Error> {
+        let amplitude = DAC_MAX / 2;
+        let offset = DAC_MAX / 2 + 1;
+        shape.fill(table, amplitude, offset);
+        let transfer =
+            paced_write_circular(timer, channel, address, table, Self::sample_rate(frequency))?;
+        Ok(Self {
+            transfer,
+            shape,
+            amplitude,
+            offset,
+        })
+    }
+
+    fn sample_rate(frequency: Hertz) -> Hertz {
+        Hertz::from_raw(frequency.raw() * N as u32)
+    }
+
+    /// Changes the output frequency, from the next sample on
+    ///
+    /// Fails with [InvalidRate](Error::InvalidRate) if `frequency` is zero.
+    pub fn set_frequency(&mut self, frequency: Hertz) -> Result<(), Error> {
+        self.transfer.set_rate(Self::sample_rate(frequency))
+    }
+
+    /// Changes the waveform
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+        self.refill();
+    }
+
+    /// Changes the peak amplitude, in DAC steps
+    pub fn set_amplitude(&mut self, amplitude: u16) {
+        self.amplitude = amplitude;
+        self.refill();
+    }
+
+    /// Changes the middle value of the waveform, in DAC steps
+    pub fn set_offset(&mut self, offset: u16) {
+        self.offset = offset;
+        self.refill();
+    }
+
+    /// Rewrites the table in place, the DMA picks up the new samples as it goes so one
+    /// period may mix the old and the new waveforms
+    fn refill(&mut self) {
+        let table: &mut [u16; N] = self.transfer.buffer_mut();
+        self.shape.fill(table, self.amplitude, self.offset);
+    }
+
+    /// Stops playing and releases the timer, the channel and the table
+    pub fn stop(self) -> (Timer<TIM>, CH, &'static mut [u16; N]) {
+        self.transfer.stop()
+    }
+}
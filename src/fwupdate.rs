@@ -0,0 +1,442 @@
+//! # Firmware update over a serial port
+//!
+//! A small stop-and-wait protocol writing an image into the inactive slot of an
+//! [AbLayout], verifying it, and swapping the slots once its CRC matches. The [Updater]
+//! only consumes bytes and produces responses, so it works over any byte stream;
+//! [SerialUpdate] runs it on a serial port with DMA.
+//!
+//! ```rust
+//! const LAYOUT: AbLayout = AbLayout::new(0x4000, 0x3_0000, 0x2_c000, 0x7_f800);
+//! static mut RING: [u8; 512] = [0; 512];
+//!
+//! let dma = dp.Dma1.split();
+//! let (tx, rx) = dp.Usart1.serial((gpioa.pa9, gpioa.pa10), Config::default(), &clocks, &mut afio)?.split();
+//! let mut update = SerialUpdate::new(rx.with_dma(dma.5), tx.with_dma(dma.4), unsafe { &mut *core::ptr::addr_of_mut!(RING) }, LAYOUT);
+//!
+//! loop {
+//!     if let Some(_slot) = update.poll(&mut flash, &mut crc32)? {
+//!         cortex_m::peripheral::SCB::sys_reset();
+//!     }
+//! }
+//! ```
+//!
+//! ## Protocol
+//!
+//! Commands and responses are frames of little endian fields:
+//!
+//! ```text
+//! SOF  command/status u8  length u16  payload[length]  CRC-16
+//! ```
+//!
+//! SOF is [COMMAND_SOF] for commands and [RESPONSE_SOF] for responses, the CRC is the
+//! CRC-16/CCITT-FALSE of everything between SOF and CRC, see [crc16].
+//!
+//! | Command      | Payload                          | Response payload                   |
+//! |--------------|----------------------------------|------------------------------------|
+//! | `0x01` Info  |                                  | slot size u32, [MAX_CHUNK] u16, progress u32 |
+//! | `0x02` Begin | image length u32, image CRC u32  | progress u32                       |
+//! | `0x03` Write | offset u32, up to [MAX_CHUNK] bytes | progress u32                    |
+//! | `0x04` Finish|                                  | slot u8, 0 for A and 1 for B       |
+//! | `0x05` Abort |                                  |                                    |
+//!
+//! Every command is answered by one response whose [Status] tells whether it succeeded,
+//! and the host sends the next command only after that response. The progress is the
+//! number of image bytes written and verified. Begin erases the whole inactive slot, which
+//! can take seconds during which the device doesn't respond.
+//!
+//! Writes go at the progress, in multiples of 4 bytes except for the last one. Each chunk is
+//! read back after programming. Finish computes the CRC of the image with the CRC unit, as
+//! [integrity](crate::integrity) does, and swaps the slots if it matches the one given to
+//! Begin.
+//!
+//! ## Resuming
+//!
+//! When a response is lost the host resends the command: a Write below the progress was
+//! already written and is only acknowledged. After a broken link the host sends Begin again,
+//! and with the length and CRC of the update in progress it answers the progress instead of
+//! erasing, so the host continues from there. The update restarts after a reset of the
+//! device.
+
+use core::sync::atomic::{self, Ordering};
+
+use embedded_storage::nor_flash::ReadNorFlash;
+use enumflags2::BitFlags;
+
+use crate::crc::Crc32Engine;
+use crate::dma::{start_byte_transfer, DMAChannel, Error, Flag, RxDma, TxDma, MAX_TRANSFER_LENGTH};
+use crate::fmc::{AbLayout, Flash, Slot};
+use crate::integrity::flash_crc;
+use crate::serial::{Instance, RegisterBlockImpl, Rx, Tx};
+
+/// Start of a command frame
+pub const COMMAND_SOF: u8 = 0x5A;
+/// Start of a response frame
+pub const RESPONSE_SOF: u8 = 0xA5;
+/// Largest data chunk of a Write command
+pub const MAX_CHUNK: usize = 256;
+/// Largest command frame, a Write of [MAX_CHUNK] bytes
+pub const MAX_FRAME: usize = 4 + 4 + MAX_CHUNK + 2;
+/// Largest response payload, the answer to Info
+const MAX_RESPONSE: usize = 10;
+
+const INFO: u8 = 0x01;
+const BEGIN: u8 = 0x02;
+const WRITE: u8 = 0x03;
+const FINISH: u8 = 0x04;
+const ABORT: u8 = 0x05;
+
+/// Status of a response
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Status {
+    Ok = 0,
+    /// The CRC or the length of the frame is wrong, the command was ignored
+    BadFrame = 1,
+    UnknownCommand = 2,
+    /// Write or Finish without Begin
+    NotStarted = 3,
+    /// Write elsewhere than at the progress, or Finish before the whole image was written
+    BadOffset = 4,
+    /// Write of a chunk that isn't a multiple of 4 bytes before the end of the image
+    Unaligned = 5,
+    /// The image doesn't fit in a slot
+    TooLarge = 6,
+    /// Erasing or programming the flash failed
+    Flash = 7,
+    /// The flash doesn't hold the written chunk
+    Verify = 8,
+    /// The CRC of the written image differs from the CRC given to Begin, the update is dropped
+    CrcMismatch = 9,
+}
+
+/// Update in progress
+#[derive(Debug, Clone, Copy)]
+struct Session {
+    len: u32,
+    crc: u32,
+    progress: u32,
+}
+
+/// Protocol state, see the [module documentation](self)
+pub struct Updater {
+    layout: AbLayout,
+    session: Option<Session>,
+    frame: [u8; MAX_FRAME],
+    pos: usize,
+}
+
+impl Updater {
+    pub const fn new(layout: AbLayout) -> Self {
+        Self {
+            layout,
+            session: None,
+            frame: [0; MAX_FRAME],
+            pos: 0,
+        }
+    }
+
+    /// Number of image bytes written and verified, `None` without an update in progress
+    pub fn progress(&self) -> Option<u32> {
+        self.session.map(|session| session.progress)
+    }
+
+    /// Handles the commands completed by `bytes`, passing each response to `respond`
+    ///
+    /// Returns the new active slot once Finish swapped the slots, the device should then
+    /// reset into it.
+    pub fn receive(
+        &mut self,
+        bytes: &[u8],
+        flash: &mut Flash,
+        crc: &mut Crc32Engine,
+        mut respond: impl FnMut(&[u8]),
+    ) -> Option<Slot> {
+        let mut swapped = None;
+        for &byte in bytes {
+            // Resynchronize on the next SOF after a bad frame
+            if self.pos == 0 && byte != COMMAND_SOF {
+                continue;
+            }
+            self.frame[self.pos] = byte;
+            self.pos += 1;
+            if self.pos < 4 {
+                continue;
+            }
+            let len = u16::from_le_bytes([self.frame[2], self.frame[3]]) as usize;
+            if 4 + len + 2 > MAX_FRAME {
+                self.pos = 0;
+                response(&mut respond, Status::BadFrame, &[]);
+                continue;
+            }
+            if self.pos < 4 + len + 2 {
+                continue;
+            }
+            self.pos = 0;
+
+            let stored = u16::from_le_bytes([self.frame[4 + len], self.frame[5 + len]]);
+            if crc16(&self.frame[1..4 + len]) != stored {
+                response(&mut respond, Status::BadFrame, &[]);
+                continue;
+            }
+            let mut payload = [0; MAX_RESPONSE];
+            let result = execute(
+                &self.layout,
+                &mut self.session,
+                self.frame[1],
+                &self.frame[4..4 + len],
+                flash,
+                crc,
+                &mut payload,
+            );
+            match result {
+                Ok((n, slot)) => {
+                    response(&mut respond, Status::Ok, &payload[..n]);
+                    swapped = slot.or(swapped);
+                }
+                Err(status) => response(&mut respond, status, &[]),
+            }
+        }
+        swapped
+    }
+}
+
+/// Runs `command`, returns the length of the response payload and the swapped slot
+fn execute(
+    layout: &AbLayout,
+    session: &mut Option<Session>,
+    command: u8,
+    payload: &[u8],
+    flash: &mut Flash,
+    crc: &mut Crc32Engine,
+    out: &mut [u8; MAX_RESPONSE],
+) -> Result<(usize, Option<Slot>), Status> {
+    match command {
+        INFO => {
+            let progress = session.map_or(0, |session| session.progress);
+            out[..4].copy_from_slice(&layout.slot_size().to_le_bytes());
+            out[4..6].copy_from_slice(&(MAX_CHUNK as u16).to_le_bytes());
+            out[6..10].copy_from_slice(&progress.to_le_bytes());
+            Ok((10, None))
+        }
+        BEGIN => {
+            if payload.len() != 8 {
+                return Err(Status::BadFrame);
+            }
+            let (len, image_crc) = (word(payload, 0), word(payload, 4));
+            if len > layout.slot_size() {
+                return Err(Status::TooLarge);
+            }
+            let progress = match *session {
+                Some(s) if s.len == len && s.crc == image_crc => s.progress,
+                _ => {
+                    *session = None;
+                    layout.erase_inactive(flash).map_err(|_| Status::Flash)?;
+                    *session = Some(Session { len, crc: image_crc, progress: 0 });
+                    0
+                }
+            };
+            out[..4].copy_from_slice(&progress.to_le_bytes());
+            Ok((4, None))
+        }
+        WRITE => {
+            let s = session.as_mut().ok_or(Status::NotStarted)?;
+            if payload.len() < 4 {
+                return Err(Status::BadFrame);
+            }
+            let offset = word(payload, 0);
+            let data = &payload[4..];
+            let end = offset as usize + data.len();
+            // Repeated after a lost response
+            if offset < s.progress && end <= s.progress as usize {
+                out[..4].copy_from_slice(&s.progress.to_le_bytes());
+                return Ok((4, None));
+            }
+            if offset != s.progress {
+                return Err(Status::BadOffset);
+            }
+            if end > s.len as usize {
+                return Err(Status::TooLarge);
+            }
+            if data.len() % 4 != 0 && end != s.len as usize {
+                return Err(Status::Unaligned);
+            }
+            // The last chunk is padded with the erased value
+            let padded = (data.len() + 3) & !3;
+            let mut chunk = [0xff; MAX_CHUNK];
+            chunk[..data.len()].copy_from_slice(data);
+            layout
+                .write_inactive(flash, offset, &chunk[..padded])
+                .map_err(|_| Status::Flash)?;
+
+            let slot = layout.offset(layout.inactive(flash).map_err(|_| Status::Flash)?);
+            let mut readback = [0; MAX_CHUNK];
+            flash
+                .read(slot + offset, &mut readback[..padded])
+                .map_err(|_| Status::Flash)?;
+            if readback[..padded] != chunk[..padded] {
+                return Err(Status::Verify);
+            }
+            s.progress = end as u32;
+            out[..4].copy_from_slice(&s.progress.to_le_bytes());
+            Ok((4, None))
+        }
+        FINISH => {
+            let s = session.ok_or(Status::NotStarted)?;
+            if s.progress != s.len {
+                return Err(Status::BadOffset);
+            }
+            let slot = layout.offset(layout.inactive(flash).map_err(|_| Status::Flash)?);
+            let computed = flash_crc(flash, crc, slot, s.len).map_err(|_| Status::Flash)?;
+            if computed != s.crc {
+                *session = None;
+                return Err(Status::CrcMismatch);
+            }
+            let active = layout.swap(flash).map_err(|_| Status::Flash)?;
+            *session = None;
+            out[0] = (active == Slot::B) as u8;
+            Ok((1, Some(active)))
+        }
+        ABORT => {
+            *session = None;
+            Ok((0, None))
+        }
+        _ => Err(Status::UnknownCommand),
+    }
+}
+
+fn word(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+}
+
+/// Frames `payload` with `status` and passes it to `respond`
+fn response(respond: &mut impl FnMut(&[u8]), status: Status, payload: &[u8]) {
+    let mut frame = [0; MAX_RESPONSE + 6];
+    frame[0] = RESPONSE_SOF;
+    frame[1] = status as u8;
+    frame[2..4].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+    frame[4..4 + payload.len()].copy_from_slice(payload);
+    let crc = crc16(&frame[1..4 + payload.len()]);
+    frame[4 + payload.len()..6 + payload.len()].copy_from_slice(&crc.to_le_bytes());
+    respond(&frame[..6 + payload.len()]);
+}
+
+/// CRC-16/CCITT-FALSE of the frames, polynomial 0x1021 starting from 0xFFFF
+pub fn crc16(bytes: &[u8]) -> u16 {
+    crate::crc::crc16_ccitt(0xffff, bytes)
+}
+
+/// [Updater] receiving on `USART` with DMA into a ring buffer of `N` bytes, and responding
+/// with DMA
+///
+/// The ring has to hold at least [MAX_FRAME] bytes, and at most [MAX_TRANSFER_LENGTH] so a
+/// single circular transfer covers it.
+pub struct SerialUpdate<USART: Instance, RXCH, TXCH, const N: usize> {
+    rx: RxDma<Rx<USART>, RXCH>,
+    tx: TxDma<Tx<USART>, TXCH>,
+    buffer: &'static mut [u8; N],
+    /// Position of the oldest byte not handled yet
+    read: usize,
+    updater: Updater,
+}
+
+impl<USART, RXCH, TXCH, const N: usize> SerialUpdate<USART, RXCH, TXCH, N>
+where
+    USART: Instance,
+    RXCH: DMAChannel,
+    TXCH: DMAChannel,
+{
+    /// Fails the build of [new](Self::new) for a ring the DMA can't count through
+    const FITS_TRANSFER: () = assert!(N <= MAX_TRANSFER_LENGTH, "the ring is longer than a DMA transfer");
+
+    /// Starts receiving commands into `buffer` for an update of the inactive slot of `layout`
+    pub fn new(
+        mut rx: RxDma<Rx<USART>, RXCH>,
+        tx: TxDma<Tx<USART>, TXCH>,
+        buffer: &'static mut [u8; N],
+        layout: AbLayout,
+    ) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_TRANSFER;
+        let peripheral = unsafe { (*USART::ptr()).peri_address() };
+        let address = buffer.as_mut_ptr() as u32;
+        // Can't fail, FITS_TRANSFER checked the length
+        start_byte_transfer(&mut rx.channel, peripheral, address, N, false, true).ok();
+        Self {
+            rx,
+            tx,
+            buffer,
+            read: 0,
+            updater: Updater::new(layout),
+        }
+    }
+
+    /// Handles the received commands and sends their responses
+    ///
+    /// Returns the new active slot once an update completed, see [Updater::receive]. The
+    /// responses have left the USART when it returns, so resetting right after is safe.
+    /// Returns [TransferError](Error::TransferError) if either channel reported a transfer
+    /// error, also while sending a response.
+    pub fn poll(&mut self, flash: &mut Flash, crc: &mut Crc32Engine) -> Result<Option<Slot>, Error> {
+        let Self { rx, tx, buffer, read, updater } = self;
+        if (rx.channel.flags() | tx.channel.flags()).contains(Flag::TransferError) {
+            return Err(Error::TransferError);
+        }
+
+        let write = (N - rx.channel.get_txnum() as usize) % N;
+        atomic::compiler_fence(Ordering::Acquire);
+        let mut failed = None;
+        let mut respond = |frame: &[u8]| {
+            if failed.is_none() {
+                failed = send(tx, frame).err();
+            }
+        };
+        let swapped = if write >= *read {
+            updater.receive(&buffer[*read..write], flash, crc, &mut respond)
+        } else {
+            let first = updater.receive(&buffer[*read..], flash, crc, &mut respond);
+            updater.receive(&buffer[..write], flash, crc, &mut respond).or(first)
+        };
+        *read = write;
+        match failed {
+            Some(error) => Err(error),
+            None => Ok(swapped),
+        }
+    }
+
+    /// Protocol state
+    pub fn updater(&self) -> &Updater {
+        &self.updater
+    }
+
+    /// Stops receiving and releases the serial port and the buffer
+    pub fn release(mut self) -> (RxDma<Rx<USART>, RXCH>, TxDma<Tx<USART>, TXCH>, &'static mut [u8; N]) {
+        self.rx.channel.stop();
+        self.rx.channel.clear_flags(BitFlags::ALL);
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.rx, self.tx, self.buffer)
+    }
+}
+
+/// Sends `frame` and waits until its last byte left the USART, so a reset right after
+/// doesn't cut the response short
+fn send<USART: Instance, TXCH: DMAChannel>(tx: &mut TxDma<Tx<USART>, TXCH>, frame: &[u8]) -> Result<(), Error> {
+    let usart = unsafe { &*USART::ptr() };
+    atomic::compiler_fence(Ordering::Release);
+    start_byte_transfer(&mut tx.channel, usart.peri_address(), frame.as_ptr() as u32, frame.len(), true, false)?;
+    let result = loop {
+        if tx.channel.flags().contains(Flag::TransferError) {
+            break Err(Error::TransferError);
+        }
+        if tx.channel.get_txnum() == 0 {
+            break Ok(());
+        }
+    };
+    tx.channel.stop();
+    tx.channel.clear_flags(BitFlags::ALL);
+    atomic::compiler_fence(Ordering::Acquire);
+    result?;
+    // TC
+    while usart.flush().is_err() {}
+    Ok(())
+}
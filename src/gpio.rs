@@ -44,6 +44,14 @@
 //! ownership reasons, you can use the closure based `with_<mode>` functions to temporarily change the pin type, do
 //! some output or input, and then have it change back once done.
 //!
+//! ### Atomicity
+//! The configuration of 8 pins shares a register, so a mode change reads it, replaces the
+//! bits of the pin and writes it back. The `into_<mode>`, `with_<mode>`, `make_<mode>` and
+//! `set_speed` functions do this in a short critical section, so pins of the same port can
+//! change modes from different interrupt priorities without corrupting each other. The pull
+//! direction of `PullUp` and `PullDown` inputs is written to the output register before the
+//! mode, with a single write of the set/reset register.
+//!
 //! ### Dynamic Mode Change
 //! The above mode change methods guarantee that you can only call input functions when the pin is
 //! in input mode, and output when in output modes, but can lead to some issues. Therefore, there
@@ -57,14 +65,16 @@
 use core::marker::PhantomData;
 
 pub mod alt;
+pub mod capture;
 mod convert;
+pub mod debounce;
 pub use convert::PinMode;
 mod partially_erased;
 pub use partially_erased::{PEPin, PartiallyErasedPin};
 mod erased;
 pub use erased::{EPin, ErasedPin};
 mod exti;
-pub use exti::ExtiPin;
+pub use exti::{ExtiLine, ExtiPin};
 mod dynamic;
 pub use dynamic::{Dynamic, DynamicPin};
 mod hal_02;
@@ -282,21 +292,12 @@ where
     MODE: marker::OutputSpeed,
 {
     /// Set pin speed
+    ///
+    /// Like the mode changes, the speed is written in a critical section, see the module documentation.
     pub fn set_speed(&mut self, speed: Speed) {
-        let offset = 2 * { N };
-
-        unsafe {
-            if N < 8 {
-                (*gpiox::<P>())
-                .pl_cfg()
-                .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | ((speed as u32) << offset)));
-            } else {
-                (*gpiox::<P>())
-                .ph_cfg()
-                .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | ((speed as u32) << offset)));
-
-            }
-        }
+        // NOTE(unsafe) only the MODE bits of this pin are written
+        let gpio = unsafe { &*gpiox::<P>() };
+        convert::write_cfg(gpio, N, 0b11, speed as u32);
     }
 
     /// Set pin speed
@@ -364,6 +365,17 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
         // NOTE(unsafe) atomic write to a stateless register
         unsafe { (*gpiox::<P>()).pbsc().write(|w| w.bits(1 << (16 + N))) }
     }
+    /// Inverts the output with a single write to the set/reset register, so pins of the same
+    /// port changed meanwhile, e.g. from an interrupt, are never overwritten
+    #[inline(always)]
+    fn _toggle(&mut self) {
+        // NOTE(unsafe) atomic read with no side effects, then atomic write to a stateless register
+        unsafe {
+            let gpio = &*gpiox::<P>();
+            let bit = if gpio.pod().read().bits() & (1 << N) == 0 { N } else { 16 + N };
+            gpio.pbsc().write(|w| w.bits(1 << bit))
+        }
+    }
     #[inline(always)]
     fn _is_set_low(&self) -> bool {
         // NOTE(unsafe) atomic read with no side effects
@@ -423,11 +435,7 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, Output<MODE>> {
     /// Toggle pin output
     #[inline(always)]
     pub fn toggle(&mut self) {
-        if self.is_set_low() {
-            self.set_high()
-        } else {
-            self.set_low()
-        }
+        self._toggle()
     }
 }
 
@@ -518,7 +526,7 @@ macro_rules! gpio {
 }
 
 
-const fn gpiox<const P: char>() -> *const crate::pac::gpioa::RegisterBlock {
+pub(crate) const fn gpiox<const P: char>() -> *const crate::pac::gpioa::RegisterBlock {
     match P {
         'A' => crate::pac::Gpioa::ptr(),
         'B' => crate::pac::Gpiob::ptr() as _,
@@ -3,7 +3,7 @@
 pub mod altmap;
 macro_rules! extipin {
     ($( $(#[$attr:meta])* $PX:ident,)*) => {
-        fn make_interrupt_source(&mut self, _syscfg: &mut $crate::pac::Afio) {
+        fn make_interrupt_source(&mut self, _syscfg: &mut $crate::afio::Parts) {
             match self {
                 $(
                     $(#[$attr])*
@@ -60,6 +60,18 @@ macro_rules! extipin {
                 _ => false,
             }
         }
+        fn claim_line(
+            &self,
+            _exticr: &mut $crate::afio::EXTICR,
+        ) -> Option<Result<$crate::gpio::ExtiLine, $crate::afio::LineClaimed>> {
+            match self {
+                $(
+                    $(#[$attr])*
+                    Self::$PX(p) => p.claim_line(_exticr),
+                )*
+                _ => None,
+            }
+        }
     };
 }
 use extipin;
@@ -328,6 +340,40 @@ pub trait QuadSpi {
 }
 
 
+// SDIO pins
+pub trait SdioCommon {
+    type Ck: crate::gpio::PinSpeed;
+    type Cmd: crate::gpio::PinSpeed;
+    type D0: crate::gpio::PinSpeed;
+    type D1: crate::gpio::PinSpeed;
+    type D2: crate::gpio::PinSpeed;
+    type D3: crate::gpio::PinSpeed;
+}
+/// 8-bit bus width
+pub trait SdioWide {
+    type D4: crate::gpio::PinSpeed;
+    type D5: crate::gpio::PinSpeed;
+    type D6: crate::gpio::PinSpeed;
+    type D7: crate::gpio::PinSpeed;
+}
+
+// Ethernet pins
+/// Reduced media independent interface
+pub trait EthRmii {
+    type RefClk;
+    type CrsDv;
+    type Rxd0;
+    type Rxd1;
+    type TxEn: crate::gpio::PinSpeed;
+    type Txd0: crate::gpio::PinSpeed;
+    type Txd1: crate::gpio::PinSpeed;
+}
+/// Station management
+pub trait EthSmi {
+    type Mdc;
+    type Mdio;
+}
+
 // SPI pins
 pub trait SpiCommon {
     type Miso;
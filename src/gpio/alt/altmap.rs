@@ -12,7 +12,7 @@ pub trait RemapIO<PER, Remapper : Remap>  {
 }
 
 pub trait Remap {
-    fn remap( afio : &mut crate::pac::Afio);
+    fn remap( afio : &mut crate::afio::Parts);
 }
 
 impl<PER,Mapper> !RemapIO<PER,Mapper> for NoPin {
@@ -29,29 +29,29 @@ pub mod spi1 {
     pub struct SPI1FullRemapRemapper();
 
     impl Remap for SPI1NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.spi1_rmp_0().clear_bit());
-            afio.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().clear_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.spi1_rmp_0().clear_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().clear_bit());
         }
     }
     impl Remap for SPI1PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.spi1_rmp_0().set_bit());
-            afio.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().clear_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.spi1_rmp_0().set_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().clear_bit());
         }
     }
 
     impl Remap for SPI1PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.spi1_rmp_0().clear_bit());
-            afio.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().set_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.spi1_rmp_0().clear_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().set_bit());
         }
     }
 
     impl Remap for SPI1FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.spi1_rmp_0().set_bit());
-            afio.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().set_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.spi1_rmp_0().set_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.spi1_rmp_1().set_bit());
         }
     }
 
@@ -135,20 +135,20 @@ pub mod spi2 {
     pub struct SPI2FullRemapRemapper();
 
     impl Remap for SPI2NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi2_rmp().bits(0b00)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi2_rmp().bits(0b00)});
         }
     }
 
     impl Remap for SPI2PartialRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi2_rmp().bits(0b01)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi2_rmp().bits(0b01)});
         }
     }
 
     impl Remap for SPI2FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi2_rmp().bits(0b11)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi2_rmp().bits(0b11)});
         }
     }
 
@@ -211,8 +211,29 @@ pub mod spi2 {
         type Mosi = Mosi;
         type Nss = Nss;
     }
+
+    // I2S2 shares WS, CK and SD with NSS, SCK and MOSI, MCK isn't remapped
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+    pin! {
+        <Mck> default: PushPull for no:NoPin, [
+            PC6,
+        ],
+    }
+
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+    impl I2sCommon for SPI {
+        type Ck = Sck;
+        type Sd = Mosi;
+        type Ws = Nss;
+    }
+
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+    impl I2sMaster for SPI {
+        type Mck = Mck;
+    }
 }
 
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 pub mod spi3 {
     use super::*;
     use crate::gpio::{self, Input, PushPull};
@@ -224,26 +245,26 @@ pub mod spi3 {
     pub struct SPI3FullRemapRemapper();
 
     impl Remap for SPI3NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b00)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b00)});
         }
     }
 
     impl Remap for SPI3PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b01)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b01)});
         }
     }
 
     impl Remap for SPI3PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b10)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b10)});
         }
     }
 
     impl Remap for SPI3FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b11)});
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.spi3_rmp().bits(0b11)});
         }
     }
 
@@ -319,6 +340,23 @@ pub mod spi3 {
         type Mosi = Mosi;
         type Nss = Nss;
     }
+
+    // I2S3 shares WS, CK and SD with NSS, SCK and MOSI, MCK isn't remapped
+    pin! {
+        <Mck> default: PushPull for no:NoPin, [
+            PC7,
+        ],
+    }
+
+    impl I2sCommon for SPI {
+        type Ck = Sck;
+        type Sd = Mosi;
+        type Ws = Nss;
+    }
+
+    impl I2sMaster for SPI {
+        type Mck = Mck;
+    }
 }
 
 
@@ -330,14 +368,14 @@ pub mod usart1 {
     pub struct USART1FullRemapRemapper();
 
     impl Remap for USART1NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.usart1_rmp().clear_bit())
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.usart1_rmp().clear_bit())
         }
     }
 
     impl Remap for USART1FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.usart1_rmp().set_bit())
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.usart1_rmp().set_bit())
         }
     }
 
@@ -410,27 +448,27 @@ pub mod usart2 {
     pub struct USART2FullRemapRemapper();
 
     impl Remap for USART2NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.usart2_rmp_0().clear_bit());
-            afio.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().clear_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.usart2_rmp_0().clear_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().clear_bit());
         }
     }
     impl Remap for USART2PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.usart2_rmp_0().set_bit());
-            afio.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().clear_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.usart2_rmp_0().set_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().clear_bit());
         }
     }
     impl Remap for USART2PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.usart2_rmp_0().clear_bit());
-            afio.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().set_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.usart2_rmp_0().clear_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().set_bit());
         }
     }
     impl Remap for USART2FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| w.usart2_rmp_0().set_bit());
-            afio.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().set_bit());
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| w.usart2_rmp_0().set_bit());
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| w.usart2_rmp_1().set_bit());
         }
     }
 
@@ -544,20 +582,20 @@ pub mod usart3 {
     pub struct USART3FullRemapRemapper();
 
     impl Remap for USART3NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.usart3_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.usart3_rmp().bits(0)})
         }
     }
 
     impl Remap for USART3PartialRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.usart3_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.usart3_rmp().bits(1)})
         }
     }
 
     impl Remap for USART3FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.usart3_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.usart3_rmp().bits(3)})
         }
     }
 
@@ -656,26 +694,26 @@ pub mod uart4 {
     pub struct UART4FullRemapRemapper();
 
     impl Remap for UART4NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(0)})
         }
     }
 
     impl Remap for UART4PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(1)})
         }
     }
 
     impl Remap for UART4PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(2)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(2)})
         }
     }
 
     impl Remap for UART4FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart4_rmp().bits(3)})
         }
     }
 
@@ -730,26 +768,26 @@ pub mod uart5 {
     pub struct UART5FullRemapRemapper();
 
     impl Remap for UART5NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(0)})
         }
     }
 
     impl Remap for UART5PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(1)})
         }
     }
 
     impl Remap for UART5PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(2)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(2)})
         }
     }
 
     impl Remap for UART5FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart5_rmp().bits(3)})
         }
     }
 
@@ -792,6 +830,7 @@ pub mod uart5 {
     }
 }
 
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 pub mod uart6 {
     use super::*;
     use crate::gpio::{self, PushPull,Input};
@@ -802,20 +841,20 @@ pub mod uart6 {
     pub(crate) struct UART6FullRemapRemapper();
 
     impl Remap for UART6NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart6_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart6_rmp().bits(0)})
         }
     }
 
     impl Remap for UART6PartialRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart6_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart6_rmp().bits(1)})
         }
     }
 
     impl Remap for UART6FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart6_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart6_rmp().bits(3)})
         }
     }
 
@@ -852,6 +891,7 @@ pub mod uart6 {
     }
 }
 
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 pub mod uart7 {
     use super::*;
     use crate::gpio::{self, PushPull,Input};
@@ -862,20 +902,20 @@ pub mod uart7 {
     pub(crate) struct UART7FullRemapRemapper();
 
     impl Remap for UART7NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart7_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart7_rmp().bits(0)})
         }
     }
 
     impl Remap for UART7PartialRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart7_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart7_rmp().bits(1)})
         }
     }
 
     impl Remap for UART7FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.uart7_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.uart7_rmp().bits(3)})
         }
     }
 
@@ -913,6 +953,133 @@ pub mod uart7 {
 }
 
 
+/// SDIO pins, which can't be remapped
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+pub mod sdio {
+    use super::*;
+    use crate::gpio::{self, PushPull};
+    use crate::{gpio::alt::altmap::pin, pac::Sdio as SDIO};
+
+    pin! {
+        <Ck> default: PushPull for [
+            PC12,
+        ],
+
+        <Cmd> default: PushPull for [
+            PD2,
+        ],
+
+        <D0> default: PushPull for [
+            PC8,
+        ],
+
+        <D1> default: PushPull for no:NoPin, [
+            PC9,
+        ],
+
+        <D2> default: PushPull for no:NoPin, [
+            PC10,
+        ],
+
+        <D3> default: PushPull for no:NoPin, [
+            PC11,
+        ],
+
+        <D4> default: PushPull for [
+            PB8,
+        ],
+
+        <D5> default: PushPull for [
+            PB9,
+        ],
+
+        <D6> default: PushPull for [
+            PC6,
+        ],
+
+        <D7> default: PushPull for [
+            PC7,
+        ],
+    }
+
+    impl SdioCommon for SDIO {
+        type Ck = Ck;
+        type Cmd = Cmd;
+        type D0 = D0;
+        type D1 = D1;
+        type D2 = D2;
+        type D3 = D3;
+    }
+
+    impl SdioWide for SDIO {
+        type D4 = D4;
+        type D5 = D5;
+        type D6 = D6;
+        type D7 = D7;
+    }
+}
+
+/// Ethernet MAC pins, RMII without the `ETH_RMP` remap of CRS_DV, RXD0 and RXD1 to PD8..PD10
+#[cfg(feature = "n32g457")]
+pub mod eth {
+    use super::*;
+    use crate::gpio::{self, PushPull};
+    use crate::{gpio::alt::altmap::pin, pac::Eth as ETH};
+
+    pin! {
+        <RefClk> default: Floating for [
+            PA1,
+        ],
+
+        <CrsDv> default: Floating for [
+            PA7,
+        ],
+
+        <Rxd0> default: Floating for [
+            PC4,
+        ],
+
+        <Rxd1> default: Floating for [
+            PC5,
+        ],
+
+        <TxEn> default: PushPull for [
+            PB11,
+        ],
+
+        <Txd0> default: PushPull for [
+            PB12,
+        ],
+
+        <Txd1> default: PushPull for [
+            PB13,
+        ],
+
+        <Mdc> default: PushPull for [
+            PC1,
+        ],
+
+        <Mdio> default: PushPull for [
+            PA2,
+        ],
+    }
+
+    impl EthRmii for ETH {
+        type RefClk = RefClk;
+        type CrsDv = CrsDv;
+        type Rxd0 = Rxd0;
+        type Rxd1 = Rxd1;
+        type TxEn = TxEn;
+        type Txd0 = Txd0;
+        type Txd1 = Txd1;
+    }
+
+    impl EthSmi for ETH {
+        type Mdc = Mdc;
+        type Mdio = Mdio;
+    }
+}
+
 pub mod tim2 {
     use super::*;
     use crate::gpio::{self, PushPull};
@@ -926,27 +1093,27 @@ pub mod tim2 {
     pub struct TIM2FullRemapRemapper();
 
     impl Remap for TIM2NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(0)})
         }
     }
 
     impl Remap for TIM2PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(1)})
         }
     }
 
     impl Remap for TIM2PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(2)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(2)})
         }
     }
 
 
     impl Remap for TIM2FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim2_rmp().bits(3)})
         }
     }
 
@@ -1028,7 +1195,7 @@ pub mod tim2 {
 
 pub mod tim1 {
     use super::*;
-    use crate::gpio::{self, PushPull};
+    use crate::gpio::{self, Input, PushPull};
     use crate::{gpio::alt::altmap::pin, pac::Tim1 as TIM};
 
     pub struct TIM1NoRemapRemapper();
@@ -1040,27 +1207,27 @@ pub mod tim1 {
     pub struct TIM1FullRemapRemapper();
 
     impl Remap for TIM1NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(0)})
         }
     }
 
     impl Remap for TIM1PartialRemapOneRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(1)})
         }
     }
 
     impl Remap for TIM1PartialRemapTwoRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(2)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(2)})
         }
     }
 
 
     impl Remap for TIM1FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg.rmp_cfg().modify(|_,w| unsafe { w.tim1_rmp().bits(3)})
         }
     }
 
@@ -1180,7 +1347,7 @@ pub mod tim1 {
             PE12,
         ],
 
-        <Etr> default: PushPull for no:NoPin, [
+        <Etr> default: Input for no:NoPin, [
             PA12,
             PE7,
         ],
@@ -1224,12 +1391,16 @@ pub mod tim1 {
         type Bkin = Bkin;
     }
 
+    impl TimEtr for TIM {
+        type Etr = Etr;
+    }
+
 }
 
 
 pub mod tim8 {
     use super::*;
-    use crate::gpio::{self, PushPull};
+    use crate::gpio::{self, Input, PushPull};
     use crate::{gpio::alt::altmap::pin, pac::Tim8 as TIM};
 
     pub struct TIM8NoRemapRemapper();
@@ -1239,22 +1410,22 @@ pub mod tim8 {
     pub struct TIM8FullRemapRemapper();
 
     impl Remap for TIM8NoRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.tim8_rmp().bits(0)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.tim8_rmp().bits(0)})
         }
     }
 
     impl Remap for TIM8PartialRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.tim8_rmp().bits(1)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.tim8_rmp().bits(1)})
         }
     }
 
 
 
     impl Remap for TIM8FullRemapRemapper {
-        fn remap( afio : &mut crate::pac::Afio) {
-            afio.rmp_cfg3().modify(|_,w| unsafe { w.tim8_rmp().bits(3)})
+        fn remap( afio : &mut crate::afio::Parts) {
+            afio.rmp_cfg3.rmp_cfg3().modify(|_,w| unsafe { w.tim8_rmp().bits(3)})
         }
     }
 
@@ -1350,7 +1521,7 @@ pub mod tim8 {
             PD2,
         ],
 
-        <Etr> default: PushPull for no:NoPin, [
+        <Etr> default: Input for no:NoPin, [
             PA0,
             PB4,
         ],
@@ -1392,4 +1563,8 @@ pub mod tim8 {
         type Bkin = Bkin;
     }
 
+    impl TimEtr for TIM {
+        type Etr = Etr;
+    }
+
 }
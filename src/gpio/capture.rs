@@ -0,0 +1,216 @@
+//! # Time-stamped edge capture
+//!
+//! [EdgeCapture] records the time of every edge of an EXTI pin, read from the counter of a
+//! free-running timer in the EXTI interrupt, into a ring buffer of `N` events. This is enough
+//! to decode slow single-wire protocols like the DHT22 response, or to measure the interrupt
+//! latency against a known stimulus.
+//!
+//! Timestamps are 16-bit counter values, so only the differences between consecutive events
+//! are meaningful, and only if the events are less than 65536 ticks apart.
+//!
+//! ```rust
+//! let pin = gpioa.pa0.into_pull_up_input();
+//! let timer = Timer::new(dp.Tim2, &clocks);
+//! let mut capture = EdgeCapture::<_, _, 64>::new(pin, timer, 1.MHz(), &mut dp.Exti, &mut afio);
+//!
+//! // EXTI0 interrupt
+//! capture.on_interrupt();
+//!
+//! // later
+//! let mut last = None;
+//! while let Some(event) = capture.pop() {
+//!     if let Some(last) = last {
+//!         let width_us = event.ticks_since(last);
+//!     }
+//!     last = Some(event);
+//! }
+//! ```
+
+use super::{Edge, ExtiPin, ReadPin};
+use crate::afio::Parts;
+use crate::pac::Exti;
+use crate::time::Hertz;
+use crate::timer::Timer;
+
+/// Timer counting freely, whose counter is used as timestamp
+pub trait FreeRunningTimer: crate::Sealed {
+    #[doc(hidden)]
+    fn start(psc: u16);
+    #[doc(hidden)]
+    fn now() -> u16;
+    /// Address of the counter register, for DMA transfers of the timestamp
+    #[doc(hidden)]
+    fn counter_address() -> u32;
+    #[doc(hidden)]
+    fn stop();
+}
+
+macro_rules! free_running_timer {
+    ($($TIM:ty,)+) => {
+        $(
+            impl FreeRunningTimer for $TIM {
+                fn start(psc: u16) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    tim.psc().write(|w| unsafe { w.psc().bits(psc) });
+                    tim.ar().write(|w| unsafe { w.bits(0xffff) });
+                    <$TIM as crate::timer::Instance>::latch();
+                    tim.sts().write(|w| w.uditf().clear_bit());
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                }
+
+                fn now() -> u16 {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.cnt().read().bits() as u16
+                }
+
+                fn counter_address() -> u32 {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.cnt().as_ptr() as u32
+                }
+
+                fn stop() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                }
+            }
+        )+
+    };
+}
+
+free_running_timer! {
+    crate::pac::Tim1,
+    crate::pac::Tim2,
+    crate::pac::Tim3,
+    crate::pac::Tim4,
+    crate::pac::Tim6,
+    crate::pac::Tim8,
+}
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+free_running_timer! {
+    crate::pac::Tim7,
+}
+
+/// Edge recorded by [EdgeCapture]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeEvent {
+    /// Counter value of the timer when the interrupt handled the edge
+    pub timestamp: u16,
+    /// Level of the pin after the edge, `true` for a rising edge
+    pub level: bool,
+}
+
+impl EdgeEvent {
+    /// Ticks elapsed between `earlier` and this event
+    pub fn ticks_since(&self, earlier: EdgeEvent) -> u16 {
+        self.timestamp.wrapping_sub(earlier.timestamp)
+    }
+}
+
+/// Edges of `PIN` time-stamped with `TIM`, buffered in a ring of `N` events
+pub struct EdgeCapture<PIN, TIM, const N: usize> {
+    pin: PIN,
+    timer: Timer<TIM>,
+    events: [EdgeEvent; N],
+    head: usize,
+    len: usize,
+    overflowed: bool,
+}
+
+impl<PIN, TIM, const N: usize> EdgeCapture<PIN, TIM, N>
+where
+    PIN: ExtiPin + ReadPin,
+    TIM: FreeRunningTimer,
+{
+    /// Starts `timer` counting at `tick` and enables the interrupt on both edges of `pin`
+    ///
+    /// The EXTI interrupt of the pin still has to be unmasked in the NVIC.
+    pub fn new(mut pin: PIN, timer: Timer<TIM>, tick: Hertz, exti: &mut Exti, afio: &mut Parts) -> Self {
+        assert!(N > 0);
+        let psc = (timer.clk.raw() / tick.raw()).clamp(1, 1 << 16) - 1;
+        TIM::start(psc as u16);
+        pin.make_interrupt_source(afio);
+        pin.trigger_on_edge(exti, Edge::RisingFalling);
+        pin.clear_interrupt_pending_bit();
+        pin.enable_interrupt(exti);
+        Self {
+            pin,
+            timer,
+            events: [EdgeEvent { timestamp: 0, level: false }; N],
+            head: 0,
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Records the edge, call this from the EXTI interrupt of the pin
+    ///
+    /// When the ring is full the oldest event is dropped and [overflowed](Self::overflowed)
+    /// reports it.
+    pub fn on_interrupt(&mut self) {
+        let timestamp = TIM::now();
+        if !self.pin.check_interrupt() {
+            return;
+        }
+        self.pin.clear_interrupt_pending_bit();
+        let event = EdgeEvent {
+            timestamp,
+            level: self.pin.is_high(),
+        };
+        let tail = (self.head + self.len) % N;
+        self.events[tail] = event;
+        if self.len == N {
+            self.head = (self.head + 1) % N;
+            self.overflowed = true;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Takes the oldest recorded event
+    pub fn pop(&mut self) -> Option<EdgeEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(event)
+    }
+
+    /// Number of recorded events
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no events are recorded
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Drops all recorded events
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+        self.overflowed = false;
+    }
+
+    /// Returns `true` if events were dropped since the last call because the ring was full
+    pub fn overflowed(&mut self) -> bool {
+        core::mem::replace(&mut self.overflowed, false)
+    }
+
+    /// Current counter value of the timer, to time-stamp other events on the same time base
+    pub fn now(&self) -> u16 {
+        TIM::now()
+    }
+
+    /// Disables the interrupt of the pin, stops the timer and releases both
+    pub fn release(mut self, exti: &mut Exti) -> (PIN, Timer<TIM>) {
+        self.pin.disable_interrupt(exti);
+        self.pin.clear_interrupt_pending_bit();
+        TIM::stop();
+        (self.pin, self.timer)
+    }
+}
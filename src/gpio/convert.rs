@@ -91,33 +91,15 @@ impl<const P: char, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
         // Input<PullUp> or Input<PullDown> mode
         let gpio = unsafe { &(*crate::gpio::gpiox::<P>()) };
 
-        if let Some(pull) = MODE::PULL {
+        if let Some(pull) = M::PULL {
             if pull {
                 gpio.pbsc().write(|w| unsafe { w.bits(1 << N) });
             } else {
                 gpio.pbc().write(|w| unsafe { w.bits(1 << N) });
             }
         }
-        
-        match self.pin_id() {
-            0 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg0().bits(M::CNF as u8).pmode0().bits(M::MODE as u8) }),
-            1 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg1().bits(M::CNF as u8).pmode1().bits(M::MODE as u8) }),
-            2 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg2().bits(M::CNF as u8).pmode2().bits(M::MODE as u8) }),
-            3 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg3().bits(M::CNF as u8).pmode3().bits(M::MODE as u8) }),
-            4 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg4().bits(M::CNF as u8).pmode4().bits(M::MODE as u8) }),
-            5 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg5().bits(M::CNF as u8).pmode5().bits(M::MODE as u8) }),
-            6 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg6().bits(M::CNF as u8).pmode6().bits(M::MODE as u8) }),
-            7 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg7().bits(M::CNF as u8).pmode7().bits(M::MODE as u8) }),
-            8 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg8().bits(M::CNF as u8).pmode8().bits(M::MODE as u8) }),
-            9 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg9().bits(M::CNF as u8).pmode9().bits(M::MODE as u8) }),
-            10 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg10().bits(M::CNF as u8).pmode10().bits(M::MODE as u8) }),
-            11 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg11().bits(M::CNF as u8).pmode11().bits(M::MODE as u8) }),
-            12 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg12().bits(M::CNF as u8).pmode12().bits(M::MODE as u8) }),
-            13 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg13().bits(M::CNF as u8).pmode13().bits(M::MODE as u8) }),
-            14 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg14().bits(M::CNF as u8).pmode14().bits(M::MODE as u8) }),
-            15 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg15().bits(M::CNF as u8).pmode15().bits(M::MODE as u8) }),
-            _ => unreachable!()
-        };
+
+        write_cfg(gpio, N, 0b1111, (M::CNF << 2) | M::MODE);
     }
 
     #[inline(always)]
@@ -135,7 +117,7 @@ impl<MODE: PinMode> ErasedPin<MODE> {
         // Input<PullUp> or Input<PullDown> mode
         let gpio = self.block();
 
-        if let Some(pull) = MODE::PULL {
+        if let Some(pull) = M::PULL {
             if pull {
                 gpio.pbsc().write(|w| unsafe { w.bits(1 << n) });
             } else {
@@ -144,25 +126,7 @@ impl<MODE: PinMode> ErasedPin<MODE> {
         }
 
 
-        match self.pin_id() {
-            0 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg0().bits(M::CNF as u8).pmode0().bits(M::MODE as u8) }),
-            1 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg1().bits(M::CNF as u8).pmode1().bits(M::MODE as u8) }),
-            2 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg2().bits(M::CNF as u8).pmode2().bits(M::MODE as u8) }),
-            3 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg3().bits(M::CNF as u8).pmode3().bits(M::MODE as u8) }),
-            4 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg4().bits(M::CNF as u8).pmode4().bits(M::MODE as u8) }),
-            5 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg5().bits(M::CNF as u8).pmode5().bits(M::MODE as u8) }),
-            6 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg6().bits(M::CNF as u8).pmode6().bits(M::MODE as u8) }),
-            7 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg7().bits(M::CNF as u8).pmode7().bits(M::MODE as u8) }),
-            8 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg8().bits(M::CNF as u8).pmode8().bits(M::MODE as u8) }),
-            9 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg9().bits(M::CNF as u8).pmode9().bits(M::MODE as u8) }),
-            10 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg10().bits(M::CNF as u8).pmode10().bits(M::MODE as u8) }),
-            11 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg11().bits(M::CNF as u8).pmode11().bits(M::MODE as u8) }),
-            12 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg12().bits(M::CNF as u8).pmode12().bits(M::MODE as u8) }),
-            13 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg13().bits(M::CNF as u8).pmode13().bits(M::MODE as u8) }),
-            14 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg14().bits(M::CNF as u8).pmode14().bits(M::MODE as u8) }),
-            15 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg15().bits(M::CNF as u8).pmode15().bits(M::MODE as u8) }),
-            _ => unreachable!()
-        };
+        write_cfg(gpio, n, 0b1111, (M::CNF << 2) | M::MODE);
     }
 
     #[inline(always)]
@@ -179,7 +143,7 @@ impl<const P: char, MODE: PinMode> PartiallyErasedPin<P, MODE> {
         let n = self.pin_id();
         // Input<PullUp> or Input<PullDown> mode
         let gpio = unsafe { &(*crate::gpio::gpiox::<P>()) };
-        if let Some(pull) = MODE::PULL {
+        if let Some(pull) = M::PULL {
             if pull {
                 gpio.pbsc().write(|w| unsafe { w.bits(1 << n) });
             } else {
@@ -188,25 +152,7 @@ impl<const P: char, MODE: PinMode> PartiallyErasedPin<P, MODE> {
         }
 
 
-        match self.pin_id() {
-            0 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg0().bits(M::CNF as u8).pmode0().bits(M::MODE as u8) }),
-            1 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg1().bits(M::CNF as u8).pmode1().bits(M::MODE as u8) }),
-            2 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg2().bits(M::CNF as u8).pmode2().bits(M::MODE as u8) }),
-            3 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg3().bits(M::CNF as u8).pmode3().bits(M::MODE as u8) }),
-            4 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg4().bits(M::CNF as u8).pmode4().bits(M::MODE as u8) }),
-            5 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg5().bits(M::CNF as u8).pmode5().bits(M::MODE as u8) }),
-            6 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg6().bits(M::CNF as u8).pmode6().bits(M::MODE as u8) }),
-            7 =>  gpio.pl_cfg().modify(|_,w| unsafe { w.pcfg7().bits(M::CNF as u8).pmode7().bits(M::MODE as u8) }),
-            8 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg8().bits(M::CNF as u8).pmode8().bits(M::MODE as u8) }),
-            9 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg9().bits(M::CNF as u8).pmode9().bits(M::MODE as u8) }),
-            10 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg10().bits(M::CNF as u8).pmode10().bits(M::MODE as u8) }),
-            11 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg11().bits(M::CNF as u8).pmode11().bits(M::MODE as u8) }),
-            12 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg12().bits(M::CNF as u8).pmode12().bits(M::MODE as u8) }),
-            13 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg13().bits(M::CNF as u8).pmode13().bits(M::MODE as u8) }),
-            14 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg14().bits(M::CNF as u8).pmode14().bits(M::MODE as u8) }),
-            15 =>  gpio.ph_cfg().modify(|_,w| unsafe { w.pcfg15().bits(M::CNF as u8).pmode15().bits(M::MODE as u8) }),
-            _ => unreachable!()
-        };
+        write_cfg(gpio, n, 0b1111, (M::CNF << 2) | M::MODE);
     }
 
     #[inline(always)]
@@ -347,6 +293,26 @@ impl<const P: char, const N: u8, CURRENT: PinMode, ORIG: PinMode> Drop
     }
 }
 
+/// Replaces the `mask` bits of the configuration nibble of pin `n` with `bits`
+///
+/// PL_CFG and PH_CFG hold the configuration of 8 pins each, so changing the mode of a pin is a
+/// read-modify-write of a register shared with other pins. It runs in a critical section: an
+/// interrupt changing the mode of another pin of the same port can't be undone by a mode change
+/// it preempted. The new configuration is applied in a single write.
+#[inline(always)]
+pub(super) fn write_cfg(gpio: &crate::pac::gpioa::RegisterBlock, n: u8, mask: u32, bits: u32) {
+    let offset = 4 * (n as u32 % 8);
+    let (mask, bits) = (mask << offset, bits << offset);
+    cortex_m::interrupt::free(|_| {
+        // NOTE(unsafe) all the bits of the register are pin configuration fields
+        if n < 8 {
+            gpio.pl_cfg().modify(|r, w| unsafe { w.bits((r.bits() & !mask) | bits) });
+        } else {
+            gpio.ph_cfg().modify(|r, w| unsafe { w.bits((r.bits() & !mask) | bits) });
+        }
+    });
+}
+
 /// Marker trait for valid pin modes (type state).
 ///
 /// It can not be implemented by outside types.
@@ -0,0 +1,98 @@
+//! # Input debouncing
+//!
+//! [Debouncer] samples a pin on every call to [Debouncer::update], typically from a
+//! periodic timer interrupt, and only accepts a new level after it was stable for
+//! `N` consecutive samples.
+//!
+//! ```rust
+//! let mut timer = Timer::new(dp.Tim2, &clocks).start_count_down(1.millis());
+//! timer.listen(Event::TimeOut);
+//! let mut button = Debouncer::<_, 5>::new(gpioa.pa0.into_pull_up_input(), PinState::Low);
+//!
+//! // in the timer interrupt
+//! timer.clear_interrupt(Event::TimeOut);
+//! button.update();
+//! if button.fell() {
+//!     // button released
+//! }
+//! ```
+
+use super::{PinState, ReadPin};
+
+/// Debounced input with `N` samples of hysteresis
+pub struct Debouncer<PIN, const N: u8> {
+    pin: PIN,
+    active: PinState,
+    count: u8,
+    pressed: bool,
+    rose: bool,
+    fell: bool,
+}
+
+impl<PIN: ReadPin, const N: u8> Debouncer<PIN, N> {
+    /// Creates a debouncer for `pin`, which is considered pressed while at level `active`
+    ///
+    /// The initial level of the pin is taken as the stable state.
+    pub fn new(pin: PIN, active: PinState) -> Self {
+        assert!(N > 0);
+        let mut debouncer = Self {
+            pin,
+            active,
+            count: 0,
+            pressed: false,
+            rose: false,
+            fell: false,
+        };
+        debouncer.pressed = debouncer.sample();
+        debouncer
+    }
+
+    fn sample(&self) -> bool {
+        match self.active {
+            PinState::High => self.pin.is_high(),
+            PinState::Low => self.pin.is_low(),
+        }
+    }
+
+    /// Samples the pin, call this from a periodic tick
+    ///
+    /// Returns true if the debounced state changed.
+    pub fn update(&mut self) -> bool {
+        if self.sample() == self.pressed {
+            self.count = 0;
+            return false;
+        }
+        self.count += 1;
+        if self.count < N {
+            return false;
+        }
+        self.count = 0;
+        self.pressed = !self.pressed;
+        if self.pressed {
+            self.rose = true;
+        } else {
+            self.fell = true;
+        }
+        true
+    }
+
+    /// Returns the debounced state
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Returns true once after the input became pressed
+    pub fn rose(&mut self) -> bool {
+        core::mem::take(&mut self.rose)
+    }
+
+    /// Returns true once after the input was released
+    pub fn fell(&mut self) -> bool {
+        core::mem::take(&mut self.fell)
+    }
+
+    /// Releases the pin
+    pub fn release(self) -> PIN {
+        self.pin
+    }
+}
@@ -25,6 +25,7 @@ pub enum Dynamic {
 }
 
 /// Error for [DynamicPin]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PinModeError {
     /// For operations unsupported in current mode
@@ -145,6 +146,38 @@ impl<const P: char, const N: u8> DynamicPin<P, N> {
         }
     }
 
+    /// Drives the pin high or low depending on `state`
+    pub fn set_state(&mut self, state: PinState) -> Result<(), PinModeError> {
+        match state {
+            PinState::High => self.set_high(),
+            PinState::Low => self.set_low(),
+        }
+    }
+
+    /// Is the output pin driven high?
+    pub fn is_set_high(&self) -> Result<bool, PinModeError> {
+        self.is_set_low().map(|b| !b)
+    }
+
+    /// Is the output pin driven low?
+    pub fn is_set_low(&self) -> Result<bool, PinModeError> {
+        if self.mode.is_output() {
+            Ok(Pin::<P, N, Unknown>::new()._is_set_low())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
+    /// Inverts the output pin
+    pub fn toggle(&mut self) -> Result<(), PinModeError> {
+        if self.mode.is_output() {
+            Pin::<P, N, Unknown>::new()._toggle();
+            Ok(())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
     /// Is the input pin high?
     pub fn is_high(&self) -> Result<bool, PinModeError> {
         self.is_low().map(|b| !b)
@@ -1,5 +1,6 @@
 use super::{marker, Edge, Pin, PinExt};
-use crate::pac::{Interrupt, Exti, Afio};
+use crate::afio::{LineClaimed, Parts, EXTICR};
+use crate::pac::{Interrupt, Exti};
 
 impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
     /// NVIC interrupt number of interrupt from this pin
@@ -23,7 +24,7 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
 /// External Interrupt Pin
 pub trait ExtiPin {
     /// Make corresponding Exti line sensitive to this pin
-    fn make_interrupt_source(&mut self, syscfg: &mut Afio);
+    fn make_interrupt_source(&mut self, afio: &mut Parts);
 
     /// Generate interrupt on rising edge, falling edge or both
     fn trigger_on_edge(&mut self, exti: &mut Exti, level: Edge);
@@ -39,6 +40,11 @@ pub trait ExtiPin {
 
     /// Reads the interrupt pending bit for this pin
     fn check_interrupt(&self) -> bool;
+
+    /// Claims the EXTI line of this pin, see [EXTICR::select_source]
+    ///
+    /// Returns `None` if there is no pin, e.g. for the `NoPin` variant of a pin enum.
+    fn claim_line(&self, exticr: &mut EXTICR) -> Option<Result<ExtiLine, LineClaimed>>;
 }
 
 impl<PIN> ExtiPin for PIN
@@ -47,28 +53,28 @@ where
     PIN::Mode: marker::Interruptible,
 {
     #[inline(always)]
-    fn make_interrupt_source(&mut self, afio: &mut Afio) {
+    fn make_interrupt_source(&mut self, afio: &mut Parts) {
         let i = self.pin_id();
         let port = self.port_id() as u32;
         let offset = 4 * (i % 4);
         match i {
             0..=3 => {
-                afio.exti_cfg1().modify(|r, w| unsafe {
+                afio.exticfg1.exti_cfg1().modify(|r, w| unsafe {
                     w.bits((r.bits() & !(0xf << offset)) | (port << offset))
                 });
             }
             4..=7 => {
-                afio.exti_cfg2().modify(|r, w| unsafe {
+                afio.exticfg2.exti_cfg2().modify(|r, w| unsafe {
                     w.bits((r.bits() & !(0xf << offset)) | (port << offset))
                 });
             }
             8..=11 => {
-                afio.exti_cfg3().modify(|r, w| unsafe {
+                afio.exticfg3.exti_cfg3().modify(|r, w| unsafe {
                     w.bits((r.bits() & !(0xf << offset)) | (port << offset))
                 });
             }
             12..=15 => {
-                afio.exti_cfg4().modify(|r, w| unsafe {
+                afio.exticfg4.exti_cfg4().modify(|r, w| unsafe {
                     w.bits((r.bits() & !(0xf << offset)) | (port << offset))
                 });
             }
@@ -78,27 +84,7 @@ where
 
     #[inline(always)]
     fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge) {
-        let i = self.pin_id();
-        match edge {
-            Edge::Rising => {
-                exti.rt_cfg()
-                    .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
-                exti.ft_cfg()
-                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
-            }
-            Edge::Falling => {
-                exti.rt_cfg()
-                    .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
-                exti.ft_cfg()
-                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
-            }
-            Edge::RisingFalling => {
-                exti.rt_cfg()
-                    .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
-                exti.ft_cfg()
-                    .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
-            }
-        }
+        set_edge(exti, self.pin_id(), edge);
     }
 
     #[inline(always)]
@@ -122,4 +108,98 @@ where
     fn check_interrupt(&self) -> bool {
         unsafe { ((*Exti::ptr()).pend().read().bits() & (1 << self.pin_id())) != 0 }
     }
+
+    #[inline(always)]
+    fn claim_line(&self, exticr: &mut EXTICR) -> Option<Result<ExtiLine, LineClaimed>> {
+        Some(exticr.select_source(self))
+    }
+}
+
+fn set_edge(exti: &mut Exti, i: u8, edge: Edge) {
+    match edge {
+        Edge::Rising => {
+            exti.rt_cfg()
+                .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+            exti.ft_cfg()
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
+        }
+        Edge::Falling => {
+            exti.rt_cfg()
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
+            exti.ft_cfg()
+                .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+        }
+        Edge::RisingFalling => {
+            exti.rt_cfg()
+                .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+            exti.ft_cfg()
+                .modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+        }
+    }
+}
+
+/// EXTI line claimed for a pin with [EXTICR::select_source](crate::afio::EXTICR::select_source)
+///
+/// The line stays claimed until it is handed back to
+/// [EXTICR::release](crate::afio::EXTICR::release).
+#[derive(Debug)]
+pub struct ExtiLine {
+    line: u8,
+    port: u8,
+}
+
+impl ExtiLine {
+    pub(crate) fn new(line: u8, port: u8) -> Self {
+        Self { line, port }
+    }
+
+    /// Number of the line, which is also the number of its pin
+    pub fn line(&self) -> u8 {
+        self.line
+    }
+
+    /// Port of the pin, starting from 0 for port A
+    pub fn port(&self) -> u8 {
+        self.port
+    }
+
+    /// NVIC interrupt number of interrupt from this line
+    pub fn interrupt(&self) -> Interrupt {
+        match self.line {
+            0 => Interrupt::EXTI0,
+            1 => Interrupt::EXTI1,
+            2 => Interrupt::EXTI2,
+            3 => Interrupt::EXTI3,
+            4 => Interrupt::EXTI4,
+            5..=9 => Interrupt::EXTI9_5,
+            _ => Interrupt::EXTI15_10,
+        }
+    }
+
+    /// Generate interrupt on rising edge, falling edge or both
+    pub fn trigger_on_edge(&mut self, exti: &mut Exti, edge: Edge) {
+        set_edge(exti, self.line, edge);
+    }
+
+    /// Enable external interrupts from this line
+    pub fn enable_interrupt(&mut self, exti: &mut Exti) {
+        exti.imask()
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << self.line)) });
+    }
+
+    /// Disable external interrupts from this line
+    pub fn disable_interrupt(&mut self, exti: &mut Exti) {
+        exti.imask()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << self.line)) });
+    }
+
+    /// Clear the interrupt pending bit for this line
+    pub fn clear_interrupt_pending_bit(&mut self) {
+        unsafe { (*Exti::ptr()).pend().write(|w| w.bits(1 << self.line)) };
+    }
+
+    /// Reads the interrupt pending bit for this line
+    pub fn check_interrupt(&self) -> bool {
+        unsafe { ((*Exti::ptr()).pend().read().bits() & (1 << self.line)) != 0 }
+    }
 }
@@ -288,6 +288,22 @@ impl<const P: char, const N: u8> OutputPin for DynamicPin<P, N> {
     }
 }
 
+impl<const P: char, const N: u8> StatefulOutputPin for DynamicPin<P, N> {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        self.is_set_high()
+    }
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        self.is_set_low()
+    }
+}
+
+impl<const P: char, const N: u8> ToggleableOutputPin for DynamicPin<P, N> {
+    type Error = PinModeError;
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.toggle()
+    }
+}
+
 impl<const P: char, const N: u8> InputPin for DynamicPin<P, N> {
     type Error = PinModeError;
     fn is_high(&self) -> Result<bool, Self::Error> {
@@ -35,6 +35,12 @@ impl<const P: char, const N: u8, MODE> StatefulOutputPin for Pin<P, N, Output<MO
     fn is_set_low(&mut self) -> Result<bool, Self::Error> {
         Ok(Self::is_set_low(self))
     }
+
+    #[inline(always)]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Self::toggle(self);
+        Ok(())
+    }
 }
 
 impl<const P: char, const N: u8, MODE> InputPin for Pin<P, N, MODE>
@@ -81,6 +87,12 @@ impl<MODE> StatefulOutputPin for ErasedPin<Output<MODE>> {
     fn is_set_low(&mut self) -> Result<bool, Self::Error> {
         Ok(Self::is_set_low(self))
     }
+
+    #[inline(always)]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Self::toggle(self);
+        Ok(())
+    }
 }
 
 impl<MODE> InputPin for ErasedPin<MODE>
@@ -127,6 +139,12 @@ impl<const P: char, MODE> StatefulOutputPin for PartiallyErasedPin<P, Output<MOD
     fn is_set_low(&mut self) -> Result<bool, Self::Error> {
         Ok(Self::is_set_low(self))
     }
+
+    #[inline(always)]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Self::toggle(self);
+        Ok(())
+    }
 }
 
 impl<const P: char, MODE> InputPin for PartiallyErasedPin<P, MODE>
@@ -158,6 +176,18 @@ impl<const P: char, const N: u8> OutputPin for DynamicPin<P, N> {
     }
 }
 
+impl<const P: char, const N: u8> StatefulOutputPin for DynamicPin<P, N> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Self::is_set_high(self)
+    }
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Self::is_set_low(self)
+    }
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Self::toggle(self)
+    }
+}
+
 impl<const P: char, const N: u8> InputPin for DynamicPin<P, N> {
     fn is_high(&mut self) -> Result<bool, Self::Error> {
         Self::is_high(self)
@@ -0,0 +1,163 @@
+//! Several output pins of one port driven with a single register write
+//!
+//! Setting pins one after another takes one write per pin and lets the outputs change
+//! at different times. The types here compute the set and reset bits of all their pins
+//! and write them to the bit set/reset register at once, so all pins switch together.
+//!
+//! ```rust
+//! // 4 bit bus on PB12..PB15
+//! let mut bus = OutPort4::new((
+//!     gpiob.pb12.into_push_pull_output(),
+//!     gpiob.pb13.into_push_pull_output(),
+//!     gpiob.pb14.into_push_pull_output(),
+//!     gpiob.pb15.into_push_pull_output(),
+//! ));
+//! bus.write(0b1010);
+//! ```
+
+use super::*;
+
+/// Output pins of one port written at once
+pub trait OutPort {
+    /// Word holding one bit per pin, bit 0 is the first pin
+    type Target;
+    /// Drives every pin high
+    fn all_high(&mut self);
+    /// Drives every pin low
+    fn all_low(&mut self);
+    /// Drives each pin to the state of its bit in `word`
+    fn write(&mut self, word: Self::Target);
+    /// Drives the pins whose bit is set in `set` high and those whose bit is set in `clear` low
+    ///
+    /// Pins in both masks are driven high.
+    fn modify(&mut self, set: Self::Target, clear: Self::Target);
+}
+
+/// Drives the port pins set in `set` high and those set in `clear` low with a single write
+///
+/// `set` wins for pins present in both masks. This doesn't check that the pins are
+/// owned or configured as outputs, see [OutPort] for a typed API.
+///
+/// # Safety
+/// The caller has to own every pin in the masks.
+#[inline(always)]
+pub unsafe fn modify_outputs<const P: char>(set: u16, clear: u16) {
+    (*gpiox::<P>()).pbsc().write(|w| w.bits(((clear as u32) << 16) | set as u32))
+}
+
+macro_rules! out_port {
+    ( $name:ident, ( $($i:tt),+ ), ( $($N:ident),+ )) => {
+        /// Output pins of one port written at once
+        pub struct $name<const P: char $(, const $N: u8)+, MODE = PushPull> (
+            $(pub Pin<P, $N, Output<MODE>>,)+
+        );
+
+        impl<const P: char $(, const $N: u8)+, MODE> $name<P $(, $N)+, MODE> {
+            const ALL: u16 = 0 $( | (1 << $N))+;
+
+            /// Groups `pins`, the first one is bit 0
+            pub fn new(pins: ($(Pin<P, $N, Output<MODE>>,)+)) -> Self {
+                Self($(pins.$i,)+)
+            }
+
+            /// Releases the pins
+            pub fn release(self) -> ($(Pin<P, $N, Output<MODE>>,)+) {
+                ($(self.$i,)+)
+            }
+
+            /// Port bits of the pins whose bit is set in `word`
+            #[inline(always)]
+            const fn port_bits(word: u8) -> u16 {
+                let mut bits = 0;
+                $(
+                    if word & (1 << $i) != 0 {
+                        bits |= 1 << $N;
+                    }
+                )+
+                bits
+            }
+        }
+
+        impl<const P: char $(, const $N: u8)+, MODE> OutPort for $name<P $(, $N)+, MODE> {
+            type Target = u8;
+
+            fn all_high(&mut self) {
+                // NOTE(unsafe) the pins are owned
+                unsafe { modify_outputs::<P>(Self::ALL, 0) }
+            }
+
+            fn all_low(&mut self) {
+                // NOTE(unsafe) the pins are owned
+                unsafe { modify_outputs::<P>(0, Self::ALL) }
+            }
+
+            fn write(&mut self, word: u8) {
+                let high = Self::port_bits(word);
+                // NOTE(unsafe) the pins are owned
+                unsafe { modify_outputs::<P>(high, Self::ALL & !high) }
+            }
+
+            fn modify(&mut self, set: u8, clear: u8) {
+                // NOTE(unsafe) the pins are owned
+                unsafe { modify_outputs::<P>(Self::port_bits(set), Self::port_bits(clear)) }
+            }
+        }
+    };
+}
+
+out_port!(OutPort2, (0, 1), (N0, N1));
+out_port!(OutPort3, (0, 1, 2), (N0, N1, N2));
+out_port!(OutPort4, (0, 1, 2, 3), (N0, N1, N2, N3));
+out_port!(OutPort5, (0, 1, 2, 3, 4), (N0, N1, N2, N3, N4));
+out_port!(OutPort6, (0, 1, 2, 3, 4, 5), (N0, N1, N2, N3, N4, N5));
+out_port!(OutPort7, (0, 1, 2, 3, 4, 5, 6), (N0, N1, N2, N3, N4, N5, N6));
+out_port!(OutPort8, (0, 1, 2, 3, 4, 5, 6, 7), (N0, N1, N2, N3, N4, N5, N6, N7));
+
+/// Up to 16 partially erased output pins of one port, the first one is bit 0
+pub struct OutPortArray<const P: char, const SIZE: usize, MODE = PushPull>(pub [PEPin<P, Output<MODE>>; SIZE]);
+
+impl<const P: char, const SIZE: usize, MODE> OutPortArray<P, SIZE, MODE> {
+    /// Fails the build of the [OutPort] methods for more pins than bits of the `u16` word
+    const FITS_WORD: () = assert!(SIZE <= 16, "an OutPortArray holds at most 16 pins");
+
+    fn all(&self) -> u16 {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_WORD;
+        self.0.iter().fold(0, |bits, pin| bits | (1 << pin.i))
+    }
+
+    fn port_bits(&self, word: u16) -> u16 {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_WORD;
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| word & (1 << bit) != 0)
+            .fold(0, |bits, (_, pin)| bits | (1 << pin.i))
+    }
+}
+
+impl<const P: char, const SIZE: usize, MODE> OutPort for OutPortArray<P, SIZE, MODE> {
+    type Target = u16;
+
+    fn all_high(&mut self) {
+        // NOTE(unsafe) the pins are owned
+        unsafe { modify_outputs::<P>(self.all(), 0) }
+    }
+
+    fn all_low(&mut self) {
+        // NOTE(unsafe) the pins are owned
+        unsafe { modify_outputs::<P>(0, self.all()) }
+    }
+
+    fn write(&mut self, word: u16) {
+        let high = self.port_bits(word);
+        // NOTE(unsafe) the pins are owned
+        unsafe { modify_outputs::<P>(high, self.all() & !high) }
+    }
+
+    fn modify(&mut self, set: u16, clear: u16) {
+        // NOTE(unsafe) the pins are owned
+        unsafe { modify_outputs::<P>(self.port_bits(set), self.port_bits(clear)) }
+    }
+}
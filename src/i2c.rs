@@ -1,4 +1,4 @@
-use core::ops::Deref;
+use core::ops::{Deref, RangeInclusive};
 
 use crate::pac::{self, I2c1, I2c2};
 #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
@@ -6,22 +6,30 @@ use crate::pac::{I2c3, I2c4};
 
 use crate::rcc::{Enable, Reset};
 
-use crate::gpio::{self, Alternate, OpenDrain};
+use crate::gpio::{self, Alternate, OpenDrain, PinMode};
 
 use crate::rcc::Clocks;
+use enumflags2::BitFlags;
 use fugit::{HertzU32 as Hertz, RateExtU32};
 
+use crate::time::MicroSecond;
+
 mod hal_02;
 mod hal_1;
 
+pub mod bitbang;
 pub mod dma;
+mod multi_master;
+pub use multi_master::{I2cMultiMaster, MultiMasterConfig};
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Eq, PartialEq)]
 pub enum DutyCycle {
     Ratio2to1,
     Ratio16to9,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, PartialEq, Eq)]
 pub enum Mode {
     Standard {
@@ -31,6 +39,11 @@ pub enum Mode {
         frequency: Hertz,
         duty_cycle: DutyCycle,
     },
+    /// Fast-mode Plus, up to 1 MHz
+    FastPlus {
+        frequency: Hertz,
+        duty_cycle: DutyCycle,
+    },
 }
 
 impl Mode {
@@ -45,10 +58,18 @@ impl Mode {
         }
     }
 
+    pub fn fast_plus(frequency: Hertz, duty_cycle: DutyCycle) -> Self {
+        Self::FastPlus {
+            frequency,
+            duty_cycle,
+        }
+    }
+
     pub fn get_frequency(&self) -> Hertz {
         match *self {
             Self::Standard { frequency } => frequency,
             Self::Fast { frequency, .. } => frequency,
+            Self::FastPlus { frequency, .. } => frequency,
         }
     }
 }
@@ -56,8 +77,14 @@ impl Mode {
 impl From<Hertz> for Mode {
     fn from(frequency: Hertz) -> Self {
         let k100: Hertz = 100.kHz();
+        let k400: Hertz = 400.kHz();
         if frequency <= k100 {
             Self::Standard { frequency }
+        } else if frequency > k400 {
+            Self::FastPlus {
+                frequency,
+                duty_cycle: DutyCycle::Ratio16to9,
+            }
         } else {
             Self::Fast {
                 frequency,
@@ -67,11 +94,107 @@ impl From<Hertz> for Mode {
     }
 }
 
+/// I2C interrupt events
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Event {
+    /// Error interrupt
+    ///
+    /// Generated on BUSERR, ARLOST, ACKFAIL, OVERRUN, PECERR, TIMOUT and SMBALERT
+    Error = 1 << 8,
+    /// Event interrupt
+    ///
+    /// Generated on STARTBF, ADDRF, ADDR10F, STOPF and BYTEF
+    Event = 1 << 9,
+    /// Buffer interrupt
+    ///
+    /// Generated on RXDATNE and TXDATE when the event interrupt is enabled
+    Buffer = 1 << 10,
+}
+
+/// I2C status flags
+///
+/// The lower half maps to STS1, the upper half to STS2
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// Start condition generated
+    Start = 1 << 0,
+    /// Address sent (master) or matched (slave)
+    Address = 1 << 1,
+    /// Byte transfer finished
+    ByteTransferFinished = 1 << 2,
+    /// 10-bit header sent
+    Address10 = 1 << 3,
+    /// Stop condition detected
+    Stop = 1 << 4,
+    /// Data register not empty
+    RxNotEmpty = 1 << 6,
+    /// Data register empty
+    TxEmpty = 1 << 7,
+    /// Bus error
+    BusError = 1 << 8,
+    /// Arbitration lost
+    ArbitrationLoss = 1 << 9,
+    /// Acknowledge failure
+    AcknowledgeFailure = 1 << 10,
+    /// Overrun/underrun
+    Overrun = 1 << 11,
+    /// PEC error in reception
+    PecError = 1 << 12,
+    /// Timeout or Tlow error
+    Timeout = 1 << 14,
+    /// SMBus alert
+    SmbusAlert = 1 << 15,
+    /// Master mode
+    Master = 1 << 16,
+    /// Bus busy
+    Busy = 1 << 17,
+    /// Transmitter (data bytes are being sent)
+    Transmitter = 1 << 18,
+    /// General call address received
+    GeneralCall = 1 << 20,
+    /// SMBus device default address received
+    SmbusDefault = 1 << 21,
+    /// SMBus host header received
+    SmbusHost = 1 << 22,
+    /// Received address matched the dual address
+    DualAddress = 1 << 23,
+}
+
+/// I2C clearable flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum CFlag {
+    /// Bus error
+    BusError = 1 << 8,
+    /// Arbitration lost
+    ArbitrationLoss = 1 << 9,
+    /// Acknowledge failure
+    AcknowledgeFailure = 1 << 10,
+    /// Overrun/underrun
+    Overrun = 1 << 11,
+    /// PEC error in reception
+    PecError = 1 << 12,
+    /// Timeout or Tlow error
+    Timeout = 1 << 14,
+    /// SMBus alert
+    SmbusAlert = 1 << 15,
+}
+
 /// I2C abstraction
 pub struct I2c<I2C: Instance, PINS>
 {
     i2c: I2C,
     pins: PINS,
+    /// APB1 clock the timings were computed from
+    clk: Hertz,
 }
 
 pub use embedded_hal::i2c::NoAcknowledgeSource;
@@ -87,6 +210,12 @@ pub enum Error {
     Bus,
     Crc,
     ArbitrationLoss,
+    /// A register read was given an empty buffer, so there was nothing to read
+    EmptyBuffer,
+    /// PCLK1 is outside 2 to 50 MHz, or can't be divided down to the SCL frequency
+    InvalidFrequency,
+    /// A DMA buffer holds more than [MAX_TRANSFER_LENGTH](crate::dma::MAX_TRANSFER_LENGTH) bytes
+    BufferTooLong,
 }
 
 impl Error {
@@ -116,47 +245,49 @@ pub trait Instance:
     fn ptr() -> *const crate::pac::i2c1::RegisterBlock;
 }
 
-pub trait Pins<I2C>: Sized {
+/// SCL and SDA pins of `I2C` on one of its remaps
+///
+/// Only implemented for pins in alternate open-drain mode: push-pull outputs would fight the
+/// other devices of the bus. Use [IntoPins::into_i2c_pins] to convert a pair of pins.
+pub trait Pins<I2C>: crate::Sealed + Sized {
     const REMAP: bool;
 }
 
-impl Pins<pac::I2c1>
-    for (
-        gpio::PB6<Alternate<OpenDrain>>,
-        gpio::PB7<Alternate<OpenDrain>>,
-    )
-{
-    const REMAP: bool = false;
-}
+/// Pair of pins that can be converted to the [Pins] of `I2C`
+pub trait IntoPins<I2C> {
+    type Pins: Pins<I2C>;
 
-impl Pins<pac::I2c1>
-    for (
-        gpio::PB8<Alternate<OpenDrain>>,
-        gpio::PB9<Alternate<OpenDrain>>,
-    )
-{
-    const REMAP: bool = true;
+    /// Configures both pins in alternate open-drain mode
+    fn into_i2c_pins(self) -> Self::Pins;
 }
 
-impl Pins<pac::I2c2>
-    for (
-        gpio::PB10<Alternate<OpenDrain>>,
-        gpio::PB11<Alternate<OpenDrain>>,
-    )
-{
-    const REMAP: bool = false;
-}
+macro_rules! pins {
+    ($($I2C:ty: ($SCL:ident, $SDA:ident, remap: $remap:literal),)+) => {
+        $(
+            impl crate::Sealed for (gpio::$SCL<Alternate<OpenDrain>>, gpio::$SDA<Alternate<OpenDrain>>) {}
 
-// editor's note: the rmp register docs in the user guide claims this is pc4 but this is a typo
-impl Pins<pac::I2c2>
-    for (
-        gpio::PA4<Alternate<OpenDrain>>,
-        gpio::PA5<Alternate<OpenDrain>>,
-    )
-{
-    const REMAP: bool = true;
+            impl Pins<$I2C> for (gpio::$SCL<Alternate<OpenDrain>>, gpio::$SDA<Alternate<OpenDrain>>) {
+                const REMAP: bool = $remap;
+            }
+
+            impl<SCLMODE: PinMode, SDAMODE: PinMode> IntoPins<$I2C> for (gpio::$SCL<SCLMODE>, gpio::$SDA<SDAMODE>) {
+                type Pins = (gpio::$SCL<Alternate<OpenDrain>>, gpio::$SDA<Alternate<OpenDrain>>);
+
+                fn into_i2c_pins(self) -> Self::Pins {
+                    (self.0.into_alternate_open_drain(), self.1.into_alternate_open_drain())
+                }
+            }
+        )+
+    };
 }
 
+pins! {
+    pac::I2c1: (PB6, PB7, remap: false),
+    pac::I2c1: (PB8, PB9, remap: true),
+    pac::I2c2: (PB10, PB11, remap: false),
+    // editor's note: the rmp register docs in the user guide claims this is pc4 but this is a typo
+    pac::I2c2: (PA4, PA5, remap: true),
+}
 
 // Implemented by all I2C instances
 macro_rules! i2c {
@@ -181,7 +312,7 @@ i2c! { pac::I2c4: I2c4Inst }
 
 impl<PINS> I2c<I2c1, PINS> {
     /// Creates a generic I2C2 object on pins PB10 and PB11 using the embedded-hal `BlockingI2c` trait.
-    pub fn i2c1<M: Into<Mode>>(i2c: I2c1, pins: PINS, mode: M, clocks: &Clocks) -> Self
+    pub fn i2c1<M: Into<Mode>>(i2c: I2c1, pins: PINS, mode: M, clocks: &Clocks) -> Result<Self, (Error, I2c1, PINS)>
     where
         PINS: Pins<I2c1>,
     {
@@ -191,7 +322,7 @@ impl<PINS> I2c<I2c1, PINS> {
 
 impl<PINS> I2c<I2c2, PINS> {
     /// Creates a generic I2C2 object on pins PB10 and PB11 using the embedded-hal `BlockingI2c` trait.
-    pub fn i2c2<M: Into<Mode>>(i2c: I2c2, pins: PINS, mode: M, clocks: &Clocks) -> Self
+    pub fn i2c2<M: Into<Mode>>(i2c: I2c2, pins: PINS, mode: M, clocks: &Clocks) -> Result<Self, (Error, I2c2, PINS)>
     where
         PINS: Pins<I2c2>,
     {
@@ -202,7 +333,7 @@ impl<PINS> I2c<I2c2, PINS> {
 #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 impl<PINS> I2c<I2c3, PINS> {
     /// Creates a generic I2C2 object on pins PB10 and PB11 using the embedded-hal `BlockingI2c` trait.
-    pub fn i2c3<M: Into<Mode>>(i2c: I2c3, pins: PINS, mode: M, clocks: &Clocks) -> Self
+    pub fn i2c3<M: Into<Mode>>(i2c: I2c3, pins: PINS, mode: M, clocks: &Clocks) -> Result<Self, (Error, I2c3, PINS)>
     where
         PINS: Pins<I2c3>,
     {
@@ -213,7 +344,7 @@ impl<PINS> I2c<I2c3, PINS> {
 #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 impl<PINS> I2c<I2c4, PINS> {
     /// Creates a generic I2C2 object on pins PB10 and PB11 using the embedded-hal `BlockingI2c` trait.
-    pub fn i2c4<M: Into<Mode>>(i2c: I2c4, pins: PINS, mode: M, clocks: &Clocks) -> Self
+    pub fn i2c4<M: Into<Mode>>(i2c: I2c4, pins: PINS, mode: M, clocks: &Clocks) -> Result<Self, (Error, I2c4, PINS)>
     where
         PINS: Pins<I2c4>,
     {
@@ -227,21 +358,25 @@ where
     I2C: Instance,
     PINS: Pins<I2C>
 {
+    /// Returns [Error::InvalidFrequency] along with the peripheral and the pins if the SCL
+    /// frequency of `mode` can't be generated from PCLK1
     pub fn new(
         i2c: I2C,
         pins: PINS,
         mode: impl Into<Mode>,
         clocks: &Clocks,
-    ) -> Self {
+    ) -> Result<Self, (Error, I2C, PINS)> {
         unsafe {
             // Enable and reset clock.
             I2C::enable_unchecked();
             I2C::reset_unchecked();
         }
 
-        let i2c = I2c { i2c, pins };
-        i2c.i2c_init(mode, clocks.pclk1());
-        i2c
+        let i2c = I2c { i2c, pins, clk: clocks.pclk1() };
+        match i2c.i2c_init(mode, clocks.pclk1()) {
+            Ok(()) => Ok(i2c),
+            Err(error) => Err((error, i2c.i2c, i2c.pins)),
+        }
     }
 
     pub fn release(self) -> (I2C, PINS) {
@@ -249,72 +384,111 @@ where
     }
 }
 
+impl<I2C: Instance, PINS> I2c<I2C, PINS> {
+    /// Returns the bus clock the I2C was set up with
+    pub fn clock(&self) -> Hertz {
+        self.clk
+    }
+
+    /// Returns the SCL frequency actually generated from the programmed clock control register
+    pub fn scl_frequency(&self, clocks: &Clocks) -> Hertz {
+        let clkctrl = self.i2c.clkctrl().read();
+        // zero before the peripheral is configured
+        let ccr = (clkctrl.clkctrl().bits() as u32).max(1);
+        let divider = match (clkctrl.fsmode().bit_is_set(), clkctrl.duty().bit_is_set()) {
+            (false, _) => 2,
+            (true, false) => 3,
+            (true, true) => 25,
+        };
+        Hertz::from_raw(clocks.pclk1().raw() / (ccr * divider))
+    }
+
+    /// Enables or disables clock stretching in slave mode (enabled after reset)
+    ///
+    /// Some masters can't handle a slave stretching SCL. With stretching disabled the
+    /// application must keep up with the transfer or OVERRUN is reported.
+    ///
+    /// Note: unlike newer I2C blocks this peripheral has no configurable analog or digital
+    /// noise filters, glitches shorter than 50 ns are always suppressed.
+    pub fn set_clock_stretching(&mut self, enabled: bool) {
+        self.i2c.ctrl1().modify(|_, w| w.noextend().bit(!enabled));
+    }
+}
+
 impl<I2C: Instance,PINS> I2c<I2C,PINS> {
-    fn i2c_init(&self, mode: impl Into<Mode>, pclk: Hertz) {
+    fn i2c_init(&self, mode: impl Into<Mode>, pclk: Hertz) -> Result<(), Error> {
         let mode = mode.into();
-        // Make sure the I2C unit is disabled so we can configure it
-        self.i2c.ctrl1().modify(|_, w| w.en().clear_bit());
 
         // Calculate settings for I2C speed modes
         let clock = pclk.raw();
         let clc_mhz = clock / 1_000_000;
-        assert!((2..=50).contains(&clc_mhz));
-
-        // Configure bus frequency into I2C peripheral
-        self.i2c
-            .ctrl2()
-            .write(|w| unsafe { w.clkfreq().bits(clc_mhz as u8) });
+        if !(2..=50).contains(&clc_mhz) {
+            return Err(Error::InvalidFrequency);
+        }
 
         let trise = match mode {
             Mode::Standard { .. } => clc_mhz + 1,
             Mode::Fast { .. } => clc_mhz * 300 / 1000 + 1,
+            Mode::FastPlus { .. } => clc_mhz * 120 / 1000 + 1,
         };
 
+        // PCLK1 periods per SCL period and CLKCTRL step, and the smallest CLKCTRL
+        let (fast, duty, divider, min) = match mode {
+            Mode::Standard { .. } => (false, false, 2, 0x04),
+            Mode::Fast { duty_cycle, .. } | Mode::FastPlus { duty_cycle, .. } => match duty_cycle {
+                DutyCycle::Ratio2to1 => (true, false, 3, 1),
+                DutyCycle::Ratio16to9 => (true, true, 25, 1),
+            },
+        };
+        let frequency = mode.get_frequency().raw() as u64;
+        if frequency == 0 {
+            return Err(Error::InvalidFrequency);
+        }
+        // CLKCTRL is 12 bits wide and has a lower bound, don't round into the range
+        let ccr = clock as u64 / (frequency * divider);
+        if !(min..=0xfff).contains(&ccr) {
+            return Err(Error::InvalidFrequency);
+        }
+
+        // Make sure the I2C unit is disabled so we can configure it
+        self.i2c.ctrl1().modify(|_, w| w.en().clear_bit());
+
+        // Configure bus frequency into I2C peripheral
+        self.i2c
+            .ctrl2()
+            .write(|w| unsafe { w.clkfreq().bits(clc_mhz as u8) });
+
         // Configure correct rise times
         unsafe { self.i2c.tmrise().write(|w| w.tmrise().bits(trise as u8)) };
 
-        match mode {
-            // I2C clock control calculation
-            Mode::Standard { frequency } => {
-                let mut ccr = (clock / (frequency.raw() * 2)).max(4);
-                if ccr < 0x04 {
-                    ccr = 0x04
-                }
-                // Set clock to standard mode with appropriate parameters for selected speed
-                self.i2c.clkctrl().modify(|_,w| unsafe {
-                    w.fsmode()
-                        .clear_bit()
-                        .duty()
-                        .clear_bit()
-                        .clkctrl()
-                        .bits(ccr as u16)
-                });
-            }
-            Mode::Fast {
-                frequency,
-                duty_cycle,
-            } => match duty_cycle {
-                DutyCycle::Ratio2to1 => {
-                    let ccr = (clock / (frequency.raw() * 3)).max(1);
-
-                    // Set clock to fast mode with appropriate parameters for selected speed (2:1 duty cycle)
-                    self.i2c.clkctrl().write(|w| unsafe {
-                        w.fsmode().set_bit().duty().clear_bit().clkctrl().bits(ccr as u16)
-                    });
-                }
-                DutyCycle::Ratio16to9 => {
-                    let ccr = (clock / (frequency.raw() * 25)).max(1);
-
-                    // Set clock to fast mode with appropriate parameters for selected speed (16:9 duty cycle)
-                    self.i2c.clkctrl().write(|w| unsafe {
-                        w.fsmode().set_bit().duty().set_bit().clkctrl().bits(ccr as u16)
-                    });
-                }
-            },
-        }
+        // I2C clock control calculation
+        self.i2c.clkctrl().write(|w| unsafe {
+            w.fsmode()
+                .bit(fast)
+                .duty()
+                .bit(duty)
+                .clkctrl()
+                .bits(ccr as u16)
+        });
 
         // Enable the I2C processing
         self.i2c.ctrl1().modify(|_, w| w.en().set_bit());
+        Ok(())
+    }
+
+    fn listen_event(&mut self, disable: Option<BitFlags<Event>>, enable: Option<BitFlags<Event>>) {
+        self.i2c.ctrl2().modify(|r, w| unsafe {
+            w.bits({
+                let mut bits = r.bits();
+                if let Some(d) = disable {
+                    bits &= !d.bits();
+                }
+                if let Some(e) = enable {
+                    bits |= e.bits();
+                }
+                bits
+            })
+        });
     }
 
     fn check_and_clear_error_flags(&self) -> Result<pac::i2c1::sts1::R, Error> {
@@ -358,12 +532,14 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
 
     /// Sends START and Address for writing
     #[inline(always)]
-    fn prepare_write(&self, addr: u8) -> Result<(), Error> {
+    fn prepare_write(&self, addr: u8, deadline: &mut impl Deadline) -> Result<(), Error> {
         // Send a START condition
         self.i2c.ctrl1().modify(|_, w| w.startgen().set_bit());
 
         // Wait until START condition was generated
-        while self.check_and_clear_error_flags()?.startbf().bit_is_clear() {}
+        while self.check_and_clear_error_flags()?.startbf().bit_is_clear() {
+            deadline.check()?;
+        }
 
         // Also wait until signalled we're master and everything is waiting for us
         loop {
@@ -373,6 +549,7 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
             if sr2.msmode().bit_is_set() && sr2.busy().bit_is_set() {
                 break;
             }
+            deadline.check()?;
         }
 
         // Set up current address, we're trying to talk to
@@ -391,6 +568,7 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
             if sts1.addrf().bit_is_set() {
                 break;
             }
+            deadline.check()?;
         }
         self.i2c.sts1().read();
         // Clear condition by reading SR2
@@ -400,20 +578,25 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
     }
 
     /// Sends START and Address for reading
-    fn prepare_read(&self, addr: u8) -> Result<(), Error> {
+    fn prepare_read(&self, addr: u8, deadline: &mut impl Deadline) -> Result<(), Error> {
         // Send a START condition and set ACK bit
         self.i2c
             .ctrl1()
             .modify(|_, w| w.startgen().set_bit().acken().set_bit());
 
-        // Wait until START condition was generated
-        while self.i2c.sts1().read().startbf().bit_is_clear() {}
+        // Wait until START condition was generated, arbitration may be lost on a shared bus
+        while self.check_and_clear_error_flags()?.startbf().bit_is_clear() {
+            deadline.check()?;
+        }
 
         // Also wait until signalled we're master and everything is waiting for us
         while {
+            self.check_and_clear_error_flags()?;
             let sts2 = self.i2c.sts2().read();
             sts2.msmode().bit_is_clear() && sts2.busy().bit_is_clear()
-        } {}
+        } {
+            deadline.check()?;
+        }
 
         // Set up current address, we're trying to talk to
         self.i2c
@@ -427,6 +610,7 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
             if self.i2c.sts1().read().addrf().bit_is_set() {
                 break;
             }
+            deadline.check()?;
         }
         self.i2c.sts1().read();
         // Clear condition by reading SR2
@@ -435,17 +619,17 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
         Ok(())
     }
 
-    fn write_bytes(&mut self, bytes: impl Iterator<Item = u8>) -> Result<(), Error> {
+    fn write_bytes(&mut self, bytes: impl Iterator<Item = u8>, deadline: &mut impl Deadline) -> Result<(), Error> {
         // Send bytes
         for c in bytes {
-            self.send_byte(c)?;
+            self.send_byte(c, deadline)?;
         }
 
         // Fallthrough is success
         Ok(())
     }
 
-    fn send_byte(&self, byte: u8) -> Result<(), Error> {
+    fn send_byte(&self, byte: u8, deadline: &mut impl Deadline) -> Result<(), Error> {
         // Wait until we're ready for sending
         // Check for any I2C errors. If a NACK occurs, the ADDR bit will never be set.
         while self
@@ -453,7 +637,9 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
             .map_err(Error::nack_addr)?
             .txdate()
             .bit_is_clear()
-        {}
+        {
+            deadline.check()?;
+        }
 
         // Push out a byte of data
         self.i2c.dat().write(|w| unsafe { w.bits(u32::from(byte)) });
@@ -465,11 +651,13 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
             .map_err(Error::nack_data)?
             .bytef()
             .bit_is_clear()
-        {}
+        {
+            deadline.check()?;
+        }
         Ok(())
     }
 
-    fn recv_byte(&self) -> Result<u8, Error> {
+    fn recv_byte(&self, deadline: &mut impl Deadline) -> Result<u8, Error> {
         loop {
             // Check for any potential error conditions.
             self.check_and_clear_error_flags()
@@ -478,35 +666,50 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
             if self.i2c.sts1().read().rxdatne().bit_is_set() {
                 break;
             }
+            deadline.check()?;
         }
 
         let value = self.i2c.dat().read().bits() as u8;
         Ok(value)
     }
 
-    fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+    fn read_bytes(&mut self, buffer: &mut [u8], deadline: &mut impl Deadline) -> Result<(), Error> {
         // Receive bytes into buffer
         for c in buffer {
-            *c = self.recv_byte()?;
+            *c = self.recv_byte(deadline)?;
         }
 
         Ok(())
     }
 
+    /// Waits for the STOP condition to be sent
+    fn wait_stop(&self, deadline: &mut impl Deadline) -> Result<(), Error> {
+        while self.i2c.ctrl1().read().stopgen().bit_is_set() {
+            deadline.check()?;
+        }
+        Ok(())
+    }
+
     pub fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read_until(addr, buffer, &mut NoDeadline)
+    }
+
+    fn read_until(&mut self, addr: u8, buffer: &mut [u8], deadline: &mut impl Deadline) -> Result<(), Error> {
         if buffer.is_empty() {
             return Err(Error::Overrun);
         }
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
 
-        self.prepare_read(addr)?;
-        self.read_wo_prepare(buffer)
+        self.prepare_read(addr, deadline)?;
+        self.read_wo_prepare(buffer, deadline)
     }
 
     /// Reads like normal but does'n generate start and don't send address
-    fn read_wo_prepare(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+    fn read_wo_prepare(&mut self, buffer: &mut [u8], deadline: &mut impl Deadline) -> Result<(), Error> {
         if let Some((last, buffer)) = buffer.split_last_mut() {
             // Read all bytes but not last
-            self.read_bytes(buffer)?;
+            self.read_bytes(buffer, deadline)?;
 
             // Prepare to send NACK then STOP after next byte
             self.i2c
@@ -514,10 +717,10 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
                 .modify(|_, w| w.acken().clear_bit().stopgen().set_bit());
 
             // Receive last byte
-            *last = self.recv_byte()?;
+            *last = self.recv_byte(deadline)?;
 
             // Wait for the STOP to be sent.
-            while self.i2c.ctrl1().read().stopgen().bit_is_set() {}
+            self.wait_stop(deadline)?;
 
             // Fallthrough is success
             Ok(())
@@ -527,19 +730,64 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
     }
 
     pub fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
-        self.prepare_write(addr)?;
-        self.write_wo_prepare(bytes)
+        self.write_until(addr, bytes, &mut NoDeadline)
+    }
+
+    /// Returns the addresses in `range` that acknowledge a zero-length write
+    ///
+    /// Every probe ends with a STOP condition, also after a NACK or an error, so the bus is
+    /// released for the next address. Addresses above 0x7f are skipped, as are addresses whose
+    /// probe fails with another error than a NACK, e.g. a lost arbitration on a shared bus. Pass
+    /// `0x08..=0x77` to leave out the reserved addresses.
+    ///
+    /// The probes wait without bound, so a device holding SCL low hangs the scan. Only
+    /// [I2cTimeout::scan] is safe on a bus that may be faulty.
+    pub fn scan(&mut self, range: RangeInclusive<u8>) -> heapless::Vec<u8, 128> {
+        scan(range, |addr| self.probe_until(addr, &mut NoDeadline))
+    }
+
+    /// Sends START, `addr` for writing and STOP, returns whether the address was acknowledged
+    fn probe_until(&mut self, addr: u8, deadline: &mut impl Deadline) -> Result<bool, Error> {
+        let acknowledged = match self.prepare_write(addr, deadline) {
+            Ok(()) => Ok(true),
+            // The NACK flag is cleared already, the master still holds the bus
+            Err(Error::NoAcknowledge(_)) => Ok(false),
+            Err(e) => Err(e),
+        };
+
+        // Release the bus on every path, the START or the address may have been sent already
+        self.i2c.ctrl1().modify(|_, w| w.stopgen().set_bit());
+        match acknowledged {
+            Ok(acknowledged) => {
+                self.wait_stop(deadline)?;
+                Ok(acknowledged)
+            }
+            Err(e) => {
+                // After a lost arbitration the peripheral is a slave and sends no STOP
+                if self.i2c.sts2().read().msmode().bit_is_set() {
+                    let _ = self.wait_stop(deadline);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn write_until(&mut self, addr: u8, bytes: &[u8], deadline: &mut impl Deadline) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
+        self.prepare_write(addr, deadline)?;
+        self.write_wo_prepare(bytes, deadline)
     }
 
     /// Writes like normal but does'n generate start and don't send address
-    fn write_wo_prepare(&mut self, bytes: &[u8]) -> Result<(), Error> {
-        self.write_bytes(bytes.iter().cloned())?;
+    fn write_wo_prepare(&mut self, bytes: &[u8], deadline: &mut impl Deadline) -> Result<(), Error> {
+        self.write_bytes(bytes.iter().cloned(), deadline)?;
 
         // Send a STOP condition
         self.i2c.ctrl1().modify(|_, w| w.stopgen().set_bit());
 
         // Wait for STOP condition to transmit.
-        while self.i2c.ctrl1().read().stopgen().bit_is_set() {}
+        self.wait_stop(deadline)?;
 
         // Fallthrough is success
         Ok(())
@@ -549,58 +797,117 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
     where
         B: IntoIterator<Item = u8>,
     {
-        self.prepare_write(addr)?;
-        self.write_bytes(bytes.into_iter())?;
+        self.write_iter_until(addr, bytes, &mut NoDeadline)
+    }
+
+    fn write_iter_until<B>(&mut self, addr: u8, bytes: B, deadline: &mut impl Deadline) -> Result<(), Error>
+    where
+        B: IntoIterator<Item = u8>,
+    {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
+        self.prepare_write(addr, deadline)?;
+        self.write_bytes(bytes.into_iter(), deadline)?;
 
         // Send a STOP condition
         self.i2c.ctrl1().modify(|_, w| w.stopgen().set_bit());
 
         // Wait for STOP condition to transmit.
-        while self.i2c.ctrl1().read().stopgen().bit_is_set() {}
+        self.wait_stop(deadline)?;
 
         // Fallthrough is success
         Ok(())
     }
 
     pub fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
-        self.prepare_write(addr)?;
-        self.write_bytes(bytes.iter().cloned())?;
-        self.read(addr, buffer)
+        self.write_iter_read(addr, bytes.iter().cloned(), buffer)
     }
 
     pub fn write_iter_read<B>(&mut self, addr: u8, bytes: B, buffer: &mut [u8]) -> Result<(), Error>
     where
         B: IntoIterator<Item = u8>,
     {
-        self.prepare_write(addr)?;
-        self.write_bytes(bytes.into_iter())?;
-        self.read(addr, buffer)
+        self.write_iter_read_until(addr, bytes, buffer, &mut NoDeadline)
+    }
+
+    fn write_iter_read_until<B>(&mut self, addr: u8, bytes: B, buffer: &mut [u8], deadline: &mut impl Deadline) -> Result<(), Error>
+    where
+        B: IntoIterator<Item = u8>,
+    {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
+
+        self.prepare_write(addr, deadline)?;
+        self.write_bytes(bytes.into_iter(), deadline)?;
+        // Checked after the write, as read does
+        if buffer.is_empty() {
+            return Err(Error::Overrun);
+        }
+        self.prepare_read(addr, deadline)?;
+        self.read_wo_prepare(buffer, deadline)
+    }
+
+    /// Writes the register address `reg` followed by `bytes` in a single write
+    ///
+    /// `reg` is sent as is, so 8 and 16 bit register addresses both work.
+    pub fn write_register(&mut self, addr: u8, reg: &[u8], bytes: &[u8]) -> Result<(), Error> {
+        self.write_register_until(addr, reg, bytes, &mut NoDeadline)
+    }
+
+    fn write_register_until(&mut self, addr: u8, reg: &[u8], bytes: &[u8], deadline: &mut impl Deadline) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
+        self.prepare_write(addr, deadline)?;
+        self.write_bytes(reg.iter().cloned(), deadline)?;
+        self.write_wo_prepare(bytes, deadline)
+    }
+
+    /// Writes the register address `reg`, then fills `buffer` after a repeated start
+    ///
+    /// Returns [Error::EmptyBuffer] without touching the bus if `buffer` is empty.
+    pub fn read_register(&mut self, addr: u8, reg: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        if buffer.is_empty() {
+            return Err(Error::EmptyBuffer);
+        }
+
+        self.write_read(addr, reg, buffer)
     }
 
     pub fn transaction<'a>(
+        &mut self,
+        addr: u8,
+        ops: impl Iterator<Item = Hal1Operation<'a>>,
+    ) -> Result<(), Error> {
+        self.transaction_until(addr, ops, &mut NoDeadline)
+    }
+
+    fn transaction_until<'a>(
         &mut self,
         addr: u8,
         mut ops: impl Iterator<Item = Hal1Operation<'a>>,
+        deadline: &mut impl Deadline,
     ) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
         if let Some(mut prev_op) = ops.next() {
             // 1. Generate Start for operation
             match &prev_op {
-                Hal1Operation::Read(_) => self.prepare_read(addr)?,
-                Hal1Operation::Write(_) => self.prepare_write(addr)?,
+                Hal1Operation::Read(_) => self.prepare_read(addr, deadline)?,
+                Hal1Operation::Write(_) => self.prepare_write(addr, deadline)?,
             };
 
             for op in ops {
                 // 2. Execute previous operations.
                 match &mut prev_op {
-                    Hal1Operation::Read(rb) => self.read_bytes(rb)?,
-                    Hal1Operation::Write(wb) => self.write_bytes(wb.iter().cloned())?,
+                    Hal1Operation::Read(rb) => self.read_bytes(rb, deadline)?,
+                    Hal1Operation::Write(wb) => self.write_bytes(wb.iter().cloned(), deadline)?,
                 };
                 // 3. If operation changes type we must generate new start
                 match (&prev_op, &op) {
                     (Hal1Operation::Read(_), Hal1Operation::Write(_)) => {
-                        self.prepare_write(addr)?
+                        self.prepare_write(addr, deadline)?
                     }
-                    (Hal1Operation::Write(_), Hal1Operation::Read(_)) => self.prepare_read(addr)?,
+                    (Hal1Operation::Write(_), Hal1Operation::Read(_)) => self.prepare_read(addr, deadline)?,
                     _ => {} // No changes if operation have not changed
                 }
 
@@ -609,8 +916,8 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
 
             // 4. Now, prev_op is last command use methods variations that will generate stop
             match prev_op {
-                Hal1Operation::Read(rb) => self.read_wo_prepare(rb)?,
-                Hal1Operation::Write(wb) => self.write_wo_prepare(wb)?,
+                Hal1Operation::Read(rb) => self.read_wo_prepare(rb, deadline)?,
+                Hal1Operation::Write(wb) => self.write_wo_prepare(wb, deadline)?,
             };
         }
 
@@ -623,7 +930,18 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
         addr: u8,
         ops_slice: &mut [Hal1Operation<'_>],
     ) -> Result<(), Error> {
-        transaction_impl!(self, addr, ops_slice, Hal1Operation);
+        self.transaction_slice_until(addr, ops_slice, &mut NoDeadline)
+    }
+
+    fn transaction_slice_until(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [Hal1Operation<'_>],
+        deadline: &mut impl Deadline,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
+        transaction_impl!(self, addr, ops_slice, Hal1Operation, deadline);
         // Fallthrough is success
         Ok(())
     }
@@ -633,35 +951,295 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
         addr: u8,
         ops_slice: &mut [Hal02Operation<'_>],
     ) -> Result<(), Error> {
-        transaction_impl!(self, addr, ops_slice, Hal02Operation);
+        self.transaction_slice_hal_02_until(addr, ops_slice, &mut NoDeadline)
+    }
+
+    fn transaction_slice_hal_02_until(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [Hal02Operation<'_>],
+        deadline: &mut impl Deadline,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::I2C_TRANSFER.scope();
+        transaction_impl!(self, addr, ops_slice, Hal02Operation, deadline);
         // Fallthrough is success
         Ok(())
     }
 }
 
+/// Probes the 7-bit addresses of `range` and collects the acknowledged ones
+fn scan(range: RangeInclusive<u8>, mut probe: impl FnMut(u8) -> Result<bool, Error>) -> heapless::Vec<u8, 128> {
+    let mut found = heapless::Vec::new();
+    for addr in range.filter(|addr| *addr <= 0x7f) {
+        if let Ok(true) = probe(addr) {
+            // At most 128 addresses, so this never fails
+            let _ = found.push(addr);
+        }
+    }
+    found
+}
+
+/// Bound on the busy-wait loops of the blocking calls
+pub trait Deadline {
+    /// Whether the bound ran out, polled while waiting for the peripheral
+    fn expired(&mut self) -> bool;
+
+    fn check(&mut self) -> Result<(), Error> {
+        if self.expired() {
+            Err(Error::Timeout)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Waits forever
+struct NoDeadline;
+
+impl Deadline for NoDeadline {
+    #[inline(always)]
+    fn expired(&mut self) -> bool {
+        false
+    }
+}
+
+/// Expires when the started timer runs out
+struct TimerDeadline<'a, TIM>(&'a mut TIM);
+
+impl<TIM: embedded_hal_02::timer::CountDown> Deadline for TimerDeadline<'_, TIM> {
+    fn expired(&mut self) -> bool {
+        self.0.wait().is_ok()
+    }
+}
+
+/// Master side of a bus, as seen by the bus-sharing logic of [I2cMultiMaster]
+///
+/// Implemented by [I2c] and, with the `mock` feature, by [I2cMock](crate::mock::I2cMock), so
+/// that logic can be tested on the host.
+pub trait MasterBus {
+    /// Whether the bus is occupied, possibly by another master
+    fn is_busy(&mut self) -> bool;
+
+    /// Sends a STOP if the peripheral is still master after a transfer stopped halfway
+    fn release_bus(&mut self);
+
+    /// Runs `ops_slice` with repeated starts, returning [Error::Timeout] once `deadline` expires
+    fn transaction_slice_until(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [embedded_hal::i2c::Operation<'_>],
+        deadline: &mut impl Deadline,
+    ) -> Result<(), Error>;
+}
+
+impl<I2C: Instance, PINS> MasterBus for I2c<I2C, PINS> {
+    fn is_busy(&mut self) -> bool {
+        self.i2c.sts2().read().busy().bit_is_set()
+    }
+
+    fn release_bus(&mut self) {
+        if self.i2c.sts2().read().msmode().bit_is_set() {
+            self.i2c.ctrl1().modify(|_, w| w.stopgen().set_bit());
+        }
+    }
+
+    fn transaction_slice_until(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [Hal1Operation<'_>],
+        deadline: &mut impl Deadline,
+    ) -> Result<(), Error> {
+        I2c::transaction_slice_until(self, addr, ops_slice, deadline)
+    }
+}
+
+impl<I2C: Instance, PINS> I2c<I2C, PINS> {
+    /// Bounds every blocking call to `timeout`, measured with `timer`
+    ///
+    /// A call that doesn't complete in time, e.g. because a device holds SCL low, sends a
+    /// STOP condition and returns [Error::Timeout].
+    pub fn with_timeout<TIM>(self, timer: TIM, timeout: MicroSecond) -> I2cTimeout<I2C, PINS, TIM>
+    where
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        I2cTimeout {
+            i2c: self,
+            timer,
+            timeout,
+        }
+    }
+}
+
+/// I2C bus whose blocking calls time out, see [I2c::with_timeout]
+pub struct I2cTimeout<I2C: Instance, PINS, TIM> {
+    i2c: I2c<I2C, PINS>,
+    timer: TIM,
+    timeout: MicroSecond,
+}
+
+impl<I2C, PINS, TIM> I2cTimeout<I2C, PINS, TIM>
+where
+    I2C: Instance,
+    TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+{
+    /// Changes the bound of the blocking calls
+    pub fn set_timeout(&mut self, timeout: MicroSecond) {
+        self.timeout = timeout;
+    }
+
+    /// Releases the bus and the timer
+    pub fn release(self) -> (I2c<I2C, PINS>, TIM) {
+        (self.i2c, self.timer)
+    }
+
+    fn bounded<R>(
+        &mut self,
+        f: impl FnOnce(&mut I2c<I2C, PINS>, &mut TimerDeadline<'_, TIM>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        self.timer.start(self.timeout);
+        let result = f(&mut self.i2c, &mut TimerDeadline(&mut self.timer));
+        if matches!(result, Err(Error::Timeout)) {
+            // Release the bus
+            self.i2c.i2c.ctrl1().modify(|_, w| w.stopgen().set_bit());
+        }
+        result
+    }
+
+    pub fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.bounded(|i2c, deadline| i2c.read_until(addr, buffer, deadline))
+    }
+
+    pub fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.bounded(|i2c, deadline| i2c.write_until(addr, bytes, deadline))
+    }
+
+    pub fn write_iter<B>(&mut self, addr: u8, bytes: B) -> Result<(), Error>
+    where
+        B: IntoIterator<Item = u8>,
+    {
+        self.bounded(|i2c, deadline| i2c.write_iter_until(addr, bytes, deadline))
+    }
+
+    /// See [I2c::scan], an address that times out is skipped after releasing the bus, so the
+    /// scan returns even if a device holds SCL low
+    pub fn scan(&mut self, range: RangeInclusive<u8>) -> heapless::Vec<u8, 128> {
+        scan(range, |addr| self.bounded(|i2c, deadline| i2c.probe_until(addr, deadline)))
+    }
+
+    pub fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.write_iter_read(addr, bytes.iter().cloned(), buffer)
+    }
+
+    pub fn write_iter_read<B>(&mut self, addr: u8, bytes: B, buffer: &mut [u8]) -> Result<(), Error>
+    where
+        B: IntoIterator<Item = u8>,
+    {
+        self.bounded(|i2c, deadline| i2c.write_iter_read_until(addr, bytes, buffer, deadline))
+    }
+
+    /// See [I2c::write_register]
+    pub fn write_register(&mut self, addr: u8, reg: &[u8], bytes: &[u8]) -> Result<(), Error> {
+        self.bounded(|i2c, deadline| i2c.write_register_until(addr, reg, bytes, deadline))
+    }
+
+    /// See [I2c::read_register]
+    pub fn read_register(&mut self, addr: u8, reg: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        if buffer.is_empty() {
+            return Err(Error::EmptyBuffer);
+        }
+
+        self.write_read(addr, reg, buffer)
+    }
+
+    pub fn transaction<'a>(
+        &mut self,
+        addr: u8,
+        ops: impl Iterator<Item = Hal1Operation<'a>>,
+    ) -> Result<(), Error> {
+        self.bounded(|i2c, deadline| i2c.transaction_until(addr, ops, deadline))
+    }
+
+    pub fn transaction_slice(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [Hal1Operation<'_>],
+    ) -> Result<(), Error> {
+        self.bounded(|i2c, deadline| i2c.transaction_slice_until(addr, ops_slice, deadline))
+    }
+
+    fn transaction_slice_hal_02(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [Hal02Operation<'_>],
+    ) -> Result<(), Error> {
+        self.bounded(|i2c, deadline| i2c.transaction_slice_hal_02_until(addr, ops_slice, deadline))
+    }
+}
+
+impl<I2C: Instance, PINS> crate::Listen for I2c<I2C, PINS> {
+    type Event = Event;
+
+    fn listen(&mut self, event: impl Into<BitFlags<Self::Event>>) {
+        self.listen_event(None, Some(event.into()));
+    }
+
+    fn listen_only(&mut self, event: impl Into<BitFlags<Self::Event>>) {
+        self.listen_event(Some(BitFlags::ALL), Some(event.into()));
+    }
+
+    fn unlisten(&mut self, event: impl Into<BitFlags<Self::Event>>) {
+        self.listen_event(Some(event.into()), None);
+    }
+}
+
+impl<I2C: Instance, PINS> crate::ClearFlags for I2c<I2C, PINS> {
+    type Flag = CFlag;
+    fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
+        // Error flags are cleared by writing 0, writing 1 to the other bits has no effect
+        let flags = flags.into();
+        self.i2c
+            .sts1()
+            .write(|w| unsafe { w.bits(0xffff & !flags.bits()) });
+    }
+}
+
+impl<I2C: Instance, PINS> crate::ReadFlags for I2c<I2C, PINS> {
+    type Flag = Flag;
+    /// Reads STS1 and STS2
+    ///
+    /// Note that reading STS2 right after STS1 clears a pending ADDRF flag
+    fn flags(&self) -> BitFlags<Self::Flag> {
+        let sts1 = self.i2c.sts1().read().bits();
+        let sts2 = self.i2c.sts2().read().bits();
+        BitFlags::from_bits_truncate(sts1 | (sts2 << 16))
+    }
+}
+
 macro_rules! transaction_impl {
-    ($self:ident, $addr:ident, $ops_slice:ident, $Operation:ident) => {
+    ($self:ident, $addr:ident, $ops_slice:ident, $Operation:ident, $deadline:ident) => {
         let i2c = $self;
         let addr = $addr;
+        let deadline = $deadline;
         let mut ops = $ops_slice.iter_mut();
 
         if let Some(mut prev_op) = ops.next() {
             // 1. Generate Start for operation
             match &prev_op {
-                $Operation::Read(_) => i2c.prepare_read(addr)?,
-                $Operation::Write(_) => i2c.prepare_write(addr)?,
+                $Operation::Read(_) => i2c.prepare_read(addr, deadline)?,
+                $Operation::Write(_) => i2c.prepare_write(addr, deadline)?,
             };
 
             for op in ops {
                 // 2. Execute previous operations.
                 match &mut prev_op {
-                    $Operation::Read(rb) => i2c.read_bytes(rb)?,
-                    $Operation::Write(wb) => i2c.write_bytes(wb.iter().cloned())?,
+                    $Operation::Read(rb) => i2c.read_bytes(rb, deadline)?,
+                    $Operation::Write(wb) => i2c.write_bytes(wb.iter().cloned(), deadline)?,
                 };
                 // 3. If operation changes type we must generate new start
                 match (&prev_op, &op) {
-                    ($Operation::Read(_), $Operation::Write(_)) => i2c.prepare_write(addr)?,
-                    ($Operation::Write(_), $Operation::Read(_)) => i2c.prepare_read(addr)?,
+                    ($Operation::Read(_), $Operation::Write(_)) => i2c.prepare_write(addr, deadline)?,
+                    ($Operation::Write(_), $Operation::Read(_)) => i2c.prepare_read(addr, deadline)?,
                     _ => {} // No changes if operation have not changed
                 }
 
@@ -670,8 +1248,8 @@ macro_rules! transaction_impl {
 
             // 4. Now, prev_op is last command use methods variations that will generate stop
             match prev_op {
-                $Operation::Read(rb) => i2c.read_wo_prepare(rb)?,
-                $Operation::Write(wb) => i2c.write_wo_prepare(wb)?,
+                $Operation::Read(rb) => i2c.read_wo_prepare(rb, deadline)?,
+                $Operation::Write(wb) => i2c.write_wo_prepare(wb, deadline)?,
             };
         }
     };
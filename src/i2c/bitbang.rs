@@ -0,0 +1,286 @@
+//! # Bit-banged I2C master
+//!
+//! Software I2C over two open-drain GPIOs, for boards where the hardware I2C pins are
+//! taken, or to get a bus back from a stuck slave with [I2cBB::recover].
+//!
+//! Both pins need a pull-up, either external or the internal one.
+//!
+//! ```rust
+//! let sda = gpiob.pb9.into_open_drain_output();
+//! let scl = gpiob.pb8.into_open_drain_output();
+//! let mut i2c = I2cBB::new(sda, scl, cp.SYST.delay(&clocks), 100.kHz());
+//! i2c.write(0x3C, &[0x00, 0xAF])?;
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::i2c::{ErrorType, Operation};
+use fugit::HertzU32 as Hertz;
+
+use super::{Error, NoAcknowledgeSource};
+
+/// Number of half periods a slave may stretch the clock before [Error::Timeout]
+const STRETCH_LIMIT: u32 = 1000;
+
+/// Bit-banged I2C master
+pub struct I2cBB<SDA, SCL, D> {
+    sda: SDA,
+    scl: SCL,
+    delay: D,
+    half_period_ns: u32,
+}
+
+impl<SDA, SCL, D> I2cBB<SDA, SCL, D>
+where
+    SDA: OutputPin + InputPin,
+    SCL: OutputPin + InputPin,
+    D: DelayNs,
+{
+    /// Creates the master and releases both lines
+    ///
+    /// The achieved bus frequency is lower than `frequency` as the GPIO accesses
+    /// add to the delays.
+    pub fn new(sda: SDA, scl: SCL, delay: D, frequency: Hertz) -> Self {
+        let mut i2c = Self {
+            sda,
+            scl,
+            delay,
+            half_period_ns: 500_000_000 / frequency.raw(),
+        };
+        i2c.sda_high();
+        i2c.scl_high();
+        i2c
+    }
+
+    /// Releases the pins and the delay
+    pub fn release(self) -> (SDA, SCL, D) {
+        (self.sda, self.scl, self.delay)
+    }
+
+    /// Clocks the bus until a slave holding SDA low lets go, then sends a stop condition
+    pub fn recover(&mut self) -> Result<(), Error> {
+        self.sda_high();
+        for _ in 0..9 {
+            if self.sda_is_high() {
+                break;
+            }
+            self.scl_low();
+            self.wait();
+            self.scl_release()?;
+            self.wait();
+        }
+        self.scl_low();
+        self.wait();
+        self.stop()
+    }
+
+    /// Reads enough bytes from slave with `addr` to fill `buffer`
+    pub fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.transaction_slice(addr, &mut [Operation::Read(buffer)])
+    }
+
+    /// Writes `bytes` to slave with address `addr`
+    pub fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.transaction_slice(addr, &mut [Operation::Write(bytes)])
+    }
+
+    /// Writes `bytes` to slave with address `addr` and then reads enough bytes to fill `buffer` in a single transaction
+    pub fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.transaction_slice(addr, &mut [Operation::Write(bytes), Operation::Read(buffer)])
+    }
+
+    /// Executes `operations` with repeated starts between operations of different directions
+    pub fn transaction_slice(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        let result = self.execute(addr, operations);
+        // Always try to leave the bus idle, but report the first error
+        let stop = self.stop();
+        result.and(stop)
+    }
+
+    fn execute(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        let count = operations.len();
+        let mut prev_read = None;
+        for i in 0..count {
+            let next_read = operations.get(i + 1).map(|op| matches!(op, Operation::Read(_)));
+            match &mut operations[i] {
+                Operation::Write(bytes) => {
+                    if prev_read != Some(false) {
+                        self.start()?;
+                        self.address(addr, false)?;
+                    }
+                    for &byte in bytes.iter() {
+                        if !self.write_byte(byte)? {
+                            return Err(Error::NoAcknowledge(NoAcknowledgeSource::Data));
+                        }
+                    }
+                    prev_read = Some(false);
+                }
+                Operation::Read(buffer) => {
+                    if prev_read != Some(true) {
+                        self.start()?;
+                        self.address(addr, true)?;
+                    }
+                    let len = buffer.len();
+                    for (j, byte) in buffer.iter_mut().enumerate() {
+                        // NACK the last byte unless the next operation continues reading
+                        let ack = j + 1 < len || next_read == Some(true);
+                        *byte = self.read_byte(ack)?;
+                    }
+                    prev_read = Some(true);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn address(&mut self, addr: u8, read: bool) -> Result<(), Error> {
+        if self.write_byte((addr << 1) | read as u8)? {
+            Ok(())
+        } else {
+            Err(Error::NoAcknowledge(NoAcknowledgeSource::Address))
+        }
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        // Also used as repeated start, where SCL is low
+        self.sda_high();
+        self.wait();
+        self.scl_release()?;
+        if !self.sda_is_high() {
+            return Err(Error::ArbitrationLoss);
+        }
+        self.wait();
+        self.sda_low();
+        self.wait();
+        self.scl_low();
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        self.sda_low();
+        self.wait();
+        self.scl_release()?;
+        self.wait();
+        self.sda_high();
+        self.wait();
+        if self.sda_is_high() {
+            Ok(())
+        } else {
+            Err(Error::ArbitrationLoss)
+        }
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error> {
+        if bit {
+            self.sda_high();
+        } else {
+            self.sda_low();
+        }
+        self.wait();
+        self.scl_release()?;
+        if bit && !self.sda_is_high() {
+            return Err(Error::ArbitrationLoss);
+        }
+        self.wait();
+        self.scl_low();
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Error> {
+        self.sda_high();
+        self.wait();
+        self.scl_release()?;
+        let bit = self.sda_is_high();
+        self.wait();
+        self.scl_low();
+        Ok(bit)
+    }
+
+    /// Returns true if the byte was acknowledged
+    fn write_byte(&mut self, byte: u8) -> Result<bool, Error> {
+        for i in (0..8).rev() {
+            self.write_bit(byte & (1 << i) != 0)?;
+        }
+        Ok(!self.read_bit()?)
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    /// Releases SCL and waits while a slave stretches the clock
+    fn scl_release(&mut self) -> Result<(), Error> {
+        self.scl_high();
+        let mut remaining = STRETCH_LIMIT;
+        while !self.scl.is_high().unwrap_or(true) {
+            if remaining == 0 {
+                return Err(Error::Timeout);
+            }
+            remaining -= 1;
+            self.wait();
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn wait(&mut self) {
+        self.delay.delay_ns(self.half_period_ns);
+    }
+
+    #[inline]
+    fn sda_is_high(&mut self) -> bool {
+        self.sda.is_high().unwrap_or(true)
+    }
+
+    #[inline]
+    fn sda_high(&mut self) {
+        let _ = self.sda.set_high();
+    }
+
+    #[inline]
+    fn sda_low(&mut self) {
+        let _ = self.sda.set_low();
+    }
+
+    #[inline]
+    fn scl_high(&mut self) {
+        let _ = self.scl.set_high();
+    }
+
+    #[inline]
+    fn scl_low(&mut self) {
+        let _ = self.scl.set_low();
+    }
+}
+
+impl<SDA, SCL, D> ErrorType for I2cBB<SDA, SCL, D> {
+    type Error = Error;
+}
+
+impl<SDA, SCL, D> embedded_hal::i2c::I2c for I2cBB<SDA, SCL, D>
+where
+    SDA: OutputPin + InputPin,
+    SCL: OutputPin + InputPin,
+    D: DelayNs,
+{
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.read(addr, buffer)
+    }
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write(addr, bytes)
+    }
+
+    fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_read(addr, bytes, buffer)
+    }
+
+    fn transaction(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        self.transaction_slice(addr, operations)
+    }
+}
@@ -3,9 +3,11 @@
 use core::{marker::PhantomData, mem::transmute};
 
 use super::{I2c, Instance};
-use crate::dma::{ChannelStatus, CompatibleChannel, DMAChannel, TransferPayload};
+use crate::dma::{ChannelStatus, CompatibleChannel, DMAChannel, TransferPayload, MAX_TRANSFER_LENGTH};
 
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
     I2CError(super::Error),
@@ -26,6 +28,8 @@ pub trait I2CMasterWriteDMA {
     /// * `addr` - slave address
     /// * `bytes` - byte slice that need to send
     ///
+    /// Returns [BufferTooLong](super::Error::BufferTooLong) if `bytes` exceeds [MAX_TRANSFER_LENGTH].
+    ///
     /// # Safety
     /// This function relies on supplied slice `bytes` until the DMA completes (e.g. in the interrupt). So the slice must live until that moment.
     ///
@@ -43,6 +47,8 @@ pub trait I2CMasterReadDMA {
     /// * `addr` - slave address
     /// * `buf` - byte slice where received bytes will be written
     ///
+    /// Returns [BufferTooLong](super::Error::BufferTooLong) if `buf` exceeds [MAX_TRANSFER_LENGTH].
+    ///
     /// # Safety
     /// This function relies on supplied slice `buf` until the DMA completes (e.g. in the interrupt). So the slice must live until that moment.
     ///
@@ -61,6 +67,8 @@ pub trait I2CMasterWriteReadDMA {
     /// * `bytes` - byte slice that need to send
     /// * `buf` - byte slice where received bytes will be written
     ///
+    /// Returns [BufferTooLong](super::Error::BufferTooLong) if either slice exceeds [MAX_TRANSFER_LENGTH].
+    ///
     /// # Safety
     /// This function relies on supplied slices `bytes` and `buf` until the DMA completion interrupt is triggered. So slices must live until that moment.
     ///
@@ -82,7 +90,7 @@ pub trait I2CMasterHandleIT {
 
 impl<I2C: Instance,PINS> I2c<I2C,PINS> {
     /// Converts blocking [I2c] to non-blocking [I2CMasterDma] that use `tx_channel` and `rx_channel` to send/receive data
-    pub fn use_dma<TX_CH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>, RX_CH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>>(
+    pub fn use_dma<TX_CH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>, RX_CH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>>(
         self,
         tx_ch: TX_CH,
         rx_ch: RX_CH,
@@ -109,7 +117,7 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
         txch: TXCH,
     ) -> I2CMasterDma<I2C, PINS, TxDMATransfer<I2C, TXCH>, NoDMA>
     where
-        TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
+        TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
         Tx<I2C>: TransferPayload,
     {
         let tx = TxDMATransfer::new(txch);
@@ -133,7 +141,7 @@ impl<I2C: Instance,PINS> I2c<I2C,PINS> {
         rx_channel: RXCH,
     ) -> I2CMasterDma<I2C, PINS, NoDMA, RxDMATransfer<I2C, RXCH>>
     where
-        RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
+        RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
         Rx<I2C>: TransferPayload,
     {
         let tx = NoDMA;
@@ -245,7 +253,7 @@ where
 impl<I2C, TXCH> TxDMATransfer<I2C, TXCH>
 where
     I2C: Instance,
-    TXCH : DMAChannel + CompatibleChannel<I2C,crate::dma::R>
+    TXCH : DMAChannel + CompatibleChannel<I2C,crate::dma::W>
 {
     fn new(channel: TXCH) -> Self {
         Self {
@@ -259,7 +267,7 @@ where
 impl<I2C, TX_CH> DMATransfer<&'static [u8]> for TxDMATransfer<I2C, TX_CH>
 where
     I2C: Instance,
-    TX_CH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::R>,
+    TX_CH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::W>,
 {
     fn create_transfer(&mut self, buf: &'static [u8]) {
         assert!(self.tx_transfer.is_none());
@@ -267,7 +275,8 @@ where
         self.tx_channel.set_transfer_direction(crate::dma::TransferDirection::MemoryToPeripheral);
         self.tx_channel.set_peripheral_address(unsafe { (*I2C::ptr()).dat().as_ptr() as u32}, false);
         self.tx_channel.set_memory_address(buf.as_ptr() as u32, true);
-        self.tx_channel.set_transfer_length(buf.len());
+        // The I2CMaster*DMA methods checked the length
+        self.tx_channel.try_set_transfer_length(buf.len()).ok();
         core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Release);
 
         self.tx_channel.listen(crate::dma::Event::TransferComplete);
@@ -294,7 +303,7 @@ where
 pub struct RxDMATransfer<I2C, RXCH>
 where
     I2C: Instance,
-    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
 {
     _rx: Rx<I2C>,
     rx_channel: RXCH,
@@ -304,7 +313,7 @@ where
 impl<I2C, RXCH> RxDMATransfer<I2C,  RXCH>
 where
     I2C: Instance,
-    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
 {
     fn new(channel: RXCH) -> Self {
 
@@ -320,7 +329,7 @@ impl<I2C, RXCH> DMATransfer<&'static mut [u8]>
     for RxDMATransfer<I2C, RXCH>
 where
     I2C: Instance,
-    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
     Rx<I2C>: TransferPayload,
 {
     fn create_transfer(&mut self, buf: &'static mut [u8]) {
@@ -329,7 +338,8 @@ where
         self.rx_channel.set_transfer_direction(crate::dma::TransferDirection::PeripheralToMemory);
         self.rx_channel.set_peripheral_address(unsafe { (*I2C::ptr()).dat().as_ptr() as u32}, false);
         self.rx_channel.set_memory_address(buf.as_ptr() as u32, true);
-        self.rx_channel.set_transfer_length(buf.len());
+        // The I2CMaster*DMA methods checked the length
+        self.rx_channel.try_set_transfer_length(buf.len()).ok();
         core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Release);
 
         self.rx_channel.listen(crate::dma::Event::TransferComplete);
@@ -578,7 +588,7 @@ impl<I2C, PINS, TXCH> I2CMasterHandleIT
 where
     I2C: Instance,
 
-    TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
+    TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
     Tx<I2C>: TransferPayload,
 {
     fn handle_dma_interrupt(&mut self) -> Result<ChannelStatus, Error> {
@@ -624,7 +634,7 @@ impl<I2C, PINS, RXCH> I2CMasterHandleIT
 where
     I2C: Instance,
 
-    RXCH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::R>,
     Rx<I2C>: TransferPayload,
 {
     fn handle_dma_interrupt(&mut self) -> Result<ChannelStatus, Error> {
@@ -670,10 +680,10 @@ impl<I2C, PINS, RXCH, TXCH> I2CMasterHandleIT
     for I2CMasterDma<I2C, PINS, TxDMATransfer<I2C, TXCH>, RxDMATransfer<I2C, RXCH>>
 where
     I2C: Instance,
-    TXCH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::R>,
+    TXCH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::W>,
     Tx<I2C>: TransferPayload,
 
-    RXCH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + crate::dma::CompatibleChannel<I2C, crate::dma::R>,
     Rx<I2C>: TransferPayload,
 {
     fn handle_dma_interrupt(&mut self) -> Result<ChannelStatus, Error> {
@@ -768,7 +778,7 @@ impl<I2C, PINS, TXCH, RX_TRANSFER> I2CMasterWriteDMA
     for I2CMasterDma<I2C, PINS, TxDMATransfer<I2C, TXCH>, RX_TRANSFER>
 where
     I2C: Instance,
-    TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
+    TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
     Tx<I2C>: TransferPayload,
 
     RX_TRANSFER: DMATransfer<&'static mut [u8]>,
@@ -779,6 +789,9 @@ where
         bytes: &[u8],
     ) -> nb::Result<(), super::Error> {
         self.busy_res()?;
+        if bytes.len() > MAX_TRANSFER_LENGTH {
+            return Err(nb::Error::Other(super::Error::BufferTooLong));
+        }
 
         // Prepare transfer
         self.enable_dma_requests();
@@ -804,7 +817,7 @@ impl<I2C, PINS, TX_TRANSFER, RXCH> I2CMasterReadDMA
 where
     I2C: Instance,
 
-    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
     Rx<I2C>: TransferPayload,
 
     TX_TRANSFER: DMATransfer<&'static [u8]>,
@@ -815,6 +828,9 @@ where
         buf: &mut [u8],
     ) -> nb::Result<(), super::Error> {
         self.busy_res()?;
+        if buf.len() > MAX_TRANSFER_LENGTH {
+            return Err(nb::Error::Other(super::Error::BufferTooLong));
+        }
 
         //  If size is small we need to set ACK=0 before cleaning ADDR(reading SR2)
         let buf_len = buf.len();
@@ -840,10 +856,10 @@ impl<I2C,PINS, TXCH, RXCH> I2CMasterWriteReadDMA
     for I2CMasterDma<I2C, PINS, TxDMATransfer<I2C, TXCH>, RxDMATransfer<I2C, RXCH>>
 where
     I2C: Instance,
-    TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
+    TXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
     Tx<I2C>: TransferPayload,
 
-    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::W>,
+    RXCH: DMAChannel + CompatibleChannel<I2C, crate::dma::R>,
     Rx<I2C>: TransferPayload,
 {
     unsafe fn write_read_dma(
@@ -853,6 +869,9 @@ where
         buf: &mut [u8],
     ) -> nb::Result<(), super::Error> {
         self.busy_res()?;
+        if bytes.len() > MAX_TRANSFER_LENGTH || buf.len() > MAX_TRANSFER_LENGTH {
+            return Err(nb::Error::Other(super::Error::BufferTooLong));
+        }
 
         self.address = addr;
         self.rx_len = buf.len();
@@ -1,5 +1,6 @@
 mod blocking {
-    use super::super::{Error, I2c, Instance};
+    use super::super::{Error, I2c, I2cTimeout, Instance};
+    use crate::time::MicroSecond;
     use embedded_hal_02::blocking::i2c::{
         Operation, Read, Transactional, Write, WriteIter, WriteIterRead, WriteRead,
     };
@@ -89,4 +90,61 @@ mod blocking {
             self.transaction_slice_hal_02(address, operations)
         }
     }
-}
\ No newline at end of file
+
+    impl<I2C, PINS, TIM> WriteRead for I2cTimeout<I2C, PINS, TIM>
+    where
+        I2C: Instance,
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        type Error = Error;
+
+        fn write_read(
+            &mut self,
+            addr: u8,
+            bytes: &[u8],
+            buffer: &mut [u8],
+        ) -> Result<(), Self::Error> {
+            self.write_read(addr, bytes, buffer)
+        }
+    }
+
+    impl<I2C, PINS, TIM> Write for I2cTimeout<I2C, PINS, TIM>
+    where
+        I2C: Instance,
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        type Error = Error;
+
+        fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.write(addr, bytes)
+        }
+    }
+
+    impl<I2C, PINS, TIM> Read for I2cTimeout<I2C, PINS, TIM>
+    where
+        I2C: Instance,
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        type Error = Error;
+
+        fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+            self.read(addr, buffer)
+        }
+    }
+
+    impl<I2C, PINS, TIM> Transactional for I2cTimeout<I2C, PINS, TIM>
+    where
+        I2C: Instance,
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        type Error = Error;
+
+        fn exec(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            self.transaction_slice_hal_02(address, operations)
+        }
+    }
+}
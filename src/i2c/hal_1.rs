@@ -7,7 +7,9 @@ impl Error for super::Error {
             Self::Bus => ErrorKind::Bus,
             Self::ArbitrationLoss => ErrorKind::ArbitrationLoss,
             Self::NoAcknowledge(nack) => ErrorKind::NoAcknowledge(nack),
-            Self::Crc | Self::Timeout => ErrorKind::Other,
+            Self::Crc | Self::Timeout | Self::EmptyBuffer | Self::InvalidFrequency | Self::BufferTooLong => {
+                ErrorKind::Other
+            }
         }
     }
 }
@@ -16,8 +18,13 @@ impl<I2C: super::Instance,PINS> ErrorType for super::I2c<I2C,PINS> {
     type Error = super::Error;
 }
 
+impl<I2C: super::Instance, PINS, TIM> ErrorType for super::I2cTimeout<I2C, PINS, TIM> {
+    type Error = super::Error;
+}
+
 mod blocking {
-    use super::super::{I2c, Instance};
+    use super::super::{I2c, I2cTimeout, Instance};
+    use crate::time::MicroSecond;
     use embedded_hal::i2c::Operation;
 
     impl<I2C: Instance, PINS> embedded_hal::i2c::I2c for I2c<I2C,PINS> {
@@ -46,4 +53,34 @@ mod blocking {
             self.transaction_slice(addr, operations)
         }
     }
-}
\ No newline at end of file
+
+    impl<I2C: Instance, PINS, TIM> embedded_hal::i2c::I2c for I2cTimeout<I2C, PINS, TIM>
+    where
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+            self.read(addr, buffer)
+        }
+
+        fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.write(addr, bytes)
+        }
+
+        fn write_read(
+            &mut self,
+            addr: u8,
+            bytes: &[u8],
+            buffer: &mut [u8],
+        ) -> Result<(), Self::Error> {
+            self.write_read(addr, bytes, buffer)
+        }
+
+        fn transaction(
+            &mut self,
+            addr: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            self.transaction_slice(addr, operations)
+        }
+    }
+}
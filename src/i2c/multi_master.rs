@@ -0,0 +1,182 @@
+//! # Multi-master buses
+//!
+//! With several masters on the bus, a transfer may lose arbitration to another master
+//! addressing a device at the same time. The peripheral then drops back to slave mode by
+//! itself, and the transfer can simply be tried again once the other master released the bus.
+//!
+//! [I2cMultiMaster] waits for the bus to be free before each START, bounded by
+//! [MultiMasterConfig::busy_timeout], and retries transfers that lost arbitration up to
+//! [MultiMasterConfig::retries] times before reporting [Error::ArbitrationLoss].
+//!
+//! ```rust
+//! let i2c = I2c::new(dp.I2c1, (scl, sda), 100.kHz(), &clocks).map_err(|(error, ..)| error)?;
+//! let timer = Timer::new(dp.Tim2, &clocks).start_count_down(1.millis());
+//! let mut i2c = i2c.multi_master(timer, MultiMasterConfig::default());
+//!
+//! i2c.write_read(0x48, &[0x00], &mut temperature)?;
+//! ```
+//!
+//! ## Testing against another master
+//!
+//! [I2cMultiMaster] works on any [MasterBus], so the crate's host tests run it on the
+//! scripted `mock::I2cMock` (feature `mock`) and play the other master as lost arbitrations
+//! and busy polls in the script. That covers the retries, the bus-free wait and the release
+//! after a failure, not the peripheral itself.
+//!
+//! Real contention needs hardware: wire I2C1 (PB6/PB7) and I2C2 (PB10/PB11) to the same bus
+//! with one target device and let both masters read it, one from the main loop and one from a
+//! timer interrupt, so their STARTs collide:
+//!
+//! ```rust
+//! // main loop, on I2C1
+//! let mut id = [0; 1];
+//! loop {
+//!     match i2c1.read_register(0x48, &[0x07], &mut id) {
+//!         Ok(()) => assert_eq!(id[0], EXPECTED_ID),
+//!         // only once all retries lost arbitration
+//!         Err(Error::ArbitrationLoss) => lost += 1,
+//!         Err(e) => panic!("{:?}", e),
+//!     }
+//! }
+//!
+//! // TIM3 interrupt every 100 us, on I2C2
+//! i2c2.read_register(0x48, &[0x07], &mut id)?;
+//! ```
+//!
+//! Every read has to return the device ID; with the default three retries
+//! [Error::ArbitrationLoss] should stay rare. Setting [MultiMasterConfig::retries] to zero
+//! shows the lost arbitrations themselves.
+
+use super::{Deadline, Error, Hal1Operation, I2c, Instance, MasterBus, TimerDeadline};
+use crate::time::{ExtU32, MicroSecond};
+
+/// Settings of an [I2cMultiMaster]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiMasterConfig {
+    /// Retries after a lost arbitration, before giving up
+    pub retries: u8,
+    /// Longest wait for another master to release the bus before a START
+    pub busy_timeout: MicroSecond,
+    /// Bound of a single transfer attempt, once the START is sent
+    pub timeout: MicroSecond,
+}
+
+impl Default for MultiMasterConfig {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            busy_timeout: 10_000.micros(),
+            timeout: 10_000.micros(),
+        }
+    }
+}
+
+impl<I2C: Instance, PINS> I2c<I2C, PINS> {
+    /// Shares the bus with other masters, measuring the timeouts with `timer`
+    pub fn multi_master<TIM>(self, timer: TIM, config: MultiMasterConfig) -> I2cMultiMaster<Self, TIM>
+    where
+        TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+    {
+        I2cMultiMaster::new(self, timer, config)
+    }
+}
+
+/// I2C master sharing the bus with other masters, see [I2c::multi_master]
+pub struct I2cMultiMaster<BUS, TIM> {
+    i2c: BUS,
+    timer: TIM,
+    config: MultiMasterConfig,
+}
+
+impl<BUS, TIM> I2cMultiMaster<BUS, TIM>
+where
+    BUS: MasterBus,
+    TIM: embedded_hal_02::timer::CountDown<Time = MicroSecond>,
+{
+    /// Shares `bus` with other masters, usually through [I2c::multi_master]
+    pub fn new(bus: BUS, timer: TIM, config: MultiMasterConfig) -> Self {
+        Self {
+            i2c: bus,
+            timer,
+            config,
+        }
+    }
+
+    /// Changes the settings
+    pub fn set_config(&mut self, config: MultiMasterConfig) {
+        self.config = config;
+    }
+
+    /// Releases the bus and the timer
+    pub fn release(self) -> (BUS, TIM) {
+        (self.i2c, self.timer)
+    }
+
+    /// Waits for the bus to be free, bounded by `busy_timeout`
+    fn wait_bus_free(&mut self) -> Result<(), Error> {
+        self.timer.start(self.config.busy_timeout);
+        let mut deadline = TimerDeadline(&mut self.timer);
+        while self.i2c.is_busy() {
+            deadline.check()?;
+        }
+        Ok(())
+    }
+
+    fn arbitrated(&mut self, addr: u8, ops_slice: &mut [Hal1Operation<'_>]) -> Result<(), Error> {
+        let mut retries = self.config.retries;
+        loop {
+            self.wait_bus_free()?;
+            self.timer.start(self.config.timeout);
+            match self
+                .i2c
+                .transaction_slice_until(addr, ops_slice, &mut TimerDeadline(&mut self.timer))
+            {
+                // The peripheral is back in slave mode, the other master owns the bus
+                Err(Error::ArbitrationLoss) if retries > 0 => retries -= 1,
+                Err(e) => {
+                    // Release the bus for the other masters if the transfer stopped halfway
+                    self.i2c.release_bus();
+                    return Err(e);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.arbitrated(addr, &mut [Hal1Operation::Read(buffer)])
+    }
+
+    pub fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.arbitrated(addr, &mut [Hal1Operation::Write(bytes)])
+    }
+
+    /// Writes `bytes`, then fills `buffer` after a repeated start
+    ///
+    /// The repeated start keeps the bus, so other masters can't take it between both parts.
+    pub fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.arbitrated(addr, &mut [Hal1Operation::Write(bytes), Hal1Operation::Read(buffer)])
+    }
+
+    /// See [I2c::write_register]
+    pub fn write_register(&mut self, addr: u8, reg: &[u8], bytes: &[u8]) -> Result<(), Error> {
+        // Adjacent writes go out as one, without a repeated start
+        self.arbitrated(addr, &mut [Hal1Operation::Write(reg), Hal1Operation::Write(bytes)])
+    }
+
+    /// See [I2c::read_register]
+    pub fn read_register(&mut self, addr: u8, reg: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        if buffer.is_empty() {
+            return Err(Error::EmptyBuffer);
+        }
+
+        self.write_read(addr, reg, buffer)
+    }
+
+    /// Runs `ops_slice` with repeated starts, retrying the whole transaction if it lost
+    /// arbitration
+    pub fn transaction_slice(&mut self, addr: u8, ops_slice: &mut [Hal1Operation<'_>]) -> Result<(), Error> {
+        self.arbitrated(addr, ops_slice)
+    }
+}
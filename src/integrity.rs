@@ -0,0 +1,170 @@
+//! # Firmware integrity self-check
+//!
+//! Verifies a flash region against a CRC32 stored right after it, typically run once at boot.
+//!
+//! The region is described by two linker symbols, e.g. in `memory.x`:
+//!
+//! ```text
+//! SECTIONS {
+//!   .image_end : {
+//!     __image_end = .;
+//!     LONG(0xFFFFFFFF); /* patched with the image CRC after linking */
+//!   } > FLASH
+//! }
+//! __image_start = ORIGIN(FLASH);
+//! ```
+//!
+//! ## Usage
+//!
+//! ```rust
+//! let (_, mut crc32) = dp.Crc.constrain().split();
+//! let mut flash = dp.Flash.constrain();
+//!
+//! let status = match ImageRegion::from_linker() {
+//!     Some(region) => integrity::check_image(&mut flash, &mut crc32, region),
+//!     None => IntegrityStatus::OutOfBounds,
+//! };
+//! if status != IntegrityStatus::Valid {
+//!     // enter a safe state
+//! }
+//! ```
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use embedded_storage::nor_flash::ReadNorFlash;
+
+use crate::crc::Crc32Engine;
+use crate::fmc::{Flash, FlashError};
+
+/// Result of an integrity check
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityStatus {
+    /// The computed CRC matches the stored CRC
+    Valid,
+    /// The computed CRC differs from the stored CRC
+    Corrupted { expected: u32, computed: u32 },
+    /// The region doesn't lie within the flash
+    OutOfBounds,
+}
+
+const STATUS_UNCHECKED: u8 = 0;
+const STATUS_VALID: u8 = 1;
+const STATUS_INVALID: u8 = 2;
+
+static LAST_STATUS: AtomicU8 = AtomicU8::new(STATUS_UNCHECKED);
+
+/// Flash region covered by the integrity check
+///
+/// The expected CRC32 is stored in the word following the region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageRegion {
+    start: u32,
+    len: u32,
+}
+
+impl ImageRegion {
+    /// Region of `len` bytes starting at address `start`
+    ///
+    /// `start` and `len` must be word aligned.
+    pub const fn new(start: u32, len: u32) -> Self {
+        assert!(start % 4 == 0 && len % 4 == 0);
+        Self { start, len }
+    }
+
+    /// Region between the `__image_start` and `__image_end` linker symbols
+    ///
+    /// Returns `None` if `__image_end` lies before `__image_start` or either isn't word aligned.
+    pub fn from_linker() -> Option<Self> {
+        extern "C" {
+            static __image_start: u32;
+            static __image_end: u32;
+        }
+        // NOTE(unsafe) only the addresses of the symbols are used
+        let start = unsafe { core::ptr::addr_of!(__image_start) } as u32;
+        let end = unsafe { core::ptr::addr_of!(__image_end) } as u32;
+        match end.checked_sub(start) {
+            Some(len) if start % 4 == 0 && len % 4 == 0 => Some(Self { start, len }),
+            _ => None,
+        }
+    }
+
+    /// Start address of the region
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// Length of the region in bytes
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns true if the region is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Computes the CRC32 of `region` and compares it with the CRC stored after it
+///
+/// The result is also kept for [last_status].
+pub fn check_image(flash: &mut Flash, crc: &mut Crc32Engine, region: ImageRegion) -> IntegrityStatus {
+    let status = compute_status(flash, crc, region);
+    LAST_STATUS.store(
+        match status {
+            IntegrityStatus::Valid => STATUS_VALID,
+            _ => STATUS_INVALID,
+        },
+        Ordering::Relaxed,
+    );
+    status
+}
+
+fn compute_status(flash: &mut Flash, crc: &mut Crc32Engine, region: ImageRegion) -> IntegrityStatus {
+    // The stored CRC follows the region, so its end has to be addressable too
+    let offset = match region.start.checked_sub(Flash::FLASH_BASE) {
+        Some(offset) if region.len.checked_add(4).and_then(|len| offset.checked_add(len)).is_some() => offset,
+        _ => return IntegrityStatus::OutOfBounds,
+    };
+
+    let computed = match flash_crc(flash, crc, offset, region.len) {
+        Ok(computed) => computed,
+        Err(_) => return IntegrityStatus::OutOfBounds,
+    };
+
+    let mut stored = [0u8; 4];
+    if flash.read(offset + region.len, &mut stored).is_err() {
+        return IntegrityStatus::OutOfBounds;
+    }
+    let expected = u32::from_ne_bytes(stored);
+
+    if expected == computed {
+        IntegrityStatus::Valid
+    } else {
+        IntegrityStatus::Corrupted { expected, computed }
+    }
+}
+
+/// CRC32 of the `len` bytes of flash at `offset`, computed with the CRC unit
+pub(crate) fn flash_crc(flash: &mut Flash, crc: &mut Crc32Engine, offset: u32, len: u32) -> Result<u32, FlashError> {
+    let mut buf = [0u8; 64];
+    let mut computed = 0;
+    crc.init();
+    let mut pos = 0;
+    while pos < len {
+        let n = buf.len().min((len - pos) as usize);
+        flash.read(offset + pos, &mut buf[..n])?;
+        computed = crc.update_bytes(&buf[..n]);
+        pos += n as u32;
+    }
+    Ok(computed)
+}
+
+/// Result of the last [check_image], `None` if no check ran yet
+pub fn last_status() -> Option<bool> {
+    match LAST_STATUS.load(Ordering::Relaxed) {
+        STATUS_VALID => Some(true),
+        STATUS_INVALID => Some(false),
+        _ => None,
+    }
+}
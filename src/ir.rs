@@ -0,0 +1,212 @@
+//! # Infrared transmitter
+//!
+//! The N32G4 timers have no IR modulator combining two channels into one output, so
+//! [IrTransmitter] pairs two timers instead: the carrier timer generates the carrier on a
+//! PWM pin and runs gated by the trigger output of the envelope timer, which is enabled in
+//! one-pulse mode for the duration of every mark.
+//!
+//! The internal trigger (ITRx) connecting the envelope timer to the carrier timer is listed
+//! in the reference manual, e.g. TIM2 is ITR1 of TIM3.
+//!
+//! ```rust
+//! let (control, carrier) = dp.Tim3.pwm_advanced(gpioa.pa6.into_alternate(), &clocks)
+//!     .frequency(38.kHz())
+//!     .finalize();
+//! let envelope = Timer::new(dp.Tim2, &clocks);
+//! let mut ir = IrTransmitter::new(control, carrier, envelope, 1)?;
+//!
+//! // NEC leader, then alternating mark and space durations in microseconds
+//! ir.send_raw(&[9000, 4500, 560, 560, 560, 1690, 560])?;
+//! ```
+
+use embedded_hal_02::PwmPin;
+
+use crate::pwm::{GateSource, PwmControl};
+use crate::timer::{Timer, TriggerSource};
+
+/// Timer generating the carrier
+pub trait CarrierTimer: crate::Sealed {
+    #[doc(hidden)]
+    fn set_count(count: u16);
+}
+
+/// Timer generating the envelope
+pub trait EnvelopeTimer: crate::Sealed {
+    #[doc(hidden)]
+    fn init(prescaler: u16);
+    #[doc(hidden)]
+    fn pulse(ticks: u16, gate: bool);
+    #[doc(hidden)]
+    fn is_done() -> bool;
+}
+
+macro_rules! carrier_timer {
+    ($($TIM:ty,)+) => {
+        $(
+            impl CarrierTimer for $TIM {
+                fn set_count(count: u16) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.cnt().write(|w| unsafe { w.cnt().bits(count) });
+                }
+            }
+
+            impl<FAULT> Gate for PwmControl<$TIM, FAULT> {
+                fn gate(&mut self, source: GateSource) -> Result<(), crate::pwm::Error> {
+                    self.set_gate(source)
+                }
+
+                fn ungate(&mut self) {
+                    self.clear_gate();
+                }
+            }
+        )+
+    };
+}
+
+carrier_timer! {
+    crate::pac::Tim1,
+    crate::pac::Tim2,
+    crate::pac::Tim3,
+    crate::pac::Tim4,
+    crate::pac::Tim5,
+    crate::pac::Tim8,
+}
+
+macro_rules! envelope_timer {
+    ($($TIM:ty,)+) => {
+        $(
+            impl EnvelopeTimer for $TIM {
+                fn init(prescaler: u16) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // One pulse mode: the counter stops at the end of every pulse
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit().onepm().set_bit());
+                    tim.psc().write(|w| unsafe { w.psc().bits(prescaler) });
+                    <$TIM as crate::timer::Instance>::latch();
+                    tim.sts().write(|w| w.uditf().clear_bit());
+                }
+
+                fn pulse(ticks: u16, gate: bool) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // TRGO follows the counter enable during marks only
+                    let mms = if gate { TriggerSource::Enable } else { TriggerSource::Reset };
+                    tim.ctrl2().modify(|_, w| unsafe { w.mmsel().bits(mms as u8) });
+                    tim.ar().write(|w| unsafe { w.ar().bits(ticks) });
+                    tim.cnt().reset();
+                    tim.sts().write(|w| w.uditf().clear_bit());
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                }
+
+                fn is_done() -> bool {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.sts().read().uditf().bit_is_set()
+                }
+            }
+        )+
+    };
+}
+
+envelope_timer! {
+    crate::pac::Tim1,
+    crate::pac::Tim2,
+    crate::pac::Tim3,
+    crate::pac::Tim4,
+    crate::pac::Tim6,
+    crate::pac::Tim8,
+}
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+envelope_timer! {
+    crate::pac::Tim7,
+}
+
+/// Error of [IrTransmitter]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+    /// A duration is 0 or longer than 65535 µs
+    InvalidDuration,
+    /// The internal trigger is above ITR3
+    InvalidTrigger,
+    /// The envelope timer clock is below 1 MHz, so it can't count microseconds
+    InvalidClock,
+}
+
+/// IR blaster made of a gated carrier timer and an envelope timer
+pub struct IrTransmitter<CTIM, ETIM, FAULT, CH> {
+    control: PwmControl<CTIM, FAULT>,
+    carrier: CH,
+    envelope: Timer<ETIM>,
+}
+
+impl<CTIM, ETIM, FAULT, CH> IrTransmitter<CTIM, ETIM, FAULT, CH>
+where
+    CTIM: CarrierTimer,
+    ETIM: EnvelopeTimer,
+    CH: PwmPin<Duty = u16>,
+    PwmControl<CTIM, FAULT>: Gate,
+{
+    /// Pairs the `carrier` channel of `control` with `envelope`, connected to the carrier
+    /// timer as internal trigger `itr`
+    ///
+    /// The carrier frequency is the PWM frequency of `carrier`, its duty cycle is set to 1/3.
+    /// The carrier output has to be active high, the output is low between marks. Returns
+    /// [InvalidTrigger](Error::InvalidTrigger) if `itr` is above 3, and
+    /// [InvalidClock](Error::InvalidClock) if the envelope timer clock is below 1 MHz.
+    pub fn new(mut control: PwmControl<CTIM, FAULT>, mut carrier: CH, envelope: Timer<ETIM>, itr: u8) -> Result<Self, Error> {
+        // 1 µs ticks
+        let prescaler = match envelope.clk.raw() / 1_000_000 {
+            div @ 1..=0x1_0000 => div - 1,
+            _ => return Err(Error::InvalidClock),
+        };
+        control
+            .gate(GateSource::Internal(itr))
+            .map_err(|_| Error::InvalidTrigger)?;
+        ETIM::init(prescaler as u16);
+        carrier.set_duty(carrier.get_max_duty() / 3);
+        let mut ir = Self {
+            control,
+            carrier,
+            envelope,
+        };
+        ir.park();
+        ir.carrier.enable();
+        Ok(ir)
+    }
+
+    /// Sends alternating mark and space durations in microseconds, starting with a mark
+    ///
+    /// Blocks until the last duration elapsed.
+    pub fn send_raw(&mut self, durations: &[u32]) -> Result<(), Error> {
+        if durations.iter().any(|&d| d == 0 || d > u16::MAX as u32) {
+            return Err(Error::InvalidDuration);
+        }
+        for (i, &duration) in durations.iter().enumerate() {
+            let mark = i % 2 == 0;
+            ETIM::pulse(duration as u16, mark);
+            while !ETIM::is_done() {}
+            if mark {
+                self.park();
+            }
+        }
+        Ok(())
+    }
+
+    /// Releases the timers and the carrier channel
+    pub fn release(mut self) -> (PwmControl<CTIM, FAULT>, CH, Timer<ETIM>) {
+        self.carrier.disable();
+        self.control.ungate();
+        (self.control, self.carrier, self.envelope)
+    }
+
+    /// Moves the frozen carrier counter past the compare value so the output stays low
+    fn park(&mut self) {
+        CTIM::set_count(self.carrier.get_duty());
+    }
+}
+
+/// Gated slave mode of a PWM timer, see `PwmControl::set_gate`
+pub trait Gate {
+    #[doc(hidden)]
+    fn gate(&mut self, source: GateSource) -> Result<(), crate::pwm::Error>;
+    #[doc(hidden)]
+    fn ungate(&mut self);
+}
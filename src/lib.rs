@@ -2,7 +2,7 @@
 //!
 //! ## Feature flags
 // #![doc = document_features::document_features!()]
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(non_camel_case_types)]
 #![feature(associated_type_defaults)]
 #![feature(impl_trait_in_assoc_type)]
@@ -57,18 +57,40 @@ pub mod adc;
 pub mod afio;
 pub mod bb;
 #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+pub mod board;
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 pub mod bkp;
 pub mod can;
 pub mod crc;
 pub mod delay;
+#[cfg(feature = "display")]
+pub mod display;
 pub mod dma;
+#[cfg(feature = "events")]
+pub mod events;
 pub mod fmc;
+pub mod funcgen;
+pub mod fwupdate;
 pub mod gpio;
 pub mod i2c;
+pub mod integrity;
+pub mod ir;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod motor;
+pub mod nb_timeout;
+pub mod onewire;
+#[cfg(feature = "pdm")]
+pub mod pdm;
+pub mod profiling;
 pub mod pwm;
 pub mod sac;
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+pub mod sdio;
 pub mod serial;
 pub mod spi;
+pub mod sync;
+pub mod telemetry;
 pub mod rcc;
 pub mod time;
 pub mod timer;
@@ -0,0 +1,412 @@
+//! # Scripted bus mocks
+//!
+//! Stand-ins for [Spi](crate::spi::Spi), [I2c](crate::i2c::I2c) and [Serial](crate::serial::Serial)
+//! that implement the same `embedded-hal` traits with the same error types, so drivers written
+//! against those traits can be tested on the host. [I2cMock] also implements
+//! [MasterBus](crate::i2c::MasterBus), so [I2cMultiMaster](crate::i2c::I2cMultiMaster) runs on
+//! top of it.
+//!
+//! Each mock replays a script of expected transactions: writes are compared with the script,
+//! reads return the scripted data and a mismatch panics. Faults are injected by scripting an
+//! error (or `WouldBlock` for the serial mock) in place of a transaction.
+//!
+//! Requires the `mock` feature.
+//!
+//! ```rust
+//! use n32g4xx_hal::mock::{I2cMock, I2cTransaction};
+//! use n32g4xx_hal::i2c::{Error, NoAcknowledgeSource};
+//!
+//! let script = [
+//!     I2cTransaction::Write(0x48, &[0x00]),
+//!     I2cTransaction::Read(0x48, &[0x12, 0x34]),
+//!     I2cTransaction::Fail(Error::NoAcknowledge(NoAcknowledgeSource::Address)),
+//! ];
+//! let mut i2c = I2cMock::new(&script);
+//! let mut buf = [0; 2];
+//! i2c.write_read(0x48, &[0x00], &mut buf)?;
+//! assert!(i2c.read(0x48, &mut buf).is_err());
+//! i2c.done();
+//! ```
+
+use embedded_hal::i2c::{ErrorType as I2cErrorType, I2c, Operation};
+use embedded_hal::spi::{ErrorType as SpiErrorType, SpiBus};
+
+use crate::i2c::{Deadline, MasterBus};
+use crate::{i2c, serial, spi};
+
+/// Expected I2C operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2cTransaction<'a> {
+    /// Write of the bytes to the address
+    Write(u8, &'a [u8]),
+    /// Read from the address, returning the bytes
+    Read(u8, &'a [u8]),
+    /// Fails the transaction the next operation belongs to
+    Fail(i2c::Error),
+    /// The next [MasterBus::is_busy] finds the bus occupied
+    Busy,
+    /// [MasterBus::release_bus] after a failed transfer
+    Stop,
+}
+
+/// Scripted I2C bus
+pub struct I2cMock<'a> {
+    script: &'a [I2cTransaction<'a>],
+    position: usize,
+}
+
+impl<'a> I2cMock<'a> {
+    /// Creates a mock replaying `script`
+    pub fn new(script: &'a [I2cTransaction<'a>]) -> Self {
+        Self { script, position: 0 }
+    }
+
+    /// Panics if the script wasn't played completely
+    pub fn done(&self) {
+        assert_eq!(self.position, self.script.len(), "i2c mock: unplayed transactions");
+    }
+
+    fn next(&mut self) -> I2cTransaction<'a> {
+        let transaction = *self
+            .script
+            .get(self.position)
+            .expect("i2c mock: unexpected transaction");
+        self.position += 1;
+        transaction
+    }
+}
+
+impl I2cErrorType for I2cMock<'_> {
+    type Error = i2c::Error;
+}
+
+impl I2c for I2cMock<'_> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        for operation in operations {
+            match (operation, self.next()) {
+                (_, I2cTransaction::Fail(error)) => return Err(error),
+                (Operation::Write(bytes), I2cTransaction::Write(addr, expected)) => {
+                    assert_eq!((address, &**bytes), (addr, expected), "i2c mock: unexpected write");
+                }
+                (Operation::Read(buffer), I2cTransaction::Read(addr, data)) => {
+                    assert_eq!(address, addr, "i2c mock: unexpected read address");
+                    assert_eq!(buffer.len(), data.len(), "i2c mock: unexpected read length");
+                    buffer.copy_from_slice(data);
+                }
+                (operation, expected) => {
+                    panic!("i2c mock: got {:?}, expected {:?}", operation, expected)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MasterBus for I2cMock<'_> {
+    fn is_busy(&mut self) -> bool {
+        if self.script.get(self.position) == Some(&I2cTransaction::Busy) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn release_bus(&mut self) {
+        match self.next() {
+            I2cTransaction::Stop => {}
+            expected => panic!("i2c mock: got stop, expected {:?}", expected),
+        }
+    }
+
+    fn transaction_slice_until(
+        &mut self,
+        addr: u8,
+        ops_slice: &mut [Operation<'_>],
+        deadline: &mut impl Deadline,
+    ) -> Result<(), i2c::Error> {
+        deadline.check()?;
+        self.transaction(addr, ops_slice)
+    }
+}
+
+/// Expected SPI operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiTransaction<'a> {
+    /// Write of the words
+    Write(&'a [u8]),
+    /// Read returning the words
+    Read(&'a [u8]),
+    /// Transfer writing the first and returning the second words
+    Transfer(&'a [u8], &'a [u8]),
+    /// Fails the next operation
+    Fail(spi::Error),
+}
+
+/// Scripted SPI bus
+pub struct SpiMock<'a> {
+    script: &'a [SpiTransaction<'a>],
+    position: usize,
+}
+
+impl<'a> SpiMock<'a> {
+    /// Creates a mock replaying `script`
+    pub fn new(script: &'a [SpiTransaction<'a>]) -> Self {
+        Self { script, position: 0 }
+    }
+
+    /// Panics if the script wasn't played completely
+    pub fn done(&self) {
+        assert_eq!(self.position, self.script.len(), "spi mock: unplayed transactions");
+    }
+
+    fn next(&mut self) -> Result<SpiTransaction<'a>, spi::Error> {
+        let transaction = *self
+            .script
+            .get(self.position)
+            .expect("spi mock: unexpected transaction");
+        self.position += 1;
+        match transaction {
+            SpiTransaction::Fail(error) => Err(error),
+            transaction => Ok(transaction),
+        }
+    }
+}
+
+impl SpiErrorType for SpiMock<'_> {
+    type Error = spi::Error;
+}
+
+impl SpiBus<u8> for SpiMock<'_> {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        match self.next()? {
+            SpiTransaction::Read(data) if data.len() == words.len() => words.copy_from_slice(data),
+            expected => panic!("spi mock: got read of {} words, expected {:?}", words.len(), expected),
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        match self.next()? {
+            SpiTransaction::Write(expected) => assert_eq!(words, expected, "spi mock: unexpected write"),
+            expected => panic!("spi mock: got write of {:?}, expected {:?}", words, expected),
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        match self.next()? {
+            SpiTransaction::Transfer(expected, data) if data.len() == read.len() => {
+                assert_eq!(write, expected, "spi mock: unexpected transfer");
+                read.copy_from_slice(data);
+            }
+            expected => panic!("spi mock: got transfer of {:?}, expected {:?}", write, expected),
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        match self.next()? {
+            SpiTransaction::Transfer(expected, data) if data.len() == words.len() => {
+                assert_eq!(&*words, expected, "spi mock: unexpected transfer");
+                words.copy_from_slice(data);
+            }
+            expected => panic!("spi mock: got transfer of {:?}, expected {:?}", words, expected),
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Expected serial operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialTransaction {
+    /// Write of the word
+    Write(u8),
+    /// Read returning the word
+    Read(u8),
+    /// Flush
+    Flush,
+    /// The next read, write or flush would block
+    WouldBlock,
+    /// Fails the next read, write or flush
+    Fail(serial::Error),
+}
+
+/// Scripted serial port
+pub struct SerialMock<'a> {
+    script: &'a [SerialTransaction],
+    position: usize,
+}
+
+impl<'a> SerialMock<'a> {
+    /// Creates a mock replaying `script`
+    pub fn new(script: &'a [SerialTransaction]) -> Self {
+        Self { script, position: 0 }
+    }
+
+    /// Panics if the script wasn't played completely
+    pub fn done(&self) {
+        assert_eq!(self.position, self.script.len(), "serial mock: unplayed transactions");
+    }
+
+    fn next(&mut self) -> nb::Result<SerialTransaction, serial::Error> {
+        let transaction = *self
+            .script
+            .get(self.position)
+            .expect("serial mock: unexpected transaction");
+        self.position += 1;
+        match transaction {
+            SerialTransaction::WouldBlock => Err(nb::Error::WouldBlock),
+            SerialTransaction::Fail(error) => Err(nb::Error::Other(error)),
+            transaction => Ok(transaction),
+        }
+    }
+}
+
+impl embedded_hal_nb::serial::ErrorType for SerialMock<'_> {
+    type Error = serial::Error;
+}
+
+impl embedded_hal_nb::serial::Read<u8> for SerialMock<'_> {
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        match self.next()? {
+            SerialTransaction::Read(word) => Ok(word),
+            expected => panic!("serial mock: got read, expected {:?}", expected),
+        }
+    }
+}
+
+impl embedded_hal_nb::serial::Write<u8> for SerialMock<'_> {
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        match self.next()? {
+            SerialTransaction::Write(expected) => {
+                assert_eq!(word, expected, "serial mock: unexpected write");
+                Ok(())
+            }
+            expected => panic!("serial mock: got write of {}, expected {:?}", word, expected),
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        match self.next()? {
+            SerialTransaction::Flush => Ok(()),
+            expected => panic!("serial mock: got flush, expected {:?}", expected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i2c::{Error, I2cMultiMaster, MultiMasterConfig, NoAcknowledgeSource};
+    use crate::time::MicroSecond;
+
+    /// Count down that runs out after `polls` waits
+    struct Polls {
+        polls: u32,
+        left: u32,
+    }
+
+    impl Polls {
+        fn new(polls: u32) -> Self {
+            Self { polls, left: polls }
+        }
+    }
+
+    impl embedded_hal_02::timer::CountDown for Polls {
+        type Time = MicroSecond;
+
+        fn start<T>(&mut self, _count: T)
+        where
+            T: Into<MicroSecond>,
+        {
+            self.left = self.polls;
+        }
+
+        fn wait(&mut self) -> nb::Result<(), void::Void> {
+            if self.left == 0 {
+                Ok(())
+            } else {
+                self.left -= 1;
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "spi mock")]
+    fn spi_transfer_rejects_short_read() {
+        let script = [SpiTransaction::Transfer(&[0x9f], &[0xef, 0x40])];
+        let mut spi = SpiMock::new(&script);
+        let mut read = [0; 1];
+        let _ = spi.transfer(&mut read, &[0x9f]);
+    }
+
+    #[test]
+    fn multi_master_waits_for_free_bus() {
+        let script = [
+            I2cTransaction::Busy,
+            I2cTransaction::Busy,
+            I2cTransaction::Write(0x48, &[0x01]),
+            I2cTransaction::Write(0x48, &[0x60, 0xa0]),
+        ];
+        let mut i2c = I2cMultiMaster::new(I2cMock::new(&script), Polls::new(u32::MAX), MultiMasterConfig::default());
+        assert_eq!(i2c.write_register(0x48, &[0x01], &[0x60, 0xa0]), Ok(()));
+        i2c.release().0.done();
+    }
+
+    #[test]
+    fn multi_master_busy_timeout() {
+        let script = [I2cTransaction::Busy];
+        let mut i2c = I2cMultiMaster::new(I2cMock::new(&script), Polls::new(0), MultiMasterConfig::default());
+        assert_eq!(i2c.write(0x48, &[0x01]), Err(Error::Timeout));
+        i2c.release().0.done();
+    }
+
+    #[test]
+    fn multi_master_releases_bus_after_failure() {
+        let nack = Error::NoAcknowledge(NoAcknowledgeSource::Address);
+        let script = [I2cTransaction::Fail(nack), I2cTransaction::Stop];
+        let mut i2c = I2cMultiMaster::new(I2cMock::new(&script), Polls::new(u32::MAX), MultiMasterConfig::default());
+        let mut buffer = [0; 2];
+        assert_eq!(i2c.read_register(0x48, &[0x00], &mut buffer), Err(nack));
+        i2c.release().0.done();
+    }
+
+    #[test]
+    fn multi_master_retries_lost_arbitration() {
+        let script = [
+            // The other master wins the first START and keeps the bus for a while
+            I2cTransaction::Fail(Error::ArbitrationLoss),
+            I2cTransaction::Busy,
+            I2cTransaction::Fail(Error::ArbitrationLoss),
+            I2cTransaction::Write(0x48, &[0x07]),
+            I2cTransaction::Read(0x48, &[0x5a]),
+        ];
+        let mut i2c = I2cMultiMaster::new(I2cMock::new(&script), Polls::new(u32::MAX), MultiMasterConfig::default());
+        let mut id = [0; 1];
+        assert_eq!(i2c.read_register(0x48, &[0x07], &mut id), Ok(()));
+        assert_eq!(id, [0x5a]);
+        i2c.release().0.done();
+    }
+
+    #[test]
+    fn multi_master_gives_up_after_retries() {
+        let config = MultiMasterConfig {
+            retries: 1,
+            ..MultiMasterConfig::default()
+        };
+        let script = [
+            I2cTransaction::Fail(Error::ArbitrationLoss),
+            I2cTransaction::Fail(Error::ArbitrationLoss),
+            I2cTransaction::Stop,
+        ];
+        let mut i2c = I2cMultiMaster::new(I2cMock::new(&script), Polls::new(u32::MAX), config);
+        assert_eq!(i2c.write(0x48, &[0x07]), Err(Error::ArbitrationLoss));
+        i2c.release().0.done();
+    }
+}
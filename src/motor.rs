@@ -0,0 +1,319 @@
+//! # Six-step block commutation of BLDC motors
+//!
+//! [SixStep] drives the three complementary channels of an advanced timer (TIM1 or TIM8) in
+//! block commutation, switching the output pattern on the commutation (COM) event instead of
+//! rewriting `CCEN` from an interrupt:
+//!
+//! * a general purpose timer is the hall sensor interface: its three inputs are XORed onto
+//!   TI1, every hall edge captures the time since the previous edge into CCR1, resets the
+//!   counter and pulses the trigger output
+//! * the trigger output of the hall timer raises the COM event of the advanced timer, which
+//!   loads the preloaded `CCxE`/`CCxNE` enables and output compare modes of all channels
+//!   at once
+//! * the COM interrupt is then used to preload the pattern for the next hall edge, so its
+//!   latency doesn't delay the commutation itself
+//!
+//! The hall sensors go on channels 1 to 3 of the hall timer, the hall timer has to be the
+//! one wired to an internal trigger of the advanced timer (TIM2, TIM3 or TIM4 for TIM1, TIM2
+//! or TIM4 for TIM8).
+//!
+//! ```rust
+//! use n32g4xx_hal::motor::{Phase, Phase::*, SixStep};
+//!
+//! // Pattern applied when the sensors leave the state, for the forward direction
+//! const FORWARD: [[Phase; 3]; 8] = [
+//!     [Float, Float, Float],
+//!     [Pwm, Low, Float],
+//!     [Float, Pwm, Low],
+//!     [Pwm, Float, Low],
+//!     [Low, Float, Pwm],
+//!     [Float, Low, Pwm],
+//!     [Low, Pwm, Float],
+//!     [Float, Float, Float],
+//! ];
+//!
+//! let (control, (u, v, w)) = dp.Tim1.pwm_advanced(pins, &clocks).frequency(20.kHz()).finalize();
+//! let hall = Timer::new(dp.Tim3, &clocks);
+//! let mut motor = SixStep::new(control, hall, FORWARD, 1.MHz(), 8)?;
+//! motor.start(read_hall_pins());
+//! motor.listen();
+//!
+//! // TIM1 commutation interrupt
+//! motor.clear_interrupt();
+//! motor.preload(read_hall_pins());
+//! ```
+
+use crate::pwm::PwmControl;
+use crate::time::Hertz;
+use crate::timer::{Error, Instance, Timer};
+
+/// State of a motor phase during a commutation step
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Both switches off, the phase floats
+    Float,
+    /// The complementary outputs are driven by the PWM
+    Pwm,
+    /// The low side switch is on
+    Low,
+}
+
+/// Output pattern of the three phases, indexed by the state of the hall sensors
+pub type CommutationTable = [[Phase; 3]; 8];
+
+/// Advanced timer that can switch its outputs on the commutation event
+pub trait CommutationTimer: Instance {
+    #[doc(hidden)]
+    fn init(itr: u8);
+    #[doc(hidden)]
+    fn preload(step: [Phase; 3]);
+    #[doc(hidden)]
+    fn commutate();
+    #[doc(hidden)]
+    fn listen(enable: bool);
+    #[doc(hidden)]
+    fn is_pending() -> bool;
+    #[doc(hidden)]
+    fn clear_interrupt();
+    #[doc(hidden)]
+    fn release();
+}
+
+/// General purpose timer that can be the hall sensor interface of the advanced timer `TIM`
+pub trait HallTimer<TIM>: Instance {
+    /// Internal trigger of `TIM` the trigger output of this timer is wired to
+    #[doc(hidden)]
+    const ITR: u8;
+    #[doc(hidden)]
+    fn init(psc: u16, filter: u8);
+    #[doc(hidden)]
+    fn edge_period() -> u16;
+    #[doc(hidden)]
+    fn release();
+}
+
+macro_rules! commutation_timer {
+    ($($TIM:ty,)+) => {
+        $(
+            impl CommutationTimer for $TIM {
+                fn init(itr: u8) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // Preloaded enables and modes, COM on COMG or a rising TRGI
+                    tim.ctrl2().modify(|_, w| w.ccpctl().set_bit().ccusel().set_bit());
+                    // TRGI = ITRx, no slave mode
+                    tim.smctrl().modify(|_, w| unsafe { w.tsel().bits(itr).smsel().bits(0b000) });
+                }
+
+                fn preload(step: [Phase; 3]) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // Outputs enabled and output compare mode: PWM mode 1 or forced inactive
+                    let [(en1, m1), (en2, m2), (en3, m3)] = step.map(|phase| match phase {
+                        Phase::Float => (false, 0b100),
+                        Phase::Pwm => (true, 0b110),
+                        Phase::Low => (true, 0b100),
+                    });
+                    tim.ccmod1().modify(|_, w| unsafe { w.oc1m().bits(m1).oc2m().bits(m2) });
+                    tim.ccmod2().modify(|_, w| unsafe { w.oc3m().bits(m3) });
+                    tim.ccen().modify(|_, w| { w
+                        .cc1en().bit(en1).cc1nen().bit(en1)
+                        .cc2en().bit(en2).cc2nen().bit(en2)
+                        .cc3en().bit(en3).cc3nen().bit(en3)
+                    });
+                }
+
+                fn commutate() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.evtgen().write(|w| w.ccudgn().set_bit());
+                }
+
+                fn listen(enable: bool) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.dinten().modify(|_, w| w.comien().bit(enable));
+                }
+
+                fn is_pending() -> bool {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.sts().read().comitf().bit_is_set()
+                }
+
+                fn clear_interrupt() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // The other flags ignore writing 1
+                    tim.sts().write(|w| unsafe { w.bits(0xffff_ffff).comitf().clear_bit() });
+                }
+
+                fn release() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    Self::listen(false);
+                    tim.ctrl2().modify(|_, w| w.ccpctl().clear_bit().ccusel().clear_bit());
+                    tim.smctrl().modify(|_, w| unsafe { w.tsel().bits(0).smsel().bits(0) });
+                    tim.ccen().modify(|_, w| { w
+                        .cc1en().clear_bit().cc1nen().clear_bit()
+                        .cc2en().clear_bit().cc2nen().clear_bit()
+                        .cc3en().clear_bit().cc3nen().clear_bit()
+                    });
+                }
+            }
+        )+
+    };
+}
+
+commutation_timer! {
+    crate::pac::Tim1,
+    crate::pac::Tim8,
+}
+
+macro_rules! hall_timer {
+    ($($HALL:ty: [$($TIM:ty => $itr:literal),+],)+) => {
+        $(
+            $(
+                impl HallTimer<$TIM> for $HALL {
+                    const ITR: u8 = $itr;
+
+                    fn init(psc: u16, filter: u8) {
+                        let tim = unsafe { &*<$HALL>::ptr() };
+                        tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                        // TI1 = XOR of the three inputs, TRGO = compare pulse
+                        tim.ctrl2().modify(|_, w| unsafe { w.ti1sel().set_bit().mmsel().bits(0b011) });
+                        // IC1 = TRC
+                        tim.ccmod1().write(|w| unsafe { w.cc1sel().bits(0b11).ic1f().bits(filter) });
+                        tim.ccen().write(|w| w.cc1en().set_bit());
+                        // Reset mode on TI1F_ED
+                        tim.smctrl().write(|w| unsafe { w.tsel().bits(0b100).smsel().bits(0b100) });
+                        tim.psc().write(|w| unsafe { w.psc().bits(psc) });
+                        tim.ar().write(|w| unsafe { w.bits(0xffff) });
+                        <$HALL as Instance>::latch();
+                        tim.sts().write(|w| w.uditf().clear_bit());
+                        tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                    }
+
+                    fn edge_period() -> u16 {
+                        let tim = unsafe { &*<$HALL>::ptr() };
+                        tim.ccr1().read().bits() as u16
+                    }
+
+                    fn release() {
+                        let tim = unsafe { &*<$HALL>::ptr() };
+                        tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                        tim.smctrl().reset();
+                        tim.ccen().reset();
+                        tim.ccmod1().reset();
+                        tim.ctrl2().reset();
+                    }
+                }
+            )+
+        )+
+    };
+}
+
+hall_timer! {
+    crate::pac::Tim2: [crate::pac::Tim1 => 1, crate::pac::Tim8 => 1],
+    crate::pac::Tim3: [crate::pac::Tim1 => 2],
+    crate::pac::Tim4: [crate::pac::Tim1 => 3, crate::pac::Tim8 => 2],
+}
+
+/// Block commutation of the channels 1 to 3 of `TIM` driven by the hall sensor interface `HALL`
+pub struct SixStep<TIM, HALL, FAULT> {
+    control: PwmControl<TIM, FAULT>,
+    hall: Timer<HALL>,
+    table: CommutationTable,
+}
+
+impl<TIM, HALL, FAULT> SixStep<TIM, HALL, FAULT>
+where
+    TIM: CommutationTimer,
+    HALL: HallTimer<TIM>,
+{
+    /// Sets up the hall sensor interface and the commutation of `control`
+    ///
+    /// `table[h]` is the pattern switched to at the first hall edge after the sensors read `h`,
+    /// reverse the rotation by using a different table. The hall timer counts at `tick`, which
+    /// sets the unit and the range of [edge_period](Self::edge_period). `filter` is the input
+    /// filter setting (0 to 15) of the hall inputs.
+    ///
+    /// All phases float until [start](Self::start) is called. Returns
+    /// [InvalidFilter](Error::InvalidFilter) if `filter` is above 15, and
+    /// [InvalidFrequency](Error::InvalidFrequency) if `tick` is zero.
+    pub fn new(
+        control: PwmControl<TIM, FAULT>,
+        hall: Timer<HALL>,
+        table: CommutationTable,
+        tick: Hertz,
+        filter: u8,
+    ) -> Result<Self, Error> {
+        if filter > 15 {
+            return Err(Error::InvalidFilter);
+        }
+        let div = hall.clk.raw().checked_div(tick.raw()).ok_or(Error::InvalidFrequency)?;
+        let psc = div.clamp(1, 1 << 16) - 1;
+        TIM::preload([Phase::Float; 3]);
+        TIM::commutate();
+        TIM::init(HALL::ITR);
+        HALL::init(psc as u16, filter);
+        Ok(Self { control, hall, table })
+    }
+
+    /// Switches to the pattern of the sensor state `hall` right away and preloads it for the next edge
+    ///
+    /// Use this to start the motor, when no hall edge happened yet.
+    pub fn start(&mut self, hall: u8) {
+        TIM::preload(self.table[hall as usize & 7]);
+        TIM::commutate();
+        self.preload(hall);
+    }
+
+    /// Preloads the pattern switched to at the next hall edge, `hall` being the current sensor state
+    ///
+    /// Call this from the commutation interrupt.
+    pub fn preload(&mut self, hall: u8) {
+        TIM::preload(self.table[hall as usize & 7]);
+    }
+
+    /// Replaces the commutation table, e.g. to reverse the rotation
+    ///
+    /// Takes effect with the next [preload](Self::preload).
+    pub fn set_table(&mut self, table: CommutationTable) {
+        self.table = table;
+    }
+
+    /// Ticks between the last two hall edges, which is inversely proportional to the speed
+    ///
+    /// Only meaningful while the edges are less than 65536 ticks apart, the counter wraps around
+    /// when the motor is slower than that.
+    pub fn edge_period(&self) -> u16 {
+        HALL::edge_period()
+    }
+
+    /// Enables the commutation interrupt
+    pub fn listen(&mut self) {
+        TIM::listen(true);
+    }
+
+    /// Disables the commutation interrupt
+    pub fn unlisten(&mut self) {
+        TIM::listen(false);
+    }
+
+    /// Returns `true` if a commutation happened since the interrupt was cleared
+    pub fn is_pending(&self) -> bool {
+        TIM::is_pending()
+    }
+
+    /// Clears the commutation interrupt flag
+    pub fn clear_interrupt(&mut self) {
+        TIM::clear_interrupt();
+    }
+
+    /// Gives access to the PWM control, e.g. to stop the outputs on a fault
+    pub fn control(&mut self) -> &mut PwmControl<TIM, FAULT> {
+        &mut self.control
+    }
+
+    /// Turns all phases off and releases the PWM control and the hall timer
+    pub fn release(self) -> (PwmControl<TIM, FAULT>, Timer<HALL>) {
+        TIM::release();
+        HALL::release();
+        (self.control, self.hall)
+    }
+}
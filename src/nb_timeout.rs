@@ -0,0 +1,80 @@
+//! # Timeouts for `nb` operations
+//!
+//! `nb::block!` spins until the operation completes, forever if a peripheral is wedged.
+//! [TimeoutExt::with_timeout] polls a non-blocking operation the same way, but gives up
+//! once a count down timer expires:
+//!
+//! ```rust
+//! let mut timer = Timer::new(dp.Tim6, &clocks).start_count_down(1.millis());
+//!
+//! // serial
+//! let byte = (|| rx.read()).with_timeout(&mut timer, 5.millis())?;
+//! // SPI
+//! (|| spi.write_nonblocking(0x9f)).with_timeout(&mut timer, 100.micros())?;
+//! let id = (|| spi.read_nonblocking()).with_timeout(&mut timer, 100.micros())?;
+//! ```
+//!
+//! Any [CountDown] works as the timer, including a [SysCounter](crate::timer::SysCounter),
+//! and the timeout is converted to its duration type. Errors of the operation are returned
+//! as [TimeoutError::Other].
+
+use embedded_hal_02::timer::CountDown;
+
+/// Error of an operation bounded by [TimeoutExt::with_timeout]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    /// The operation didn't complete in time
+    Timeout,
+    /// The operation failed
+    Other(E),
+}
+
+impl<E> TimeoutError<E> {
+    /// Converts the error of the operation, e.g. into the error of a driver
+    pub fn map<F>(self, f: impl FnOnce(E) -> F) -> TimeoutError<F> {
+        match self {
+            TimeoutError::Timeout => TimeoutError::Timeout,
+            TimeoutError::Other(e) => TimeoutError::Other(f(e)),
+        }
+    }
+}
+
+impl<E> From<E> for TimeoutError<E> {
+    fn from(e: E) -> Self {
+        TimeoutError::Other(e)
+    }
+}
+
+/// Polls a non-blocking operation until it completes or a timer expires
+pub trait TimeoutExt<T, E> {
+    /// Polls the operation until it returns anything but `WouldBlock`, or until `timeout`
+    /// measured with `timer` elapsed
+    ///
+    /// `timer` is restarted with `timeout`, so it can be shared by consecutive operations.
+    fn with_timeout<C>(self, timer: &mut C, timeout: impl Into<C::Time>) -> Result<T, TimeoutError<E>>
+    where
+        C: CountDown;
+}
+
+impl<F, T, E> TimeoutExt<T, E> for F
+where
+    F: FnMut() -> nb::Result<T, E>,
+{
+    fn with_timeout<C>(mut self, timer: &mut C, timeout: impl Into<C::Time>) -> Result<T, TimeoutError<E>>
+    where
+        C: CountDown,
+    {
+        timer.start(timeout);
+        loop {
+            match self() {
+                Ok(value) => return Ok(value),
+                Err(nb::Error::Other(e)) => return Err(TimeoutError::Other(e)),
+                Err(nb::Error::WouldBlock) => {}
+            }
+            if timer.wait().is_ok() {
+                return Err(TimeoutError::Timeout);
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! # 1-Wire master
+//!
+//! 1-Wire over a single open-drain GPIO with a pull-up, timed with a microsecond delay. Each
+//! time slot runs with interrupts disabled, at most 70 µs for read and write slots, as an
+//! interrupt stretching the low pulse of a slot would turn a 1 into a 0. The reset pulse and
+//! the recovery times between slots may be stretched, so interrupts are served there.
+//!
+//! ```rust
+//! let pin = gpiob.pb0.into_open_drain_output();
+//! let mut bus = OneWire::new(pin, cp.SYST.delay(&clocks));
+//!
+//! let mut search = Search::new();
+//! while let Some(rom) = bus.search(&mut search)? {
+//!     bus.reset()?;
+//!     bus.match_rom(rom);
+//!     bus.write_byte(0x44); // DS18B20 convert temperature
+//! }
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+
+/// Standard speed timings, in microseconds
+const RESET_LOW_US: u32 = 480;
+const PRESENCE_SAMPLE_US: u32 = 70;
+const RESET_RECOVERY_US: u32 = 410;
+const SLOT_START_US: u32 = 6;
+const WRITE_0_LOW_US: u32 = 60;
+const READ_SAMPLE_US: u32 = 9;
+const SLOT_US: u32 = 70;
+const RECOVERY_US: u32 = 5;
+
+const MATCH_ROM: u8 = 0x55;
+const SKIP_ROM: u8 = 0xCC;
+const SEARCH_ROM: u8 = 0xF0;
+
+/// 1-Wire error
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// No device answered the reset pulse
+    NoPresence,
+    /// The line stays low, it is shorted or the pull-up is missing
+    BusLow,
+    /// A search found inconsistent bits, a device left the bus during the search
+    Search,
+    /// The CRC of a ROM code doesn't match
+    Crc,
+}
+
+/// 64-bit ROM code of a device: family code, serial number and CRC
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rom(pub [u8; 8]);
+
+impl Rom {
+    /// Family code, the device type
+    pub fn family(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns `true` if the CRC byte matches the other bytes
+    pub fn is_valid(&self) -> bool {
+        crc8(&self.0[..7]) == self.0[7]
+    }
+}
+
+/// Dallas/Maxim CRC-8 (polynomial x^8 + x^5 + x^4 + 1) used by ROM codes and scratchpads
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0;
+    for &byte in data {
+        let mut byte = byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 1;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+/// State of a ROM search, see [OneWire::search]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Search {
+    rom: [u8; 8],
+    /// Bit index of the last branch where the 0 path was taken, 0 if none
+    last_discrepancy: u8,
+    done: bool,
+}
+
+impl Search {
+    /// Starts a search over all the devices of the bus
+    pub fn new() -> Self {
+        Self {
+            rom: [0; 8],
+            last_discrepancy: 0,
+            done: false,
+        }
+    }
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 1-Wire master on `PIN`
+pub struct OneWire<PIN, D> {
+    pin: PIN,
+    delay: D,
+}
+
+impl<PIN, D> OneWire<PIN, D>
+where
+    PIN: OutputPin + InputPin,
+    D: DelayNs,
+{
+    /// Creates the master and releases the line
+    pub fn new(mut pin: PIN, delay: D) -> Self {
+        let _ = pin.set_high();
+        Self { pin, delay }
+    }
+
+    /// Releases the pin and the delay
+    pub fn release(self) -> (PIN, D) {
+        (self.pin, self.delay)
+    }
+
+    fn is_high(&mut self) -> bool {
+        self.pin.is_high().unwrap_or(false)
+    }
+
+    /// Sends a reset pulse and waits for the presence pulse of the devices
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if !self.is_high() {
+            return Err(Error::BusLow);
+        }
+        let _ = self.pin.set_low();
+        self.delay.delay_us(RESET_LOW_US);
+        let present = cortex_m::interrupt::free(|_| {
+            let _ = self.pin.set_high();
+            self.delay.delay_us(PRESENCE_SAMPLE_US);
+            !self.is_high()
+        });
+        self.delay.delay_us(RESET_RECOVERY_US);
+        if present {
+            Ok(())
+        } else {
+            Err(Error::NoPresence)
+        }
+    }
+
+    /// Writes a single bit
+    pub fn write_bit(&mut self, bit: bool) {
+        cortex_m::interrupt::free(|_| {
+            let _ = self.pin.set_low();
+            if bit {
+                self.delay.delay_us(SLOT_START_US);
+                let _ = self.pin.set_high();
+                self.delay.delay_us(SLOT_US - SLOT_START_US);
+            } else {
+                self.delay.delay_us(WRITE_0_LOW_US);
+                let _ = self.pin.set_high();
+                self.delay.delay_us(SLOT_US - WRITE_0_LOW_US);
+            }
+        });
+        self.delay.delay_us(RECOVERY_US);
+    }
+
+    /// Reads a single bit
+    pub fn read_bit(&mut self) -> bool {
+        let bit = cortex_m::interrupt::free(|_| {
+            let _ = self.pin.set_low();
+            self.delay.delay_us(SLOT_START_US);
+            let _ = self.pin.set_high();
+            self.delay.delay_us(READ_SAMPLE_US);
+            let bit = self.is_high();
+            self.delay.delay_us(SLOT_US - SLOT_START_US - READ_SAMPLE_US);
+            bit
+        });
+        self.delay.delay_us(RECOVERY_US);
+        bit
+    }
+
+    /// Writes a byte, least significant bit first
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Reads a byte, least significant bit first
+    pub fn read_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, i| byte | (self.read_bit() as u8) << i)
+    }
+
+    /// Writes all the bytes of `data`
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Fills `data` with the bytes read
+    pub fn read_bytes(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Addresses the device with `rom`, after a [reset](Self::reset)
+    pub fn match_rom(&mut self, rom: Rom) {
+        self.write_byte(MATCH_ROM);
+        self.write_bytes(&rom.0);
+    }
+
+    /// Addresses all the devices at once, after a [reset](Self::reset)
+    pub fn skip_rom(&mut self) {
+        self.write_byte(SKIP_ROM);
+    }
+
+    /// Finds the next device of the bus, returns `None` once all were found
+    ///
+    /// Resets the bus first. Pass the same [Search] to successive calls to enumerate the
+    /// devices.
+    pub fn search(&mut self, search: &mut Search) -> Result<Option<Rom>, Error> {
+        if search.done {
+            return Ok(None);
+        }
+        self.reset()?;
+        self.write_byte(SEARCH_ROM);
+
+        let mut last_zero = 0;
+        for bit_index in 1..=64u8 {
+            let byte = ((bit_index - 1) / 8) as usize;
+            let mask = 1 << ((bit_index - 1) % 8);
+            let id_bit = self.read_bit();
+            let complement = self.read_bit();
+            let direction = match (id_bit, complement) {
+                (true, true) => return Err(Error::Search),
+                // All remaining devices have the same bit
+                (bit, _) if bit != complement => bit,
+                // Discrepancy, devices with both values
+                _ => {
+                    let direction = if bit_index < search.last_discrepancy {
+                        search.rom[byte] & mask != 0
+                    } else {
+                        bit_index == search.last_discrepancy
+                    };
+                    if !direction {
+                        last_zero = bit_index;
+                    }
+                    direction
+                }
+            };
+            if direction {
+                search.rom[byte] |= mask;
+            } else {
+                search.rom[byte] &= !mask;
+            }
+            self.write_bit(direction);
+        }
+
+        search.last_discrepancy = last_zero;
+        search.done = last_zero == 0;
+        let rom = Rom(search.rom);
+        if !rom.is_valid() {
+            return Err(Error::Crc);
+        }
+        Ok(Some(rom))
+    }
+}
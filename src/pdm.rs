@@ -0,0 +1,90 @@
+//! # PDM microphone capture
+//!
+//! A PDM microphone outputs one bit per clock, its pulse density following the sound
+//! pressure. A receive-only SPI master clocks the microphone and a circular DMA transfer
+//! captures the bitstream, see [CircReadDma](crate::dma::CircReadDma); [Cic] then decimates
+//! each half of the buffer to 16-bit PCM as it fills up.
+//!
+//! The decimation factor is `8 * B` bits per sample, e.g. a 3.072 MHz clock with `B = 8`
+//! gives 48 kHz PCM. The filter runs four integrators per bit, so at high rates most of the
+//! CPU time goes into it: keep the halves large enough that the interrupt overhead stays low.
+//!
+//! ```rust
+//! static mut PDM: [[u8; 256]; 2] = [[0; 256]; 2];
+//!
+//! let spi = dp.Spi2.spi_rxonly((sck, miso), spi::MODE_0, 3_072.kHz(), &clocks);
+//! let mut capture = spi.with_rx_dma(dma.4).circ_read(unsafe { &mut PDM });
+//! let mut cic = Cic::<8>::new();
+//! let mut pcm = [0i16; 32];
+//!
+//! // DMA half/complete transfer interrupt
+//! let samples = capture.peek(|half, _| cic.process(half, &mut pcm))?;
+//! ```
+
+/// Number of integrator and comb stages
+const ORDER: usize = 4;
+
+/// Fourth order CIC decimator from 1-bit PDM, most significant bit first, to 16-bit PCM
+///
+/// Decimates by `8 * B`, `B` being the number of PDM bytes per PCM sample. `B` is 2, 4, 8
+/// or 16: the filter state has to fit in 32 bits and the output in 16 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cic<const B: usize> {
+    integrators: [i32; ORDER],
+    combs: [i32; ORDER],
+}
+
+impl<const B: usize> Cic<B> {
+    const DECIMATION: u32 = 8 * B as u32;
+    /// The gain is DECIMATION^ORDER, keep the 16 most significant bits
+    const SHIFT: u32 = ORDER as u32 * Self::DECIMATION.trailing_zeros() - 15;
+
+    /// Creates the filter with cleared state
+    pub const fn new() -> Self {
+        assert!(B.is_power_of_two() && B >= 2 && B <= 16);
+        Self {
+            integrators: [0; ORDER],
+            combs: [0; ORDER],
+        }
+    }
+
+    /// Clears the filter state, e.g. after a gap in the bitstream
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Decimates `pdm` into `pcm`, returns the number of samples written
+    ///
+    /// Writes one sample per `B` bytes of `pdm`, stopping when `pcm` is full. Trailing
+    /// bytes short of a full sample are ignored, so pass whole multiples of `B`.
+    pub fn process(&mut self, pdm: &[u8], pcm: &mut [i16]) -> usize {
+        let mut written = 0;
+        for (chunk, sample) in pdm.chunks_exact(B).zip(pcm.iter_mut()) {
+            for &byte in chunk {
+                for bit in (0..8).rev() {
+                    // Map the bits to -1 and +1 so silence averages to zero
+                    let mut acc = if byte & (1 << bit) != 0 { 1 } else { -1 };
+                    for integrator in &mut self.integrators {
+                        *integrator = integrator.wrapping_add(acc);
+                        acc = *integrator;
+                    }
+                }
+            }
+            // The wrapping arithmetic cancels out in the combs as long as the output fits
+            let mut acc = self.integrators[ORDER - 1];
+            for comb in &mut self.combs {
+                let delayed = core::mem::replace(comb, acc);
+                acc = acc.wrapping_sub(delayed);
+            }
+            *sample = (acc >> Self::SHIFT).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            written += 1;
+        }
+        written
+    }
+}
+
+impl<const B: usize> Default for Cic<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,22 +1,54 @@
+//! Traits needed to use the HAL
+//!
+//! `use n32g4xx_hal::prelude::*;` brings the methods of the `embedded-hal` 1.0 traits, the
+//! `embedded-hal` 0.2 traits and the extension traits of this crate into scope. All traits
+//! are imported anonymously, so methods of both `embedded-hal` versions can be called on the
+//! same driver and the trait names don't clash with the ones imported by the user.
+
+// embedded-hal 1.0
 pub use embedded_hal::delay::DelayNs as _;
+pub use embedded_hal::digital::InputPin as _;
+pub use embedded_hal::digital::OutputPin as _;
+pub use embedded_hal::digital::StatefulOutputPin as _;
+pub use embedded_hal::i2c::I2c as _;
+pub use embedded_hal::spi::SpiBus as _;
+pub use embedded_hal_nb::serial::Read as _embedded_hal_serial_nb_Read;
+pub use embedded_hal_nb::serial::Write as _embedded_hal_serial_nb_Write;
+
+// embedded-hal 0.2
 pub use embedded_hal_02::adc::OneShot as _embedded_hal_adc_OneShot;
+pub use embedded_hal_02::blocking::delay::DelayMs as _embedded_hal_blocking_delay_DelayMs;
+pub use embedded_hal_02::blocking::delay::DelayUs as _embedded_hal_blocking_delay_DelayUs;
+pub use embedded_hal_02::blocking::i2c::Read as _embedded_hal_blocking_i2c_Read;
+pub use embedded_hal_02::blocking::i2c::Write as _embedded_hal_blocking_i2c_Write;
+pub use embedded_hal_02::blocking::i2c::WriteRead as _embedded_hal_blocking_i2c_WriteRead;
 pub use embedded_hal_02::blocking::serial::Write as _embedded_hal_blocking_serial_Write;
+pub use embedded_hal_02::blocking::spi::Transfer as _embedded_hal_blocking_spi_Transfer;
+pub use embedded_hal_02::blocking::spi::Write as _embedded_hal_blocking_spi_Write;
+pub use embedded_hal_02::digital::v2::InputPin as _embedded_hal_digital_v2_InputPin;
+pub use embedded_hal_02::digital::v2::OutputPin as _embedded_hal_digital_v2_OutputPin;
+pub use embedded_hal_02::digital::v2::StatefulOutputPin as _embedded_hal_digital_v2_StatefulOutputPin;
+pub use embedded_hal_02::digital::v2::ToggleableOutputPin as _embedded_hal_digital_v2_ToggleableOutputPin;
+pub use embedded_hal_02::timer::CountDown as _embedded_hal_timer_CountDown;
 pub use embedded_hal_02::Capture as _embedded_hal_Capture;
 pub use embedded_hal_02::Pwm as _embedded_hal_Pwm;
 pub use embedded_hal_02::Qei as _embedded_hal_Qei;
-pub use embedded_hal_nb::serial::Read as _embedded_hal_serial_nb_Read;
-pub use embedded_hal_nb::serial::Write as _embedded_hal_serial_nb_Write;
+
 pub use fugit::ExtU32 as _fugit_ExtU32;
 pub use fugit::RateExtU32 as _fugit_RateExtU32;
 
 pub use crate::dma::DmaExt as _;
 pub use crate::serial::SerialDma as _;
+pub use crate::delay::DelayExt as _n32g4xx_hal_delay_DelayExt;
+pub use crate::delay::SYSTDelayExt as _n32g4xx_hal_delay_SYSTDelayExt;
 pub use crate::gpio::ExtiPin as _n32g4xx_hal_gpio_ExtiPin;
 pub use crate::gpio::GpioExt as _n32g4xx_hal_gpio_GpioExt;
+pub use crate::gpio::outport::OutPort as _n32g4xx_hal_gpio_outport_OutPort;
 pub use crate::i2c::dma::I2CMasterHandleIT as _n32g4xx_hal_i2c_dma_I2CMasterHandleIT;
 pub use crate::i2c::dma::I2CMasterReadDMA as _n32g4xx_hal_i2c_dma_I2CMasterReadDMA;
 pub use crate::i2c::dma::I2CMasterWriteDMA as _n32g4xx_hal_i2c_dma_I2CMasterWriteDMA;
 pub use crate::i2c::dma::I2CMasterWriteReadDMA as _n32g4xx_hal_i2c_dma_I2CMasterWriteReadDMA;
+pub use crate::nb_timeout::TimeoutExt as _n32g4xx_hal_nb_timeout_TimeoutExt;
 pub use crate::rcc::RccExt as _n32g4xx_hal_rcc_RccExt;
 pub use crate::pwr::PwrExt as _n32g4xx_hal_pwr_PwrExt;
 pub use crate::serial::RxISR as _n32g4xx_hal_serial_RxISR;
@@ -24,6 +56,7 @@ pub use crate::serial::RxListen as _n32g4xx_hal_serial_RxListen;
 pub use crate::serial::SerialExt as _n32g4xx_hal_serial_SerialExt;
 pub use crate::serial::TxISR as _n32g4xx_hal_serial_TxISR;
 pub use crate::serial::TxListen as _n32g4xx_hal_serial_TxListen;
+pub use crate::spi::InterruptHandler as _n32g4xx_hal_spi_InterruptHandler;
 pub use crate::spi::SpiExt as _n32g4xx_hal_spi_SpiExt;
 pub use crate::afio::AfioExt as _n32g4xx_hal_afio_AfioExt;
 pub use crate::time::U32Ext as _n32g4xx_hal_time_U32Ext;
@@ -36,4 +69,4 @@ pub use crate::timer::SysMonoTimerExt as _n32g4xx_hal_timer_SysMonoTimerExt;
 
 pub use crate::ClearFlags as _;
 pub use crate::Listen as _;
-pub use crate::ReadFlags as _;
\ No newline at end of file
+pub use crate::ReadFlags as _;
@@ -0,0 +1,167 @@
+//! # Cycle count profiling
+//!
+//! Measures code sections with the DWT cycle counter. A [Probe] accumulates the number of
+//! runs, the total and the worst case cycle count of a section; a [Scope] measures one run
+//! and adds it to its probe when dropped.
+//!
+//! With the `profiling` feature the blocking SPI and I2C transfers and the DMA transfer
+//! waits are measured by the probes in [probes].
+//!
+//! ```rust
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! profiling::enable(&mut cp.DCB, &mut cp.DWT);
+//!
+//! static FILTER: Probe = Probe::new("filter");
+//! {
+//!     let _scope = FILTER.scope();
+//!     run_filter(&mut samples);
+//! }
+//! // with the `defmt` feature
+//! FILTER.report();
+//! ```
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::{DCB, DWT};
+
+/// Starts the DWT cycle counter
+pub fn enable(dcb: &mut DCB, dwt: &mut DWT) {
+    dcb.enable_trace();
+    dwt.enable_cycle_counter();
+}
+
+/// Current value of the cycle counter, wraps around every 2^32 cycles
+#[inline(always)]
+pub fn cycles() -> u32 {
+    DWT::cycle_count()
+}
+
+/// Runs `f` and returns its result along with the cycles it took
+pub fn measure<R>(f: impl FnOnce() -> R) -> (R, u32) {
+    let start = cycles();
+    let result = f();
+    (result, cycles().wrapping_sub(start))
+}
+
+/// Accumulated cycle costs of a code section
+pub struct Probe {
+    name: &'static str,
+    count: AtomicU32,
+    total: AtomicU32,
+    max: AtomicU32,
+}
+
+/// Snapshot of a [Probe]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeStats {
+    /// Number of measured runs
+    pub count: u32,
+    /// Cycles of all runs, wraps around
+    pub total: u32,
+    /// Cycles of the longest run
+    pub max: u32,
+}
+
+impl ProbeStats {
+    /// Average cycles per run
+    pub fn average(&self) -> u32 {
+        self.total.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+impl Probe {
+    /// Creates a probe named `name`
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            count: AtomicU32::new(0),
+            total: AtomicU32::new(0),
+            max: AtomicU32::new(0),
+        }
+    }
+
+    /// Name of the probe
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Starts measuring a run, which ends when the returned scope is dropped
+    #[inline(always)]
+    pub fn scope(&self) -> Scope<'_> {
+        Scope {
+            probe: self,
+            start: cycles(),
+        }
+    }
+
+    /// Adds a run of `cycles` cycles
+    pub fn record(&self, cycles: u32) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(cycles, Ordering::Relaxed);
+        self.max.fetch_max(cycles, Ordering::Relaxed);
+    }
+
+    /// Returns the accumulated costs
+    pub fn stats(&self) -> ProbeStats {
+        ProbeStats {
+            count: self.count.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Clears the accumulated costs
+    pub fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+
+    /// Prints the accumulated costs
+    #[cfg(feature = "defmt")]
+    pub fn report(&self) {
+        let stats = self.stats();
+        defmt::info!(
+            "{=str}: {} runs, {} cycles avg, {} cycles max",
+            self.name,
+            stats.count,
+            stats.average(),
+            stats.max
+        );
+    }
+}
+
+/// Measurement of a single run, recorded in its [Probe] on drop
+pub struct Scope<'a> {
+    probe: &'a Probe,
+    start: u32,
+}
+
+impl Drop for Scope<'_> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.probe.record(cycles().wrapping_sub(self.start));
+    }
+}
+
+/// Probes of the built-in instrumentation points
+#[cfg(feature = "profiling")]
+pub mod probes {
+    use super::Probe;
+
+    /// Blocking SPI reads, writes and transfers
+    pub static SPI_TRANSFER: Probe = Probe::new("spi transfer");
+    /// Blocking I2C reads, writes and transactions
+    pub static I2C_TRANSFER: Probe = Probe::new("i2c transfer");
+    /// Waits for DMA transfers to complete
+    pub static DMA_WAIT: Probe = Probe::new("dma wait");
+
+    /// Prints the costs of all built-in probes
+    #[cfg(feature = "defmt")]
+    pub fn report() {
+        SPI_TRANSFER.report();
+        I2C_TRANSFER.report();
+        DMA_WAIT.report();
+    }
+}
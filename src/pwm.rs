@@ -152,7 +152,7 @@
 //!
 //! Deadtime is applied based on the logical active/inactive levels. Depending on the PWM polarity and complementary polarity, both pins can be high or low during deadtime; they will both be in the inactive state.
 //!
-//! The deadtime must be 4032 counts of the timer clock or less or the builder will assert/panic. For a 200MHz timer this is 20 microseconds; slower timers can have even longer deadtimes.
+//! The deadtime must be 4032 counts of the timer clock or less or [PwmBuilder::with_deadtime](struct.PwmBuilder.html#method.with_deadtime) will panic, [PwmBuilder::try_with_deadtime](struct.PwmBuilder.html#method.try_with_deadtime) returns an error along with the builder instead. For a 200MHz timer this is 20 microseconds; slower timers can have even longer deadtimes.
 //!
 //! ## Disabled or faulted state
 //!
@@ -175,11 +175,14 @@ use core::mem::MaybeUninit;
 use crate::gpio::*;
 use crate::pac::Rcc;
 
-use crate::pac::{Tim1, Tim2, Tim3, Tim4, Tim5, Tim6, Tim7, Tim8};
+use crate::pac::{Tim1, Tim2, Tim3, Tim4, Tim5, Tim8};
 
 use crate::rcc::{Enable, BusTimerClock, Clocks, Reset};
+pub use crate::timer::{EtrConfig, EtrMode, TriggerSource};
 use crate::time::{ExtU32, Hertz, NanoSecond, RateExtU32};
 
+pub mod servo;
+
 // This trait marks that a GPIO pin can be used with a specific timer channel
 // TIM is the timer being used
 // CHANNEL is a marker struct for the channel (or multi channels for tuples)
@@ -217,18 +220,33 @@ pub struct ComplementaryDisabled;
 pub struct ComplementaryEnabled;
 
 /// Enum for IO polarity
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Polarity {
     ActiveHigh,
     ActiveLow,
 }
 
 /// Configuration enum to keep track of which break input corresponds with which FaultPins
-#[derive(PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BreakInput {
     BreakIn,
     BreakIn2,
 }
 
+/// Error of the fallible deadtime and gate setters
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+    /// The deadtime is longer than 4032 ticks of the timer clock
+    DeadtimeTooLong,
+    /// The input filter setting is above 15
+    InvalidFilter,
+    /// The internal trigger is above ITR3
+    InvalidTrigger,
+}
+
 /// Internal enum that keeps track of the count settings before PWM is finalized
 enum CountSettings<WIDTH> {
     Frequency(Hertz),
@@ -241,6 +259,7 @@ pub struct ActiveHigh;
 pub struct ActiveLow;
 
 /// Whether a PWM signal is left-aligned, right-aligned, or center-aligned
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Copy, Clone, Debug)]
 pub enum Alignment {
     Left,
@@ -248,6 +267,14 @@ pub enum Alignment {
     Center,
 }
 
+/// Interrupt events of a PWM channel
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The counter matched the compare value of the channel
+    Compare,
+}
+
 /// Pwm represents one PWM channel; it is created by calling TIM?.pwm(...) and lets you control the channel through the PwmPin trait
 pub struct Pwm<TIM, CHANNEL, COMP, POL, NPOL> {
     _channel: PhantomData<CHANNEL>,
@@ -285,12 +312,24 @@ pub trait FaultMonitor {
 }
 
 /// Exposes timer wide advanced features, such as [FaultMonitor](trait.FaultMonitor.html)
-/// or future features like trigger outputs for synchronization with ADCs and other peripherals
+/// and trigger outputs for synchronization with ADCs and other peripherals
 pub struct PwmControl<TIM, FAULT> {
     _tim: PhantomData<TIM>,
     _fault: PhantomData<FAULT>,
 }
 
+/// Compare and dead-time updates of a timer collected by [PwmControl::commit]
+pub struct PwmBatch<TIM> {
+    _tim: PhantomData<TIM>,
+}
+
+impl<TIM> PwmBatch<TIM> {
+    /// Sets the duty cycle of `pin`, taking effect together with the rest of the batch
+    pub fn set_duty<P: embedded_hal_02::PwmPin>(&mut self, pin: &mut P, duty: P::Duty) {
+        pin.set_duty(duty);
+    }
+}
+
 /// Marker struct indicating that a PwmControl is in charge of fault monitoring
 pub struct FaultEnabled;
 /// Marker struct indicating that a PwmControl does not handle fault monitoring
@@ -686,15 +725,15 @@ fn calculate_frequency_16bit(base_freq: Hertz, freq: Hertz, alignment: Alignment
     let period = (ideal_period + (prescale >> 1)) / (prescale + 1) - 1;
 
     // It should be impossible to fail these asserts
-    assert!(period <= 0xFFFF);
-    assert!(prescale <= 0xFFFF);
+    debug_assert!(period <= 0xFFFF);
+    debug_assert!(prescale <= 0xFFFF);
 
     (period, prescale as u16)
 }
 
 // Deadtime calculator helper function
 // Returns (BDTR.DTG, CR1.CKD)
-fn calculate_deadtime(base_freq: Hertz, deadtime: NanoSecond) -> (u8, u8) {
+fn calculate_deadtime(base_freq: Hertz, deadtime: NanoSecond) -> Result<(u8, u8), Error> {
     // tDTS is based on tCK_INT which is before the prescaler
     // It uses its own separate prescaler CR1.CKD
 
@@ -705,20 +744,20 @@ fn calculate_deadtime(base_freq: Hertz, deadtime: NanoSecond) -> (u8, u8) {
     let deadtime_ticks = deadtime.ticks() as u64 * base_freq.raw() as u64;
     // Make sure we won't overflow when multiplying; DTG is max 1008 ticks and CKD is max prescaler of 4
     // so deadtimes over 4032 ticks are impossible (4032*10^9 before dividing)
-    assert!(deadtime_ticks <= 4_032_000_000_000u64);
+    if deadtime_ticks > 4_032_000_000_000u64 {
+        return Err(Error::DeadtimeTooLong);
+    }
     let deadtime_ticks = deadtime_ticks * 42950;
     let deadtime_ticks = (deadtime_ticks >> 32) as u32;
     let deadtime_ticks = deadtime_ticks as u64 * 429497;
     let deadtime_ticks = (deadtime_ticks >> 32) as u32;
 
-    // Choose CR1 CKD divider of 1, 2, or 4 to determine tDTS
+    // Choose CR1 CKD divider of 1, 2, or 4 to determine tDTS, as the CKD field value
     let (deadtime_ticks, ckd) = match deadtime_ticks {
-        t if t <= 1008 => (deadtime_ticks, 1),
-        t if t <= 2016 => (deadtime_ticks / 2, 2),
-        t if t <= 4032 => (deadtime_ticks / 4, 4),
-        _ => {
-            panic!("Deadtime must be less than 4032 ticks of timer base clock.")
-        }
+        t if t <= 1008 => (deadtime_ticks, 0),
+        t if t <= 2016 => (deadtime_ticks / 2, 1),
+        t if t <= 4032 => (deadtime_ticks / 4, 2),
+        _ => return Err(Error::DeadtimeTooLong),
     };
 
     // Choose BDTR DTG bits to match deadtime_ticks
@@ -732,11 +771,12 @@ fn calculate_deadtime(base_freq: Hertz, deadtime: NanoSecond) -> (u8, u8) {
         };
 
         if actual_deadtime >= deadtime_ticks {
-            return (dtg as u8, ckd);
+            return Ok((dtg as u8, ckd));
         }
     }
 
-    panic!("This should be unreachable.");
+    // 1008 ticks are reachable with any CKD
+    Err(Error::DeadtimeTooLong)
 }
 
 // PwmExt trait
@@ -781,6 +821,103 @@ macro_rules! tim_hal {
         $(
             pwm_ext_hal!($TIMX: $timX);
 
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Selects the signal driven on the timer's trigger output (TRGO)
+                ///
+                /// This can be used to synchronise ADC conversions or other timers with the PWM period,
+                /// e.g. [TriggerSource::Update] together with an ADC `*_trgo` external trigger
+                pub fn set_trgo(&mut self, source: TriggerSource) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.ctrl2().modify(|_, w| unsafe { w.mmsel().bits(source as u8) });
+                }
+
+                /// Starts listening for the update event at the end of each PWM period
+                ///
+                /// The compare events of single channels are listened for with [Pwm::listen].
+                pub fn listen_update(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dinten().modify(|_, w| w.uien().set_bit());
+                }
+
+                /// Stops listening for the update event
+                pub fn unlisten_update(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.dinten().modify(|_, w| w.uien().clear_bit());
+                }
+
+                /// Returns true if an update event occurred since the flag was last cleared
+                pub fn is_update_pending(&self) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.sts().read().uditf().bit_is_set()
+                }
+
+                /// Clears the update event flag
+                pub fn clear_update(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // NOTE(unsafe) the flags are cleared by writing 0, the other bits are left as they are
+                    tim.sts().write(|w| unsafe { w.bits(0xffff_ffff).uditf().clear_bit() });
+                }
+
+                /// Updates several channels so that all new values take effect in the same PWM period
+                ///
+                /// The compare registers are preloaded and only copied to the active registers on
+                /// an update event. The update events are disabled while `f` runs, so a period
+                /// ending in the middle of the batch keeps the old values instead of applying part
+                /// of the new ones; the whole batch is applied at the next update event after `f`.
+                ///
+                /// ```rust
+                /// control.commit(|batch| {
+                ///     batch.set_duty(&mut u, duty_u);
+                ///     batch.set_duty(&mut v, duty_v);
+                ///     batch.set_duty(&mut w, duty_w);
+                /// });
+                /// ```
+                pub fn commit<R>(&mut self, f: impl FnOnce(&mut PwmBatch<$TIMX>) -> R) -> R {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.ctrl1().modify(|_, w| w.updis().set_bit());
+                    let result = f(&mut PwmBatch { _tim: PhantomData });
+                    tim.ctrl1().modify(|_, w| w.updis().clear_bit());
+                    result
+                }
+            }
+
+            $(
+                impl PwmBatch<$TIMX> {
+                    /// Sets the deadtime of the complementary channels
+                    ///
+                    /// Unlike the compare registers the deadtime isn't preloaded, it changes as soon as
+                    /// the batch runs.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if the deadtime is longer than 4032 ticks of the timer clock, see
+                    /// [try_set_deadtime](Self::try_set_deadtime).
+                    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on long deadtimes, use try_set_deadtime"))]
+                    pub fn set_deadtime<T: Into<NanoSecond>>(&mut self, deadtime: T, clocks: &Clocks) {
+                        if self.try_set_deadtime(deadtime, clocks).is_err() {
+                            panic!("Deadtime must be less than 4032 ticks of timer base clock.")
+                        }
+                    }
+
+                    /// Sets the deadtime of the complementary channels, or leaves it unchanged
+                    /// if it is longer than 4032 ticks of the timer clock
+                    pub fn try_set_deadtime<T: Into<NanoSecond>>(&mut self, deadtime: T, clocks: &Clocks) -> Result<(), Error> {
+                        let tim = unsafe { &*$TIMX::ptr() };
+
+                        let (dtg, ckd) = calculate_deadtime($TIMX::timer_clock(clocks), deadtime.into())?;
+                        tim.ctrl1().modify(|_, w| unsafe { w.clkd().bits(ckd) });
+                        tim.$bdtr().modify(|_, w| unsafe { w.dtgn().bits(dtg) });
+                        Ok(())
+                    }
+                }
+            )*
+
             /// Configures PWM
             fn $timX<PINS, T, U>(
                 tim: $TIMX,
@@ -882,14 +1019,11 @@ macro_rules! tim_hal {
                     tim.ar().write(|w| unsafe { w.ar().bits(period as u16) });
 
                     $(
-                        let (dtg, ckd) = calculate_deadtime(self.base_freq, self.deadtime);
+                        // with_deadtime and try_with_deadtime only store deadtimes that fit, so this
+                        // falls back to the longest deadtime for nothing
+                        let (dtg, ckd) = calculate_deadtime(self.base_freq, self.deadtime).unwrap_or((u8::MAX, 2));
 
-                        match ckd {
-                            1 => tim.ctrl1().modify(|_, w| unsafe { w.clkd().bits(0) }),
-                            2 => tim.ctrl1().modify(|_, w| unsafe { w.clkd().bits(1) }),
-                            4 => tim.ctrl1().modify(|_, w| unsafe { w.clkd().bits(2) }),
-                            _ => panic!("Should be unreachable, invalid deadtime prescaler"),
-                        }
+                        tim.ctrl1().modify(|_, w| unsafe { w.clkd().bits(ckd) });
 
                         let bkp = match self.fault_polarity {
                             Polarity::ActiveLow => false,
@@ -972,14 +1106,33 @@ macro_rules! tim_hal {
                 // Timers with complementary and deadtime and faults
                 $(
                     /// Set the deadtime for complementary PWM channels of this timer
-                    pub fn with_deadtime<T: Into<NanoSecond>>(mut self, deadtime: T) -> Self {
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if the deadtime is longer than 4032 ticks of the timer clock, see
+                    /// [try_with_deadtime](Self::try_with_deadtime).
+                    #[cfg_attr(feature = "panic-free", deprecated(note = "panics on long deadtimes, use try_with_deadtime"))]
+                    pub fn with_deadtime<T: Into<NanoSecond>>(self, deadtime: T) -> Self {
+                        match self.try_with_deadtime(deadtime) {
+                            Ok(builder) => builder,
+                            Err(_) => panic!("Deadtime must be less than 4032 ticks of timer base clock."),
+                        }
+                    }
+
+                    /// Set the deadtime for complementary PWM channels of this timer, or return
+                    /// [DeadtimeTooLong](Error::DeadtimeTooLong) along with the builder if it is longer
+                    /// than 4032 ticks of the timer clock
+                    pub fn try_with_deadtime<T: Into<NanoSecond>>(mut self, deadtime: T) -> Result<Self, (Error, Self)> {
                         // $bdtr is an Ident that only exists for timers with deadtime, so we can use it as a variable name to
                         // only implement this method for timers that support deadtime.
                         let $bdtr = deadtime.into();
+                        if let Err(error) = calculate_deadtime(self.base_freq, $bdtr) {
+                            return Err((error, self));
+                        }
 
                         self.deadtime = $bdtr;
 
-                        self
+                        Ok(self)
                     }
                 )*
 
@@ -1055,17 +1208,132 @@ macro_rules! tim_hal {
     }
 }
 
+// Advanced-control timers, with complementary outputs and break
 tim_hal! {
     Tim1: (tim1, u16, 16, DIR: camsel, BDTR: bkdt, set_bit),
+    Tim8: (tim8, u16, 16, DIR: camsel, BDTR: bkdt, set_bit),
+}
+// General-purpose timers
+tim_hal! {
     Tim2: (tim2, u16, 16, DIR: camsel),
     Tim3: (tim3, u16, 16, DIR: camsel),
     Tim4: (tim4, u16, 16, DIR: camsel),
     Tim5: (tim5, u16, 16, DIR: camsel),
 }
-tim_hal! {
-    Tim8: (tim8, u16, 16, DIR: camsel, BDTR: bkdt, set_bit),
-    Tim6: (tim7, u16, 16),
-    Tim7: (tim6, u16, 16),
+// The basic timers TIM6 and TIM7 have no channels, so no PWM
+
+/// Signal gating the counter of a PWM timer, see `PwmControl::set_gate`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum GateSource {
+    /// External trigger input, the ETR pin has to be configured as input
+    ///
+    /// The counter runs while ETR is high, or low if `inverted`. `filter` is the input
+    /// filter setting (0 to 15).
+    Etr { inverted: bool, filter: u8 },
+    /// Trigger output of another timer, internal trigger ITR0 to ITR3
+    ///
+    /// The counter runs while the TRGO of the master timer is high, e.g. the OCxREF of the
+    /// envelope timer selected with its `set_trgo`. The timer behind each ITRx is listed in
+    /// the reference manual.
+    Internal(u8),
+}
+
+macro_rules! pwm_gate {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Runs the counter only while `gate` is active (gated slave mode)
+                ///
+                /// While the gate is inactive the counter is frozen and the outputs keep their
+                /// level, so the compare values should make the outputs idle at the count the
+                /// burst ends on. The counter is reset to 0 so the first burst starts a full period.
+                ///
+                /// Returns [InvalidFilter](Error::InvalidFilter) for an ETR filter above 15 and
+                /// [InvalidTrigger](Error::InvalidTrigger) for an internal trigger above ITR3.
+                pub fn set_gate(&mut self, gate: GateSource) -> Result<(), Error> {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    match gate {
+                        GateSource::Etr { filter, .. } if filter > 15 => return Err(Error::InvalidFilter),
+                        GateSource::Internal(itr) if itr > 3 => return Err(Error::InvalidTrigger),
+                        _ => {}
+                    }
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    tim.smctrl().write(|w| unsafe {
+                        let w = match gate {
+                            // TRGI = ETRF
+                            GateSource::Etr { inverted, filter } => w.tsel().bits(0b111).extp().bit(inverted).extf().bits(filter),
+                            // TRGI = ITRx
+                            GateSource::Internal(itr) => w.tsel().bits(itr),
+                        };
+                        // Gated mode
+                        w.smsel().bits(0b101)
+                    });
+                    tim.cnt().reset();
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                    Ok(())
+                }
+
+                /// Lets the counter run freely again, also undoes [set_etr](Self::set_etr) on TIM1 and TIM8
+                pub fn clear_gate(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.smctrl().reset();
+                }
+            }
+        )+
+    }
+}
+
+pwm_gate! {
+    Tim1,
+    Tim2,
+    Tim3,
+    Tim4,
+    Tim5,
+    Tim8,
+}
+
+macro_rules! pwm_etr {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Drives the counter from the ETR pin, conditioned by `config`
+                ///
+                /// In [EtrMode::Clock] the period counts ETR edges instead of timer clock
+                /// cycles. [EtrMode::Reset] restarts the period on each active edge, which
+                /// synchronizes the PWM to an external signal, and [EtrMode::Gate] works as
+                /// [set_gate](Self::set_gate) with the prescaler of `config` as well. Returns the
+                /// ETR pin, it has to stay in its alternate function mode.
+                pub fn set_etr(
+                    &mut self,
+                    pin: impl Into<<$TIMX as crate::gpio::alt::TimEtr>::Etr>,
+                    config: EtrConfig,
+                    mode: EtrMode,
+                ) -> <$TIMX as crate::gpio::alt::TimEtr>::Etr {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let pin = pin.into();
+
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    crate::timer::write_etr!(tim, config, |w| match mode {
+                        EtrMode::Clock => w.excen().set_bit(),
+                        // TS = ETRF
+                        EtrMode::Reset => w.tsel().bits(0b111).smsel().bits(0b100),
+                        EtrMode::Gate => w.tsel().bits(0b111).smsel().bits(0b101),
+                    });
+                    tim.cnt().reset();
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                    pin
+                }
+            }
+        )+
+    }
+}
+
+pwm_etr! {
+    Tim1,
+    Tim8,
 }
 
 pub trait PwmPinEnable {
@@ -1078,7 +1346,7 @@ macro_rules! tim_pin_hal {
     // Standard pins (no complementary functionality)
     ($($TIMX:ident:
        ($CH:ty, $ccxe:ident, $ccxp:ident, $ccmrx_output:ident, $ocxpe:ident, $ocxm:ident,
-        $ccrx:ident, $typ:ident $(,$ccxne:ident, $ccxnp:ident)*),)+
+        $ccrx:ident, $ccxien:ident, $ccxitf:ident, $typ:ident $(,$ccxne:ident, $ccxnp:ident)*),)+
     ) => {
         $(
             impl<COMP, POL, NPOL> embedded_hal_02::PwmPin for Pwm<$TIMX, $CH, COMP, POL, NPOL>
@@ -1153,6 +1421,51 @@ macro_rules! tim_pin_hal {
                 }
             }
 
+            impl<COMP, POL, NPOL> Pwm<$TIMX, $CH, COMP, POL, NPOL> {
+                /// Starts listening for `event` of this channel
+                ///
+                /// The compare event fires at the duty cycle point of each period, e.g. to sample
+                /// at a fixed phase of the PWM or to commutate in software. The timer's capture
+                /// compare interrupt has to be unmasked in the NVIC too.
+                pub fn listen(&mut self, event: Event) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    match event {
+                        Event::Compare => tim.dinten().modify(|_, w| w.$ccxien().set_bit()),
+                    };
+                }
+
+                /// Stops listening for `event` of this channel
+                pub fn unlisten(&mut self, event: Event) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    match event {
+                        Event::Compare => tim.dinten().modify(|_, w| w.$ccxien().clear_bit()),
+                    };
+                }
+
+                /// Returns true if `event` of this channel occurred since its flag was last cleared
+                ///
+                /// The flag is set whether or not the event is listened for.
+                pub fn is_pending(&self, event: Event) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    match event {
+                        Event::Compare => tim.sts().read().$ccxitf().bit_is_set(),
+                    }
+                }
+
+                /// Clears the flag of `event` of this channel
+                pub fn clear_interrupt(&mut self, event: Event) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // NOTE(unsafe) the flags are cleared by writing 0, the other bits are left as they are
+                    match event {
+                        Event::Compare => tim.sts().write(|w| unsafe { w.bits(0xffff_ffff).$ccxitf().clear_bit() }),
+                    };
+                }
+            }
+
             impl<COMP, NPOL> Pwm<$TIMX, $CH, COMP, ActiveHigh, NPOL> {
                 pub fn into_active_low(self) -> Pwm<$TIMX, $CH, COMP, ActiveLow, NPOL> {
                     let tim = unsafe { &*$TIMX::ptr() };
@@ -1274,39 +1587,39 @@ macro_rules! tim_pin_hal {
 
 // Quad channel timers
 tim_pin_hal! {
-    Tim1: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, u16, cc1nen, cc1np),
-    Tim1: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, u16, cc2nen, cc2np),
-    Tim1: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, u16, cc3nen, cc3np),
-    Tim1: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, u16),
+    Tim1: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, cc1ien, cc1itf, u16, cc1nen, cc1np),
+    Tim1: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, cc2ien, cc2itf, u16, cc2nen, cc2np),
+    Tim1: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, cc3ien, cc3itf, u16, cc3nen, cc3np),
+    Tim1: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, cc4ien, cc4itf, u16),
 }
 tim_pin_hal! {
-    Tim2: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, u16),
-    Tim2: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, u16),
-    Tim2: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, u16),
-    Tim2: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, u16),
+    Tim2: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, cc1ien, cc1itf, u16),
+    Tim2: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, cc2ien, cc2itf, u16),
+    Tim2: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, cc3ien, cc3itf, u16),
+    Tim2: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, cc4ien, cc4itf, u16),
 }
 tim_pin_hal! {
-    Tim3: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, u16),
-    Tim3: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, u16),
-    Tim3: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, u16),
-    Tim3: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, u16),
+    Tim3: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, cc1ien, cc1itf, u16),
+    Tim3: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, cc2ien, cc2itf, u16),
+    Tim3: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, cc3ien, cc3itf, u16),
+    Tim3: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, cc4ien, cc4itf, u16),
 }
 tim_pin_hal! {
-    Tim4: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, u16),
-    Tim4: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, u16),
-    Tim4: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, u16),
-    Tim4: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, u16),
+    Tim4: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, cc1ien, cc1itf, u16),
+    Tim4: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, cc2ien, cc2itf, u16),
+    Tim4: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, cc3ien, cc3itf, u16),
+    Tim4: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, cc4ien, cc4itf, u16),
 }
 tim_pin_hal! {
-    Tim5: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, u16),
-    Tim5: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, u16),
-    Tim5: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, u16),
-    Tim5: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, u16),
+    Tim5: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, cc1ien, cc1itf, u16),
+    Tim5: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, cc2ien, cc2itf, u16),
+    Tim5: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, cc3ien, cc3itf, u16),
+    Tim5: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, cc4ien, cc4itf, u16),
 }
 // Quad channel timers
 tim_pin_hal! {
-    Tim8: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, u16, cc1nen, cc1np),
-    Tim8: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, u16, cc2nen, cc2np),
-    Tim8: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, u16, cc3nen, cc3np),
-    Tim8: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, u16),
+    Tim8: (C1, cc1en, cc1p, ccmod1, oc1pen, oc1m, ccr1, cc1ien, cc1itf, u16, cc1nen, cc1np),
+    Tim8: (C2, cc2en, cc2p, ccmod1, oc2pen, oc2m, ccr2, cc2ien, cc2itf, u16, cc2nen, cc2np),
+    Tim8: (C3, cc3en, cc3p, ccmod2, oc3pen, oc3m, ccr3, cc3ien, cc3itf, u16, cc3nen, cc3np),
+    Tim8: (C4, cc4en, cc4p, ccmod2, oc4pen, oc4m, ccr4, cc4ien, cc4itf, u16),
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+//! Hobby servos driven by a PWM channel
+//!
+//! RC servos expect a pulse every 20 ms (50 Hz) whose width, usually between 1 and 2 ms,
+//! sets the position. Counting microseconds makes the pulse width exact: [prescaler] gives
+//! a 1 MHz count from the timer clock and [PERIOD] a 20 ms frame at that count, which fits the
+//! 16-bit timers with 1 µs resolution.
+//!
+//! ```rust
+//! let clk = pac::Tim3::timer_clock(&clocks);
+//! let (_, (ch1, ch2)) = dp.Tim3
+//!     .pwm_advanced((gpioa.pa6, gpioa.pa7), &clocks)
+//!     .prescaler(servo::prescaler(clk)?)
+//!     .period(servo::PERIOD)
+//!     .finalize();
+//!
+//! let mut pan = Servo::new(ch1, servo::FRAME_RATE, Calibration::default())
+//!     .map_err(|(error, _)| error)?;
+//! pan.enable();
+//! pan.set_us(1500);
+//! pan.set_angle(45.0);
+//! ```
+
+use embedded_hal_02::PwmPin;
+
+use crate::time::Hertz;
+
+/// Frame rate of RC servos
+pub const FRAME_RATE: Hertz = Hertz::from_raw(50);
+
+/// Period of a 20 ms frame counting at 1 MHz, see [prescaler]
+pub const PERIOD: u16 = 19_999;
+
+/// Error of the servo setup
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+    /// The timer clock is below 1 MHz
+    ClockTooSlow,
+    /// The frame rate is zero or above 1 MHz, so a frame isn't a whole number of microseconds
+    InvalidFrameRate,
+}
+
+/// Prescaler counting at 1 MHz from the timer clock `clk`, or [ClockTooSlow](Error::ClockTooSlow)
+/// if `clk` is below 1 MHz
+pub fn prescaler(clk: Hertz) -> Result<u16, Error> {
+    let div = clk.raw() / 1_000_000;
+    if div == 0 {
+        return Err(Error::ClockTooSlow);
+    }
+    Ok((div - 1) as u16)
+}
+
+/// Pulse widths of a servo at both ends of its travel
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    /// Pulse width at `min_angle`, in microseconds
+    pub min_us: u16,
+    /// Pulse width at `max_angle`, in microseconds
+    pub max_us: u16,
+    /// Angle reached with `min_us`, in degrees
+    pub min_angle: f32,
+    /// Angle reached with `max_us`, in degrees
+    pub max_angle: f32,
+}
+
+impl Default for Calibration {
+    /// The common 1 to 2 ms range over 180°
+    fn default() -> Self {
+        Self {
+            min_us: 1000,
+            max_us: 2000,
+            min_angle: 0.0,
+            max_angle: 180.0,
+        }
+    }
+}
+
+/// Servo on the PWM channel `P`
+pub struct Servo<P> {
+    pwm: P,
+    /// Length of a PWM period in microseconds
+    frame_us: u32,
+    calibration: Calibration,
+}
+
+impl<P: PwmPin<Duty = u16>> Servo<P> {
+    /// Drives a servo from `pwm`, running at `frame_rate`
+    ///
+    /// `frame_rate` is the frequency the channel was set up with, [FRAME_RATE] with
+    /// [prescaler] and [PERIOD]. The output stays as it is until [enable](Self::enable).
+    ///
+    /// Returns [InvalidFrameRate](Error::InvalidFrameRate) along with `pwm` if `frame_rate` is
+    /// zero or above 1 MHz.
+    pub fn new(pwm: P, frame_rate: Hertz, calibration: Calibration) -> Result<Self, (Error, P)> {
+        let frame_us = match 1_000_000u32.checked_div(frame_rate.raw()) {
+            Some(frame_us) if frame_us != 0 => frame_us,
+            _ => return Err((Error::InvalidFrameRate, pwm)),
+        };
+        Ok(Self {
+            pwm,
+            frame_us,
+            calibration,
+        })
+    }
+
+    /// Starts the pulses, at the width set last
+    pub fn enable(&mut self) {
+        self.pwm.enable();
+    }
+
+    /// Stops the pulses, most servos then stop holding their position
+    pub fn disable(&mut self) {
+        self.pwm.disable();
+    }
+
+    /// Sets the pulse width in microseconds, clamped to the calibrated range
+    pub fn set_us(&mut self, us: u16) {
+        let us = us.clamp(self.calibration.min_us, self.calibration.max_us) as u32;
+        let duty = us * self.pwm.get_max_duty() as u32 / self.frame_us;
+        self.pwm.set_duty(duty as u16);
+    }
+
+    /// Pulse width in microseconds
+    pub fn us(&self) -> u16 {
+        let max = self.pwm.get_max_duty() as u32;
+        (self.pwm.get_duty() as u32 * self.frame_us / max) as u16
+    }
+
+    /// Moves to `angle` in degrees, clamped to the calibrated range
+    pub fn set_angle(&mut self, angle: f32) {
+        let Calibration { min_us, max_us, min_angle, max_angle } = self.calibration;
+        let t = ((angle - min_angle) / (max_angle - min_angle)).clamp(0.0, 1.0);
+        let us = min_us as f32 + t * (max_us as f32 - min_us as f32);
+        self.set_us((us + 0.5) as u16);
+    }
+
+    /// Angle in degrees matching the current pulse width
+    pub fn angle(&self) -> f32 {
+        let Calibration { min_us, max_us, min_angle, max_angle } = self.calibration;
+        let t = (self.us() as f32 - min_us as f32) / (max_us as f32 - min_us as f32);
+        min_angle + t * (max_angle - min_angle)
+    }
+
+    /// Calibration in use
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    /// Changes the calibration, the pulse width is left as it is until the next move
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Releases the PWM channel
+    pub fn release(self) -> P {
+        self.pwm
+    }
+}
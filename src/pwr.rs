@@ -1,5 +1,7 @@
 use crate::pac::{Pwr,Rcc};
 use crate::rcc::{Enable,Reset};
+#[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
+use crate::gpio::{gpioa::PA0, Input, PullDown};
 pub trait PwrExt {
     fn constrain(self) -> Pwr;
 }
@@ -11,4 +13,91 @@ impl PwrExt for Pwr {
         Pwr::reset(rcc);
         self
     }
-}
\ No newline at end of file
+}
+
+/// Wake-up from standby on a rising edge of the WKUP pin (PA0)
+///
+/// The pin has a fixed rising edge polarity and no filter on this family; while
+/// enabled the hardware forces PA0 to a pulled down input.
+///
+/// ```rust
+/// let mut pwr = dp.Pwr.constrain();
+/// let wakeup = WakeupPin::new(&mut pwr, gpioa.pa0);
+/// pwr::enter_standby(&mut pwr, &mut cp.SCB, wakeup);
+/// ```
+#[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
+pub struct WakeupPin {
+    pin: PA0<Input<PullDown>>,
+}
+
+#[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
+mod standby {
+    use super::WakeupPin;
+    use crate::gpio::gpioa::PA0;
+    use crate::gpio::{Input, PinMode, PullDown};
+    use crate::pac::Pwr;
+    use cortex_m::peripheral::SCB;
+
+    impl WakeupPin {
+        /// Enables the WKUP pin as standby wake-up source
+        pub fn new<MODE: PinMode>(pwr: &mut Pwr, pin: PA0<MODE>) -> Self {
+            let pin = pin.into_pull_down_input();
+            pwr.ctrlsts().modify(|_, w| w.wkupen().set_bit());
+            Self { pin }
+        }
+
+        /// Disables the wake-up function and returns the pin
+        pub fn release(self, pwr: &mut Pwr) -> PA0<Input<PullDown>> {
+            pwr.ctrlsts().modify(|_, w| w.wkupen().clear_bit());
+            self.pin
+        }
+    }
+
+    /// Enters standby mode, which only ends with a reset
+    ///
+    /// Taking a [WakeupPin] ensures a wake-up source is configured. The wake-up flag is
+    /// cleared first, as a set flag would wake the device immediately.
+    pub fn enter_standby(pwr: &mut Pwr, scb: &mut SCB, _wakeup: WakeupPin) -> ! {
+        pwr.ctrl().modify(|_, w| w.pds().set_bit().clrwkup().set_bit());
+        scb.set_sleepdeep();
+        cortex_m::asm::dsb();
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Enters stop mode and returns after the wake-up
+    ///
+    /// Any EXTI line configured as interrupt or event wakes the device up, the pending
+    /// interrupt is served before this returns. RAM and registers are retained, but HSE and
+    /// the PLL are off after the wake-up and the core runs on HSI: restore the clocks with
+    /// [Clocks::resume_after_stop](crate::rcc::Clocks::resume_after_stop).
+    ///
+    /// `low_power_regulator` lowers the consumption in stop mode at the cost of a longer
+    /// wake-up time.
+    pub fn enter_stop(pwr: &mut Pwr, scb: &mut SCB, low_power_regulator: bool) {
+        pwr.ctrl()
+            .modify(|_, w| w.pds().clear_bit().lps().bit(low_power_regulator).clrwkup().set_bit());
+        scb.set_sleepdeep();
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+        scb.clear_sleepdeep();
+    }
+
+    /// Returns true if the device was woken up from standby
+    pub fn woke_from_standby(pwr: &Pwr) -> bool {
+        pwr.ctrlsts().read().stbyf().bit_is_set()
+    }
+
+    /// Returns true if a wake-up event occurred
+    pub fn wakeup_flag(pwr: &Pwr) -> bool {
+        pwr.ctrlsts().read().wkupf().bit_is_set()
+    }
+
+    /// Clears the standby and wake-up flags
+    pub fn clear_flags(pwr: &mut Pwr) {
+        pwr.ctrl().modify(|_, w| w.clrwkup().set_bit().clrstby().set_bit());
+    }
+}
+#[cfg(any(feature="n32g451",feature="n32g452",feature="n32g455",feature="n32g457",feature="n32g4fr"))]
+pub use standby::{clear_flags, enter_standby, enter_stop, wakeup_flag, woke_from_standby};
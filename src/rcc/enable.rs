@@ -6,6 +6,8 @@ macro_rules! bus_enable {
         impl Enable for crate::pac::$PER {
             #[inline(always)]
             fn enable(rcc: &RccRB) {
+                // NOTE(unsafe) single store to the bit-band alias, atomic with respect to
+                // the other enable bits of the register
                 unsafe {
                     bb::set(Self::Bus::pclken(rcc), $bit);
                 }
@@ -132,6 +134,9 @@ bus! {
 
 }
 
+// Peripherals of the largest parts without a HAL driver yet: ETH, SDIO and QSPI are enabled
+// and reset here, and the ETH and SDIO pins are in `gpio::alt`, so they can be driven through
+// the PAC. The QSPI pins and DVP aren't covered yet.
 #[cfg(feature = "n32g457")]
 bus! {
     Eth => (AHB, 16),
@@ -42,6 +42,7 @@
 
 use crate::pac::rcc::cfg::{Ahbpres,Sclksw, Apb1pres};
 use crate::pac::{self, rcc, Rcc};
+use crate::fmc;
 
 use fugit::HertzU32 as Hertz;
 use fugit::RateExtU32;
@@ -51,6 +52,9 @@ use pll::MainPll;
 mod pll;
 
 mod enable;
+
+mod reset;
+pub use reset::ResetReason;
 use crate::pac::rcc::RegisterBlock as RccRB;
 
 /// Bus associated to peripheral
@@ -60,6 +64,11 @@ pub trait RccBus: crate::Sealed {
 }
 
 /// Enable/disable peripheral
+///
+/// The enable bits of all the peripherals on a bus share one register. Each bit is written
+/// alone through its bit-band alias, in a single store, so peripherals can be enabled and
+/// disabled from interrupts and from thread mode concurrently without dropping each
+/// other's bits. Only concurrent calls for the same peripheral race, on that peripheral.
 #[allow(clippy::missing_safety_doc)]
 pub trait Enable: RccBus {
     /// Enables peripheral
@@ -95,6 +104,8 @@ pub trait Enable: RccBus {
 }
 
 /// Low power enable/disable peripheral
+///
+/// Implementations write their bit atomically, as [Enable] does.
 #[allow(clippy::missing_safety_doc)]
 pub trait LPEnable: RccBus {
     /// Enables peripheral in low power mode
@@ -130,6 +141,9 @@ pub trait LPEnable: RccBus {
 }
 
 /// Reset peripheral
+///
+/// The reset bit is set and cleared through its bit-band alias, see [Enable] for the
+/// concurrency guarantees.
 #[allow(clippy::missing_safety_doc)]
 pub trait Reset: RccBus {
     /// Resets peripheral
@@ -270,6 +284,9 @@ impl RccExt for Rcc {
                 pclk1: None,
                 pclk2: None,
                 sysclk: None,
+                flash_latency: None,
+                flash_prefetch: true,
+                flash_icache: true,
             },
         }
     }
@@ -316,6 +333,9 @@ pub struct CFGR {
     pclk1: Option<u32>,
     pclk2: Option<u32>,
     sysclk: Option<u32>,
+    flash_latency: Option<u8>,
+    flash_prefetch: bool,
+    flash_icache: bool,
 }
 
 impl CFGR {
@@ -367,26 +387,40 @@ impl CFGR {
         PllSetup {
             use_pll: main_pll.use_pll,
             pllsysclk: main_pll.pllsysclk,
+            prediv: main_pll.prediv,
+            mul: main_pll.mul,
         }
     }
 
  
 
-    fn flash_setup(sysclk: u32) {
-        use crate::pac::Flash;
+    /// Sets the number of flash wait states instead of the minimum for the system clock
+    ///
+    /// `freeze` panics if they are too few, unless overclocking with `freeze_unchecked`.
+    pub fn flash_latency(mut self, wait_states: u8) -> Self {
+        self.flash_latency = Some(wait_states);
+        self
+    }
+
+    /// Enables or disables the flash prefetch buffer, enabled by default
+    pub fn flash_prefetch(mut self, enable: bool) -> Self {
+        self.flash_prefetch = enable;
+        self
+    }
 
+    /// Enables or disables the flash instruction cache, enabled by default
+    pub fn flash_icache(mut self, enable: bool) -> Self {
+        self.flash_icache = enable;
+        self
+    }
 
-        let flash_latency_step = 24_000_000;
+    fn flash_setup(&self, sysclk: u32, unchecked: bool) {
+        let required = fmc::required_latency(sysclk.Hz());
+        let latency = self.flash_latency.unwrap_or(required);
+        assert!(unchecked || latency >= required);
 
-        unsafe {
-            let flash = &(*Flash::ptr());
-            // Adjust flash wait states
-            flash.ac().modify(|_, w| {
-                w.latency().bits(((sysclk - 1) / flash_latency_step) as u8);
-                w.prftbfe().set_bit();
-                w.icahen().set_bit()
-            })
-        }
+        // Adjust flash wait states
+        fmc::Acr::configure(latency, self.flash_prefetch, self.flash_icache);
     }
 
     /// Initialises the hardware according to CFGR state returning a Clocks instance.
@@ -473,7 +507,7 @@ impl CFGR {
 
         assert!(unchecked || pclk2 <= PCLK2_MAX);
 
-        Self::flash_setup(sysclk);
+        self.flash_setup(sysclk, unchecked);
 
         if self.hse.is_some() {
             // enable HSE and wait for it to be ready
@@ -562,11 +596,42 @@ impl CFGR {
             })
         });
 
+        // USBPRES divides the PLL output by 1.5, 1, 2 or 3
+        let usbclk = plls.pllsysclk.filter(|_| plls.use_pll).map(|pllclk| {
+            let (num, den) = match usb_pres {
+                0x0 => (2, 3),
+                0x1 => (1, 1),
+                0x2 => (1, 2),
+                _ => (1, 3),
+            };
+            (pllclk * num / den).Hz()
+        });
+
         let clocks = Clocks {
             hclk: hclk.Hz(),
             pclk1: pclk1.Hz(),
             pclk2: pclk2.Hz(),
             sysclk: sysclk.Hz(),
+            sysclk_source: if sysclk_on_pll {
+                SysclkSource::Pll
+            } else if self.hse.is_some() {
+                SysclkSource::Hse
+            } else {
+                SysclkSource::Hsi
+            },
+            pll: plls.use_pll.then_some(PllConfig {
+                source: if self.hse.is_some() { PllSource::Hse } else { PllSource::Hsi },
+                prediv: plls.prediv,
+                mul: plls.mul,
+            }),
+            ahb_prescaler: hpre_div as u16,
+            apb1_prescaler: ppre1,
+            apb2_prescaler: ppre2,
+            // ADCHPRES = 0b0001 and ADCPLLPRES = 0b10001 both divide by 2
+            adc_hclk: (hclk / 2).Hz(),
+            adc_pllclk: plls.pllsysclk.filter(|_| plls.use_pll).map(|pllclk| (pllclk / 2).Hz()),
+            adc_1mclk: (if adc_1m_sel { pllsrcclk } else { HSI } / (adc_1m_pres + 1)).Hz(),
+            usbclk,
         };
 
         clocks
@@ -578,6 +643,8 @@ impl CFGR {
 struct PllSetup {
     use_pll: bool,
     pllsysclk: Option<u32>,
+    prediv: u8,
+    mul: u8,
 }
 
 /// Frozen clock frequencies
@@ -590,6 +657,41 @@ pub struct Clocks {
     pub pclk1: Hertz,
     pub pclk2: Hertz,
     pub sysclk: Hertz,
+    sysclk_source: SysclkSource,
+    pll: Option<PllConfig>,
+    ahb_prescaler: u16,
+    apb1_prescaler: u8,
+    apb2_prescaler: u8,
+    adc_hclk: Hertz,
+    adc_pllclk: Option<Hertz>,
+    adc_1mclk: Hertz,
+    usbclk: Option<Hertz>,
+}
+
+/// Source of the system clock
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SysclkSource {
+    Hsi,
+    Hse,
+    Pll,
+}
+
+/// Input of the main PLL
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PllSource {
+    Hsi,
+    Hse,
+}
+
+/// Main PLL configuration: `source / prediv * mul`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PllConfig {
+    pub source: PllSource,
+    pub prediv: u8,
+    pub mul: u8,
 }
 
 impl Clocks {
@@ -612,4 +714,108 @@ impl Clocks {
     pub fn sysclk(&self) -> Hertz {
         self.sysclk
     }
+
+    /// Returns the source of the system clock
+    pub fn sysclk_source(&self) -> SysclkSource {
+        self.sysclk_source
+    }
+
+    /// Returns the main PLL configuration, `None` if the PLL is off
+    pub fn pll(&self) -> Option<PllConfig> {
+        self.pll
+    }
+
+    /// Returns the AHB prescaler (SYSCLK / HCLK)
+    pub fn ahb_prescaler(&self) -> u16 {
+        self.ahb_prescaler
+    }
+
+    /// Returns the APB1 prescaler (HCLK / PCLK1)
+    pub fn apb1_prescaler(&self) -> u8 {
+        self.apb1_prescaler
+    }
+
+    /// Returns the APB2 prescaler (HCLK / PCLK2)
+    pub fn apb2_prescaler(&self) -> u8 {
+        self.apb2_prescaler
+    }
+
+    /// Returns the ADC clock derived from HCLK
+    pub fn adc_hclk(&self) -> Hertz {
+        self.adc_hclk
+    }
+
+    /// Returns the ADC clock derived from the PLL, `None` if the PLL is off
+    pub fn adc_pllclk(&self) -> Option<Hertz> {
+        self.adc_pllclk
+    }
+
+    /// Returns the 1 MHz ADC timing clock
+    pub fn adc_1mclk(&self) -> Hertz {
+        self.adc_1mclk
+    }
+
+    /// Returns the USB clock, `None` if the PLL is off. USB only works if this is 48 MHz.
+    pub fn usbclk(&self) -> Option<Hertz> {
+        self.usbclk
+    }
+
+    /// Restores the system clock after a wake-up from Stop mode
+    ///
+    /// Stop mode turns HSE and the PLL off and the core wakes up on HSI. The prescalers, the
+    /// PLL configuration, the HSE bypass and the flash wait states are retained, so this only
+    /// restarts the oscillators the frozen configuration uses and switches SYSCLK back. Call
+    /// it first thing after [enter_stop](crate::pwr::enter_stop) returns, as everything runs
+    /// at the HSI frequency until then.
+    pub fn resume_after_stop(&self) {
+        let rcc = unsafe { &*Rcc::ptr() };
+
+        let hse = self.sysclk_source == SysclkSource::Hse
+            || self.pll.map_or(false, |pll| pll.source == PllSource::Hse);
+        if hse {
+            rcc.ctrl().modify(|_, w| w.hseen().set_bit());
+            while rcc.ctrl().read().hserdf().bit_is_clear() {}
+        }
+
+        if self.pll.is_some() {
+            rcc.ctrl().modify(|_, w| w.pllen().set_bit());
+            while rcc.ctrl().read().pllrdf().bit_is_clear() {}
+        }
+
+        let sclksw = match self.sysclk_source {
+            SysclkSource::Hsi => Sclksw::Hsi,
+            SysclkSource::Hse => Sclksw::Hse,
+            SysclkSource::Pll => Sclksw::Pll,
+        };
+        rcc.cfg().modify(|_, w| w.sclksw().variant(sclksw));
+        // SCLKSTS (bits 3:2) follows SCLKSW once the switch is done
+        while (rcc.cfg().read().bits() >> 2) & 0b11 != u8::from(sclksw) as u32 {}
+    }
+
+    /// Prints the clock tree with `defmt`
+    #[cfg(feature = "defmt")]
+    pub fn dump(&self) {
+        defmt::info!(
+            "SYSCLK {} Hz from {}, PLL {}",
+            self.sysclk.raw(),
+            self.sysclk_source,
+            self.pll
+        );
+        defmt::info!(
+            "HCLK {} Hz (/{}), PCLK1 {} Hz (/{}), PCLK2 {} Hz (/{})",
+            self.hclk.raw(),
+            self.ahb_prescaler,
+            self.pclk1.raw(),
+            self.apb1_prescaler,
+            self.pclk2.raw(),
+            self.apb2_prescaler
+        );
+        defmt::info!(
+            "ADC HCLK {} Hz, ADC PLL {} Hz, ADC 1M {} Hz, USB {} Hz",
+            self.adc_hclk.raw(),
+            self.adc_pllclk.map(|f| f.raw()),
+            self.adc_1mclk.raw(),
+            self.usbclk.map(|f| f.raw())
+        );
+    }
 }
@@ -3,6 +3,8 @@ use crate::pac::Rcc;
 pub struct MainPll {
     pub use_pll: bool,
     pub pllsysclk: Option<u32>,
+    pub prediv: u8,
+    pub mul: u8,
 }
 
 impl MainPll {
@@ -14,7 +16,9 @@ impl MainPll {
         if pllsysclk.is_none() {
             return MainPll {
                 use_pll: false,
-                pllsysclk: None
+                pllsysclk: None,
+                prediv: 1,
+                mul: 1,
             }
         }
         let target_freq = pllsysclk.unwrap();
@@ -50,6 +54,8 @@ impl MainPll {
         MainPll {
             use_pll: true,
             pllsysclk: Some(real_pllsysclk),
+            prediv: pll_presc as u8,
+            mul: pll_mul as u8,
         }
     }
 
@@ -0,0 +1,65 @@
+use crate::pac::Rcc;
+
+/// Cause of the last reset, from the flags of `RCC_CTRLSTS`
+///
+/// ```rust
+/// let reason = rcc::ResetReason::read_and_clear();
+/// defmt::info!("reset by {}", reason);
+/// ```
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetReason {
+    /// Power-on or brown-out reset
+    PowerOn,
+    /// Low level on the NRST pin
+    PinReset,
+    /// Independent watchdog timeout
+    IWdg,
+    /// Window watchdog timeout
+    WWdg,
+    /// `SYSRESETREQ`, e.g. [SCB::sys_reset](cortex_m::peripheral::SCB::sys_reset)
+    Software,
+    /// Entering Standby or Stop mode while the option bytes forbid it
+    LowPower,
+    /// No flag set, they were cleared since the last reset
+    Unknown,
+}
+
+impl ResetReason {
+    /// Reads the reset flags without clearing them
+    ///
+    /// A reset sets several flags at once, e.g. a power-on reset also reports a pin reset as
+    /// the pin follows the internal reset, so the most specific cause is returned.
+    pub fn read() -> Self {
+        // NOTE(unsafe) atomic read with no side effects
+        let flags = unsafe { (*Rcc::ptr()).ctrlsts().read() };
+        if flags.lpwrrstf().bit_is_set() {
+            Self::LowPower
+        } else if flags.iwdgrstf().bit_is_set() {
+            Self::IWdg
+        } else if flags.wwdgrstf().bit_is_set() {
+            Self::WWdg
+        } else if flags.sftrstf().bit_is_set() {
+            Self::Software
+        } else if flags.porrstf().bit_is_set() {
+            Self::PowerOn
+        } else if flags.pinrstf().bit_is_set() {
+            Self::PinReset
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Reads the reset flags and clears them, so the next reset reports only its own cause
+    ///
+    /// Call it once early in `main`, the flags persist across resets until cleared.
+    pub fn read_and_clear() -> Self {
+        let reason = Self::read();
+        // NOTE(unsafe) RMRSTF clears all the reset flags, the other bits are kept. LSIEN
+        // shares the register, so no interrupt may modify it in between
+        cortex_m::interrupt::free(|_| unsafe {
+            (*Rcc::ptr()).ctrlsts().modify(|_, w| w.rmrstf().set_bit())
+        });
+        reason
+    }
+}
@@ -28,12 +28,15 @@ impl CryptoEngine {
 
     pub fn init_with_trng(&self) {
         let rcc = unsafe { &(*Rcc::ptr()) };
-        rcc.cfg3().modify(|_,w| w.trng1men().set_bit());
-        rcc.ahbpclken().modify(|_,w| w.sacen().clear_bit().rngcen().clear_bit());
-        rcc.ahbpclken().modify(|_,w| w.sacen().set_bit().rngcen().set_bit());
-        rcc.ahbprst().modify(|_,w| w.sacrst().set_bit().rngcrst().set_bit());
-        rcc.ahbprst().modify(|_,w| w.sacrst().clear_bit().rngcrst().clear_bit());
-
+        // These registers are shared with the other peripherals, keep an interrupt from
+        // changing them between the read and the write
+        cortex_m::interrupt::free(|_| {
+            rcc.cfg3().modify(|_,w| w.trng1men().set_bit());
+            rcc.ahbpclken().modify(|_,w| w.sacen().clear_bit().rngcen().clear_bit());
+            rcc.ahbpclken().modify(|_,w| w.sacen().set_bit().rngcen().set_bit());
+            rcc.ahbprst().modify(|_,w| w.sacrst().set_bit().rngcrst().set_bit());
+            rcc.ahbprst().modify(|_,w| w.sacrst().clear_bit().rngcrst().clear_bit());
+        });
     }
     pub fn reset(&self) {
         let rcc = unsafe { &(*Rcc::ptr()) };
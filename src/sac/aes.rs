@@ -1,7 +1,7 @@
 
 use super::CryptoEngine;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 
 pub enum AesMode {
@@ -0,0 +1,583 @@
+//! # SD cards on the SDIO interface
+//!
+//! [Sdio] initializes an SD or SDHC card on the 1 or 4-bit SDIO bus and reads and writes
+//! 512 byte blocks. The bus is 4-bit wide unless one of D1 to D3 is `NoPin`.
+//!
+//! The FIFO is served by the CPU, with the hardware flow control of the SDIO clock: the
+//! clock stops while the FIFO is empty or full, so the transfers don't under or overrun
+//! when an interrupt delays the copy, they just take longer.
+//!
+//! ```rust
+//! let gpioc = dp.Gpioc.split();
+//! let gpiod = dp.Gpiod.split();
+//! let mut sdio = Sdio::new(
+//!     dp.Sdio,
+//!     (gpioc.pc12, gpiod.pd2, gpioc.pc8, gpioc.pc9, gpioc.pc10, gpioc.pc11),
+//!     &clocks,
+//! );
+//! let card = sdio.init(24.MHz())?;
+//!
+//! let mut block = [0; 512];
+//! sdio.read_block(0, &mut block)?;
+//! ```
+//!
+//! With the `sdmmc` feature, [SdioBlockDevice] implements the `BlockDevice` of
+//! [embedded-sdmmc](https://crates.io/crates/embedded-sdmmc) for FAT filesystems:
+//!
+//! ```rust
+//! let mut volumes = embedded_sdmmc::VolumeManager::new(sdio.into_block_device(), time_source);
+//! ```
+
+use crate::gpio::alt::altmap::sdio::{Ck, Cmd, D0, D1, D2, D3};
+use crate::pac::sdio::sts;
+use crate::pac::Sdio as SDIO;
+use crate::rcc::{Clocks, Enable, Reset};
+use crate::time::Hertz;
+
+/// Size of a block
+pub const BLOCK_SIZE: usize = 512;
+
+/// Clock of the card during the identification
+const INIT_CLOCK: u32 = 400_000;
+/// Clock cycles the card needs after power up before the first command
+const INIT_CYCLES: u32 = 74;
+
+/// CMDRESP of a short response
+const RESP_SHORT: u8 = 0b01;
+/// CMDRESP of a long response
+const RESP_LONG: u8 = 0b11;
+/// BLKSIZE of 512 byte blocks
+const BLKSIZE_512: u8 = 9;
+
+// R1 card status
+const R1_ERRORS: u32 = 0xfdf9_8008;
+const R1_READY_FOR_DATA: u32 = 1 << 8;
+const R1_STATE_TRAN: u32 = 4;
+
+/// Attempts of ACMD41 before giving up on the card, about a second at 400 kHz
+const OP_COND_ATTEMPTS: u32 = 2_000;
+/// Status polls before giving up on a busy card after a write
+const BUSY_ATTEMPTS: u32 = 1_000_000;
+
+/// SDIO error
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The card didn't answer a command, or there is no card
+    CommandTimeout,
+    /// The CRC of a response is wrong
+    CommandCrc,
+    /// The card didn't send or accept data in time
+    DataTimeout,
+    /// The CRC of a data block is wrong
+    DataCrc,
+    /// The FIFO overran while receiving
+    RxOverrun,
+    /// The FIFO underran while transmitting
+    TxUnderrun,
+    /// No start bit on all the data lines
+    StartBit,
+    /// The card reported errors, with its R1 status
+    Card(u32),
+    /// The card never finished its power up, or stayed busy
+    NotReady,
+    /// The card isn't an SD card of a supported version
+    Unsupported,
+    /// The blocks are past the end of the card, or the buffer isn't a whole number of blocks
+    OutOfRange,
+    /// [Sdio::init] wasn't called, or failed
+    NoCard,
+    /// [Sdio::init] was asked for a card clock of zero
+    InvalidClock,
+}
+
+/// Capacity class of the card
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardType {
+    /// Standard capacity, up to 2 GB, addressed in bytes
+    Sdsc,
+    /// High or extended capacity, addressed in blocks
+    Sdhc,
+}
+
+/// Card found by [Sdio::init]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Card {
+    pub card_type: CardType,
+    /// Relative card address
+    pub rca: u16,
+    /// Number of 512 byte blocks
+    pub blocks: u32,
+    /// Clock of the card
+    pub clock: Hertz,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Response {
+    None,
+    Short,
+    /// Short response without a valid CRC (R3)
+    ShortNoCrc,
+    Long,
+}
+
+/// SD card on the SDIO interface
+pub struct Sdio {
+    sdio: SDIO,
+    pins: (Ck, Cmd, D0, D1, D2, D3),
+    wide: bool,
+    hclk: Hertz,
+    card: Option<Card>,
+}
+
+impl Sdio {
+    /// Enables and resets the SDIO, and powers the card bus up
+    ///
+    /// The bus is 4-bit wide if D1, D2 and D3 are pins, 1-bit if any of them is `NoPin`.
+    pub fn new(
+        sdio: SDIO,
+        pins: (
+            impl Into<Ck>,
+            impl Into<Cmd>,
+            impl Into<D0>,
+            impl Into<D1>,
+            impl Into<D2>,
+            impl Into<D3>,
+        ),
+        clocks: &Clocks,
+    ) -> Self {
+        unsafe {
+            SDIO::enable_unchecked();
+            SDIO::reset_unchecked();
+        }
+
+        let pins = (
+            pins.0.into(),
+            pins.1.into(),
+            pins.2.into(),
+            pins.3.into(),
+            pins.4.into(),
+            pins.5.into(),
+        );
+        let wide = !matches!(pins.3, D1::None(_))
+            && !matches!(pins.4, D2::None(_))
+            && !matches!(pins.5, D3::None(_));
+
+        // NOTE(unsafe) PWRCTRL = 0b11 powers the card clock and lines on
+        sdio.pwrctrl().write(|w| unsafe { w.pwrctrl().bits(0b11) });
+
+        Self {
+            sdio,
+            pins,
+            wide,
+            hclk: clocks.hclk,
+            card: None,
+        }
+    }
+
+    /// Identifies the card, selects it and switches to the widest bus and a clock of at most
+    /// `clock`
+    ///
+    /// Call it again after the card was swapped. SD cards run at up to 25 MHz in the default
+    /// speed mode. Returns [InvalidClock](Error::InvalidClock) without touching the bus if `clock`
+    /// is zero.
+    pub fn init(&mut self, clock: Hertz) -> Result<Card, Error> {
+        if clock.raw() == 0 {
+            return Err(Error::InvalidClock);
+        }
+        self.card = None;
+        let clock_in = self.set_clock(INIT_CLOCK, false);
+        // The card needs 74 clocks after power up, it ignores commands until then
+        cortex_m::asm::delay(INIT_CYCLES * (self.hclk.raw() / clock_in.raw()));
+
+        // GO_IDLE_STATE
+        self.command(0, 0, Response::None)?;
+
+        // SEND_IF_COND, 2.7 to 3.6 V with check pattern 0xaa. Version 1 cards don't answer
+        let v2 = match self.command(8, 0x1aa, Response::Short) {
+            Ok(r7) if r7 & 0xfff == 0x1aa => true,
+            Ok(_) => return Err(Error::Unsupported),
+            Err(Error::CommandTimeout) => false,
+            Err(e) => return Err(e),
+        };
+
+        // SD_SEND_OP_COND until the card is powered up, asking for high capacity on v2 cards
+        let hcs = if v2 { 1 << 30 } else { 0 };
+        let mut ocr = 0;
+        for _ in 0..OP_COND_ATTEMPTS {
+            self.command(55, 0, Response::Short)?;
+            ocr = self.command(41, 0x80ff_8000 | hcs, Response::ShortNoCrc)?;
+            if ocr & (1 << 31) != 0 {
+                break;
+            }
+        }
+        if ocr & (1 << 31) == 0 {
+            return Err(Error::NotReady);
+        }
+        let card_type = if ocr & (1 << 30) != 0 { CardType::Sdhc } else { CardType::Sdsc };
+
+        // ALL_SEND_CID, SEND_RELATIVE_ADDR
+        self.command(2, 0, Response::Long)?;
+        let rca = (self.command(3, 0, Response::Short)? >> 16) as u16;
+        let arg = (rca as u32) << 16;
+
+        // SEND_CSD
+        self.command(9, arg, Response::Long)?;
+        let blocks = self.csd_blocks()?;
+
+        // SELECT_CARD
+        self.command(7, arg, Response::Short)?;
+        self.wait_ready(arg)?;
+        if card_type == CardType::Sdsc {
+            // SET_BLOCKLEN
+            self.command_r1(16, BLOCK_SIZE as u32)?;
+        }
+        if self.wide {
+            // SET_BUS_WIDTH to 4 bits
+            self.command_r1(55, arg)?;
+            self.command_r1(6, 0b10)?;
+        }
+
+        let clock = self.set_clock(clock.raw().min(25_000_000), self.wide);
+        let card = Card {
+            card_type,
+            rca,
+            blocks,
+            clock,
+        };
+        self.card = Some(card);
+        Ok(card)
+    }
+
+    /// Card found by the last [init](Self::init)
+    pub fn card(&self) -> Option<Card> {
+        self.card
+    }
+
+    /// Reads block `block` into `buffer`
+    pub fn read_block(&mut self, block: u32, buffer: &mut [u8; BLOCK_SIZE]) -> Result<(), Error> {
+        self.read_blocks(block, buffer)
+    }
+
+    /// Reads consecutive blocks from `block` on into `buffer`, a whole number of blocks long
+    pub fn read_blocks(&mut self, block: u32, buffer: &mut [u8]) -> Result<(), Error> {
+        let (arg, count) = self.address(block, buffer.len())?;
+        if count == 0 {
+            return Ok(());
+        }
+        self.start_data(buffer.len() as u32, true);
+        // READ_SINGLE_BLOCK, READ_MULTIPLE_BLOCK
+        let cmd = if count == 1 { 17 } else { 18 };
+        if let Err(e) = self.command_r1(cmd, arg) {
+            self.stop_data();
+            return Err(e);
+        }
+
+        let mut chunks = buffer.chunks_exact_mut(4);
+        let result = loop {
+            let sts = self.sdio.sts().read();
+            if let Some(e) = data_error(&sts) {
+                break Err(e);
+            }
+            if sts.rfifohf().bit_is_set() {
+                // At least 8 words waiting
+                for word in chunks.by_ref().take(8) {
+                    word.copy_from_slice(&self.sdio.datfifo().read().bits().to_le_bytes());
+                }
+            } else if sts.rdatvalid().bit_is_set() {
+                if let Some(word) = chunks.next() {
+                    word.copy_from_slice(&self.sdio.datfifo().read().bits().to_le_bytes());
+                }
+            } else if sts.datend().bit_is_set() {
+                break Ok(());
+            }
+        };
+        self.finish_data(count, result)
+    }
+
+    /// Writes `buffer` to block `block`
+    pub fn write_block(&mut self, block: u32, buffer: &[u8; BLOCK_SIZE]) -> Result<(), Error> {
+        self.write_blocks(block, buffer)
+    }
+
+    /// Writes `buffer`, a whole number of blocks long, to consecutive blocks from `block` on
+    ///
+    /// Returns once the card finished programming.
+    pub fn write_blocks(&mut self, block: u32, buffer: &[u8]) -> Result<(), Error> {
+        let (arg, count) = self.address(block, buffer.len())?;
+        if count == 0 {
+            return Ok(());
+        }
+        // WRITE_BLOCK, WRITE_MULTIPLE_BLOCK, the data path starts after the response
+        let cmd = if count == 1 { 24 } else { 25 };
+        self.command_r1(cmd, arg)?;
+        self.start_data(buffer.len() as u32, false);
+
+        let mut chunks = buffer.chunks_exact(4);
+        let result = loop {
+            let sts = self.sdio.sts().read();
+            if let Some(e) = data_error(&sts) {
+                break Err(e);
+            }
+            if sts.tfifohe().bit_is_set() && chunks.len() != 0 {
+                // At least 8 words free
+                for word in chunks.by_ref().take(8) {
+                    let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+                    self.sdio.datfifo().write(|w| unsafe { w.bits(word) });
+                }
+            } else if sts.datend().bit_is_set() {
+                break Ok(());
+            }
+        };
+        self.finish_data(count, result)?;
+        let rca = self.card.map_or(0, |card| card.rca);
+        self.wait_ready((rca as u32) << 16)
+    }
+
+    /// Converts the SDIO into an `embedded_sdmmc::BlockDevice`
+    #[cfg(feature = "sdmmc")]
+    pub fn into_block_device(self) -> SdioBlockDevice {
+        SdioBlockDevice {
+            sdio: core::cell::RefCell::new(self),
+        }
+    }
+
+    /// Powers the card bus off and releases the peripheral and the pins
+    pub fn release(self) -> (SDIO, (Ck, Cmd, D0, D1, D2, D3)) {
+        self.sdio.clkctrl().reset();
+        self.sdio.pwrctrl().reset();
+        (self.sdio, self.pins)
+    }
+
+    /// Sets the card clock to at most `clock`, which isn't zero, returns the actual one
+    fn set_clock(&mut self, clock: u32, wide: bool) -> Hertz {
+        // SDIO_CK = HCLK / (CLKDIV + 2)
+        let div = ((self.hclk.raw() + clock - 1) / clock).clamp(2, 257) - 2;
+        self.sdio.clkctrl().write(|w| unsafe {
+            w.clkdiv().bits(div as u8);
+            w.clken().set_bit();
+            // Stop the clock while the FIFO is empty or full
+            w.hwclken().set_bit();
+            // 0b01 is the 4-bit bus
+            w.busmode().bits(wide as u8)
+        });
+        Hertz::from_raw(self.hclk.raw() / (div + 2))
+    }
+
+    /// Sends a command and returns the first word of the response
+    fn command(&mut self, index: u8, arg: u32, response: Response) -> Result<u32, Error> {
+        while self.sdio.sts().read().cmdrun().bit_is_set() {}
+        self.clear_flags();
+        self.sdio.cmdarg().write(|w| unsafe { w.bits(arg) });
+        let resp = match response {
+            Response::None => 0,
+            Response::Short | Response::ShortNoCrc => RESP_SHORT,
+            Response::Long => RESP_LONG,
+        };
+        self.sdio.cmdctrl().write(|w| unsafe {
+            w.cmdidx().bits(index);
+            w.cmdresp().bits(resp);
+            w.cpsmen().set_bit()
+        });
+
+        let sts = loop {
+            let sts = self.sdio.sts().read();
+            let done = if response == Response::None {
+                sts.cmdsend().bit_is_set()
+            } else {
+                sts.cmdresprecv().bit_is_set()
+            };
+            if done || sts.cmdtimeout().bit_is_set() || sts.ccrcerr().bit_is_set() {
+                break sts;
+            }
+        };
+        self.sdio.intclr().write(|w| {
+            w.cmdresprecv().set_bit();
+            w.cmdsend().set_bit();
+            w.cmdtimeout().set_bit();
+            w.ccrcerr().set_bit()
+        });
+        if sts.cmdtimeout().bit_is_set() {
+            return Err(Error::CommandTimeout);
+        }
+        if sts.ccrcerr().bit_is_set() && response != Response::ShortNoCrc {
+            return Err(Error::CommandCrc);
+        }
+        Ok(self.sdio.response1().read().bits())
+    }
+
+    /// Sends a command with an R1 response and checks the card status
+    fn command_r1(&mut self, index: u8, arg: u32) -> Result<u32, Error> {
+        let r1 = self.command(index, arg, Response::Short)?;
+        if r1 & R1_ERRORS != 0 {
+            return Err(Error::Card(r1));
+        }
+        Ok(r1)
+    }
+
+    /// Polls SEND_STATUS until the card is ready for data in the transfer state
+    fn wait_ready(&mut self, arg: u32) -> Result<(), Error> {
+        for _ in 0..BUSY_ATTEMPTS {
+            let r1 = self.command_r1(13, arg)?;
+            if r1 & R1_READY_FOR_DATA != 0 && (r1 >> 9) & 0xf == R1_STATE_TRAN {
+                return Ok(());
+            }
+        }
+        Err(Error::NotReady)
+    }
+
+    /// Number of blocks from the CSD in the response registers
+    fn csd_blocks(&self) -> Result<u32, Error> {
+        let csd0 = self.sdio.response1().read().bits();
+        let csd1 = self.sdio.response2().read().bits();
+        let csd2 = self.sdio.response3().read().bits();
+        match csd0 >> 30 {
+            // CSD version 1: (C_SIZE + 1) << (C_SIZE_MULT + 2) blocks of READ_BL_LEN
+            0 => {
+                let read_bl_len = (csd1 >> 16) & 0xf;
+                let c_size = ((csd1 & 0x3ff) << 2) | (csd2 >> 30);
+                let c_size_mult = (csd2 >> 15) & 0x7;
+                // READ_BL_LEN is 9 to 11, blocks below 512 bytes aren't valid
+                let shift = (c_size_mult + 2 + read_bl_len)
+                    .checked_sub(9)
+                    .ok_or(Error::Unsupported)?;
+                Ok((c_size + 1) << shift)
+            }
+            // CSD version 2: (C_SIZE + 1) * 512 KiB
+            1 => {
+                let c_size = ((csd1 & 0x3f) << 16) | (csd2 >> 16);
+                Ok((c_size + 1) * 1024)
+            }
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Command argument and number of blocks of a transfer
+    fn address(&self, block: u32, len: usize) -> Result<(u32, u32), Error> {
+        let card = self.card.ok_or(Error::NoCard)?;
+        if len % BLOCK_SIZE != 0 {
+            return Err(Error::OutOfRange);
+        }
+        let count = (len / BLOCK_SIZE) as u32;
+        if block.checked_add(count).map_or(true, |end| end > card.blocks) {
+            return Err(Error::OutOfRange);
+        }
+        let arg = match card.card_type {
+            CardType::Sdhc => block,
+            CardType::Sdsc => block * BLOCK_SIZE as u32,
+        };
+        Ok((arg, count))
+    }
+
+    fn start_data(&mut self, len: u32, read: bool) {
+        // Data timeout of about 250 ms, in card clock periods
+        let clock = self.card.map_or(INIT_CLOCK, |card| card.clock.raw());
+        self.sdio.dtimer().write(|w| unsafe { w.bits(clock / 4) });
+        self.sdio.datlen().write(|w| unsafe { w.bits(len) });
+        self.clear_flags();
+        self.sdio.datctrl().write(|w| unsafe {
+            w.daten().set_bit();
+            w.datdir().bit(read);
+            w.blksize().bits(BLKSIZE_512)
+        });
+    }
+
+    fn stop_data(&mut self) {
+        self.sdio.datctrl().reset();
+        self.clear_flags();
+    }
+
+    /// Clears the static flags of STS
+    fn clear_flags(&mut self) {
+        self.sdio.intclr().write(|w| {
+            w.ccrcerr().set_bit();
+            w.dcrcerr().set_bit();
+            w.cmdtimeout().set_bit();
+            w.dattimeout().set_bit();
+            w.txurerr().set_bit();
+            w.rxorerr().set_bit();
+            w.cmdresprecv().set_bit();
+            w.cmdsend().set_bit();
+            w.datend().set_bit();
+            w.sberr().set_bit();
+            w.datblkend().set_bit()
+        });
+    }
+
+    /// Stops the data path, and a multiple block transfer with STOP_TRANSMISSION
+    fn finish_data(&mut self, count: u32, result: Result<(), Error>) -> Result<(), Error> {
+        self.stop_data();
+        if count > 1 {
+            let stop = self.command_r1(12, 0);
+            result?;
+            stop?;
+        }
+        result
+    }
+}
+
+fn data_error(sts: &sts::R) -> Option<Error> {
+    if sts.dcrcerr().bit_is_set() {
+        Some(Error::DataCrc)
+    } else if sts.dattimeout().bit_is_set() {
+        Some(Error::DataTimeout)
+    } else if sts.txurerr().bit_is_set() {
+        Some(Error::TxUnderrun)
+    } else if sts.rxorerr().bit_is_set() {
+        Some(Error::RxOverrun)
+    } else if sts.sberr().bit_is_set() {
+        Some(Error::StartBit)
+    } else {
+        None
+    }
+}
+
+/// [Sdio] as an `embedded_sdmmc::BlockDevice`, see [Sdio::into_block_device]
+#[cfg(feature = "sdmmc")]
+pub struct SdioBlockDevice {
+    sdio: core::cell::RefCell<Sdio>,
+}
+
+#[cfg(feature = "sdmmc")]
+impl SdioBlockDevice {
+    /// Releases the [Sdio]
+    pub fn release(self) -> Sdio {
+        self.sdio.into_inner()
+    }
+}
+
+#[cfg(feature = "sdmmc")]
+impl embedded_sdmmc::BlockDevice for SdioBlockDevice {
+    type Error = Error;
+
+    fn read(
+        &self,
+        blocks: &mut [embedded_sdmmc::Block],
+        start_block_idx: embedded_sdmmc::BlockIdx,
+    ) -> Result<(), Self::Error> {
+        let mut sdio = self.sdio.borrow_mut();
+        for (i, block) in blocks.iter_mut().enumerate() {
+            sdio.read_block(start_block_idx.0 + i as u32, &mut block.contents)?;
+        }
+        Ok(())
+    }
+
+    fn write(
+        &self,
+        blocks: &[embedded_sdmmc::Block],
+        start_block_idx: embedded_sdmmc::BlockIdx,
+    ) -> Result<(), Self::Error> {
+        let mut sdio = self.sdio.borrow_mut();
+        for (i, block) in blocks.iter().enumerate() {
+            sdio.write_block(start_block_idx.0 + i as u32, &block.contents)?;
+        }
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> Result<embedded_sdmmc::BlockCount, Self::Error> {
+        let card = self.sdio.borrow().card().ok_or(Error::NoCard)?;
+        Ok(embedded_sdmmc::BlockCount(card.blocks))
+    }
+}
@@ -0,0 +1,150 @@
+//! Forwarding between two serial ports with DMA
+//!
+//! [Bridge] receives from one serial port with DMA into a ring buffer and transmits the
+//! received bytes from that same buffer to another serial port with a second DMA channel,
+//! without copying them.
+//!
+//! Reception only ever writes into the free part of the ring: when the transmitter is slower
+//! than the receiver and the ring fills up, reception pauses until bytes were sent, so no
+//! received byte is overwritten. Bytes arriving during the pause overrun the receiver,
+//! unless the sender is held off with hardware flow control.
+//!
+//! ```rust
+//! static mut RING: [u8; 256] = [0; 256];
+//!
+//! let dma = dp.Dma1.split();
+//! let rx = dp.Usart1.rx(gpioa.pa10, 115_200.bps(), &clocks, &mut afio)?.with_dma(dma.5);
+//! let tx = dp.Usart2.tx(gpioa.pa2, 115_200.bps(), &clocks, &mut afio)?.with_dma(dma.7);
+//! let mut bridge = serial::bridge(rx, tx, unsafe { &mut *core::ptr::addr_of_mut!(RING) });
+//!
+//! loop {
+//!     bridge.poll()?;
+//! }
+//! ```
+
+use core::sync::atomic::{self, Ordering};
+
+use enumflags2::BitFlags;
+
+use super::{Instance, RegisterBlockImpl, Rx, Tx};
+use crate::dma::{start_byte_transfer, DMAChannel, Error, Flag, RxDma, TxDma, MAX_TRANSFER_LENGTH};
+
+/// Bytes received on `RX` and forwarded to `TX` through a ring buffer of `N` bytes
+pub struct Bridge<RX: Instance, TX: Instance, RXCH, TXCH, const N: usize> {
+    rx: RxDma<Rx<RX>, RXCH>,
+    tx: TxDma<Tx<TX>, TXCH>,
+    buffer: &'static mut [u8; N],
+    /// Position of the oldest byte not sent yet
+    head: usize,
+    /// Number of bytes received and not sent yet, including the ones being sent
+    pending: usize,
+    /// Length of the running reception, 0 if none
+    rx_len: usize,
+    /// Bytes of the running reception already added to `pending`
+    rx_seen: usize,
+    /// Length of the running transmission starting at `head`, 0 if none
+    tx_len: usize,
+}
+
+/// Forwards everything received by `rx` to `tx` using `buffer` as ring buffer
+///
+/// Nothing happens until [Bridge::poll] is called.
+pub fn bridge<RX, TX, RXCH, TXCH, const N: usize>(
+    rx: RxDma<Rx<RX>, RXCH>,
+    tx: TxDma<Tx<TX>, TXCH>,
+    buffer: &'static mut [u8; N],
+) -> Bridge<RX, TX, RXCH, TXCH, N>
+where
+    RX: Instance,
+    TX: Instance,
+    RXCH: DMAChannel,
+    TXCH: DMAChannel,
+{
+    Bridge {
+        rx,
+        tx,
+        buffer,
+        head: 0,
+        pending: 0,
+        rx_len: 0,
+        rx_seen: 0,
+        tx_len: 0,
+    }
+}
+
+impl<RX, TX, RXCH, TXCH, const N: usize> Bridge<RX, TX, RXCH, TXCH, N>
+where
+    RX: Instance,
+    TX: Instance,
+    RXCH: DMAChannel,
+    TXCH: DMAChannel,
+{
+    /// Hands received bytes to the transmitter and restarts finished transfers
+    ///
+    /// Has to be called often enough for the ring not to fill up, e.g. from the idle line
+    /// interrupt of the receiver and the transfer complete interrupts of both channels.
+    /// Returns [TransferError](Error::TransferError) if either channel reported a transfer error.
+    pub fn poll(&mut self) -> Result<(), Error> {
+        if (self.rx.channel.flags() | self.tx.channel.flags()).contains(Flag::TransferError) {
+            return Err(Error::TransferError);
+        }
+
+        if self.rx_len != 0 {
+            let received = self.rx_len - self.rx.channel.get_txnum() as usize;
+            atomic::compiler_fence(Ordering::Acquire);
+            self.pending += received - self.rx_seen;
+            self.rx_seen = received;
+            if received == self.rx_len {
+                self.rx.channel.stop();
+                self.rx.channel.clear_flags(BitFlags::ALL);
+                self.rx_len = 0;
+            }
+        }
+
+        if self.tx_len != 0 && self.tx.channel.get_txnum() == 0 {
+            self.tx.channel.stop();
+            self.tx.channel.clear_flags(BitFlags::ALL);
+            self.head = (self.head + self.tx_len) % N;
+            self.pending -= self.tx_len;
+            self.tx_len = 0;
+        }
+
+        if self.tx_len == 0 && self.pending != 0 {
+            self.tx_len = self.pending.min(N - self.head).min(MAX_TRANSFER_LENGTH);
+            let address = self.buffer.as_ptr() as u32 + self.head as u32;
+            let peripheral = unsafe { (*TX::ptr()).peri_address() };
+            atomic::compiler_fence(Ordering::Release);
+            start_byte_transfer(&mut self.tx.channel, peripheral, address, self.tx_len, true, false)?;
+        }
+
+        if self.rx_len == 0 && self.pending != N {
+            let tail = (self.head + self.pending) % N;
+            // Up to the end of the ring or the oldest byte not sent yet, in transfers the DMA can count
+            let free = if tail >= self.head { N - tail } else { self.head - tail };
+            self.rx_len = free.min(MAX_TRANSFER_LENGTH);
+            self.rx_seen = 0;
+            let address = self.buffer.as_mut_ptr() as u32 + tail as u32;
+            let peripheral = unsafe { (*RX::ptr()).peri_address() };
+            start_byte_transfer(&mut self.rx.channel, peripheral, address, self.rx_len, false, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Number of bytes received and not sent yet
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Stops both transfers and releases the serial ports and the buffer
+    ///
+    /// Bytes that weren't sent yet are dropped.
+    pub fn release(mut self) -> (RxDma<Rx<RX>, RXCH>, TxDma<Tx<TX>, TXCH>, &'static mut [u8; N]) {
+        self.rx.channel.stop();
+        self.tx.channel.stop();
+        self.rx.channel.clear_flags(BitFlags::ALL);
+        self.tx.channel.clear_flags(BitFlags::ALL);
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.rx, self.tx, self.buffer)
+    }
+}
@@ -1,10 +1,14 @@
 use crate::time::Bps;
 use crate::time::U32Ext;
 
+/// Number of data bits, not counting the parity bit
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WordLength {
+    /// 7 data bits, only valid with parity. The received words contain the parity bit as MSB.
+    DataBits7,
     DataBits8,
+    /// 9 data bits, only valid without parity
     DataBits9,
 }
 
@@ -49,6 +53,10 @@ pub enum DmaConfig {
     TxRx,
 }
 
+/// Serial configuration
+///
+/// The frame holds at most 9 bits including the parity bit. The USARTs of this family
+/// can't invert or swap the RX/TX pins.
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Config {
@@ -65,6 +73,11 @@ impl Config {
         self
     }
 
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
     pub fn parity_none(mut self) -> Self {
         self.parity = Parity::ParityNone;
         self
@@ -80,6 +93,16 @@ impl Config {
         self
     }
 
+    pub fn wordlength(mut self, wordlength: WordLength) -> Self {
+        self.wordlength = wordlength;
+        self
+    }
+
+    pub fn wordlength_7(mut self) -> Self {
+        self.wordlength = WordLength::DataBits7;
+        self
+    }
+
     pub fn wordlength_8(mut self) -> Self {
         self.wordlength = WordLength::DataBits8;
         self
@@ -101,9 +124,41 @@ impl Config {
     }
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug)]
 pub struct InvalidConfig;
 
+/// Error of [Serial::remap](super::Serial::remap)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug)]
+pub enum RemapError {
+    /// A current pin isn't of the type requested for it
+    PinMismatch,
+    /// The configuration is invalid for the new pins
+    InvalidConfig,
+}
+
+impl From<InvalidConfig> for RemapError {
+    fn from(_: InvalidConfig) -> Self {
+        RemapError::InvalidConfig
+    }
+}
+
+impl Config {
+    /// Returns the hardware frame length (`true` for 9 bits) or an error if the
+    /// word length and parity don't fit in a frame
+    pub(crate) fn frame_9bits(&self) -> Result<bool, InvalidConfig> {
+        match (self.wordlength, self.parity) {
+            (WordLength::DataBits7, Parity::ParityNone) => Err(InvalidConfig),
+            (WordLength::DataBits7, _) => Ok(false),
+            (WordLength::DataBits8, Parity::ParityNone) => Ok(false),
+            (WordLength::DataBits8, _) => Ok(true),
+            (WordLength::DataBits9, Parity::ParityNone) => Ok(true),
+            (WordLength::DataBits9, _) => Err(InvalidConfig),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Config {
         let baudrate = 115_200_u32.bps();
@@ -10,6 +10,7 @@ mod nb {
     {
         type Error = Error;
 
+        #[inline(always)]
         fn read(&mut self) -> nb::Result<WORD, Self::Error> {
             self.rx.read()
         }
@@ -18,6 +19,7 @@ mod nb {
     impl<USART: Instance> Read<u8> for Rx<USART, u8> {
         type Error = Error;
 
+        #[inline(always)]
         fn read(&mut self) -> nb::Result<u8, Self::Error> {
             unsafe { (*USART::ptr()).read_u8() }
         }
@@ -31,6 +33,7 @@ mod nb {
     impl<USART: Instance> Read<u16> for Rx<USART, u16> {
         type Error = Error;
 
+        #[inline(always)]
         fn read(&mut self) -> nb::Result<u16, Self::Error> {
             unsafe { (*USART::ptr()).read_u16() }
         }
@@ -42,10 +45,12 @@ mod nb {
     {
         type Error = Error;
 
+        #[inline(always)]
         fn flush(&mut self) -> nb::Result<(), Self::Error> {
             self.tx.flush()
         }
 
+        #[inline(always)]
         fn write(&mut self, byte: WORD) -> nb::Result<(), Self::Error> {
             self.tx.write(byte)
         }
@@ -57,9 +62,11 @@ mod nb {
     {
         type Error = Error;
 
+        #[inline(always)]
         fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
             self.usart.write_u8(word)
         }
+        #[inline(always)]
         fn flush(&mut self) -> nb::Result<(), Self::Error> {
             self.usart.flush()
         }
@@ -76,10 +83,12 @@ mod nb {
     {
         type Error = Error;
 
+        #[inline(always)]
         fn write(&mut self, word: u16) -> nb::Result<(), Self::Error> {
             self.usart.write_u16(word)
         }
 
+        #[inline(always)]
         fn flush(&mut self) -> nb::Result<(), Self::Error> {
             self.usart.flush()
         }
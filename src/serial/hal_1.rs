@@ -30,12 +30,14 @@ mod nb {
     where
         Rx<USART, WORD>: Read<WORD, Error = Error>,
     {
+        #[inline(always)]
         fn read(&mut self) -> nb::Result<WORD, Self::Error> {
             self.rx.read()
         }
     }
 
     impl<USART: Instance> Read<u8> for Rx<USART, u8> {
+        #[inline(always)]
         fn read(&mut self) -> nb::Result<u8, Self::Error> {
             unsafe { (*USART::ptr()).read_u8() }
         }
@@ -47,6 +49,7 @@ mod nb {
     /// 9 received data bits and all other bits set to zero. Otherwise, the returned value will contain
     /// 8 received data bits and all other bits set to zero.
     impl<USART: Instance> Read<u16> for Rx<USART, u16> {
+        #[inline(always)]
         fn read(&mut self) -> nb::Result<u16, Self::Error> {
             unsafe { (*USART::ptr()).read_u16() }
         }
@@ -56,10 +59,12 @@ mod nb {
     where
         Tx<USART, WORD>: Write<WORD, Error = Error>,
     {
+        #[inline(always)]
         fn flush(&mut self) -> nb::Result<(), Self::Error> {
             self.tx.flush()
         }
 
+        #[inline(always)]
         fn write(&mut self, byte: WORD) -> nb::Result<(), Self::Error> {
             self.tx.write(byte)
         }
@@ -69,9 +74,11 @@ mod nb {
     where
         USART: Deref<Target = <USART as Instance>::RegisterBlock>,
     {
+        #[inline(always)]
         fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
             self.usart.write_u8(word)
         }
+        #[inline(always)]
         fn flush(&mut self) -> nb::Result<(), Self::Error> {
             self.usart.flush()
         }
@@ -86,10 +93,12 @@ mod nb {
     where
         USART: Deref<Target = <USART as Instance>::RegisterBlock>,
     {
+        #[inline(always)]
         fn write(&mut self, word: u16) -> nb::Result<(), Self::Error> {
             self.usart.write_u16(word)
         }
 
+        #[inline(always)]
         fn flush(&mut self) -> nb::Result<(), Self::Error> {
             self.usart.flush()
         }
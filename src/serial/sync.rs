@@ -0,0 +1,196 @@
+use embedded_hal::spi::{ErrorKind, ErrorType, SpiBus};
+
+use super::uart_impls::RegisterBlockImpl;
+use super::{config, Error, Instance, Serial};
+use crate::gpio::alt::altmap::{Remap, RemapIO};
+use crate::gpio::alt::{SerialAsync as CommonPins, SerialSync};
+use crate::gpio::{Floating, PushPull};
+use crate::rcc::Clocks;
+use crate::spi::{BitFormat, Mode, Phase, Polarity};
+
+/// Clock settings of a [Synchronous] USART
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncConfig {
+    /// Clock polarity and phase
+    pub mode: Mode,
+    /// Output a clock pulse for the last data bit too
+    ///
+    /// Most SPI devices need it, without it the last bit of each frame is never clocked.
+    pub last_bit_clock: bool,
+    /// Bit order of the frames, the USART itself only shifts the least significant bit first
+    pub bit_format: BitFormat,
+}
+
+impl From<Mode> for SyncConfig {
+    fn from(mode: Mode) -> Self {
+        Self {
+            mode,
+            last_bit_clock: true,
+            bit_format: BitFormat::MsbFirst,
+        }
+    }
+}
+
+/// USART in synchronous master mode, clocking its frames on the CK pin like an SPI master
+///
+/// Every frame sent clocks one frame in, so it implements [SpiBus] for SPI-ish devices
+/// when the SPI instances are exhausted. The chip select has to be driven separately, and
+/// the CK frequency is the baud rate of the [Config](config::Config).
+///
+/// ```rust
+/// let mut bus = Serial::synchronous(
+///     dp.Usart1,
+///     (gpioa.pa9, gpioa.pa10, gpioa.pa8),
+///     Config::default().baudrate(1_000_000.bps()),
+///     SyncConfig::from(Mode::from(embedded_hal::spi::MODE_0)),
+///     &clocks,
+///     &mut afio,
+/// )?;
+/// bus.transfer_in_place(&mut frame)?;
+/// ```
+pub struct Synchronous<USART: Instance + SerialSync> {
+    serial: Serial<USART, u8>,
+    ck: USART::Ck,
+    bit_format: BitFormat,
+}
+
+impl<USART> Serial<USART, u8>
+where
+    USART: Instance<RegisterBlock = crate::pac::usart1::RegisterBlock> + SerialSync,
+{
+    /// Configures the USART as synchronous master with the clock on `ck`
+    pub fn synchronous<RMP, TX, RX>(
+        usart: USART,
+        pins: (TX, RX, impl Into<USART::Ck>),
+        config: impl Into<config::Config>,
+        sync: impl Into<SyncConfig>,
+        clocks: &Clocks,
+        afio: &mut crate::afio::Parts,
+    ) -> Result<Synchronous<USART>, config::InvalidConfig>
+    where
+        RMP: Remap,
+        TX: RemapIO<USART, RMP> + Into<<USART as CommonPins>::Tx<PushPull>>,
+        RX: RemapIO<USART, RMP> + Into<<USART as CommonPins>::Rx<Floating>>,
+    {
+        let sync = sync.into();
+        RMP::remap(afio);
+        let serial = Serial::new(usart, (pins.0, pins.1), config, clocks, afio)?;
+        let usart = unsafe { &*USART::ptr() };
+        // The clock settings can only be changed with the USART disabled
+        usart.ctrl1().modify(|_, w| w.uen().clear_bit());
+        usart.ctrl2().modify(|_, w| { w
+            .clken() .set_bit()
+            .lbclk() .bit(sync.last_bit_clock)
+            .clkpha().bit(sync.mode.phase == Phase::CaptureOnSecondTransition)
+            .clkpol().bit(sync.mode.polarity == Polarity::IdleHigh)
+        });
+        usart.ctrl1().modify(|_, w| w.uen().set_bit());
+        Ok(Synchronous {
+            serial,
+            ck: pins.2.into(),
+            bit_format: sync.bit_format,
+        })
+    }
+}
+
+impl<USART> Synchronous<USART>
+where
+    USART: Instance<RegisterBlock = crate::pac::usart1::RegisterBlock> + SerialSync,
+{
+    fn order(&self, word: u8) -> u8 {
+        match self.bit_format {
+            BitFormat::LsbFirst => word,
+            BitFormat::MsbFirst => word.reverse_bits(),
+        }
+    }
+
+    fn exchange(&mut self, word: u8) -> Result<u8, Error> {
+        let usart = unsafe { &*USART::ptr() };
+        nb::block!(usart.write_u8(self.order(word)))?;
+        let word = nb::block!(usart.read_u8())?;
+        Ok(self.order(word))
+    }
+
+    /// Drops a frame left over in the receiver, and its error flags
+    fn drain(&mut self) {
+        let usart = unsafe { &*USART::ptr() };
+        let _ = usart.read_u8();
+    }
+
+    /// Disables the clock output and releases the USART and its pins
+    #[allow(clippy::type_complexity)]
+    pub fn release(
+        self,
+    ) -> (
+        USART,
+        (<USART as CommonPins>::Tx<PushPull>, <USART as CommonPins>::Rx<Floating>, USART::Ck),
+    ) {
+        let usart = unsafe { &*USART::ptr() };
+        usart.ctrl1().modify(|_, w| w.uen().clear_bit());
+        usart.ctrl2().modify(|_, w| w.clken().clear_bit());
+        let (usart, (tx, rx)) = self.serial.release();
+        (usart, (tx, rx, self.ck))
+    }
+}
+
+impl embedded_hal::spi::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl<USART> ErrorType for Synchronous<USART>
+where
+    USART: Instance<RegisterBlock = crate::pac::usart1::RegisterBlock> + SerialSync,
+{
+    type Error = Error;
+}
+
+impl<USART> SpiBus<u8> for Synchronous<USART>
+where
+    USART: Instance<RegisterBlock = crate::pac::usart1::RegisterBlock> + SerialSync,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.drain();
+        for word in words {
+            *word = self.exchange(0)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.drain();
+        for word in words {
+            self.exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.drain();
+        for i in 0..read.len().max(write.len()) {
+            let word = self.exchange(write.get(i).copied().unwrap_or(0))?;
+            if let Some(r) = read.get_mut(i) {
+                *r = word;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.drain();
+        for word in words {
+            *word = self.exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let usart = unsafe { &*USART::ptr() };
+        nb::block!(usart.flush())
+    }
+}
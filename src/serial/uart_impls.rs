@@ -4,12 +4,17 @@ use enumflags2::BitFlags;
 use nb::block;
 
 use super::{
-    config, CFlag, Error, Event, Flag, Rx, RxISR, RxListen, Serial, SerialExt, Tx, TxISR, TxListen,
+    config, CFlag, Error, Event, Flag, Rx, RxISR, RxListen, RxTimeout, Serial, SerialExt, Tx, TxISR,
+    TxListen,
 };
 use crate::gpio::Floating;
 use crate::gpio::{alt::altmap::Remap, Input};
 use crate::gpio::{alt::SerialAsync as CommonPins, NoPin, PushPull};
 use crate::rcc::{self, Clocks};
+use crate::time::{Bps, MicroSecond};
+use crate::timer::{CountDownTimer, Timer};
+use crate::delay::CountDown;
+use embedded_hal_02::timer::{Cancel, CountDown as _};
 
 pub(crate) use crate::pac::uart4::RegisterBlock as RegisterBlockUart;
 pub(crate) use crate::pac::usart1::RegisterBlock as RegisterBlockUsart;
@@ -39,11 +44,13 @@ pub trait RegisterBlockImpl: crate::Sealed {
     fn read_u16(&self) -> nb::Result<u16, Error>;
     fn write_u16(&self, word: u16) -> nb::Result<(), Error>;
 
+    #[inline(always)]
     fn read_u8(&self) -> nb::Result<u8, Error> {
         // Delegate to u16 version, then truncate to 8 bits
         self.read_u16().map(|word16| word16 as u8)
     }
 
+    #[inline(always)]
     fn write_u8(&self, word: u8) -> nb::Result<(), Error> {
         // Delegate to u16 version
         self.write_u16(u16::from(word))
@@ -72,12 +79,15 @@ pub trait RegisterBlockImpl: crate::Sealed {
     // ISR
     fn flags(&self) -> BitFlags<Flag>;
 
+    #[inline(always)]
     fn is_idle(&self) -> bool {
         self.flags().contains(Flag::Idle)
     }
+    #[inline(always)]
     fn is_rx_not_empty(&self) -> bool {
         self.flags().contains(Flag::RxNotEmpty)
     }
+    #[inline(always)]
     fn is_tx_empty(&self) -> bool {
         self.flags().contains(Flag::TxEmpty)
     }
@@ -112,10 +122,20 @@ pub trait RegisterBlockImpl: crate::Sealed {
         self.listen_event(Some(Event::TxEmpty.into()), None)
     }
 
+    // Break
+    fn send_break(&self);
+    fn set_break_detection(&self, enable: bool, long: bool);
+    fn set_break_interrupt(&self, enable: bool);
+
     // PeriAddress
     fn peri_address(&self) -> u32;
 }
 
+/// PEF, FEF, NEF and OREF of STS
+const ERROR_FLAGS: u32 = 0b1111;
+/// RXDNE of STS
+const RXDNE: u32 = 1 << 5;
+
 macro_rules! uartCommon {
     ($RegisterBlock:ty) => {
         impl RegisterBlockImpl for $RegisterBlock {
@@ -129,6 +149,7 @@ macro_rules! uartCommon {
                 use self::config::*;
 
                 let config = config.into();
+                let frame_9bits = config.frame_9bits()?;
                 unsafe {
                     // Enable clock.
                     UART::enable_unchecked();
@@ -138,7 +159,7 @@ macro_rules! uartCommon {
                 let pclk_freq = UART::clock(clocks).raw();
                 let baud = config.baudrate.0;
 
-                let div = if (pclk_freq / 16) >= baud {
+                let div = if baud != 0 && (pclk_freq / 16) >= baud {
 
                     let integerdivider = ((25 * pclk_freq) / (4 * (baud)));
                     let mut tmpregister = (integerdivider / 100) << 4;
@@ -165,12 +186,12 @@ macro_rules! uartCommon {
                 // and configure frame
 
                 let serial = Serial {
-                    tx: Tx::new(uart, pins.0.into()),
+                    tx: Tx::new(uart, pins.0.into(), UART::clock(clocks)),
                     rx: Rx::new(pins.1.into()),
                 };
                 serial.tx.usart.set_stopbits(config.stopbits);
                 register_block.ctrl1().modify(|_,w| {
-                    w.wl().bit(config.wordlength == WordLength::DataBits9)
+                    w.wl().bit(frame_9bits)
                      .pcen().bit(config.parity != Parity::ParityNone)
                      .psel().bit(config.parity == Parity::ParityOdd)
                      .txen().set_bit()
@@ -191,35 +212,36 @@ macro_rules! uartCommon {
                 Ok(serial)
             }
 
+            #[inline(always)]
             fn read_u16(&self) -> nb::Result<u16, Error> {
                 // NOTE(unsafe) atomic read with no side effects
                 let sr = self.sts().read();
 
-                // Any error requires the dr to be read to clear
-                if sr.pef().bit_is_set()
-                    || sr.fef().bit_is_set()
-                    || sr.nef().bit_is_set()
-                    || sr.oref().bit_is_set()
-                {
-                    self.dat().read();
+                // Fast path, a single test of the status word when there is no error
+                if sr.bits() & ERROR_FLAGS == 0 {
+                    return if sr.bits() & RXDNE != 0 {
+                        // NOTE(unsafe) atomic read from stateless register
+                        Ok(self.dat().read().datv().bits())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    };
                 }
 
+                // Any error requires the dr to be read to clear
+                self.dat().read();
+
                 Err(if sr.pef().bit_is_set() {
                     Error::Parity.into()
                 } else if sr.fef().bit_is_set() {
                     Error::FrameFormat.into()
                 } else if sr.nef().bit_is_set() {
                     Error::Noise.into()
-                } else if sr.oref().bit_is_set() {
-                    Error::Overrun.into()
-                } else if sr.rxdne().bit_is_set() {
-                    // NOTE(unsafe) atomic read from stateless register
-                    return Ok(self.dat().read().datv().bits());
                 } else {
-                    nb::Error::WouldBlock
+                    Error::Overrun.into()
                 })
             }
 
+            #[inline(always)]
             fn write_u16(&self, word: u16) -> nb::Result<(), Error> {
                 // NOTE(unsafe) atomic read with no side effects
                 if self.sts().read().txde().bit_is_set() {
@@ -242,14 +264,17 @@ macro_rules! uartCommon {
                 }
             }
 
+            #[inline(always)]
             fn flags(&self) -> BitFlags<Flag> {
                 BitFlags::from_bits_truncate(self.sts().read().bits())
             }
 
+            #[inline(always)]
             fn clear_flags(&self, flags: BitFlags<CFlag>) {
                 self.sts().write(|w| unsafe { w.bits(0xffff & !flags.bits()) });
             }
 
+            #[inline(always)]
             fn clear_idle_interrupt(&self) {
                 let _ = self.sts().read();
                 let _ = self.dat().read();
@@ -274,6 +299,18 @@ macro_rules! uartCommon {
                 });
             }
 
+            fn send_break(&self) {
+                self.ctrl1().modify(|_, w| w.sdbrk().set_bit());
+            }
+
+            fn set_break_detection(&self, enable: bool, long: bool) {
+                self.ctrl2().modify(|_, w| w.linmen().bit(enable).linbdl().bit(long));
+            }
+
+            fn set_break_interrupt(&self, enable: bool) {
+                self.ctrl2().modify(|_, w| w.linbdien().bit(enable));
+            }
+
             fn peri_address(&self) -> u32 {
                 self.dat().as_ptr() as u32
             }
@@ -288,30 +325,36 @@ impl<UART: Instance, WORD> RxISR for Serial<UART, WORD>
 where
     Rx<UART, WORD>: RxISR,
 {
+    #[inline(always)]
     fn is_idle(&self) -> bool {
         self.rx.is_idle()
     }
 
+    #[inline(always)]
     fn is_rx_not_empty(&self) -> bool {
         self.rx.is_rx_not_empty()
     }
 
     /// This clears `Idle`, `Overrun`, `Noise`, `FrameError` and `ParityError` flags
+    #[inline(always)]
     fn clear_idle_interrupt(&self) {
         self.rx.clear_idle_interrupt();
     }
 }
 
 impl<UART: Instance, WORD> RxISR for Rx<UART, WORD> {
+    #[inline(always)]
     fn is_idle(&self) -> bool {
         unsafe { (*UART::ptr()).is_idle() }
     }
 
+    #[inline(always)]
     fn is_rx_not_empty(&self) -> bool {
         unsafe { (*UART::ptr()).is_rx_not_empty() }
     }
 
     /// This clears `Idle`, `Overrun`, `Noise`, `FrameError` and `ParityError` flags
+    #[inline(always)]
     fn clear_idle_interrupt(&self) {
         unsafe {
             (*UART::ptr()).clear_idle_interrupt();
@@ -323,6 +366,7 @@ impl<UART: Instance, WORD> TxISR for Serial<UART, WORD>
 where
     Tx<UART, WORD>: TxISR,
 {
+    #[inline(always)]
     fn is_tx_empty(&self) -> bool {
         self.tx.is_tx_empty()
     }
@@ -332,6 +376,7 @@ impl<UART: Instance, WORD> TxISR for Tx<UART, WORD>
 where
     UART: Deref<Target = <UART as Instance>::RegisterBlock>,
 {
+    #[inline(always)]
     fn is_tx_empty(&self) -> bool {
         self.usart.is_tx_empty()
     }
@@ -355,6 +400,147 @@ impl<UART: Instance, WORD> RxListen for Rx<UART, WORD> {
     }
 }
 
+impl<UART: Instance, WORD> Rx<UART, WORD> {
+    /// Enables detection of break conditions on the line
+    ///
+    /// `long` selects an 11 bit break detection length instead of 10 bits.
+    /// This switches the peripheral to LIN mode, which requires 1 stop bit and no clock output.
+    pub fn enable_break_detection(&mut self, long: bool) {
+        unsafe { (*UART::ptr()).set_break_detection(true, long) }
+    }
+
+    /// Disables detection of break conditions
+    pub fn disable_break_detection(&mut self) {
+        unsafe { (*UART::ptr()).set_break_detection(false, false) }
+    }
+
+    /// Returns true if a break condition was detected
+    pub fn is_break_detected(&self) -> bool {
+        unsafe { (*UART::ptr()).flags().contains(Flag::LinBreak) }
+    }
+
+    /// Clears the break detection flag
+    pub fn clear_break(&mut self) {
+        unsafe { (*UART::ptr()).clear_flags(CFlag::LinBreak.into()) }
+    }
+
+    /// Start listening for a break detection interrupt event
+    ///
+    /// Note, you will also have to enable the corresponding interrupt
+    /// in the NVIC to start receiving events.
+    pub fn listen_break(&mut self) {
+        unsafe { (*UART::ptr()).set_break_interrupt(true) }
+    }
+
+    /// Stop listening for the break detection interrupt event
+    pub fn unlisten_break(&mut self) {
+        unsafe { (*UART::ptr()).set_break_interrupt(false) }
+    }
+}
+
+impl<UART: Instance, WORD> Rx<UART, WORD> {
+    /// Adds a receiver timeout of `bit_times` bits at `baudrate`, emulated with `timer`
+    ///
+    /// Returns [InvalidConfig](config::InvalidConfig) along with the receiver and the timer if
+    /// `baudrate` is zero.
+    #[allow(clippy::type_complexity)]
+    pub fn with_timeout<TIM>(
+        self,
+        timer: Timer<TIM>,
+        bit_times: u32,
+        baudrate: Bps,
+    ) -> Result<RxTimeout<UART, TIM, WORD>, (config::InvalidConfig, Self, Timer<TIM>)>
+    where
+        CountDownTimer<TIM>: CountDown<Time = MicroSecond> + Cancel,
+    {
+        if baudrate.0 == 0 {
+            return Err((config::InvalidConfig, self, timer));
+        }
+        let baudrate = baudrate.0 as u64;
+        let micros = (bit_times as u64 * 1_000_000 + baudrate - 1) / baudrate;
+        let timeout = MicroSecond::from_ticks(micros.min(u32::MAX as u64) as u32);
+        let mut timer = timer.start_count_down(timeout);
+        let _ = timer.cancel();
+        Ok(RxTimeout {
+            rx: self,
+            timer,
+            timeout,
+            armed: false,
+        })
+    }
+}
+
+impl<UART: Instance, TIM, WORD> RxTimeout<UART, TIM, WORD>
+where
+    CountDownTimer<TIM>: CountDown<Time = MicroSecond> + Cancel,
+{
+    fn restart(&mut self) {
+        self.timer.start(self.timeout);
+        self.armed = true;
+    }
+
+    /// Returns true once, when no word was received for the configured timeout
+    /// after the last received word
+    pub fn is_timed_out(&mut self) -> bool {
+        if self.armed && self.timer.wait().is_ok() {
+            self.armed = false;
+            let _ = self.timer.cancel();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Timer used for the timeout
+    ///
+    /// Use it to listen for its `TimeOut` event to get an interrupt when the receiver times out.
+    pub fn timer_mut(&mut self) -> &mut CountDownTimer<TIM> {
+        &mut self.timer
+    }
+
+    /// Stops the timer and returns the receiver and the timer
+    pub fn release(mut self) -> (Rx<UART, WORD>, CountDownTimer<TIM>) {
+        let _ = self.timer.cancel();
+        (self.rx, self.timer)
+    }
+}
+
+impl<UART: Instance, TIM> RxTimeout<UART, TIM, u8>
+where
+    CountDownTimer<TIM>: CountDown<Time = MicroSecond> + Cancel,
+{
+    /// Reads a word, restarting the timeout when one was received
+    pub fn read(&mut self) -> nb::Result<u8, Error> {
+        let word = unsafe { (*UART::ptr()).read_u8() }?;
+        self.restart();
+        Ok(word)
+    }
+}
+
+impl<UART: Instance, TIM> RxTimeout<UART, TIM, u16>
+where
+    CountDownTimer<TIM>: CountDown<Time = MicroSecond> + Cancel,
+{
+    /// Reads a word, restarting the timeout when one was received
+    pub fn read(&mut self) -> nb::Result<u16, Error> {
+        let word = unsafe { (*UART::ptr()).read_u16() }?;
+        self.restart();
+        Ok(word)
+    }
+}
+
+impl<UART: Instance, WORD> Tx<UART, WORD>
+where
+    UART: Deref<Target = <UART as Instance>::RegisterBlock>,
+{
+    /// Sends a break character after the current transmission
+    ///
+    /// The hardware clears the request once the break was sent.
+    pub fn send_break(&mut self) {
+        self.usart.send_break()
+    }
+}
+
 impl<UART: Instance, WORD> TxListen for Tx<UART, WORD>
 where
     UART: Deref<Target = <UART as Instance>::RegisterBlock>,
@@ -442,7 +628,7 @@ impl<UART: Instance> SerialExt for UART {
         pins: (TX,RX),
         config: impl Into<config::Config>,
         clocks: &Clocks,
-        afio: &mut crate::pac::Afio
+        afio: &mut crate::afio::Parts
     ) -> Result<Serial<Self, WORD>, config::InvalidConfig> {
         RMP::remap(afio);
         Serial::new(self, (pins.0.into(),pins.1.into()), config, clocks,afio)
@@ -452,7 +638,7 @@ impl<UART: Instance> SerialExt for UART {
         tx_pin: TX,
         config: impl Into<config::Config>,
         clocks: &Clocks,
-        afio: &mut crate::pac::Afio
+        afio: &mut crate::afio::Parts
     ) -> Result<Tx<Self, WORD>, config::InvalidConfig>
     where
         NoPin<Input>: Into<Self::Rx<Floating>>,
@@ -465,7 +651,7 @@ impl<UART: Instance> SerialExt for UART {
         rx_pin: RX,
         config: impl Into<config::Config>,
         clocks: &Clocks,
-        afio: &mut crate::pac::Afio
+        afio: &mut crate::afio::Parts
     ) -> Result<Rx<Self, WORD>, config::InvalidConfig>
     where
         NoPin<PushPull>: Into<Self::Tx<PushPull>>,
@@ -475,13 +661,49 @@ impl<UART: Instance> SerialExt for UART {
     }
 }
 
+impl<UART: Instance, WORD> Serial<UART, WORD> {
+    /// Moves the serial to the pins of remap `RMP` at runtime
+    ///
+    /// The current pins are switched to the modes of `OTX` and `ORX` and returned before the
+    /// AFIO remap changes, so they stop driving their connector first, e.g.
+    /// `serial.remap::<USART2PartialRemapOneRemapper, _, _, PA2<Analog>, PA3<Analog>>((pd5, pd6), config, &clocks, &mut afio)`.
+    ///
+    /// Returns [PinMismatch](config::RemapError::PinMismatch) if a current pin isn't of type
+    /// `OTX`/`ORX`. The serial port is released by then, so the error isn't recoverable.
+    #[allow(clippy::type_complexity)]
+    pub fn remap<RMP, TX, RX, OTX, ORX>(
+        self,
+        pins: (TX, RX),
+        config: impl Into<config::Config>,
+        clocks: &Clocks,
+        afio: &mut crate::afio::Parts,
+    ) -> Result<(Serial<UART, WORD>, (OTX, ORX)), config::RemapError>
+    where
+        RMP: Remap,
+        TX: crate::gpio::alt::altmap::RemapIO<UART, RMP> + Into<UART::Tx<PushPull>>,
+        RX: crate::gpio::alt::altmap::RemapIO<UART, RMP> + Into<UART::Rx<Floating>>,
+        OTX: TryFrom<UART::Tx<PushPull>>,
+        ORX: TryFrom<UART::Rx<Floating>>,
+    {
+        // Don't cut off the frame being sent
+        let _ = block!(unsafe { &*UART::ptr() }.flush());
+        let (usart, (tx, rx)) = self.release();
+        let old_pins = (
+            OTX::try_from(tx).map_err(|_| config::RemapError::PinMismatch)?,
+            ORX::try_from(rx).map_err(|_| config::RemapError::PinMismatch)?,
+        );
+        let serial = usart.serial::<WORD, RMP, TX, RX>(pins, config, clocks, afio)?;
+        Ok((serial, old_pins))
+    }
+}
+
 impl<UART: Instance, WORD> Serial<UART, WORD> {
     pub fn tx(
         usart: UART,
         tx_pin: impl Into<UART::Tx<PushPull>>,
         config: impl Into<config::Config>,
         clocks: &Clocks,
-        afio: &mut crate::pac::Afio
+        afio: &mut crate::afio::Parts
     ) -> Result<Tx<UART, WORD>, config::InvalidConfig>
     where
         NoPin<Input>: Into<UART::Rx<Floating>>,
@@ -496,7 +718,7 @@ impl<UART: Instance, WORD> Serial<UART, WORD> {
         rx_pin: impl Into<UART::Rx<Floating>>,
         config: impl Into<config::Config>,
         clocks: &Clocks,
-        afio: &mut crate::pac::Afio
+        afio: &mut crate::afio::Parts
     ) -> Result<Rx<UART, WORD>, config::InvalidConfig>
     where
     NoPin<PushPull>: Into<UART::Tx<PushPull>>,
@@ -9,6 +9,7 @@ use crate::pac;
 use embedded_dma::WriteBuffer;
 use embedded_dma::ReadBuffer;
 /// Clock polarity
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Polarity {
     /// Clock signal low when idle
@@ -18,6 +19,7 @@ pub enum Polarity {
 }
 
 /// Clock phase
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Phase {
     /// Data in "captured" on the first clock transition
@@ -26,6 +28,7 @@ pub enum Phase {
     CaptureOnSecondTransition,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, ConstParamTy)]
 pub enum TransferMode {
     TransferModeNormal,
@@ -35,6 +38,7 @@ pub enum TransferMode {
 }
 
 /// SPI mode
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Mode {
     /// Clock polarity
@@ -43,8 +47,18 @@ pub struct Mode {
     pub phase: Phase,
 }
 
+mod any;
 mod hal_02;
 mod hal_1;
+mod i2s;
+mod irq;
+mod shared;
+mod three_wire;
+pub use any::AnySpi;
+pub use i2s::{DataFormat, I2s, I2sConfig, I2sError, I2sInstance, I2sMode, I2sStandard, SampleRate};
+pub use irq::IrqTransfer;
+pub use shared::{DeviceConfig, SharedBus, SharedDevice};
+pub use three_wire::ThreeWire;
 
 use crate::pac::spi1;
 use crate::rcc;
@@ -126,9 +140,45 @@ pub enum Flag {
 pub enum CFlag {
     /// CRC error flag
     CrcError = 1 << 4,
+    /// Mode fault
+    ///
+    /// Cleared by reading STS followed by a write to CTRL1
+    ModeFault = 1 << 5,
+    /// Overrun flag
+    ///
+    /// Cleared by reading DAT followed by a read of STS
+    Overrun = 1 << 6,
 }
 
-pub trait FrameSize: Copy + Default {
+/// Event decoded by [InterruptHandler::handle_interrupt]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum InterruptEvent<W> {
+    /// An error flag was set, it has already been cleared
+    Error(Error),
+    /// A frame was received
+    Received(W),
+    /// The transmit buffer is empty and can be written
+    TxEmpty,
+    /// No enabled flag was set
+    None,
+}
+
+/// Decodes and acknowledges SPI interrupts
+///
+/// Reads STS once, handles error flags with their clear sequences and returns
+/// a typed event so interrupt handlers don't have to touch the registers. Only the flags
+/// whose interrupt is enabled with `Listen` are reported.
+pub trait InterruptHandler {
+    /// Handles a pending interrupt
+    ///
+    /// Errors take priority over received data, which takes priority over TxEmpty.
+    /// On [InterruptEvent::Received] the data register has been read.
+    fn handle_interrupt<W: FrameSize>(&mut self) -> InterruptEvent<W>;
+}
+
+/// Frame width of an SPI, 8 or 16 bits, also the element type of its DMA buffers
+pub trait FrameSize: Copy + Default + crate::dma::DmaWord {
     const DFF: bool;
 }
 
@@ -141,6 +191,7 @@ impl FrameSize for u16 {
 }
 
 /// The bit format to send the data in
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BitFormat {
     /// Least significant bit first
@@ -159,6 +210,10 @@ pub struct Inner<SPI: Instance> {
 pub struct Spi<SPI: Instance, const XFER_MODE : TransferMode = {TransferMode::TransferModeNormal}, W = u8> {
     inner: Inner<SPI>,
     pins: (SPI::Sck, SPI::Miso, SPI::Mosi),
+    /// NSS pin driven by the SPI, see [Spi::with_hardware_cs]
+    cs: Option<(SPI::Nss, CsFraming)>,
+    /// Bus clock of the SPI
+    clk: Hertz,
     _operation: PhantomData<W>,
 }
 
@@ -175,11 +230,44 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> DerefMut for Spi<SPI, XFE
     }
 }
 
+/// When a master with [hardware chip select](Spi::with_hardware_cs) ends a frame
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsFraming {
+    /// Pulse NSS high after every word
+    PerWord,
+    /// Pulse NSS high after every blocking transfer, or on [Spi::end_frame]
+    PerTransfer,
+}
+
+/// How a [SpiSlave] realigns to the frames of the master after losing track of them
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameSync {
+    /// Realign right away when a desync is detected, the master has to be idle
+    Immediate,
+    /// Stop receiving when a desync is detected and realign at the next NSS deassertion
+    NssPulse,
+}
+
+/// Frame boundary signalled by the NSS pin of a [SpiSlave]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NssEvent {
+    /// NSS was asserted, the master starts a frame
+    FrameStart,
+    /// NSS was deasserted, the master ended the frame
+    FrameEnd,
+}
+
 /// Spi in Slave mode
 #[derive(Debug)]
 pub struct SpiSlave<SPI: Instance, const XFER_MODE : TransferMode = {TransferMode::TransferModeNormal}, W = u8> {
     inner: Inner<SPI>,
     pins: (SPI::Sck, SPI::Miso, SPI::Mosi, Option<SPI::Nss>),
+    frame_sync: FrameSync,
+    desynced: bool,
+    nss_line: Option<gpio::ExtiLine>,
     _operation: PhantomData<W>,
 }
 
@@ -225,6 +313,7 @@ macro_rules! spi {
 
 spi! { pac::Spi1: Spi1, SpiSlave1 }
 spi! { pac::Spi2: Spi2, SpiSlave2 }
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 spi! { pac::Spi3: Spi3, SpiSlave3 }
 
 
@@ -238,7 +327,7 @@ pub trait SpiExt: Sized + Instance {
         mode: impl Into<Mode>,
         freq: Hertz,
         clocks: &Clocks,
-        afio: &mut pac::Afio,
+        afio: &mut crate::afio::Parts,
     ) -> Spi<Self, {TransferMode::TransferModeNormal}, u8>;
 
     fn spi_bidi<RMP : Remap,
@@ -249,7 +338,7 @@ pub trait SpiExt: Sized + Instance {
         mode: impl Into<Mode>,
         freq: Hertz,
         clocks: &Clocks,
-        afio: &mut pac::Afio,
+        afio: &mut crate::afio::Parts,
     ) -> Spi<Self, {TransferMode::TransferModeBidirectional}, u8>
     where
         NoPin: Into<Self::Miso>;
@@ -262,7 +351,7 @@ pub trait SpiExt: Sized + Instance {
         mode: impl Into<Mode>,
         freq: Hertz,
         clocks: &Clocks,
-        afio: &mut pac::Afio,
+        afio: &mut crate::afio::Parts,
     ) -> Spi<Self, {TransferMode::TransferModeRecieveOnly}, u8>
     where
         NoPin: Into<Self::Mosi>;
@@ -309,7 +398,7 @@ impl<SPI: Instance> SpiExt for SPI {
         mode: impl Into<Mode>,
         freq: Hertz,
         clocks: &Clocks,
-        afio: &mut pac::Afio,
+        afio: &mut crate::afio::Parts,
     ) -> Spi<Self, {TransferMode::TransferModeNormal}, u8> {
         RMP::remap(afio);
         Spi::new(self, pins, mode, freq, clocks)
@@ -327,7 +416,7 @@ impl<SPI: Instance> SpiExt for SPI {
         mode: impl Into<Mode>,
         freq: Hertz,
         clocks: &Clocks,
-        afio: &mut pac::Afio,
+        afio: &mut crate::afio::Parts,
     ) -> Spi<Self, {TransferMode::TransferModeBidirectional}, u8>
     where
         NoPin: Into<Self::Miso>,
@@ -349,7 +438,7 @@ impl<SPI: Instance> SpiExt for SPI {
         mode: impl Into<Mode>,
         freq: Hertz,
         clocks: &Clocks,
-        afio: &mut pac::Afio,
+        afio: &mut crate::afio::Parts,
 
     ) -> Spi<Self, {TransferMode::TransferModeRecieveOnly}, u8>
     where
@@ -402,10 +491,15 @@ impl<SPI: Instance> SpiExt for SPI {
 
 impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> Spi<SPI, XFER_MODE, W> {
     pub fn init(self) -> Self {
+        // NSS follows SPE, keep it driven when the mode changes
+        self.spi.ctrl2().modify(|_, w| w.ssoen().bit(self.cs.is_some()));
+        self.spi.ctrl1().modify(|_, w| w.ssmen().bit(self.cs.is_none()));
         self.spi.ctrl1().modify(|_, w| {
             // bidimode: 2-line or 1-line unidirectional
             w.bidirmode().bit(XFER_MODE == TransferMode::TransferModeBidirectional);
             w.bidiroen().bit(XFER_MODE == TransferMode::TransferModeBidirectional);
+            // rxonly: kept in step with the mode when it changes
+            w.ronly().bit(XFER_MODE == TransferMode::TransferModeRecieveOnly);
             // data frame size
             w.datff().bit(W::DFF);
             // spe: enable the SPI bus
@@ -436,6 +530,29 @@ impl<SPI: Instance, W: FrameSize> Spi<SPI, {TransferMode::TransferModeNormal}, W
     pub fn to_bidi_transfer_mode(self) -> Spi<SPI, {TransferMode::TransferModeBidirectional}, W> {
         self.into_mode()
     }
+
+    /// Drives `nss` from the SPI as chip select, framing words or transfers with it
+    ///
+    /// The hardware drives NSS low as long as the master is enabled, so a frame is ended by
+    /// disabling and re-enabling the SPI once the bus is idle, which pulses NSS high for a few
+    /// APB cycles. This suits DACs and ADCs that latch or start a conversion on the rising
+    /// edge of their chip select; NSS stays low between frames. The blocking transfers end the
+    /// frames by themselves, call [end_frame](Self::end_frame) after DMA or interrupt driven
+    /// transfers.
+    pub fn with_hardware_cs(mut self, nss: impl Into<SPI::Nss>, framing: CsFraming) -> Self {
+        while self.is_busy() {}
+        self.enable(false);
+        self.cs = Some((nss.into(), framing));
+        self.init()
+    }
+
+    /// Stops driving NSS and returns the pin
+    pub fn without_hardware_cs(mut self) -> (Self, Option<SPI::Nss>) {
+        while self.is_busy() {}
+        self.enable(false);
+        let nss = self.cs.take().map(|(nss, _)| nss);
+        (self.init(), nss)
+    }
 }
 
 impl<SPI: Instance, W: FrameSize> Spi<SPI, {TransferMode::TransferModeBidirectional}, W> {
@@ -519,7 +636,7 @@ impl<SPI: Instance> Spi<SPI, {TransferMode::TransferModeNormal}, u8> {
 
         let pins = (pins.0.into(), pins.1.into(), pins.2.into());
 
-        Self::_new(spi, pins)
+        Self::_new(spi, pins, SPI::clock(clocks))
             .pre_init(mode.into(), freq, SPI::clock(clocks))
             .init()
     }
@@ -548,7 +665,7 @@ impl<SPI: Instance> Spi<SPI, {TransferMode::TransferModeRecieveOnly}, u8> {
 
         let pins = (pins.0.into(),  pins.1.into(),NoPin::new().into());
         
-        Self::_new(spi, pins)
+        Self::_new(spi, pins, SPI::clock(clocks))
             .pre_init(mode.into(), freq, SPI::clock(clocks))
             .init()
     }
@@ -578,7 +695,7 @@ impl<SPI: Instance> Spi<SPI, {TransferMode::TransferModeBidirectional}, u8> {
 
         let pins = (pins.0.into(), NoPin::new().into(), pins.1.into());
 
-        Self::_new(spi, pins)
+        Self::_new(spi, pins, SPI::clock(clocks))
             .pre_init(mode.into(), freq, SPI::clock(clocks))
             .init()
     }
@@ -641,13 +758,17 @@ impl<SPI: Instance> SpiSlave<SPI, {TransferMode::TransferModeBidirectional}, u8>
 }
 
 impl<SPI: Instance, const XFER_MODE : TransferMode, W> Spi<SPI, XFER_MODE, W> {
+    /// Releases the SPI and its pins, with the NSS pin given to [with_hardware_cs](Self::with_hardware_cs)
     #[allow(clippy::type_complexity)]
-    pub fn release(self) -> (SPI, (SPI::Sck, SPI::Miso, SPI::Mosi)) {
-        (self.inner.spi, self.pins)
+    pub fn release(self) -> (SPI, (SPI::Sck, SPI::Miso, SPI::Mosi, Option<SPI::Nss>)) {
+        let (sck, miso, mosi) = self.pins;
+        (self.inner.spi, (sck, miso, mosi, self.cs.map(|(nss, _)| nss)))
     }
 }
 
 impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE, W> {
+    /// Releases the SPI and its pins, call [unlisten_nss](Self::unlisten_nss) first to free
+    /// the EXTI line of the NSS pin
     #[allow(clippy::type_complexity)]
     pub fn release(self) -> (SPI, (SPI::Sck, SPI::Miso, SPI::Mosi, Option<SPI::Nss>)) {
         (self.inner.spi, self.pins)
@@ -655,17 +776,25 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE,
 }
 
 impl<SPI: Instance, const XFER_MODE : TransferMode, W> Spi<SPI, XFER_MODE, W> {
-    fn _new(spi: SPI, pins: (SPI::Sck, SPI::Miso, SPI::Mosi)) -> Self {
+    fn _new(spi: SPI, pins: (SPI::Sck, SPI::Miso, SPI::Mosi), clk: Hertz) -> Self {
         Self {
             inner: Inner::new(spi),
             pins,
+            cs: None,
+            clk,
             _operation: PhantomData,
         }
     }
 
+    /// Returns the bus clock the SPI was set up with
+    pub fn clock(&self) -> Hertz {
+        self.clk
+    }
+
     /// Convert the spi to another mode.
     fn into_mode<const XFER_MODE2: TransferMode, W2: FrameSize>(self) -> Spi<SPI, XFER_MODE2, W2> {
-        let mut spi = Spi::_new(self.inner.spi, self.pins);
+        let mut spi = Spi::_new(self.inner.spi, self.pins, self.clk);
+        spi.cs = self.cs;
         spi.enable(false);
         spi.init()
     }
@@ -676,6 +805,9 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE,
         Self {
             inner: Inner::new(spi),
             pins,
+            frame_sync: FrameSync::Immediate,
+            desynced: false,
+            nss_line: None,
             _operation: PhantomData,
         }
     }
@@ -683,6 +815,8 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE,
     /// Convert the spi to another mode.
     fn into_mode<const XFER_MODE2: TransferMode, W2: FrameSize>(self) -> SpiSlave<SPI, XFER_MODE2, W2> {
         let mut spi = SpiSlave::_new(self.inner.spi, self.pins);
+        spi.frame_sync = self.frame_sync;
+        spi.nss_line = self.nss_line;
         spi.enable(false);
         spi.init()
     }
@@ -694,17 +828,7 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> Spi<SPI, XFER_MODE, W> {
         // disable SS output
         self.spi.ctrl2().modify(|_,w| w.ssoen().clear_bit());
 
-        let br = match clock.raw() / freq.raw() {
-            0 => unreachable!(),
-            1..=2 => 0b000,
-            3..=5 => 0b001,
-            6..=11 => 0b010,
-            12..=23 => 0b011,
-            24..=47 => 0b100,
-            48..=95 => 0b101,
-            96..=191 => 0b110,
-            _ => 0b111,
-        };
+        let br = baud_rate_bits(freq, clock);
 
         self.spi.ctrl1().modify(|_,w| {
             w.clkpha().bit(mode.phase == Phase::CaptureOnSecondTransition);
@@ -727,6 +851,71 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> Spi<SPI, XFER_MODE, W> {
     }
 }
 
+/// BR field value for the highest frequency not above `freq`
+fn baud_rate_bits(freq: Hertz, clock: Hertz) -> u8 {
+    match clock.raw() / freq.raw() {
+        0 => unreachable!(),
+        1..=2 => 0b000,
+        3..=5 => 0b001,
+        6..=11 => 0b010,
+        12..=23 => 0b011,
+        24..=47 => 0b100,
+        48..=95 => 0b101,
+        96..=191 => 0b110,
+        _ => 0b111,
+    }
+}
+
+impl<SPI: Instance, const XFER_MODE : TransferMode, W> Spi<SPI, XFER_MODE, W> {
+    /// Changes the mode and the BR field of an initialized master, waits for the bus to be idle first
+    pub(crate) fn reconfigure(&mut self, mode: Mode, br: u8) {
+        while self.is_busy() {}
+        self.enable(false);
+        self.spi.ctrl1().modify(|_, w| {
+            w.clkpha().bit(mode.phase == Phase::CaptureOnSecondTransition);
+            w.clkpol().bit(mode.polarity == Polarity::IdleHigh);
+            unsafe { w.br().bits(br) }
+        });
+        // spe: a receive-only master clocks as soon as it's enabled, so it's left disabled as in init
+        self.enable(XFER_MODE != TransferMode::TransferModeRecieveOnly);
+    }
+
+    /// Returns the clock polarity and phase currently configured
+    pub(crate) fn mode(&self) -> Mode {
+        let ctrl1 = self.spi.ctrl1().read();
+        Mode {
+            polarity: if ctrl1.clkpol().bit_is_set() {
+                Polarity::IdleHigh
+            } else {
+                Polarity::IdleLow
+            },
+            phase: if ctrl1.clkpha().bit_is_set() {
+                Phase::CaptureOnSecondTransition
+            } else {
+                Phase::CaptureOnFirstTransition
+            },
+        }
+    }
+
+    /// Changes the SCK frequency to the highest one not above `freq`
+    ///
+    /// Waits for the current frame to end, so a single bus can serve devices with different
+    /// speeds between transfers.
+    pub fn set_frequency(&mut self, freq: Hertz, clocks: &Clocks) {
+        self.clk = SPI::clock(clocks);
+        let mode = self.mode();
+        self.reconfigure(mode, baud_rate_bits(freq, self.clk));
+    }
+
+    /// Changes the clock polarity and phase, waits for the current frame to end first
+    ///
+    /// Change the mode while no device is selected, as SCK moves to its new idle level.
+    pub fn set_mode(&mut self, mode: impl Into<Mode>) {
+        let br = self.spi.ctrl1().read().br().bits();
+        self.reconfigure(mode.into(), br);
+    }
+}
+
 impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE, W> {
     /// Pre initializing the SPI bus.
     fn pre_init(self, mode: Mode) -> Self {
@@ -755,6 +944,124 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE,
     pub fn set_internal_nss(&mut self, value: bool) {
         self.spi.ctrl1().modify(|_, w| w.ssel().bit(value));
     }
+
+    /// Selects how [recover](Self::recover) realigns to the frames of the master
+    pub fn set_frame_sync(&mut self, frame_sync: FrameSync) {
+        self.frame_sync = frame_sync;
+    }
+
+    /// Drops the partially shifted frame, the received frame and the error flags
+    ///
+    /// Disabling the peripheral resets its bit counter, so the next SCK edge is taken as the
+    /// first bit of a frame. The frame already written for transmission is dropped too.
+    pub fn resync(&mut self) {
+        self.enable(false);
+        // Read DAT then STS to clear OVR, then write CTRL1 to clear MODF
+        let _ = self.spi.dat().read();
+        let _ = self.spi.sts().read();
+        self.spi.ctrl1().modify(|_r, w| w);
+        self.desynced = false;
+        self.enable(true);
+    }
+
+    /// Checks for a desync, i.e. an overrun or a mode fault, and recovers from it
+    ///
+    /// With [FrameSync::Immediate] the slave is resynchronized right away. With
+    /// [FrameSync::NssPulse] it stops receiving until [on_nss_deasserted](Self::on_nss_deasserted)
+    /// reports the end of the frame, so the remaining bits of the broken frame are ignored.
+    /// Returns `true` if a desync was detected.
+    pub fn recover(&mut self) -> bool {
+        let sts = self.spi.sts().read();
+        if sts.over().bit_is_clear() && sts.moderr().bit_is_clear() {
+            return false;
+        }
+        match self.frame_sync {
+            FrameSync::Immediate => self.resync(),
+            FrameSync::NssPulse => {
+                self.enable(false);
+                self.desynced = true;
+            }
+        }
+        true
+    }
+
+    /// Marks a frame boundary, call this from the EXTI interrupt of the NSS pin on its
+    /// rising edge
+    ///
+    /// With [FrameSync::NssPulse] every deassertion resynchronizes the slave, which discards a
+    /// frame the master gave up on halfway, so write the next frame to transmit after this
+    /// call. It does nothing with [FrameSync::Immediate].
+    pub fn on_nss_deasserted(&mut self) {
+        if self.frame_sync == FrameSync::NssPulse {
+            self.resync();
+        }
+    }
+
+    /// Returns `true` while waiting for the NSS deassertion after a desync
+    pub fn is_desynced(&self) -> bool {
+        self.desynced
+    }
+}
+
+impl<SPI: Instance, const XFER_MODE : TransferMode, W> SpiSlave<SPI, XFER_MODE, W>
+where
+    SPI::Nss: gpio::ExtiPin + gpio::ReadPin,
+{
+    /// Claims the EXTI line of the NSS pin, triggers it on both edges and enables the interrupt
+    ///
+    /// Unmask the EXTI interrupt of the pin's line in the NVIC too, and call
+    /// [nss_event](Self::nss_event) from it. Returns `false` without a hardware NSS pin, and
+    /// [LineClaimed](crate::afio::LineClaimed) if a pin of another port holds the line.
+    pub fn listen_nss(
+        &mut self,
+        afio: &mut crate::afio::Parts,
+        exti: &mut pac::Exti,
+    ) -> Result<bool, crate::afio::LineClaimed> {
+        if self.nss_line.is_some() {
+            return Ok(true);
+        }
+        let mut line = match self.pins.3.as_ref().and_then(|nss| nss.claim_line(&mut afio.exticr)) {
+            Some(line) => line?,
+            None => return Ok(false),
+        };
+        line.trigger_on_edge(exti, gpio::Edge::RisingFalling);
+        line.clear_interrupt_pending_bit();
+        line.enable_interrupt(exti);
+        self.nss_line = Some(line);
+        Ok(true)
+    }
+
+    /// Disables the EXTI interrupt of the NSS pin and frees its line
+    pub fn unlisten_nss(&mut self, afio: &mut crate::afio::Parts, exti: &mut pac::Exti) {
+        if let Some(line) = self.nss_line.take() {
+            afio.exticr.release(line, exti);
+        }
+    }
+
+    /// Returns the frame boundary that raised the EXTI interrupt of the NSS pin, and clears it
+    ///
+    /// The edge is told from the level of the pin, so an assertion and deassertion that both
+    /// happened before the call are reported as the later one. A deassertion also calls
+    /// [on_nss_deasserted](Self::on_nss_deasserted). Returns `None` if the interrupt wasn't
+    /// raised by NSS, e.g. for another pin sharing the EXTI interrupt.
+    pub fn nss_event(&mut self) -> Option<NssEvent> {
+        let nss = self.pins.3.as_mut()?;
+        if !nss.check_interrupt() {
+            return None;
+        }
+        nss.clear_interrupt_pending_bit();
+        if nss.is_high() {
+            self.on_nss_deasserted();
+            Some(NssEvent::FrameEnd)
+        } else {
+            Some(NssEvent::FrameStart)
+        }
+    }
+
+    /// Returns `true` while the master selects the slave
+    pub fn is_selected(&self) -> bool {
+        matches!(&self.pins.3, Some(nss) if nss.is_low())
+    }
 }
 
 impl<SPI: Instance> Inner<SPI> {
@@ -909,7 +1216,18 @@ impl<SPI: Instance> crate::Listen for Inner<SPI> {
 impl<SPI: Instance> crate::ClearFlags for Inner<SPI> {
     type Flag = CFlag;
     fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
-        if flags.into().contains(CFlag::CrcError) {
+        let flags = flags.into();
+        if flags.contains(CFlag::Overrun) {
+            // Read DAT then STS to clear OVR
+            let _ = self.spi.dat().read();
+            let _ = self.spi.sts().read();
+        }
+        if flags.contains(CFlag::ModeFault) {
+            // Read STS then write CTRL1 to clear MODF
+            let _ = self.spi.sts().read();
+            self.spi.ctrl1().modify(|_r, w| w);
+        }
+        if flags.contains(CFlag::CrcError) {
             self.spi
                 .sts()
                 .write(|w| unsafe { w.bits(0xffff).crcerr().clear_bit() })
@@ -917,6 +1235,47 @@ impl<SPI: Instance> crate::ClearFlags for Inner<SPI> {
     }
 }
 
+impl<SPI: Instance> InterruptHandler for Inner<SPI> {
+    fn handle_interrupt<W: FrameSize>(&mut self) -> InterruptEvent<W> {
+        let enabled = BitFlags::<Event>::from_bits_truncate(self.spi.ctrl2().read().bits());
+        let mut flags: BitFlags<Flag> = crate::ReadFlags::flags(self);
+        // Only the flags whose interrupt is enabled, e.g. TxEmpty is set most of the time
+        if !enabled.contains(Event::Error) {
+            flags &= !(Flag::Overrun | Flag::ModeFault | Flag::CrcError);
+        }
+        if !enabled.contains(Event::RxNotEmpty) {
+            flags &= !Flag::RxNotEmpty;
+        }
+        if !enabled.contains(Event::TxEmpty) {
+            flags &= !Flag::TxEmpty;
+        }
+
+        let error = if flags.contains(Flag::Overrun) {
+            let _ = self.spi.dat().read();
+            let _ = self.spi.sts().read();
+            Some(Error::Overrun)
+        } else if flags.contains(Flag::ModeFault) {
+            self.spi.ctrl1().modify(|_r, w| w);
+            Some(Error::ModeFault)
+        } else if flags.contains(Flag::CrcError) {
+            self.spi.sts().write(|w| unsafe { w.bits(0xffff).crcerr().clear_bit() });
+            Some(Error::Crc)
+        } else {
+            None
+        };
+
+        if let Some(e) = error {
+            InterruptEvent::Error(e)
+        } else if flags.contains(Flag::RxNotEmpty) {
+            InterruptEvent::Received(self.read_data_reg())
+        } else if flags.contains(Flag::TxEmpty) {
+            InterruptEvent::TxEmpty
+        } else {
+            InterruptEvent::None
+        }
+    }
+}
+
 impl<SPI: Instance> crate::ReadFlags for Inner<SPI> {
     type Flag = Flag;
     fn flags(&self) -> BitFlags<Self::Flag> {
@@ -975,6 +1334,27 @@ impl<SPI: Instance> DmaBuilder<SPI> {
 }
 
 impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> Spi<SPI, XFER_MODE, W> {
+    /// Pulses NSS high once the bus is idle, when it is driven by [Spi::with_hardware_cs]
+    pub fn end_frame(&mut self) {
+        if self.cs.is_some() {
+            while self.is_busy() {}
+            self.enable(false);
+            self.enable(true);
+        }
+    }
+
+    fn end_word(&mut self) {
+        if matches!(self.cs, Some((_, CsFraming::PerWord))) {
+            self.end_frame();
+        }
+    }
+
+    fn end_transfer(&mut self) {
+        if matches!(self.cs, Some((_, CsFraming::PerTransfer))) {
+            self.end_frame();
+        }
+    }
+
     pub fn read_nonblocking(&mut self) -> nb::Result<W, Error> {
         if XFER_MODE == TransferMode::TransferModeBidirectional {
             self.bidi_input();
@@ -990,22 +1370,34 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> Spi<SPI, XFER_
     }
 
     pub fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::SPI_TRANSFER.scope();
         for word in words {
             nb::block!(self.write_nonblocking(*word))?;
             *word = nb::block!(self.read_nonblocking())?;
+            self.end_word();
         }
 
+        self.end_transfer();
         Ok(())
     }
 
+    /// Writes `data` while reading into `buff`
+    ///
+    /// The shorter of the two is padded, with zero words sent or the extra words read discarded.
     pub fn transfer(&mut self, buff: &mut [W], data: &[W]) -> Result<(), Error> {
-        assert_eq!(data.len(), buff.len());
-
-        for (d, b) in data.iter().cloned().zip(buff.iter_mut()) {
-            nb::block!(self.write_nonblocking(d))?;
-            *b = nb::block!(self.read_nonblocking())?;
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::SPI_TRANSFER.scope();
+        for i in 0..data.len().max(buff.len()) {
+            nb::block!(self.write_nonblocking(data.get(i).copied().unwrap_or_default()))?;
+            let word = nb::block!(self.read_nonblocking())?;
+            if let Some(b) = buff.get_mut(i) {
+                *b = word;
+            }
+            self.end_word();
         }
 
+        self.end_transfer();
         Ok(())
     }
 
@@ -1014,42 +1406,55 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> Spi<SPI, XFER_
     }
 
     pub fn write(&mut self, words: &[W]) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::SPI_TRANSFER.scope();
         if XFER_MODE == TransferMode::TransferModeBidirectional {
             self.bidi_output();
             for word in words {
                 nb::block!(self.check_send(*word))?;
+                self.end_word();
             }
         } else {
             for word in words {
                 nb::block!(self.check_send(*word))?;
                 nb::block!(self.check_read::<W>())?;
+                self.end_word();
             }
         }
 
+        self.end_transfer();
         Ok(())
     }
 
     pub fn write_iter(&mut self, words: impl IntoIterator<Item = W>) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::SPI_TRANSFER.scope();
         if XFER_MODE == TransferMode::TransferModeBidirectional {
             self.bidi_output();
             for word in words.into_iter() {
                 nb::block!(self.check_send(word))?;
+                self.end_word();
             }
         } else {
             for word in words.into_iter() {
                 nb::block!(self.check_send(word))?;
                 nb::block!(self.check_read::<W>())?;
+                self.end_word();
             }
         }
 
+        self.end_transfer();
         Ok(())
     }
 
     pub fn read(&mut self, words: &mut [W]) -> Result<(), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::SPI_TRANSFER.scope();
         if XFER_MODE == TransferMode::TransferModeBidirectional {
             self.bidi_input();
             for word in words {
                 *word = nb::block!(self.check_read())?;
+                self.end_word();
             }
         } else if XFER_MODE == TransferMode::TransferModeRecieveOnly {
             self.spi.ctrl1().modify(|_,w| w.spien().set_bit());
@@ -1061,9 +1466,11 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> Spi<SPI, XFER_
             for word in words {
                 nb::block!(self.check_send(W::default()))?;
                 *word = nb::block!(self.check_read())?;
+                self.end_word();
             }
         }
 
+        self.end_transfer();
         Ok(())
     }
 }
@@ -1092,12 +1499,16 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> SpiSlave<SPI,
         Ok(())
     }
 
+    /// Writes `data` while reading into `buff`
+    ///
+    /// The shorter of the two is padded, with zero words sent or the extra words read discarded.
     pub fn transfer(&mut self, buff: &mut [W], data: &[W]) -> Result<(), Error> {
-        assert_eq!(data.len(), buff.len());
-
-        for (d, b) in data.iter().cloned().zip(buff.iter_mut()) {
-            nb::block!(self.write_nonblocking(d))?;
-            *b = nb::block!(self.read_nonblocking())?;
+        for i in 0..data.len().max(buff.len()) {
+            nb::block!(self.write_nonblocking(data.get(i).copied().unwrap_or_default()))?;
+            let word = nb::block!(self.read_nonblocking())?;
+            if let Some(b) = buff.get_mut(i) {
+                *b = word;
+            }
         }
 
         Ok(())
@@ -1140,32 +1551,32 @@ impl<SPI: Instance, const XFER_MODE : TransferMode, W: FrameSize> SpiSlave<SPI,
     }
 }
 
-pub type SpiTxDma<SPI, const XFER_MODE : TransferMode, CHANNEL> = TxDma<Spi<SPI, XFER_MODE, u8>, CHANNEL>;
-pub type SpiRxDma<SPI, const XFER_MODE : TransferMode, CHANNEL> = RxDma<Spi<SPI, XFER_MODE, u8>, CHANNEL>;
-pub type SpiRxTxDma<SPI, const XFER_MODE : TransferMode, RXCHANNEL, TXCHANNEL> =
-    RxTxDma<Spi<SPI, XFER_MODE, u8>, RXCHANNEL, TXCHANNEL>;
+pub type SpiTxDma<SPI, const XFER_MODE : TransferMode, CHANNEL, FRAME = u8> = TxDma<Spi<SPI, XFER_MODE, FRAME>, CHANNEL>;
+pub type SpiRxDma<SPI, const XFER_MODE : TransferMode, CHANNEL, FRAME = u8> = RxDma<Spi<SPI, XFER_MODE, FRAME>, CHANNEL>;
+pub type SpiRxTxDma<SPI, const XFER_MODE : TransferMode, RXCHANNEL, TXCHANNEL, FRAME = u8> =
+    RxTxDma<Spi<SPI, XFER_MODE, FRAME>, RXCHANNEL, TXCHANNEL>;
 
-pub trait SpiDma<PER : Instance, const XFER_MODE : TransferMode, RXCH : crate::dma::CompatibleChannel<PER,R> + crate::dma::DMAChannel, TXCH : crate::dma::CompatibleChannel<PER,W> + crate::dma::DMAChannel> {
+pub trait SpiDma<PER : Instance, const XFER_MODE : TransferMode, RXCH : crate::dma::CompatibleChannel<PER,R> + crate::dma::DMAChannel, TXCH : crate::dma::CompatibleChannel<PER,W> + crate::dma::DMAChannel, FRAME : FrameSize = u8> {
     fn with_rx_tx_dma(
         self,
         rxchannel: RXCH,
         txchannel: TXCH,
-    ) -> SpiRxTxDma<PER, XFER_MODE, RXCH, TXCH>;
-    fn with_rx_dma(self, channel: RXCH) -> SpiRxDma<PER, XFER_MODE, RXCH>;
-    fn with_tx_dma(self, channel: TXCH) -> SpiTxDma<PER, XFER_MODE, TXCH>;
+    ) -> SpiRxTxDma<PER, XFER_MODE, RXCH, TXCH, FRAME>;
+    fn with_rx_dma(self, channel: RXCH) -> SpiRxDma<PER, XFER_MODE, RXCH, FRAME>;
+    fn with_tx_dma(self, channel: TXCH) -> SpiTxDma<PER, XFER_MODE, TXCH, FRAME>;
 }
 
 macro_rules! spi_dma {
     ($SPIi:ty, $rxdma:ident, $txdma:ident, $rxtxdma:ident) => {
-        pub type $rxdma<const XFER_MODE : TransferMode, RXCH> = SpiRxDma<$SPIi, XFER_MODE, RXCH>;
-        pub type $txdma<const XFER_MODE : TransferMode, TXCH> = SpiTxDma<$SPIi, XFER_MODE, TXCH>;
-        pub type $rxtxdma<const XFER_MODE : TransferMode,RXCH,TXCH> = SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH>;
+        pub type $rxdma<const XFER_MODE : TransferMode, RXCH, FRAME = u8> = SpiRxDma<$SPIi, XFER_MODE, RXCH, FRAME>;
+        pub type $txdma<const XFER_MODE : TransferMode, TXCH, FRAME = u8> = SpiTxDma<$SPIi, XFER_MODE, TXCH, FRAME>;
+        pub type $rxtxdma<const XFER_MODE : TransferMode,RXCH,TXCH, FRAME = u8> = SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH, FRAME>;
 
-        impl<const XFER_MODE : TransferMode, RXCH,TXCH> SpiDma<$SPIi,XFER_MODE,RXCH,TXCH> for Spi<$SPIi,XFER_MODE,u8>  where
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode, RXCH,TXCH> SpiDma<$SPIi,XFER_MODE,RXCH,TXCH,FRAME> for Spi<$SPIi,XFER_MODE,FRAME>  where
         RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,
         TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel
         {
-            fn with_tx_dma(self, mut channel: TXCH) -> SpiTxDma<$SPIi, XFER_MODE, TXCH> {
+            fn with_tx_dma(self, mut channel: TXCH) -> SpiTxDma<$SPIi, XFER_MODE, TXCH, FRAME> {
                 self.spi.ctrl2().modify(|_, w| w.tdmaen().set_bit());
                 channel.configure_channel();
                 SpiTxDma {
@@ -1173,7 +1584,7 @@ macro_rules! spi_dma {
                     channel,
                 }
             }
-            fn with_rx_dma(self, mut channel: RXCH) -> SpiRxDma<$SPIi, XFER_MODE, RXCH>
+            fn with_rx_dma(self, mut channel: RXCH) -> SpiRxDma<$SPIi, XFER_MODE, RXCH, FRAME>
             {
                self.spi.ctrl2().modify(|_, w| w.rdmaen().set_bit());
                channel.configure_channel();
@@ -1186,7 +1597,7 @@ macro_rules! spi_dma {
                 self,
                 mut rxchannel: RXCH,
                 mut txchannel: TXCH,
-            ) -> SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH> {
+            ) -> SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH, FRAME> {
                 self.spi
                 .ctrl2()
                 .modify(|_, w| w.rdmaen().set_bit().tdmaen().set_bit());
@@ -1201,44 +1612,44 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<const XFER_MODE : TransferMode,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> Transmit for SpiTxDma<$SPIi, XFER_MODE, TXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> Transmit for SpiTxDma<$SPIi, XFER_MODE, TXCH, FRAME> {
             type TxChannel = TXCH;
-            type ReceivedWord = u8;
+            type ReceivedWord = FRAME;
         }
 
-        impl<const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> Receive for SpiRxDma<$SPIi, XFER_MODE, RXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> Receive for SpiRxDma<$SPIi, XFER_MODE, RXCH, FRAME> {
             type RxChannel = RXCH;
-            type TransmittedWord = u8;
+            type TransmittedWord = FRAME;
         }
 
-        impl<const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> Transmit for SpiRxTxDma<$SPIi, XFER_MODE, RXCH,TXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> Transmit for SpiRxTxDma<$SPIi, XFER_MODE, RXCH,TXCH, FRAME> {
             type TxChannel = TXCH;
-            type ReceivedWord = u8;
+            type ReceivedWord = FRAME;
         }
 
-        impl<const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> Receive for SpiRxTxDma<$SPIi, XFER_MODE, RXCH,TXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> Receive for SpiRxTxDma<$SPIi, XFER_MODE, RXCH,TXCH, FRAME> {
             type RxChannel = RXCH;
-            type TransmittedWord = u8;
+            type TransmittedWord = FRAME;
         }
 
-        impl<const XFER_MODE : TransferMode, TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> SpiTxDma<$SPIi, XFER_MODE, TXCH> {
-            pub fn release(self) -> (Spi<$SPIi, XFER_MODE, u8>, TXCH) {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode, TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> SpiTxDma<$SPIi, XFER_MODE, TXCH, FRAME> {
+            pub fn release(self) -> (Spi<$SPIi, XFER_MODE, FRAME>, TXCH) {
                 let SpiTxDma { payload, channel } = self;
                 payload.spi.ctrl2().modify(|_, w| w.tdmaen().clear_bit());
                 (payload, channel)
             }
         }
 
-        impl<const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> SpiRxDma<$SPIi, XFER_MODE, RXCH> {
-            pub fn release(self) -> (Spi<$SPIi, XFER_MODE, u8>, RXCH) {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> SpiRxDma<$SPIi, XFER_MODE, RXCH, FRAME> {
+            pub fn release(self) -> (Spi<$SPIi, XFER_MODE, FRAME>, RXCH) {
                 let SpiRxDma { payload, channel } = self;
                 payload.spi.ctrl2().modify(|_, w| w.rdmaen().clear_bit());
                 (payload, channel)
             }
         }
 
-        impl<const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH> {
-            pub fn release(self) -> (Spi<$SPIi, XFER_MODE, u8>, RXCH, TXCH) {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH, FRAME> {
+            pub fn release(self) -> (Spi<$SPIi, XFER_MODE, FRAME>, RXCH, TXCH) {
                 let SpiRxTxDma {
                     payload,
                     rxchannel,
@@ -1252,7 +1663,7 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<const XFER_MODE : TransferMode,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> TransferPayload for SpiTxDma<$SPIi, XFER_MODE, TXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> TransferPayload for SpiTxDma<$SPIi, XFER_MODE, TXCH, FRAME> {
             fn start(&mut self) {
                 self.channel.start();
             }
@@ -1261,7 +1672,7 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> TransferPayload for SpiRxDma<$SPIi, XFER_MODE, RXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> TransferPayload for SpiRxDma<$SPIi, XFER_MODE, RXCH, FRAME> {
             fn start(&mut self) {
                 self.channel.start();
                 if XFER_MODE == TransferMode::TransferModeRecieveOnly {
@@ -1277,7 +1688,7 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> TransferPayload for SpiRxTxDma<$SPIi, XFER_MODE,RXCH,TXCH> {
+        impl<FRAME: FrameSize, const XFER_MODE : TransferMode,RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> TransferPayload for SpiRxTxDma<$SPIi, XFER_MODE,RXCH,TXCH, FRAME> {
             fn start(&mut self) {
                 self.rxchannel.start();
                 self.txchannel.start();
@@ -1288,36 +1699,34 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<B, const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> crate::dma::ReadDma<B, u8> for SpiRxDma<$SPIi, XFER_MODE, RXCH>
+        impl<B, FRAME: FrameSize, const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> crate::dma::ReadDma<B, FRAME> for SpiRxDma<$SPIi, XFER_MODE, RXCH, FRAME>
         where
-            B: WriteBuffer<Word = u8>,
+            B: WriteBuffer<Word = FRAME>,
         {
-            fn read(mut self, mut buffer: B) -> Transfer<W, B, Self> {
+            fn try_read(mut self, mut buffer: B) -> Result<Transfer<W, B, Self>, (crate::dma::Error, Self, B)> {
                 // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
                 // until the end of the transfer.
                 let (ptr, len) = unsafe { buffer.write_buffer() };
+                if let Err(error) = self.channel.try_set_transfer_length(len) {
+                    return Err((error, self, buffer));
+                }
                 self.channel.set_peripheral_address(
                     unsafe { (*<$SPIi>::ptr()).dat().as_ptr() as u32 },
                     false,
                 );
                 self.channel.set_memory_address(ptr as u32, true);
-                self.channel.set_transfer_length(len);
 
                 atomic::compiler_fence(Ordering::Release);
+                self.channel.set_word_size(FRAME::SIZE, FRAME::SIZE);
+                let priority = self.channel.priority_or(crate::dma::Priority::Medium) as u8;
                 self.channel.st().chcfg().modify(|_, w| {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
                         .disabled()
-                        // medium channel priority level
+                        // channel priority level, medium unless set with DMAChannel::set_priority
                         .priolvl()
-                        .medium()
-                        // 8-bit memory size
-                        .msize()
-                        .bits8()
-                        // 8-bit peripheral size
-                        .psize()
-                        .bits8()
+                        .bits(priority)
                         // circular mode disabled
                         .circ()
                         .disabled()
@@ -1327,41 +1736,88 @@ macro_rules! spi_dma {
                 });
                 self.start();
 
-                Transfer::w(buffer, self)
+                Ok(Transfer::w(buffer, self))
             }
         }
 
-        impl<B, const XFER_MODE : TransferMode,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> crate::dma::WriteDma<B, u8>
-            for SpiTxDma<$SPIi, XFER_MODE, TXCH>
+        /// Continuous reception, the master keeps clocking until the [CircBuffer](crate::dma::CircBuffer) is stopped
+        ///
+        /// Only available in receive-only mode, as a full-duplex master only clocks while transmitting.
+        impl<B, FRAME: FrameSize, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel> crate::dma::CircReadDma<B, FRAME>
+            for SpiRxDma<$SPIi, {TransferMode::TransferModeRecieveOnly}, RXCH, FRAME>
         where
-            B: ReadBuffer<Word = u8>,
+            &'static mut [B; 2]: WriteBuffer<Word = FRAME>,
+            B: 'static,
         {
-            fn write(mut self, buffer: B) -> Transfer<R, B, Self> {
+            fn try_circ_read(
+                mut self,
+                mut buffer: &'static mut [B; 2],
+            ) -> Result<crate::dma::CircBuffer<B, Self>, (crate::dma::Error, Self, &'static mut [B; 2])> {
+                // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                // until the end of the transfer.
+                let (ptr, len) = unsafe { buffer.write_buffer() };
+                if let Err(error) = self.channel.try_set_transfer_length(len) {
+                    return Err((error, self, buffer));
+                }
+                self.channel.set_peripheral_address(
+                    unsafe { (*<$SPIi>::ptr()).dat().as_ptr() as u32 },
+                    false,
+                );
+                self.channel.set_memory_address(ptr as u32, true);
+
+                atomic::compiler_fence(Ordering::Release);
+                self.channel.set_word_size(FRAME::SIZE, FRAME::SIZE);
+                let priority = self.channel.priority_or(crate::dma::Priority::Medium) as u8;
+                self.channel.st().chcfg().modify(|_, w| {
+                    w
+                        // memory to memory mode disabled
+                        .mem2mem()
+                        .disabled()
+                        // channel priority level, medium unless set with DMAChannel::set_priority
+                        .priolvl()
+                        .bits(priority)
+                        // circular mode enabled
+                        .circ()
+                        .enabled()
+                        // write to memory
+                        .dir()
+                        .from_peripheral()
+                });
+                self.start();
+
+                Ok(crate::dma::CircBuffer::new(buffer, self))
+            }
+        }
+
+        impl<B, FRAME: FrameSize, const XFER_MODE : TransferMode,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> crate::dma::WriteDma<B, FRAME>
+            for SpiTxDma<$SPIi, XFER_MODE, TXCH, FRAME>
+        where
+            B: ReadBuffer<Word = FRAME>,
+        {
+            fn try_write(mut self, buffer: B) -> Result<Transfer<R, B, Self>, (crate::dma::Error, Self, B)> {
                 // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
                 // until the end of the transfer.
                 let (ptr, len) = unsafe { buffer.read_buffer() };
+                if let Err(error) = self.channel.try_set_transfer_length(len) {
+                    return Err((error, self, buffer));
+                }
                 self.channel.set_peripheral_address(
                     unsafe { (*<$SPIi>::ptr()).dat().as_ptr() as u32 },
                     false,
                 );
                 self.channel.set_memory_address(ptr as u32, true);
-                self.channel.set_transfer_length(len);
 
                 atomic::compiler_fence(Ordering::Release);
+                self.channel.set_word_size(FRAME::SIZE, FRAME::SIZE);
+                let priority = self.channel.priority_or(crate::dma::Priority::Medium) as u8;
                 self.channel.st().chcfg().modify(|_, w| {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
                         .disabled()
-                        // medium channel priority level
+                        // channel priority level, medium unless set with DMAChannel::set_priority
                         .priolvl()
-                        .medium()
-                        // 8-bit memory size
-                        .msize()
-                        .bits8()
-                        // 8-bit peripheral size
-                        .psize()
-                        .bits8()
+                        .bits(priority)
                         // circular mode disabled
                         .circ()
                         .disabled()
@@ -1371,28 +1827,34 @@ macro_rules! spi_dma {
                 });
                 self.start();
 
-                Transfer::r(buffer, self)
+                Ok(Transfer::r(buffer, self))
             }
         }
 
-        impl<RXB, TXB, const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> crate::dma::ReadWriteDma<RXB, TXB, u8>
-            for SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH>
+        impl<RXB, TXB, FRAME: FrameSize, const XFER_MODE : TransferMode, RXCH: crate::dma::CompatibleChannel<$SPIi,R> + crate::dma::DMAChannel,TXCH: crate::dma::CompatibleChannel<$SPIi,W> + crate::dma::DMAChannel> crate::dma::ReadWriteDma<RXB, TXB, FRAME>
+            for SpiRxTxDma<$SPIi, XFER_MODE, RXCH, TXCH, FRAME>
         where
-            RXB: WriteBuffer<Word = u8>,
-            TXB: ReadBuffer<Word = u8>,
+            RXB: WriteBuffer<Word = FRAME>,
+            TXB: ReadBuffer<Word = FRAME>,
         {
-            fn read_write(
+            fn try_read_write(
                 mut self,
                 mut rxbuffer: RXB,
                 txbuffer: TXB,
-            ) -> Transfer<W, (RXB, TXB), Self> {
+            ) -> Result<Transfer<W, (RXB, TXB), Self>, (crate::dma::Error, Self, (RXB, TXB))> {
                 // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
                 // until the end of the transfer.
                 let (rxptr, rxlen) = unsafe { rxbuffer.write_buffer() };
                 let (txptr, txlen) = unsafe { txbuffer.read_buffer() };
 
                 if rxlen != txlen {
-                    panic!("receive and send buffer lengths do not match!");
+                    return Err((crate::dma::Error::LengthMismatch, self, (rxbuffer, txbuffer)));
+                }
+                if let Err(error) = self.rxchannel.try_set_transfer_length(rxlen) {
+                    return Err((error, self, (rxbuffer, txbuffer)));
+                }
+                if let Err(error) = self.txchannel.try_set_transfer_length(txlen) {
+                    return Err((error, self, (rxbuffer, txbuffer)));
                 }
 
                 self.rxchannel.set_peripheral_address(
@@ -1400,30 +1862,24 @@ macro_rules! spi_dma {
                     false,
                 );
                 self.rxchannel.set_memory_address(rxptr as u32, true);
-                self.rxchannel.set_transfer_length(rxlen);
 
                 self.txchannel.set_peripheral_address(
                     unsafe { (*<$SPIi>::ptr()).dat().as_ptr() as u32 },
                     false,
                 );
                 self.txchannel.set_memory_address(txptr as u32, true);
-                self.txchannel.set_transfer_length(txlen);
 
                 atomic::compiler_fence(Ordering::Release);
+                self.rxchannel.set_word_size(FRAME::SIZE, FRAME::SIZE);
+                let priority = self.rxchannel.priority_or(crate::dma::Priority::Medium) as u8;
                 self.rxchannel.st().chcfg().modify(|_, w| {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
                         .disabled()
-                        // medium channel priority level
+                        // channel priority level, medium unless set with DMAChannel::set_priority
                         .priolvl()
-                        .medium()
-                        // 8-bit memory size
-                        .msize()
-                        .bits8()
-                        // 8-bit peripheral size
-                        .psize()
-                        .bits8()
+                        .bits(priority)
                         // circular mode disabled
                         .circ()
                         .disabled()
@@ -1431,20 +1887,16 @@ macro_rules! spi_dma {
                         .dir()
                         .from_peripheral()
                 });
+                self.txchannel.set_word_size(FRAME::SIZE, FRAME::SIZE);
+                let priority = self.txchannel.priority_or(crate::dma::Priority::Medium) as u8;
                 self.txchannel.st().chcfg().modify(|_, w| {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
                         .disabled()
-                        // medium channel priority level
+                        // channel priority level, medium unless set with DMAChannel::set_priority
                         .priolvl()
-                        .medium()
-                        // 8-bit memory size
-                        .msize()
-                        .bits8()
-                        // 8-bit peripheral size
-                        .psize()
-                        .bits8()
+                        .bits(priority)
                         // circular mode disabled
                         .circ()
                         .disabled()
@@ -1454,7 +1906,7 @@ macro_rules! spi_dma {
                 });
                 self.start();
 
-                Transfer::w((rxbuffer, txbuffer), self)
+                Ok(Transfer::w((rxbuffer, txbuffer), self))
             }
         }
     };
@@ -1472,6 +1924,7 @@ spi_dma!(
     Spi2TxDma,
     Spi2RxTxDma
 );
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
 spi_dma!(
     pac::Spi3,
     Spi3RxDma,
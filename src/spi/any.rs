@@ -0,0 +1,196 @@
+//! SPI master with its transfer mode chosen at runtime
+//!
+//! The transfer mode of a [Spi] is part of its type, so masters set up for different
+//! devices are different types. [AnySpi] holds a master in any of the modes and switches
+//! between them at runtime, at the cost of a match on each call:
+//!
+//! ```rust
+//! struct Devices {
+//!     spi: AnySpi<Spi1>,
+//! }
+//!
+//! // 4-wire flash
+//! devices.spi.set_transfer_mode(TransferMode::TransferModeNormal);
+//! devices.spi.transfer(&mut id, &[0x9f, 0, 0, 0])?;
+//! // 3-wire sensor sharing SCK and MOSI
+//! devices.spi.set_transfer_mode(TransferMode::TransferModeBidirectional);
+//! devices.spi.write(&[0x80 | REG])?;
+//! devices.spi.read(&mut value)?;
+//! ```
+
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+use super::{Error, FrameSize, Inner, Instance, Spi, TransferMode};
+use crate::rcc::Clocks;
+use crate::time::Hertz;
+
+/// [Spi] master in a transfer mode chosen at runtime
+#[derive(Debug)]
+pub enum AnySpi<SPI: Instance, W = u8> {
+    Normal(Spi<SPI, { TransferMode::TransferModeNormal }, W>),
+    Bidirectional(Spi<SPI, { TransferMode::TransferModeBidirectional }, W>),
+    ReceiveOnly(Spi<SPI, { TransferMode::TransferModeRecieveOnly }, W>),
+}
+
+macro_rules! any {
+    ($self:expr, $spi:ident => $e:expr) => {
+        match $self {
+            AnySpi::Normal($spi) => $e,
+            AnySpi::Bidirectional($spi) => $e,
+            AnySpi::ReceiveOnly($spi) => $e,
+        }
+    };
+}
+
+impl<SPI: Instance, W: FrameSize> AnySpi<SPI, W> {
+    /// Current transfer mode
+    pub fn transfer_mode(&self) -> TransferMode {
+        match self {
+            AnySpi::Normal(_) => TransferMode::TransferModeNormal,
+            AnySpi::Bidirectional(_) => TransferMode::TransferModeBidirectional,
+            AnySpi::ReceiveOnly(_) => TransferMode::TransferModeRecieveOnly,
+        }
+    }
+
+    /// Converts the master to `mode`, once the bus is idle
+    ///
+    /// A master transmits with `TransferModeNormal`, so `TransferModeTransmitOnly` selects it.
+    pub fn into_transfer_mode(self, mode: TransferMode) -> Self {
+        if mode == self.transfer_mode()
+            || (mode == TransferMode::TransferModeTransmitOnly && matches!(self, AnySpi::Normal(_)))
+        {
+            return self;
+        }
+        any!(self, spi => {
+            while spi.is_busy() {}
+            match mode {
+                TransferMode::TransferModeNormal | TransferMode::TransferModeTransmitOnly => {
+                    AnySpi::Normal(spi.into_mode())
+                }
+                TransferMode::TransferModeBidirectional => AnySpi::Bidirectional(spi.into_mode()),
+                TransferMode::TransferModeRecieveOnly => AnySpi::ReceiveOnly(spi.into_mode()),
+            }
+        })
+    }
+
+    /// Switches the master to `mode` in place, see [into_transfer_mode](Self::into_transfer_mode)
+    pub fn set_transfer_mode(&mut self, mode: TransferMode) {
+        // NOTE(unsafe) the conversion only writes registers and can't panic, so `self` is
+        // always written back
+        unsafe {
+            let spi = ptr::read(self);
+            ptr::write(self, spi.into_transfer_mode(mode));
+        }
+    }
+
+    pub fn read_nonblocking(&mut self) -> nb::Result<W, Error> {
+        any!(self, spi => spi.read_nonblocking())
+    }
+
+    pub fn write_nonblocking(&mut self, word: W) -> nb::Result<(), Error> {
+        any!(self, spi => spi.write_nonblocking(word))
+    }
+
+    pub fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Error> {
+        any!(self, spi => spi.transfer_in_place(words))
+    }
+
+    pub fn transfer(&mut self, buff: &mut [W], data: &[W]) -> Result<(), Error> {
+        any!(self, spi => spi.transfer(buff, data))
+    }
+
+    pub fn write(&mut self, words: &[W]) -> Result<(), Error> {
+        any!(self, spi => spi.write(words))
+    }
+
+    pub fn read(&mut self, words: &mut [W]) -> Result<(), Error> {
+        any!(self, spi => spi.read(words))
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Changes the SCK frequency, see [Spi::set_frequency]
+    pub fn set_frequency(&mut self, freq: Hertz, clocks: &Clocks) {
+        any!(self, spi => spi.set_frequency(freq, clocks))
+    }
+
+    /// Returns the bus clock the SPI was set up with
+    pub fn clock(&self) -> Hertz {
+        any!(self, spi => spi.clock())
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn release(self) -> (SPI, (SPI::Sck, SPI::Miso, SPI::Mosi, Option<SPI::Nss>)) {
+        any!(self, spi => spi.release())
+    }
+}
+
+impl<SPI: Instance, W> Deref for AnySpi<SPI, W> {
+    type Target = Inner<SPI>;
+    fn deref(&self) -> &Self::Target {
+        any!(self, spi => &spi.inner)
+    }
+}
+
+impl<SPI: Instance, W> DerefMut for AnySpi<SPI, W> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        any!(self, spi => &mut spi.inner)
+    }
+}
+
+impl<SPI: Instance, W: FrameSize> From<Spi<SPI, { TransferMode::TransferModeNormal }, W>> for AnySpi<SPI, W> {
+    fn from(spi: Spi<SPI, { TransferMode::TransferModeNormal }, W>) -> Self {
+        AnySpi::Normal(spi)
+    }
+}
+
+impl<SPI: Instance, W: FrameSize> From<Spi<SPI, { TransferMode::TransferModeBidirectional }, W>> for AnySpi<SPI, W> {
+    fn from(spi: Spi<SPI, { TransferMode::TransferModeBidirectional }, W>) -> Self {
+        AnySpi::Bidirectional(spi)
+    }
+}
+
+impl<SPI: Instance, W: FrameSize> From<Spi<SPI, { TransferMode::TransferModeRecieveOnly }, W>> for AnySpi<SPI, W> {
+    fn from(spi: Spi<SPI, { TransferMode::TransferModeRecieveOnly }, W>) -> Self {
+        AnySpi::ReceiveOnly(spi)
+    }
+}
+
+impl<SPI: Instance, W> embedded_hal::spi::ErrorType for AnySpi<SPI, W> {
+    type Error = Error;
+}
+
+impl<SPI: Instance, W: FrameSize> embedded_hal_nb::spi::FullDuplex<W> for AnySpi<SPI, W> {
+    fn read(&mut self) -> nb::Result<W, Error> {
+        self.read_nonblocking()
+    }
+
+    fn write(&mut self, word: W) -> nb::Result<(), Error> {
+        self.write_nonblocking(word)
+    }
+}
+
+impl<SPI: Instance, W: FrameSize + 'static> embedded_hal::spi::SpiBus<W> for AnySpi<SPI, W> {
+    fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        self.transfer_in_place(words)
+    }
+
+    fn transfer(&mut self, buff: &mut [W], data: &[W]) -> Result<(), Self::Error> {
+        self.transfer(buff, data)
+    }
+
+    fn read(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        self.read(words)
+    }
+
+    fn write(&mut self, words: &[W]) -> Result<(), Self::Error> {
+        self.write(words)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.flush()
+    }
+}
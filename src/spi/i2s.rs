@@ -0,0 +1,308 @@
+//! I2S master mode of SPI2 and SPI3
+//!
+//! Both drive an audio codec as I2S master, and can output the master clock (MCK) of the
+//! codec on a dedicated pin, at 256 times the sample rate. The bit clock is divided from
+//! SYSCLK by an 8-bit prescaler, so most sample rates are only approximated: [SampleRate]
+//! reports the error for the actual clocks, and [I2sConfig::max_error_ppm] rejects
+//! configurations too far off.
+//!
+//! ```rust
+//! let config = I2sConfig::default().sample_rate(48.kHz()).master_clock(true);
+//! // 48 kHz from a 144 MHz SYSCLK with MCK is 46.875 kHz, 23437 ppm off
+//! let rate = config.check(&clocks)?;
+//! assert!(config.max_error_ppm(1000).check(&clocks).is_err());
+//!
+//! let mut i2s = I2s::new(dp.Spi2, (gpiob.pb12, gpiob.pb13, gpiob.pb15), gpioc.pc6, config, &clocks)?;
+//! nb::block!(i2s.write(left))?;
+//! nb::block!(i2s.write(right))?;
+//! ```
+
+use super::Instance;
+use crate::gpio::alt::{I2sCommon, I2sMaster};
+use crate::pac::spi1::i2scfg;
+use crate::rcc::Clocks;
+use fugit::HertzU32 as Hertz;
+use fugit::RateExtU32;
+
+/// SPI instance with an I2S mode
+pub trait I2sInstance: Instance + I2sCommon + I2sMaster {}
+
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+impl I2sInstance for crate::pac::Spi2 {}
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+impl I2sInstance for crate::pac::Spi3 {}
+
+/// Direction of the I2S master
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2sMode {
+    Transmit = 0b10,
+    Receive = 0b11,
+}
+
+/// Frame format
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2sStandard {
+    Philips,
+    MsbJustified,
+    LsbJustified,
+    /// PCM with a one bit frame sync
+    PcmShort,
+    /// PCM with a 13 bit frame sync
+    PcmLong,
+}
+
+/// Data and channel lengths
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Data16Channel16,
+    Data16Channel32,
+    Data24Channel32,
+    Data32Channel32,
+}
+
+impl DataFormat {
+    fn channel_bits(self) -> u32 {
+        match self {
+            DataFormat::Data16Channel16 => 16,
+            _ => 32,
+        }
+    }
+}
+
+/// Configuration of the I2S master
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct I2sConfig {
+    pub mode: I2sMode,
+    pub standard: I2sStandard,
+    pub format: DataFormat,
+    /// Idle level of the bit clock is high
+    pub clock_idle_high: bool,
+    pub sample_rate: Hertz,
+    /// Output MCK at 256 times the sample rate
+    pub master_clock: bool,
+    /// Largest accepted error of the sample rate, in parts per million
+    pub max_error_ppm: Option<u32>,
+}
+
+impl Default for I2sConfig {
+    /// Philips standard 16-bit transmitter at 48 kHz, without MCK
+    fn default() -> Self {
+        Self {
+            mode: I2sMode::Transmit,
+            standard: I2sStandard::Philips,
+            format: DataFormat::Data16Channel16,
+            clock_idle_high: false,
+            sample_rate: 48.kHz(),
+            master_clock: false,
+            max_error_ppm: None,
+        }
+    }
+}
+
+impl I2sConfig {
+    pub fn mode(mut self, mode: I2sMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn standard(mut self, standard: I2sStandard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    pub fn format(mut self, format: DataFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn clock_idle_high(mut self, high: bool) -> Self {
+        self.clock_idle_high = high;
+        self
+    }
+
+    pub fn sample_rate(mut self, rate: Hertz) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    pub fn master_clock(mut self, enable: bool) -> Self {
+        self.master_clock = enable;
+        self
+    }
+
+    pub fn max_error_ppm(mut self, ppm: u32) -> Self {
+        self.max_error_ppm = Some(ppm);
+        self
+    }
+
+    /// Returns the sample rate these `clocks` give, or why they can't
+    pub fn check(&self, clocks: &Clocks) -> Result<SampleRate, I2sError> {
+        self.prescaler(clocks).map(|(_, rate)| rate)
+    }
+
+    /// Returns the I2SPREDIV value and the sample rate
+    fn prescaler(&self, clocks: &Clocks) -> Result<(u32, SampleRate), I2sError> {
+        // Bit clock periods per sample, MCK is always 256 times the sample rate
+        let frame = if self.master_clock { 256 } else { 2 * self.format.channel_bits() };
+        let clk = clocks.sysclk.raw() as u64;
+        let requested = self.sample_rate.raw() as u64;
+        if requested == 0 {
+            return Err(I2sError::SampleRateOutOfRange);
+        }
+        // 2 * I2SDIV + ODD, rounded to nearest
+        let divider = (2 * clk + frame as u64 * requested) / (2 * frame as u64 * requested);
+        if !(4..=511).contains(&divider) {
+            return Err(I2sError::SampleRateOutOfRange);
+        }
+        let rate = SampleRate {
+            requested: self.sample_rate,
+            actual: Hertz::from_raw((clk / (frame as u64 * divider)) as u32),
+        };
+        if let Some(max) = self.max_error_ppm {
+            if rate.error_ppm().unsigned_abs() > max {
+                return Err(I2sError::SampleRateError(rate));
+            }
+        }
+        let prediv = ((self.master_clock as u32) << 9) | (((divider & 1) as u32) << 8) | (divider >> 1) as u32;
+        Ok((prediv, rate))
+    }
+
+    /// Writes the I2SCFG fields, without enabling the I2S
+    fn write_i2scfg<'w>(&self, w: &'w mut i2scfg::W) -> &'w mut i2scfg::W {
+        let (std, pcmsync) = match self.standard {
+            I2sStandard::Philips => (0b00, false),
+            I2sStandard::MsbJustified => (0b01, false),
+            I2sStandard::LsbJustified => (0b10, false),
+            I2sStandard::PcmShort => (0b11, false),
+            I2sStandard::PcmLong => (0b11, true),
+        };
+        let (datlen, chlen) = match self.format {
+            DataFormat::Data16Channel16 => (0b00, false),
+            DataFormat::Data16Channel32 => (0b00, true),
+            DataFormat::Data24Channel32 => (0b01, true),
+            DataFormat::Data32Channel32 => (0b10, true),
+        };
+        unsafe {
+            // I2S instead of SPI
+            w.modsel().set_bit();
+            w.modcfg().bits(self.mode as u8);
+            w.pcmfsync().bit(pcmsync);
+            w.stdsel().bits(std);
+            w.clkpol().bit(self.clock_idle_high);
+            w.datlen().bits(datlen);
+            w.chlen().bit(chlen)
+        }
+    }
+}
+
+/// Sample rate achieved for a requested one
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleRate {
+    pub requested: Hertz,
+    pub actual: Hertz,
+}
+
+impl SampleRate {
+    /// Deviation of the actual rate from the requested one, in parts per million
+    pub fn error_ppm(&self) -> i32 {
+        let requested = self.requested.raw() as i64;
+        ((self.actual.raw() as i64 - requested) * 1_000_000 / requested) as i32
+    }
+}
+
+/// I2S error
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2sError {
+    /// The prescaler can't divide SYSCLK down to the sample rate
+    SampleRateOutOfRange,
+    /// The achievable sample rate is further off than [I2sConfig::max_error_ppm]
+    SampleRateError(SampleRate),
+    /// The receiver wasn't read in time
+    Overrun,
+    /// The transmitter ran out of data
+    Underrun,
+}
+
+/// I2S master on an SPI instance
+pub struct I2s<SPI: I2sInstance> {
+    spi: SPI,
+    pins: (SPI::Ws, SPI::Ck, SPI::Sd, SPI::Mck),
+    rate: SampleRate,
+}
+
+impl<SPI: I2sInstance> I2s<SPI> {
+    /// Enables the SPI clock, resets the peripheral and starts it as I2S master
+    ///
+    /// `mck` only outputs the master clock with [I2sConfig::master_clock], pass `NoPin`
+    /// otherwise. Samples are exchanged in 16-bit halves, the left channel first.
+    pub fn new(
+        spi: SPI,
+        pins: (impl Into<SPI::Ws>, impl Into<SPI::Ck>, impl Into<SPI::Sd>),
+        mck: impl Into<SPI::Mck>,
+        config: I2sConfig,
+        clocks: &Clocks,
+    ) -> Result<Self, I2sError> {
+        let (prediv, rate) = config.prescaler(clocks)?;
+
+        unsafe {
+            SPI::enable_unchecked();
+            SPI::reset_unchecked();
+        }
+
+        let pins = (pins.0.into(), pins.1.into(), pins.2.into(), mck.into());
+        // I2SCFG and I2SPREDIV are configured while I2SE is clear
+        spi.i2sprediv().write(|w| unsafe { w.bits(prediv) });
+        spi.i2scfg().write(|w| config.write_i2scfg(w));
+        spi.i2scfg().modify(|_, w| w.i2sen().set_bit());
+
+        Ok(Self { spi, pins, rate })
+    }
+
+    /// Sample rate of the actual clocks
+    pub fn sample_rate(&self) -> SampleRate {
+        self.rate
+    }
+
+    /// Queues the next 16 bits to transmit
+    pub fn write(&mut self, half: u16) -> nb::Result<(), I2sError> {
+        let sts = self.spi.sts().read();
+        // Cleared by the read of STS
+        if sts.under().bit_is_set() {
+            return Err(nb::Error::Other(I2sError::Underrun));
+        }
+        if sts.te().bit_is_set() {
+            self.spi.dat().write(|w| unsafe { w.bits(half as u32) });
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Returns the next 16 bits received
+    pub fn read(&mut self) -> nb::Result<u16, I2sError> {
+        let sts = self.spi.sts().read();
+        if sts.over().bit_is_set() {
+            // Cleared by reading DAT then STS
+            let _ = self.spi.dat().read();
+            let _ = self.spi.sts().read();
+            return Err(nb::Error::Other(I2sError::Overrun));
+        }
+        if sts.rne().bit_is_set() {
+            Ok(self.spi.dat().read().bits() as u16)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Stops the I2S master and releases the peripheral and the pins
+    pub fn release(self) -> (SPI, (SPI::Ws, SPI::Ck, SPI::Sd, SPI::Mck)) {
+        self.spi.i2scfg().modify(|_, w| w.i2sen().clear_bit());
+        (self.spi, self.pins)
+    }
+}
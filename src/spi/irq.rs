@@ -0,0 +1,110 @@
+use enumflags2::BitFlags;
+
+use super::{Error, Event, FrameSize, Instance, Spi, TransferMode};
+use crate::{ClearFlags, Listen};
+
+/// Full-duplex transfer kept going by the SPI interrupts
+///
+/// The buffer is sent and overwritten in place with the received frames. The interrupt
+/// handler moves one frame in each direction per call, and at most two frames are in flight,
+/// so the interrupt has to be serviced within one frame time to avoid an overrun. This suits
+/// medium-sized transfers when no DMA channel is left but busy-waiting costs too much.
+///
+/// ```rust
+/// let mut transfer = spi.transfer_irq([0u8; 32]);
+///
+/// // SPI1 interrupt
+/// transfer.on_interrupt();
+///
+/// // later
+/// if transfer.is_done() {
+///     let (spi, buffer, result) = transfer.release();
+/// }
+/// ```
+pub struct IrqTransfer<SPI: Instance, W, BUF> {
+    spi: Spi<SPI, { TransferMode::TransferModeNormal }, W>,
+    buffer: BUF,
+    sent: usize,
+    received: usize,
+    result: Option<Result<(), Error>>,
+}
+
+impl<SPI: Instance, W: FrameSize> Spi<SPI, { TransferMode::TransferModeNormal }, W> {
+    /// Starts transferring `buffer` from the SPI interrupt
+    ///
+    /// The SPI interrupt still has to be unmasked in the NVIC.
+    pub fn transfer_irq<BUF: AsMut<[W]>>(self, buffer: BUF) -> IrqTransfer<SPI, W, BUF> {
+        let mut transfer = IrqTransfer {
+            spi: self,
+            buffer,
+            sent: 0,
+            received: 0,
+            result: None,
+        };
+        if transfer.buffer.as_mut().is_empty() {
+            transfer.result = Some(Ok(()));
+        } else {
+            transfer.spi.listen(Event::RxNotEmpty | Event::TxEmpty | Event::Error);
+        }
+        transfer
+    }
+}
+
+impl<SPI: Instance, W: FrameSize, BUF: AsMut<[W]>> IrqTransfer<SPI, W, BUF> {
+    /// Moves the next frames, call this from the SPI interrupt
+    pub fn on_interrupt(&mut self) {
+        if self.result.is_some() {
+            return;
+        }
+        let buffer = self.buffer.as_mut();
+        match self.spi.check_read::<W>() {
+            Ok(frame) => {
+                buffer[self.received] = frame;
+                self.received += 1;
+            }
+            Err(nb::Error::Other(e)) => {
+                self.spi.clear_flags(BitFlags::ALL);
+                self.finish(Err(e));
+                return;
+            }
+            Err(nb::Error::WouldBlock) => {}
+        }
+        if self.received == buffer.len() {
+            self.finish(Ok(()));
+            return;
+        }
+        // One frame in the shift register and one in the data register at most, so the
+        // receiver never falls more than one frame behind
+        if self.sent < buffer.len() && self.sent - self.received < 2 && self.spi.is_tx_empty() {
+            self.spi.write_data_reg(buffer[self.sent]);
+            self.sent += 1;
+        }
+        if self.sent < buffer.len() && self.sent - self.received < 2 {
+            self.spi.listen(Event::TxEmpty);
+        } else {
+            self.spi.unlisten(Event::TxEmpty);
+        }
+    }
+
+    fn finish(&mut self, result: Result<(), Error>) {
+        self.spi.unlisten(BitFlags::ALL);
+        self.result = Some(result);
+    }
+
+    /// Returns `true` once every frame was received or an error stopped the transfer
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Number of frames received so far
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// Stops the transfer and releases the SPI and the buffer, with the result of the transfer
+    /// if it is done
+    pub fn release(mut self) -> (Spi<SPI, { TransferMode::TransferModeNormal }, W>, BUF, Option<Result<(), Error>>) {
+        self.spi.unlisten(BitFlags::ALL);
+        (self.spi, self.buffer, self.result)
+    }
+}
@@ -0,0 +1,163 @@
+use core::cell::{Cell, RefCell};
+
+use cortex_m::interrupt::{self, Mutex};
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+use super::{baud_rate_bits, Error, Instance, Mode, Spi, TransferMode};
+use crate::rcc::{BusClock, Clocks};
+use crate::time::Hertz;
+
+/// Bus settings applied before each transaction of a [SharedDevice]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceConfig {
+    /// SPI mode of the device
+    pub mode: Mode,
+    /// Highest SCK frequency of the device
+    pub frequency: Hertz,
+}
+
+/// SPI master shared by several devices
+///
+/// Each [SharedDevice] has its own chip select and optionally its own [DeviceConfig], and
+/// implements [SpiDevice]. Devices take the bus for the whole of a transaction, a device
+/// finding the bus in use waits for it to be returned. The bus can also be taken out for a
+/// DMA transfer with [SharedDevice::take_bus], the other devices then wait until it's given
+/// back with [SharedDevice::return_bus].
+///
+/// Waiting for the bus from an interrupt that preempted its user never ends, so devices of
+/// one bus should be used from a single priority level.
+///
+/// ```rust
+/// let spi = dp.Spi1.spi((sck, miso, mosi), embedded_hal::spi::MODE_0, 8.MHz(), &clocks);
+/// let bus = SharedBus::new(spi, &clocks);
+/// let mut flash = bus.device(flash_cs, None);
+/// let mut adc = bus.device(adc_cs, Some(DeviceConfig { mode: embedded_hal::spi::MODE_1.into(), frequency: 1.MHz() }));
+/// ```
+pub struct SharedBus<SPI: Instance, const XFER_MODE: TransferMode = { TransferMode::TransferModeNormal }> {
+    spi: Mutex<RefCell<Option<Spi<SPI, XFER_MODE, u8>>>>,
+    /// Settings currently applied to the bus
+    config: Mutex<Cell<DeviceConfig>>,
+    /// Settings the bus was created with, used by devices without their own
+    default: DeviceConfig,
+    pclk: Hertz,
+    sysclk: Hertz,
+}
+
+impl<SPI: Instance, const XFER_MODE: TransferMode> SharedBus<SPI, XFER_MODE> {
+    /// Shares `spi`, its current mode and frequency are the default device settings
+    pub fn new(spi: Spi<SPI, XFER_MODE, u8>, clocks: &Clocks) -> Self {
+        let pclk = SPI::clock(clocks);
+        let default = DeviceConfig {
+            mode: spi.mode(),
+            frequency: pclk / (2u32 << spi.spi.ctrl1().read().br().bits()),
+        };
+        Self {
+            spi: Mutex::new(RefCell::new(Some(spi))),
+            config: Mutex::new(Cell::new(default)),
+            default,
+            pclk,
+            sysclk: clocks.sysclk(),
+        }
+    }
+
+    /// Creates a device selected by `cs`, using `config` or the bus defaults
+    pub fn device<CS: OutputPin>(&self, mut cs: CS, config: Option<DeviceConfig>) -> SharedDevice<'_, SPI, XFER_MODE, CS> {
+        let _ = cs.set_high();
+        SharedDevice {
+            bus: self,
+            cs,
+            config: config.unwrap_or(self.default),
+        }
+    }
+
+    /// Returns the SPI master, `None` if it's taken out by a device
+    pub fn release(self) -> Option<Spi<SPI, XFER_MODE, u8>> {
+        self.spi.into_inner().into_inner()
+    }
+
+    /// Waits for the bus and applies `config` to it
+    fn acquire(&self, config: DeviceConfig) -> Spi<SPI, XFER_MODE, u8> {
+        let mut spi = loop {
+            if let Some(spi) = interrupt::free(|cs| self.spi.borrow(cs).borrow_mut().take()) {
+                break spi;
+            }
+        };
+        let current = interrupt::free(|cs| self.config.borrow(cs).replace(config));
+        if current != config {
+            spi.reconfigure(config.mode, baud_rate_bits(config.frequency, self.pclk));
+        }
+        spi
+    }
+
+    fn give_back(&self, spi: Spi<SPI, XFER_MODE, u8>) {
+        interrupt::free(|cs| *self.spi.borrow(cs).borrow_mut() = Some(spi));
+    }
+}
+
+/// Device on a [SharedBus]
+pub struct SharedDevice<'a, SPI: Instance, const XFER_MODE: TransferMode, CS> {
+    bus: &'a SharedBus<SPI, XFER_MODE>,
+    cs: CS,
+    config: DeviceConfig,
+}
+
+impl<SPI: Instance, const XFER_MODE: TransferMode, CS: OutputPin> SharedDevice<'_, SPI, XFER_MODE, CS> {
+    /// Takes the bus out with this device selected, e.g. to start a DMA transfer
+    ///
+    /// Other devices wait until the bus is given back with [SharedDevice::return_bus].
+    pub fn take_bus(&mut self) -> Spi<SPI, XFER_MODE, u8> {
+        let spi = self.bus.acquire(self.config);
+        let _ = self.cs.set_low();
+        spi
+    }
+
+    /// Deselects this device once the bus is idle and gives the bus back
+    pub fn return_bus(&mut self, spi: Spi<SPI, XFER_MODE, u8>) {
+        while spi.is_busy() {}
+        let _ = self.cs.set_high();
+        self.bus.give_back(spi);
+    }
+
+    /// Releases the chip select pin
+    pub fn release(self) -> CS {
+        self.cs
+    }
+
+    fn run(&mut self, spi: &mut Spi<SPI, XFER_MODE, u8>, operations: &mut [Operation<'_, u8>]) -> Result<(), Error> {
+        for op in operations {
+            match op {
+                Operation::Read(words) => spi.read(words)?,
+                Operation::Write(words) => spi.write(words)?,
+                Operation::Transfer(read, write) => {
+                    // The longer buffer sets the length, missing words are sent as 0 or dropped
+                    let common = read.len().min(write.len());
+                    spi.transfer(&mut read[..common], &write[..common])?;
+                    spi.write(&write[common..])?;
+                    spi.read(&mut read[common..])?;
+                }
+                Operation::TransferInPlace(words) => spi.transfer_in_place(words)?,
+                Operation::DelayNs(ns) => {
+                    while spi.is_busy() {}
+                    let cycles = (self.bus.sysclk.raw() as u64 * *ns as u64 + 999_999_999) / 1_000_000_000;
+                    cortex_m::asm::delay(cycles as u32);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<SPI: Instance, const XFER_MODE: TransferMode, CS> ErrorType for SharedDevice<'_, SPI, XFER_MODE, CS> {
+    type Error = Error;
+}
+
+impl<SPI: Instance, const XFER_MODE: TransferMode, CS: OutputPin> SpiDevice for SharedDevice<'_, SPI, XFER_MODE, CS> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Error> {
+        let mut spi = self.take_bus();
+        let result = self.run(&mut spi, operations);
+        self.return_bus(spi);
+        result
+    }
+}
@@ -0,0 +1,123 @@
+use embedded_hal::digital::OutputPin;
+
+use super::{Error, Instance, Spi, TransferMode};
+use crate::rcc::{BusClock, Clocks};
+
+/// Half-duplex (3-wire) master for register based devices
+///
+/// Takes care of the direction turnaround on the shared data line: the bus is idle
+/// before the direction is switched, and in receive mode the clock is stopped after
+/// each frame so no extra frames are clocked out of the device.
+///
+/// ```rust
+/// let spi = dp.Spi1.spi_bidi((sck, mosi), spi::MODE_3, 1.MHz(), &clocks);
+/// let mut sensor = ThreeWire::new(spi, cs, &clocks);
+/// let id = sensor.read_register(0x0F)?;
+/// ```
+pub struct ThreeWire<SPI: Instance, CS> {
+    spi: Spi<SPI, { TransferMode::TransferModeBidirectional }, u8>,
+    cs: CS,
+    read_flag: u8,
+    dummy_bytes: u8,
+    cycles_per_clock: u32,
+}
+
+impl<SPI: Instance, CS: OutputPin> ThreeWire<SPI, CS> {
+    /// Wraps a bidirectional SPI master and its chip select pin
+    ///
+    /// By default bit 7 of the address marks a read and no dummy bytes are expected.
+    pub fn new(spi: Spi<SPI, { TransferMode::TransferModeBidirectional }, u8>, mut cs: CS, clocks: &Clocks) -> Self {
+        let _ = cs.set_high();
+        // One SCK period in core clock cycles
+        let br = spi.spi.ctrl1().read().br().bits() as u32;
+        let cycles_per_clock = (clocks.sysclk().raw() / SPI::clock(clocks).raw()) << (br + 1);
+        Self {
+            spi,
+            cs,
+            read_flag: 0x80,
+            dummy_bytes: 0,
+            cycles_per_clock,
+        }
+    }
+
+    /// Sets the bits or-ed into the address of read accesses
+    pub fn read_flag(mut self, flag: u8) -> Self {
+        self.read_flag = flag;
+        self
+    }
+
+    /// Sets the number of dummy frames the device sends between the address and the data of a read
+    pub fn dummy_bytes(mut self, count: u8) -> Self {
+        self.dummy_bytes = count;
+        self
+    }
+
+    /// Writes `value` to the register at `addr`
+    pub fn write_register(&mut self, addr: u8, value: u8) -> Result<(), Error> {
+        self.write_registers(addr, &[value])
+    }
+
+    /// Writes `values` starting at the register at `addr`
+    pub fn write_registers(&mut self, addr: u8, values: &[u8]) -> Result<(), Error> {
+        let _ = self.cs.set_low();
+        let result = self.send(&[addr & !self.read_flag]).and_then(|_| self.send(values));
+        let _ = self.cs.set_high();
+        result
+    }
+
+    /// Reads the register at `addr`
+    pub fn read_register(&mut self, addr: u8) -> Result<u8, Error> {
+        let mut value = [0];
+        self.read_registers(addr, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Reads `values.len()` registers starting at `addr`
+    pub fn read_registers(&mut self, addr: u8, values: &mut [u8]) -> Result<(), Error> {
+        let _ = self.cs.set_low();
+        let result = self.send(&[addr | self.read_flag]).and_then(|_| {
+            for _ in 0..self.dummy_bytes {
+                self.receive_frame()?;
+            }
+            for value in values.iter_mut() {
+                *value = self.receive_frame()?;
+            }
+            Ok(())
+        });
+        let _ = self.cs.set_high();
+        result
+    }
+
+    /// Releases the SPI peripheral and the chip select pin
+    pub fn release(self) -> (Spi<SPI, { TransferMode::TransferModeBidirectional }, u8>, CS) {
+        (self.spi, self.cs)
+    }
+
+    fn send(&mut self, words: &[u8]) -> Result<(), Error> {
+        self.spi.enable(true);
+        self.spi.write(words)?;
+        // Let the last frame leave the shift register before the line is turned around
+        while !self.spi.is_tx_empty() {}
+        while self.spi.is_busy() {}
+        Ok(())
+    }
+
+    fn receive_frame(&mut self) -> Result<u8, Error> {
+        // A master in receive mode clocks as long as it is enabled. It is switched
+        // off during the first bit so the current frame completes and no other starts.
+        self.spi.enable(false);
+        while self.spi.is_rx_not_empty() {
+            let _ = self.spi.read_data_reg::<u8>();
+        }
+        self.spi.bidi_input();
+        cortex_m::interrupt::free(|_| {
+            self.spi.enable(true);
+            cortex_m::asm::delay(self.cycles_per_clock);
+            self.spi.enable(false);
+        });
+        let value = nb::block!(self.spi.check_read());
+        self.spi.bidi_output();
+        self.spi.enable(true);
+        value
+    }
+}
@@ -0,0 +1,340 @@
+//! # Timer triggered acquisition chains
+//!
+//! A periodic acquisition involves three peripherals agreeing with each other: the timer drives
+//! its update event on TRGO, the ADC converts on that trigger instead of continuously, and a
+//! circular DMA transfer collects the regular sequence. [Chain] sets all of these bits in the
+//! right order and only starts the timer once the ADC and the DMA are ready, so the first
+//! trigger already lands in the buffer.
+//!
+//! The timer/ADC pairs are checked at compile time: only timers whose TRGO is routed to the
+//! ADC are accepted, see [RegularTrgo] and [InjectedTrgo].
+//!
+//! [control_loop] adds the DAC on the same trigger, for sampled control loops.
+//!
+//! ```rust
+//! static mut SAMPLES: [[[u16; 2]; 32]; 2] = [[[0; 2]; 32]; 2];
+//!
+//! let mut adc = Adc::adc1(dp.Adc1, true, AdcConfig::default()).unwrap();
+//! adc.configure_regular_channel(&pa0, RegularSequence::One, SampleTime::Cycles_28_5);
+//! adc.configure_regular_channel(&pa1, RegularSequence::Two, SampleTime::Cycles_28_5);
+//!
+//! let (timer, mut samples) = sync::Chain::new()
+//!     .timer_trgo(Timer::new(dp.Tim3, &clocks), 100.micros())
+//!     .adc_regular(adc)
+//!     .dma(dma.1, unsafe { &mut SAMPLES })
+//!     .map_err(|(error, ..)| error)?;
+//!
+//! // DMA half/complete transfer interrupt
+//! samples.swap(|block, _| process(block))?;
+//! ```
+
+use embedded_hal_02::timer::CountDown;
+
+use crate::adc::config::{Continuous, Eoc, ExternalTrigger, InjectedTrigger, Scan, TriggerMode};
+use crate::adc::{Adc, DoubleBuffer};
+use crate::dma::{CompatibleChannel, DMAChannel, R};
+use crate::pac;
+use crate::time::MicroSecond;
+use crate::timer::{CountDownTimer, Timer, TriggerSource};
+
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+mod control;
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+pub use control::{control_loop, ControlLoop, DacChannel, DacTrgo};
+
+/// Timer whose trigger output can be selected
+pub trait MasterTimer: crate::Sealed + Sized {
+    /// Selects the signal driven on TRGO, see `Timer::set_trigger_source`
+    fn set_trgo(timer: &mut Timer<Self>, source: TriggerSource);
+}
+
+/// Timer whose TRGO can trigger the regular conversions of `ADC`
+pub trait RegularTrgo<ADC>: MasterTimer {
+    /// `EXTRSEL` source of the timer
+    const TRIGGER: ExternalTrigger;
+}
+
+/// Timer whose TRGO can trigger the injected conversions of `ADC`
+pub trait InjectedTrgo<ADC>: MasterTimer {
+    /// `EXTJSEL` source of the timer
+    const TRIGGER: InjectedTrigger;
+}
+
+/// ADC that can be driven by a [Chain]
+pub trait ChainAdc: crate::Sealed + Sized {
+    /// Converts the regular sequence on `trigger` only, scanning it if it has several channels
+    fn trigger_regular(adc: &mut Adc<Self>, trigger: ExternalTrigger);
+    /// Converts the injected sequence on `trigger` only
+    fn trigger_injected(adc: &mut Adc<Self>, trigger: InjectedTrigger);
+    /// Raises the ADC interrupt at the end of the injected sequence
+    fn listen_injected(adc: &mut Adc<Self>);
+    /// Clears the end of injected sequence flags
+    fn clear_injected(adc: &mut Adc<Self>);
+    /// Starts collecting the regular sequence into `buffers` with `channel`
+    ///
+    /// Returns the ADC, the channel and the buffers along with the error.
+    #[allow(clippy::type_complexity)]
+    fn start_double_buffered<CH, const N: usize, const M: usize>(
+        adc: Adc<Self>,
+        channel: CH,
+        buffers: &'static mut [[[u16; N]; M]; 2],
+    ) -> Result<DoubleBuffer<Self, CH, N, M>, (crate::dma::Error, Adc<Self>, CH, &'static mut [[[u16; N]; M]; 2])>
+    where
+        CH: CompatibleChannel<Self, R> + DMAChannel;
+}
+
+/// Acquisition chain being set up, see the [module documentation](self)
+pub struct Chain<T = (), A = ()> {
+    timer: T,
+    adc: A,
+}
+
+/// Timer of a [Chain] and its period
+pub struct Trgo<TIM> {
+    timer: Timer<TIM>,
+    period: MicroSecond,
+}
+
+/// ADC of a [Chain] triggered on its regular sequence
+pub struct Regular<ADC>(Adc<ADC>);
+
+/// ADC of a [Chain] triggered on its injected sequence
+pub struct Injected<ADC>(Adc<ADC>);
+
+impl Chain {
+    /// Starts an empty chain
+    pub fn new() -> Self {
+        Self { timer: (), adc: () }
+    }
+
+    /// Triggers the chain with the update event of `timer`, every `period`
+    ///
+    /// The timer is only started by the last step of the chain.
+    pub fn timer_trgo<TIM: MasterTimer>(self, mut timer: Timer<TIM>, period: impl Into<MicroSecond>) -> Chain<Trgo<TIM>> {
+        TIM::set_trgo(&mut timer, TriggerSource::Update);
+        Chain {
+            timer: Trgo { timer, period: period.into() },
+            adc: (),
+        }
+    }
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TIM> Chain<Trgo<TIM>> {
+    /// Converts the regular sequence of `adc` on each timer update, configure the sequence first
+    pub fn adc_regular<ADC>(self, mut adc: Adc<ADC>) -> Chain<Trgo<TIM>, Regular<ADC>>
+    where
+        TIM: RegularTrgo<ADC>,
+        ADC: ChainAdc,
+    {
+        ADC::trigger_regular(&mut adc, TIM::TRIGGER);
+        Chain {
+            timer: self.timer,
+            adc: Regular(adc),
+        }
+    }
+
+    /// Converts the injected sequence of `adc` on each timer update, configure the sequence first
+    pub fn adc_injected<ADC>(self, mut adc: Adc<ADC>) -> Chain<Trgo<TIM>, Injected<ADC>>
+    where
+        TIM: InjectedTrgo<ADC>,
+        ADC: ChainAdc,
+    {
+        ADC::trigger_injected(&mut adc, TIM::TRIGGER);
+        Chain {
+            timer: self.timer,
+            adc: Injected(adc),
+        }
+    }
+}
+
+impl<TIM, ADC: ChainAdc> Chain<Trgo<TIM>, Regular<ADC>> {
+    /// Collects the regular sequence into `buffers` with `channel` and starts the timer
+    ///
+    /// Returns the running timer and the buffers, see [DoubleBuffer::swap]. On the errors of
+    /// [AdcDma::start_double_buffered](crate::adc::AdcDma) the timer isn't started, and the
+    /// chain, the channel and the buffers are returned with the error.
+    #[allow(clippy::type_complexity)]
+    pub fn dma<CH, const N: usize, const M: usize>(
+        self,
+        channel: CH,
+        buffers: &'static mut [[[u16; N]; M]; 2],
+    ) -> Result<
+        (CountDownTimer<TIM>, DoubleBuffer<ADC, CH, N, M>),
+        (crate::dma::Error, Self, CH, &'static mut [[[u16; N]; M]; 2]),
+    >
+    where
+        CH: CompatibleChannel<ADC, R> + DMAChannel,
+        CountDownTimer<TIM>: CountDown<Time = MicroSecond>,
+    {
+        let Chain { timer, adc } = self;
+        match ADC::start_double_buffered(adc.0, channel, buffers) {
+            Ok(samples) => {
+                let Trgo { timer, period } = timer;
+                Ok((timer.start_count_down(period), samples))
+            }
+            Err((error, adc, channel, buffers)) => Err((
+                error,
+                Chain {
+                    timer,
+                    adc: Regular(adc),
+                },
+                channel,
+                buffers,
+            )),
+        }
+    }
+}
+
+impl<TIM, ADC> Chain<Trgo<TIM>, Injected<ADC>> {
+    /// Starts the timer, read the conversions with `Adc::injected_sample`
+    pub fn start(self) -> (CountDownTimer<TIM>, Adc<ADC>)
+    where
+        CountDownTimer<TIM>: CountDown<Time = MicroSecond>,
+    {
+        let Trgo { timer, period } = self.timer;
+        (timer.start_count_down(period), self.adc.0)
+    }
+}
+
+macro_rules! master_timer {
+    ($($TIM:ident,)+) => {
+        $(
+            impl MasterTimer for pac::$TIM {
+                fn set_trgo(timer: &mut Timer<Self>, source: TriggerSource) {
+                    timer.set_trigger_source(source);
+                }
+            }
+        )+
+    };
+}
+
+master_timer! {
+    Tim1,
+    Tim2,
+    Tim3,
+    Tim4,
+    Tim6,
+    Tim8,
+}
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+master_timer! {
+    Tim7,
+}
+
+impl RegularTrgo<pac::Adc1> for pac::Tim3 {
+    const TRIGGER: ExternalTrigger = ExternalTrigger::Tim_3_trgo;
+}
+
+impl RegularTrgo<pac::Adc2> for pac::Tim3 {
+    const TRIGGER: ExternalTrigger = ExternalTrigger::Tim_3_trgo;
+}
+
+impl InjectedTrgo<pac::Adc1> for pac::Tim1 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_1_trgo;
+}
+
+impl InjectedTrgo<pac::Adc2> for pac::Tim1 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_1_trgo;
+}
+
+impl InjectedTrgo<pac::Adc1> for pac::Tim2 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_2_trgo;
+}
+
+impl InjectedTrgo<pac::Adc2> for pac::Tim2 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_2_trgo;
+}
+
+impl InjectedTrgo<pac::Adc1> for pac::Tim4 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_4_trgo;
+}
+
+impl InjectedTrgo<pac::Adc2> for pac::Tim4 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_4_trgo;
+}
+
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+impl RegularTrgo<pac::Adc3> for pac::Tim8 {
+    const TRIGGER: ExternalTrigger = ExternalTrigger::Tim_8_trgo;
+}
+
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+impl RegularTrgo<pac::Adc4> for pac::Tim8 {
+    const TRIGGER: ExternalTrigger = ExternalTrigger::Tim_8_trgo;
+}
+
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+impl InjectedTrgo<pac::Adc3> for pac::Tim1 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_1_trgo;
+}
+
+#[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+impl InjectedTrgo<pac::Adc4> for pac::Tim1 {
+    const TRIGGER: InjectedTrigger = InjectedTrigger::Tim_1_trgo;
+}
+
+macro_rules! chain_adc {
+    ($($(#[$attr:meta])* $ADC:ident,)+) => {
+        $(
+            $(#[$attr])*
+            impl ChainAdc for pac::$ADC {
+                fn trigger_regular(adc: &mut Adc<Self>, trigger: ExternalTrigger) {
+                    adc.set_continuous(Continuous::Single);
+                    let scan = if adc.sequence_length() > 1 { Scan::Enabled } else { Scan::Disabled };
+                    adc.set_scan(scan);
+                    // RegularTrgo only names triggers routed to this ADC
+                    adc.set_regular_channel_external_trigger((TriggerMode::RisingEdge, trigger)).ok();
+                    adc.enable();
+                }
+
+                fn trigger_injected(adc: &mut Adc<Self>, trigger: InjectedTrigger) {
+                    adc.set_continuous(Continuous::Single);
+                    adc.set_scan(Scan::Enabled);
+                    // InjectedTrgo only names triggers routed to this ADC
+                    adc.set_injected_channel_external_trigger((TriggerMode::RisingEdge, trigger)).ok();
+                    adc.enable();
+                }
+
+                fn listen_injected(adc: &mut Adc<Self>) {
+                    adc.set_end_of_injected_conversion_interrupt(Eoc::Sequence);
+                }
+
+                fn clear_injected(adc: &mut Adc<Self>) {
+                    adc.clear_end_of_injected_conversion_flag();
+                }
+
+                #[allow(clippy::type_complexity)]
+                fn start_double_buffered<CH, const N: usize, const M: usize>(
+                    adc: Adc<Self>,
+                    channel: CH,
+                    buffers: &'static mut [[[u16; N]; M]; 2],
+                ) -> Result<DoubleBuffer<Self, CH, N, M>, (crate::dma::Error, Adc<Self>, CH, &'static mut [[[u16; N]; M]; 2])>
+                where
+                    CH: CompatibleChannel<Self, R> + DMAChannel,
+                {
+                    adc.with_dma(channel)
+                        .start_double_buffered(buffers)
+                        .map_err(|(error, adc, buffers)| {
+                            let (adc, channel) = adc.release();
+                            (error, adc, channel, buffers)
+                        })
+                }
+            }
+        )+
+    };
+}
+
+chain_adc! {
+    Adc1,
+    Adc2,
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+    Adc3,
+    #[cfg(any(feature = "n32g451",feature = "n32g452",feature = "n32g455",feature = "n32g457",feature = "n32g4fr"))]
+    Adc4,
+}
@@ -0,0 +1,169 @@
+//! # Sampled control loops
+//!
+//! The usual digital control topology samples the plant, computes the next command and
+//! applies it, once per period. [control_loop] triggers both the injected sequence of the
+//! ADC and the DAC on the update event of the same timer, and runs the step of the loop from
+//! the end of injected sequence interrupt:
+//!
+//! ```text
+//! TRGO ──┬─ ADC samples n ──> interrupt: step(n) writes the DAC holding register
+//!        └─ DAC outputs step(n - 1)
+//! ```
+//!
+//! The DAC only takes the new command on the next trigger, so the output changes exactly
+//! one period after the samples it was computed from, however long the step and the
+//! interrupt latency are, as long as the step completes within the period.
+//!
+//! ```rust
+//! let mut adc = Adc::adc1(dp.Adc1, true, AdcConfig::default()).unwrap();
+//! adc.configure_injected_channel(&pa0, InjectedSequence::One, SampleTime::Cycles_13_5);
+//!
+//! let mut pid = Pid::new(..);
+//! let mut control = sync::control_loop(
+//!     Timer::new(dp.Tim2, &clocks),
+//!     50.micros(),
+//!     adc,
+//!     dp.Dac,
+//!     DacChannel::One,
+//!     move |adc| pid.update(adc.injected_sample(InjectedSequence::One)),
+//! );
+//!
+//! // ADC1_2 interrupt
+//! control.on_interrupt();
+//! ```
+
+use embedded_hal_02::timer::{Cancel, CountDown};
+
+use super::{Chain, ChainAdc, InjectedTrgo, MasterTimer};
+use crate::adc::Adc;
+use crate::funcgen::DAC_MAX;
+use crate::pac::{self, Rcc};
+use crate::rcc::Enable;
+use crate::time::MicroSecond;
+use crate::timer::{CountDownTimer, Timer};
+
+/// Output channel of the DAC
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DacChannel {
+    /// PA4
+    One,
+    /// PA5
+    Two,
+}
+
+/// Timer whose TRGO can trigger the DAC
+pub trait DacTrgo: MasterTimer {
+    /// `TSEL` source of the timer
+    const TSEL: u8;
+}
+
+impl DacTrgo for pac::Tim2 {
+    const TSEL: u8 = 0b100;
+}
+
+impl DacTrgo for pac::Tim4 {
+    const TSEL: u8 = 0b101;
+}
+
+impl DacTrgo for pac::Tim6 {
+    const TSEL: u8 = 0b000;
+}
+
+impl DacTrgo for pac::Tim7 {
+    const TSEL: u8 = 0b010;
+}
+
+impl DacTrgo for pac::Tim8 {
+    const TSEL: u8 = 0b001;
+}
+
+/// Sampled control loop, see [control_loop]
+pub struct ControlLoop<TIM, ADC, F> {
+    timer: CountDownTimer<TIM>,
+    adc: Adc<ADC>,
+    dac: pac::Dac,
+    channel: DacChannel,
+    step: F,
+}
+
+/// Samples the injected sequence of `adc` every `period` of `timer` and applies the result
+/// of `step` on `channel` of the DAC one period later
+///
+/// Configure the injected sequence of `adc` and the analog mode of the DAC pin first, and
+/// call [ControlLoop::on_interrupt] from the ADC interrupt. The DAC outputs mid-scale
+/// until the first step.
+pub fn control_loop<TIM, ADC, F>(
+    timer: Timer<TIM>,
+    period: impl Into<MicroSecond>,
+    mut adc: Adc<ADC>,
+    dac: pac::Dac,
+    channel: DacChannel,
+    step: F,
+) -> ControlLoop<TIM, ADC, F>
+where
+    TIM: InjectedTrgo<ADC> + DacTrgo,
+    ADC: ChainAdc,
+    F: FnMut(&Adc<ADC>) -> u16,
+    CountDownTimer<TIM>: CountDown<Time = MicroSecond>,
+{
+    ADC::listen_injected(&mut adc);
+    let chain = Chain::new().timer_trgo(timer, period).adc_injected(adc);
+
+    let rcc = unsafe { &(*Rcc::ptr()) };
+    pac::Dac::enable(rcc);
+    // NOTE(unsafe) TSEL values of the DacTrgo impls are valid sources
+    dac.ctrl().modify(|_, w| unsafe {
+        match channel {
+            DacChannel::One => w.tsel1().bits(TIM::TSEL).ten1().set_bit().ch1en().set_bit(),
+            DacChannel::Two => w.tsel2().bits(TIM::TSEL).ten2().set_bit().ch2en().set_bit(),
+        }
+    });
+    write_dac(&dac, channel, DAC_MAX / 2);
+
+    // Both are ready, the first update event starts the loop
+    let (timer, adc) = chain.start();
+    ControlLoop {
+        timer,
+        adc,
+        dac,
+        channel,
+        step,
+    }
+}
+
+impl<TIM, ADC, F> ControlLoop<TIM, ADC, F>
+where
+    ADC: ChainAdc,
+    F: FnMut(&Adc<ADC>) -> u16,
+{
+    /// Runs one step of the loop, call it from the ADC interrupt
+    ///
+    /// The command is clamped to [DAC_MAX].
+    pub fn on_interrupt(&mut self) {
+        ADC::clear_injected(&mut self.adc);
+        let command = (self.step)(&self.adc).min(DAC_MAX);
+        write_dac(&self.dac, self.channel, command);
+    }
+
+    /// Stops the timer and the DAC channel, and releases the peripherals and the step
+    pub fn stop(mut self) -> (CountDownTimer<TIM>, Adc<ADC>, pac::Dac, F)
+    where
+        CountDownTimer<TIM>: Cancel,
+    {
+        self.timer.cancel().ok();
+        self.dac.ctrl().modify(|_, w| match self.channel {
+            DacChannel::One => w.ch1en().clear_bit().ten1().clear_bit(),
+            DacChannel::Two => w.ch2en().clear_bit().ten2().clear_bit(),
+        });
+        (self.timer, self.adc, self.dac, self.step)
+    }
+}
+
+fn write_dac(dac: &pac::Dac, channel: DacChannel, value: u16) {
+    // NOTE(unsafe) 12-bit right aligned holding register
+    match channel {
+        DacChannel::One => dac.dr12ch1().write(|w| unsafe { w.bits(value as u32) }),
+        DacChannel::Two => dac.dr12ch2().write(|w| unsafe { w.bits(value as u32) }),
+    };
+}
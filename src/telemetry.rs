@@ -0,0 +1,228 @@
+//! # ADC to serial telemetry
+//!
+//! [Telemetry] takes the blocks of a [double buffered](crate::adc::DoubleBuffer) ADC
+//! sequence, encodes each one into a frame and sends the frame with USART transmit DMA.
+//!
+//! The ADC can't be held off, so when the serial link is slower than the sampling the
+//! pipeline applies back-pressure by dropping whole blocks while a frame is still being sent,
+//! and counts them in [Stats]. Frames are never torn: a block is only encoded while the
+//! transmitter is idle and only sent if the ADC didn't overwrite it meanwhile.
+//!
+//! Each frame is [COBS](https://en.wikipedia.org/wiki/Consistent_Overhead_Byte_Stuffing)
+//! encoded and terminated by a zero byte, so a receiver resynchronises on the next zero after
+//! any loss. The decoded payload is:
+//!
+//! | bytes      | content                                                     |
+//! |------------|-------------------------------------------------------------|
+//! | 1          | sequence number, incremented for every block, sent or not   |
+//! | `2 * N * M`| samples in conversion order, little endian                  |
+//! | 2          | CRC-16/CCITT-FALSE of the bytes above, little endian        |
+//!
+//! ```rust
+//! static mut SAMPLES: [[[u16; 4]; 16]; 2] = [[[0; 4]; 16]; 2];
+//! static mut FRAME: [u8; telemetry::frame_len(4 * 16)] = [0; telemetry::frame_len(4 * 16)];
+//!
+//! let dma = dp.Dma1.split();
+//! let adc = adc.with_dma(dma.1).start_double_buffered(unsafe { &mut SAMPLES })
+//!     .map_err(|(error, ..)| error)?;
+//! let tx = dp.Usart1.tx(gpioa.pa9, 921_600.bps(), &clocks, &mut afio)?.with_dma(dma.4);
+//! let mut telemetry = Telemetry::new(adc, tx, unsafe { &mut FRAME });
+//!
+//! // ADC DMA half and complete transfer interrupt
+//! telemetry.poll()?;
+//! ```
+
+use core::sync::atomic::{self, Ordering};
+
+use enumflags2::BitFlags;
+
+pub use crate::crc::crc16_ccitt as crc16;
+
+use crate::adc::DoubleBuffer;
+use crate::dma::{start_byte_transfer, DMAChannel, Error, Flag, TxDma};
+use crate::serial::{Instance, RegisterBlockImpl, Tx};
+
+/// Size of the payload of a frame with `samples` samples
+const fn payload_len(samples: usize) -> usize {
+    1 + 2 * samples + 2
+}
+
+/// Size of the buffer needed for a frame with `samples` samples, delimiter included
+pub const fn frame_len(samples: usize) -> usize {
+    let payload = payload_len(samples);
+    // COBS adds a code byte per started run of 254 bytes
+    payload + payload / 254 + 1 + 1
+}
+
+/// Fails the build of [encode] and [Telemetry::new] for a frame buffer of `L` bytes shorter than
+/// [frame_len] of `N * M` samples
+struct FrameBuffer<const N: usize, const M: usize, const L: usize>;
+
+impl<const N: usize, const M: usize, const L: usize> FrameBuffer<N, M, L> {
+    const FITS: () = assert!(L >= frame_len(N * M), "the frame buffer is shorter than telemetry::frame_len");
+}
+
+/// Streaming COBS encoder writing into a buffer large enough for the frame
+struct Cobs<'a> {
+    out: &'a mut [u8],
+    /// Position of the code byte of the current run
+    code: usize,
+    pos: usize,
+}
+
+impl<'a> Cobs<'a> {
+    fn new(out: &'a mut [u8]) -> Self {
+        Self { out, code: 0, pos: 1 }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if byte != 0 {
+            self.out[self.pos] = byte;
+            self.pos += 1;
+        }
+        if byte == 0 || self.pos - self.code == 0xFF {
+            self.out[self.code] = (self.pos - self.code) as u8;
+            self.code = self.pos;
+            self.pos += 1;
+        }
+    }
+
+    /// Closes the last run and appends the delimiter, returns the frame length
+    fn finish(self) -> usize {
+        self.out[self.code] = (self.pos - self.code) as u8;
+        self.out[self.pos] = 0;
+        self.pos + 1
+    }
+}
+
+/// Encodes a block of samples into `out`, returns the frame length
+///
+/// `out` has to hold [frame_len] of the number of samples, a shorter buffer doesn't build.
+pub fn encode<const N: usize, const M: usize, const L: usize>(sequence: u8, samples: &[[u16; N]; M], out: &mut [u8; L]) -> usize {
+    #[allow(clippy::let_unit_value)]
+    let () = FrameBuffer::<N, M, L>::FITS;
+    let mut cobs = Cobs::new(out);
+    let mut crc = crc16(0xFFFF, &[sequence]);
+    cobs.push(sequence);
+    for sample in samples.iter().flatten() {
+        let bytes = sample.to_le_bytes();
+        crc = crc16(crc, &bytes);
+        cobs.push(bytes[0]);
+        cobs.push(bytes[1]);
+    }
+    for byte in crc.to_le_bytes() {
+        cobs.push(byte);
+    }
+    cobs.finish()
+}
+
+/// Counters of a [Telemetry] pipeline
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Frames handed to the transmitter
+    pub sent: u32,
+    /// Blocks dropped because the previous frame was still being sent
+    pub dropped: u32,
+    /// Blocks lost because the ADC overwrote them before they were encoded
+    pub overruns: u32,
+}
+
+/// ADC blocks of `M` passes over `N` channels sent as frames on `USART`
+pub struct Telemetry<ADC, ADCCH, USART: Instance, TXCH, const N: usize, const M: usize, const L: usize> {
+    adc: DoubleBuffer<ADC, ADCCH, N, M>,
+    tx: TxDma<Tx<USART>, TXCH>,
+    frame: &'static mut [u8; L],
+    sequence: u8,
+    /// A frame is being sent
+    sending: bool,
+    stats: Stats,
+}
+
+impl<ADC, ADCCH, USART, TXCH, const N: usize, const M: usize, const L: usize> Telemetry<ADC, ADCCH, USART, TXCH, N, M, L>
+where
+    ADCCH: DMAChannel,
+    USART: Instance,
+    TXCH: DMAChannel,
+{
+    /// Sends the blocks of `adc` on `tx`, encoding each one into `frame`
+    ///
+    /// Nothing is sent until [Telemetry::poll] is called. `L` has to be at least [frame_len] of
+    /// `N * M`, a shorter frame doesn't build.
+    pub fn new(adc: DoubleBuffer<ADC, ADCCH, N, M>, tx: TxDma<Tx<USART>, TXCH>, frame: &'static mut [u8; L]) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = FrameBuffer::<N, M, L>::FITS;
+        Self {
+            adc,
+            tx,
+            frame,
+            sequence: 0,
+            sending: false,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Encodes and sends the ADC block completed since the previous call
+    ///
+    /// Call it from the half and complete transfer interrupts of the ADC channel, or at least
+    /// once per block. Returns `WouldBlock` while no new block is ready,
+    /// [Overrun](Error::Overrun) if the ADC overwrote the block and
+    /// [TransferError](Error::TransferError) if the transmit DMA failed.
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        if self.tx.channel.flags().contains(Flag::TransferError) {
+            self.tx.channel.stop();
+            self.tx.channel.clear_flags(BitFlags::ALL);
+            self.sending = false;
+            return Err(nb::Error::Other(Error::TransferError));
+        }
+        if self.sending && self.tx.channel.get_txnum() == 0 {
+            self.tx.channel.stop();
+            self.tx.channel.clear_flags(BitFlags::ALL);
+            self.sending = false;
+        }
+
+        let sending = self.sending;
+        let sequence = self.sequence;
+        let frame = &mut *self.frame;
+        let result = self.adc.swap(|samples, _| (!sending).then(|| encode(sequence, samples, frame)));
+        match result {
+            Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+            Err(nb::Error::Other(e)) => {
+                self.sequence = self.sequence.wrapping_add(1);
+                self.stats.overruns += 1;
+                return Err(nb::Error::Other(e));
+            }
+            Ok(None) => self.stats.dropped += 1,
+            Ok(Some(len)) => {
+                let address = self.frame.as_ptr() as u32;
+                let peripheral = unsafe { (*USART::ptr()).peri_address() };
+                atomic::compiler_fence(Ordering::Release);
+                start_byte_transfer(&mut self.tx.channel, peripheral, address, len, true, false).map_err(nb::Error::Other)?;
+                self.sending = true;
+                self.stats.sent += 1;
+            }
+        }
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Returns `true` while a frame is being sent
+    pub fn is_sending(&self) -> bool {
+        self.sending && self.tx.channel.get_txnum() != 0
+    }
+
+    /// Counters since the pipeline was created
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Stops the transmission and releases the ADC, the serial port and the frame buffer
+    ///
+    /// The ADC keeps converting, stop it with [DoubleBuffer::stop].
+    pub fn release(mut self) -> (DoubleBuffer<ADC, ADCCH, N, M>, TxDma<Tx<USART>, TXCH>, &'static mut [u8; L]) {
+        self.tx.channel.stop();
+        self.tx.channel.clear_flags(BitFlags::ALL);
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.adc, self.tx, self.frame)
+    }
+}
@@ -15,6 +15,14 @@ use crate::pac::Rcc;
 use crate::rcc::{self, Clocks};
 use crate::time::{Hertz, MicroSecond};
 
+mod capture;
+mod etr;
+mod paced;
+pub use capture::{CaptureDma, CaptureTimer};
+pub use etr::{EtrConfig, EtrMode, EtrPolarity, EtrPrescaler};
+pub(crate) use etr::write_etr;
+pub use paced::{paced_write, paced_write_circular, PacedWrite, UpdateDma};
+
 /// Timer wrapper
 pub struct Timer<TIM> {
     pub(crate) tim: TIM,
@@ -43,15 +51,33 @@ where
     }
 }
 
+impl<TIM> Timer<TIM> {
+    /// Returns the clock the timer counts from
+    pub fn clock(&self) -> Hertz {
+        self.clk
+    }
+}
+
+impl<TIM> CountDownTimer<TIM> {
+    /// Returns the clock the timer counts from
+    pub fn clock(&self) -> Hertz {
+        self.clk
+    }
+}
+
 impl<TIM> Periodic for CountDownTimer<TIM> {}
 
 /// Interrupt events
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Event {
     /// CountDownTimer timed out / count down ended
     TimeOut,
 }
 
 /// Trigger output source
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum TriggerSource {
     /// Timer reset - UG as trigger output
     Reset,
@@ -71,10 +97,70 @@ pub enum TriggerSource {
     Compare4 = 0b111,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Error {
     /// CountDownTimer is disabled
     Disabled,
+    /// Timer clock is too slow for the requested frequency
+    InvalidFrequency,
+    /// Requested period is zero or too long for the timer
+    InvalidPeriod,
+    /// Input filter setting above 15
+    InvalidFilter,
+}
+
+/// DMA request raised by the update event of `TIM`
+///
+/// Used as peripheral of [CompatibleChannel](crate::dma::CompatibleChannel) for DMA transfers paced by a timer.
+pub struct UpdateRequest<TIM>(core::marker::PhantomData<TIM>);
+
+/// DMA request raised by capture/compare channel `C` (1..=4) of `TIM`
+///
+/// Used as peripheral of [CompatibleChannel](crate::dma::CompatibleChannel), see
+/// [chmap](crate::dma::chmap) for the channels that can raise a request.
+pub struct CaptureCompareRequest<TIM, const C: u8>(core::marker::PhantomData<TIM>);
+
+/// Clock input of a [PulseCounter]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum PulseInput {
+    /// External trigger pin (TIMx_ETR), external clock mode 2
+    Etr,
+    /// Channel 1 pin (TI1FP1), external clock mode 1
+    Ti1,
+    /// Channel 2 pin (TI2FP2), external clock mode 1
+    Ti2,
+}
+
+/// Events raising the update interrupt and DMA request (URS)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum UpdateSource {
+    /// Counter overflows, [force_update](Timer::force_update) and slave mode resets
+    Any,
+    /// Counter overflows only
+    Overflow,
+}
+
+/// Edge counted by a [PulseCounter]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum PulseEdge {
+    Rising,
+    Falling,
+}
+
+/// Counts pulses on a timer input, with a 64 bit software extended count
+///
+/// The hardware counter wraps every 65536 pulses. The overflows are accumulated
+/// either from the update interrupt, see [PulseCounter::on_interrupt], or by calling
+/// [PulseCounter::count] at least once per 65536 pulses.
+///
+/// The input pin has to be configured as input by the user.
+pub struct PulseCounter<TIM> {
+    tim: TIM,
+    overflows: u64,
 }
 
 impl Timer<SYST> {
@@ -152,6 +238,175 @@ impl Cancel for CountDownTimer<SYST> {
     }
 }
 
+/// SysTick counter with a fixed tick frequency of `FREQ` Hz
+///
+/// Unlike [CountDownTimer<SYST>], durations are typed with fugit, so durations of
+/// another tick rate have to be converted explicitly. Whether the core clock can
+/// tick at `FREQ` is checked at runtime by [Timer::counter].
+pub struct SysCounter<const FREQ: u32> {
+    tim: SYST,
+    clk: Hertz,
+}
+
+/// Callback invoked from [sys_tick_handler]
+static SYS_TICK_CALLBACK: cortex_m::interrupt::Mutex<core::cell::Cell<Option<fn()>>> =
+    cortex_m::interrupt::Mutex::new(core::cell::Cell::new(None));
+/// Number of SysTick interrupts since [SysCounter::start_tick]
+static SYS_TICKS: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+
+impl Timer<SYST> {
+    /// Creates a [SysCounter] ticking at `FREQ` Hz
+    ///
+    /// Fails with [Error::InvalidFrequency] if `FREQ` is zero or above the core clock, and
+    /// hands the timer back along with the error.
+    pub fn counter<const FREQ: u32>(self) -> Result<SysCounter<FREQ>, (Error, Self)> {
+        if FREQ == 0 || self.clk.raw() < FREQ {
+            return Err((Error::InvalidFrequency, self));
+        }
+        Ok(SysCounter {
+            tim: self.tim,
+            clk: self.clk,
+        })
+    }
+}
+
+impl<const FREQ: u32> SysCounter<FREQ> {
+    /// Starts the counter, [wait](Self::wait) will return `Ok` every `timeout`
+    ///
+    /// Fails with [Error::InvalidPeriod] if `timeout` is zero or above [max_period](Self::max_period).
+    pub fn start(&mut self, timeout: fugit::TimerDurationU32<FREQ>) -> Result<(), Error> {
+        let rvr = match (self.clk.raw() / FREQ)
+            .checked_mul(timeout.ticks())
+            .and_then(|cycles| cycles.checked_sub(1))
+        {
+            Some(rvr) if rvr < (1 << 24) => rvr,
+            _ => return Err(Error::InvalidPeriod),
+        };
+
+        self.tim.set_reload(rvr);
+        self.tim.clear_current();
+        self.tim.enable_counter();
+        Ok(())
+    }
+
+    /// Returns `Ok` once the timeout elapsed
+    pub fn wait(&mut self) -> nb::Result<(), Error> {
+        if !self.tim.is_counter_enabled() {
+            return Err(nb::Error::Other(Error::Disabled));
+        }
+        if self.tim.has_wrapped() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Stops the counter
+    pub fn cancel(&mut self) -> Result<(), Error> {
+        if !self.tim.is_counter_enabled() {
+            return Err(Error::Disabled);
+        }
+
+        self.tim.disable_counter();
+        Ok(())
+    }
+
+    /// Longest timeout accepted by [start](Self::start)
+    pub fn max_period(&self) -> fugit::TimerDurationU32<FREQ> {
+        fugit::TimerDurationU32::from_ticks(((1 << 24) - 1) / (self.clk.raw() / FREQ))
+    }
+
+    /// Starts a periodic tick, calling `callback` from [sys_tick_handler] on every period
+    ///
+    /// Note, you will have to call [sys_tick_handler] from the `SysTick` exception handler.
+    pub fn start_tick(&mut self, period: fugit::TimerDurationU32<FREQ>, callback: Option<fn()>) -> Result<(), Error> {
+        cortex_m::interrupt::free(|cs| SYS_TICK_CALLBACK.borrow(cs).set(callback));
+        SYS_TICKS.store(0, core::sync::atomic::Ordering::Relaxed);
+        self.start(period)?;
+        self.tim.enable_interrupt();
+        Ok(())
+    }
+
+    /// Number of periods elapsed since [start_tick](Self::start_tick)
+    pub fn ticks(&self) -> u32 {
+        SYS_TICKS.load(core::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Starts listening for an `event`
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::TimeOut => self.tim.enable_interrupt(),
+        }
+    }
+
+    /// Stops listening for an `event`
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::TimeOut => self.tim.disable_interrupt(),
+        }
+    }
+
+    /// Releases the SYST peripheral
+    pub fn release(mut self) -> Timer<SYST> {
+        self.tim.disable_interrupt();
+        self.tim.disable_counter();
+        cortex_m::interrupt::free(|cs| SYS_TICK_CALLBACK.borrow(cs).set(None));
+        Timer {
+            tim: self.tim,
+            clk: self.clk,
+        }
+    }
+}
+
+/// The embedded-hal traits can't report errors: a timeout [SysCounter::start] rejects stops
+/// the counter instead, and `wait` never returns `Ok` for a stopped counter. Use the inherent
+/// methods to get the [Error].
+impl<const FREQ: u32> embedded_hal_02::timer::CountDown for SysCounter<FREQ> {
+    type Time = fugit::TimerDurationU32<FREQ>;
+
+    fn start<T>(&mut self, timeout: T)
+    where
+        T: Into<Self::Time>,
+    {
+        if self.start(timeout.into()).is_err() {
+            self.tim.disable_counter();
+        }
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        match self.wait() {
+            Ok(()) => Ok(()),
+            // A stopped counter doesn't expire
+            Err(_) => Err(nb::Error::WouldBlock),
+        }
+    }
+}
+
+impl<const FREQ: u32> Periodic for SysCounter<FREQ> {}
+
+impl<const FREQ: u32> Cancel for SysCounter<FREQ> {
+    type Error = Error;
+
+    fn cancel(&mut self) -> Result<(), Self::Error> {
+        self.cancel()
+    }
+}
+
+/// Counts the tick and calls the callback registered with [SysCounter::start_tick]
+///
+/// ```rust
+/// #[exception]
+/// fn SysTick() {
+///     n32g4xx_hal::timer::sys_tick_handler();
+/// }
+/// ```
+pub fn sys_tick_handler() {
+    SYS_TICKS.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+    if let Some(callback) = cortex_m::interrupt::free(|cs| SYS_TICK_CALLBACK.borrow(cs).get()) {
+        callback();
+    }
+}
+
 /// A monotonic non-decreasing timer
 ///
 /// This uses the timer in the debug watch trace peripheral. This means, that if the
@@ -203,7 +458,11 @@ impl Instant {
         DWT::cycle_count().wrapping_sub(self.now)
     }
 }
-pub trait Instance: crate::Sealed + rcc::Enable + rcc::Reset + rcc::BusTimerClock {}
+pub trait Instance: crate::Sealed + rcc::Enable + rcc::Reset + rcc::BusTimerClock {
+    /// Loads the prescaler and auto-reload value, see `Timer::latch`
+    #[doc(hidden)]
+    fn latch();
+}
 
 impl<TIM> Timer<TIM>
 where
@@ -230,6 +489,14 @@ macro_rules! hal_ext_trgo {
     ($($TIM:ty: ($tim:ident, $mms:ident),)+) => {
         $(
             impl Timer<$TIM> {
+                /// Selects the master mode, i.e. the signal driven on the trigger output (TRGO)
+                pub fn set_trigger_source(&mut self, trigger_source: TriggerSource) {
+                    self.tim.ctrl2().modify(|_, w| unsafe {w.$mms().bits(trigger_source as u8)});
+                }
+            }
+
+            impl CountDownTimer<$TIM> {
+                /// Selects the master mode, i.e. the signal driven on the trigger output (TRGO)
                 pub fn set_trigger_source(&mut self, trigger_source: TriggerSource) {
                     self.tim.ctrl2().modify(|_, w| unsafe {w.$mms().bits(trigger_source as u8)});
                 }
@@ -238,10 +505,49 @@ macro_rules! hal_ext_trgo {
     }
 }
 
+macro_rules! update_control {
+    ($TIM:ty, $Timer:ident) => {
+        impl $Timer<$TIM> {
+            /// Generates an update event (UG): the prescaler and the auto-reload value written
+            /// since the last update are loaded, and the counter restarts
+            ///
+            /// Raises the update interrupt and DMA request, unless the update source is
+            /// [UpdateSource::Overflow].
+            pub fn force_update(&mut self) {
+                self.tim.evtgen().write(|w| w.udgn().set_bit());
+            }
+
+            /// Selects the events raising the update interrupt and DMA request
+            pub fn set_update_source(&mut self, source: UpdateSource) {
+                self.tim.ctrl1().modify(|_, w| w.uprs().bit(source == UpdateSource::Overflow));
+            }
+
+            /// Loads the prescaler and auto-reload value written since the last update right
+            /// away, without raising the update interrupt or DMA request
+            ///
+            /// The counter restarts. The update source is left as it was.
+            pub fn latch(&mut self) {
+                <$TIM as Instance>::latch();
+            }
+        }
+    };
+}
+
 macro_rules! hal {
     ($($TIM:ty: ($tim:ident),)+) => {
         $(
-            impl Instance for $TIM { }
+            impl Instance for $TIM {
+                fn latch() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    let urs = tim.ctrl1().read().uprs().bit();
+                    tim.ctrl1().modify(|_, w| w.uprs().set_bit());
+                    tim.evtgen().write(|w| w.udgn().set_bit());
+                    tim.ctrl1().modify(|_, w| w.uprs().bit(urs));
+                }
+            }
+
+            update_control!($TIM, Timer);
+            update_control!($TIM, CountDownTimer);
 
             impl CountDownTimer<$TIM> {
                 /// Starts listening for an `event`
@@ -308,10 +614,8 @@ macro_rules! hal {
                     let arr = u16(ticks / u32(psc + 1)).unwrap();
                     self.tim.ar().write(|w| unsafe { w.bits(u32(arr)) });
 
-                    // Trigger update event to load the registers
-                    self.tim.ctrl1().modify(|_, w| w.uprs().set_bit());
-                    self.tim.evtgen().write(|w| w.udgn().set_bit());
-                    self.tim.ctrl1().modify(|_, w| w.uprs().clear_bit());
+                    // Load the registers without an update interrupt
+                    self.latch();
 
                     // start counter
                     self.tim.ctrl1().modify(|_, w| w.cnten().set_bit());
@@ -352,22 +656,149 @@ macro_rules! hal {
     }
 }
 
+macro_rules! hal_pulse_counter {
+    ($($TIM:ty: ($tim:ident),)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Turns the timer into a [PulseCounter] clocked by `input`
+                ///
+                /// `filter` is the input filter setting (0 to 15), which requires that
+                /// many stable samples before an edge is accepted. Returns
+                /// [InvalidFilter](Error::InvalidFilter) if it is above 15.
+                pub fn pulse_counter(self, input: PulseInput, edge: PulseEdge, filter: u8) -> Result<PulseCounter<$TIM>, Error> {
+                    if filter > 15 {
+                        return Err(Error::InvalidFilter);
+                    }
+                    let tim = self.tim;
+                    let falling = edge == PulseEdge::Falling;
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    match input {
+                        PulseInput::Etr => {
+                            // External clock mode 2
+                            tim.smctrl().write(|w| unsafe {
+                                w.excen().set_bit().extp().bit(falling).extf().bits(filter)
+                            });
+                        }
+                        PulseInput::Ti1 => {
+                            // IC1 = TI1
+                            tim.ccmod1().write(|w| unsafe { w.cc1sel().bits(0b01).ic1f().bits(filter) });
+                            tim.ccen().write(|w| w.cc1p().bit(falling));
+                            // External clock mode 1 on TI1FP1
+                            tim.smctrl().write(|w| unsafe { w.tsel().bits(0b101).smsel().bits(0b111) });
+                        }
+                        PulseInput::Ti2 => {
+                            // IC2 = TI2
+                            tim.ccmod1().write(|w| unsafe { w.cc2sel().bits(0b01).ic2f().bits(filter) });
+                            tim.ccen().write(|w| w.cc2p().bit(falling));
+                            // External clock mode 1 on TI2FP2
+                            tim.smctrl().write(|w| unsafe { w.tsel().bits(0b110).smsel().bits(0b111) });
+                        }
+                    }
+                    tim.psc().write(|w| unsafe { w.psc().bits(0) });
+                    tim.ar().write(|w| unsafe { w.bits(0xffff) });
+                    <$TIM as Instance>::latch();
+                    tim.cnt().reset();
+                    tim.sts().write(|w| w.uditf().clear_bit());
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                    Ok(PulseCounter { tim, overflows: 0 })
+                }
+            }
+
+            impl PulseCounter<$TIM> {
+                /// Returns the number of pulses counted since the start or the last [reset](Self::reset)
+                pub fn count(&mut self) -> u64 {
+                    loop {
+                        self.on_interrupt();
+                        let cnt = self.tim.cnt().read().bits() & 0xffff;
+                        // No overflow since the flag was handled, so cnt matches self.overflows
+                        if self.tim.sts().read().uditf().bit_is_clear() {
+                            return (self.overflows << 16) | cnt as u64;
+                        }
+                    }
+                }
+
+                /// Accumulates a pending overflow, call this from the timer update interrupt
+                pub fn on_interrupt(&mut self) {
+                    if self.tim.sts().read().uditf().bit_is_set() {
+                        self.tim.sts().write(|w| w.uditf().clear_bit());
+                        self.overflows += 1;
+                    }
+                }
+
+                /// Resets the count to zero
+                pub fn reset(&mut self) {
+                    self.tim.cnt().reset();
+                    self.tim.sts().write(|w| w.uditf().clear_bit());
+                    self.overflows = 0;
+                }
+
+                /// Enables the overflow interrupt
+                pub fn listen(&mut self) {
+                    self.tim.dinten().modify(|_, w| w.uien().set_bit());
+                }
+
+                /// Disables the overflow interrupt
+                pub fn unlisten(&mut self) {
+                    self.tim.dinten().modify(|_, w| w.uien().clear_bit());
+                }
+
+                /// Stops counting and releases the TIM peripheral
+                pub fn release(self) -> $TIM {
+                    self.tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    self.tim.smctrl().reset();
+                    self.tim
+                }
+            }
+        )+
+    }
+}
+
+// Advanced-control timers
 hal! {
     crate::pac::Tim1: (tim1),
+    crate::pac::Tim8: (tim8),
+}
+hal_ext_trgo! {
+    crate::pac::Tim1: (tim1, mmsel),
+    crate::pac::Tim8: (tim8, mmsel),
+}
+hal_pulse_counter! {
+    crate::pac::Tim1: (tim1),
+    crate::pac::Tim8: (tim8),
+}
+
+// General-purpose timers
+hal! {
     crate::pac::Tim2: (tim2),
     crate::pac::Tim3: (tim3),
     crate::pac::Tim4: (tim4),
-    crate::pac::Tim6: (tim6),
-    crate::pac::Tim7: (tim7),
-    crate::pac::Tim8: (tim8),
+    crate::pac::Tim5: (tim5),
 }
-
 hal_ext_trgo! {
-    crate::pac::Tim1: (tim1, mmsel),
     crate::pac::Tim2: (tim2, mmsel),
     crate::pac::Tim3: (tim3, mmsel),
     crate::pac::Tim4: (tim4, mmsel),
+    crate::pac::Tim5: (tim5, mmsel),
+}
+hal_pulse_counter! {
+    crate::pac::Tim2: (tim2),
+    crate::pac::Tim3: (tim3),
+    crate::pac::Tim4: (tim4),
+    crate::pac::Tim5: (tim5),
+}
+
+// Basic timers, only a counter and TRGO. TIM7 is missing on the N32G401 and N32G430
+hal! {
+    crate::pac::Tim6: (tim6),
+}
+hal_ext_trgo! {
     crate::pac::Tim6: (tim6, mmsel),
+}
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+hal! {
+    crate::pac::Tim7: (tim7),
+}
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+hal_ext_trgo! {
     crate::pac::Tim7: (tim7, mmsel),
-    crate::pac::Tim8: (tim8, mmsel),
 }
@@ -0,0 +1,314 @@
+//! Input capture streamed by DMA
+//!
+//! Every active edge on a timer channel latches the counter into its capture register and
+//! requests a DMA transfer, which copies it into a circular buffer. Nothing runs on the CPU
+//! per edge, so edge rates far above what a capture interrupt sustains can be recorded, e.g.
+//! RC PPM frames or tachometer bursts.
+//!
+//! ```rust
+//! n32g4xx_hal::dma::buffers! {
+//!     static EDGES: [u16; 64] = [0; 64];
+//! }
+//!
+//! let _ppm = gpioa.pa8.into_floating_input();
+//! let mut capture = CaptureDma::<_, _, 1, 64>::new(
+//!     Timer::new(dp.Tim1, &clocks),
+//!     dma1.7,
+//!     EDGES.take().unwrap(),
+//!     PulseEdge::Rising,
+//!     0,
+//!     1.MHz(),
+//! )?;
+//!
+//! while let Some(width_us) = capture.pop_period() {
+//!     // a PPM sync gap is longer than 3 ms
+//! }
+//! ```
+//!
+//! The captures are 16-bit counter values, so only the differences between consecutive
+//! edges less than 65536 ticks apart are meaningful.
+//!
+//! Edges are lost when the buffer is overwritten before they were read, or when an edge
+//! arrives before the DMA collected the previous one (overcapture). Both are counted by
+//! [CaptureDma::lost]. The buffer laps are counted from the transfer complete flag of the DMA
+//! channel, so the stream has to be read at least once per lap of the buffer, or
+//! [CaptureDma::poll] called from the half and complete transfer interrupts of the channel.
+
+use core::sync::atomic::{self, Ordering};
+
+use super::{CaptureCompareRequest, PulseEdge, Timer};
+use crate::dma::{CompatibleChannel, DMAChannel, Error, Flag, WordSize, R};
+use crate::time::Hertz;
+
+/// Timer whose channels can capture edges into a DMA stream
+pub trait CaptureTimer: crate::Sealed {
+    #[doc(hidden)]
+    fn configure(c: u8, psc: u16, edge: PulseEdge, filter: u8);
+    #[doc(hidden)]
+    fn capture_address(c: u8) -> u32;
+    #[doc(hidden)]
+    fn start(c: u8);
+    #[doc(hidden)]
+    fn stop(c: u8);
+    /// Returns and clears the overcapture flag of channel `c`
+    #[doc(hidden)]
+    fn overcapture(c: u8) -> bool;
+}
+
+macro_rules! capture_timer {
+    ($($(#[$attr:meta])* $TIM:ty,)+) => {
+        $(
+            $(#[$attr])*
+            impl CaptureTimer for $TIM {
+                fn configure(c: u8, psc: u16, edge: PulseEdge, filter: u8) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    let i = (c - 1) as u32;
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    // CCxE off while the channel is set up as input
+                    tim.ccen().modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << (4 * i))) });
+                    // CCxS = TIx, no capture prescaler, ICxF
+                    let shift = 8 * (i % 2);
+                    let ccmod = (0b01 | ((filter as u32) << 4)) << shift;
+                    if i < 2 {
+                        tim.ccmod1().modify(|r, w| unsafe { w.bits((r.bits() & !(0xff << shift)) | ccmod) });
+                    } else {
+                        tim.ccmod2().modify(|r, w| unsafe { w.bits((r.bits() & !(0xff << shift)) | ccmod) });
+                    }
+                    // CCxP
+                    let falling = (edge == PulseEdge::Falling) as u32;
+                    tim.ccen().modify(|r, w| unsafe { w.bits(r.bits() | (falling << (4 * i + 1))) });
+                    tim.psc().write(|w| unsafe { w.psc().bits(psc) });
+                    tim.ar().write(|w| unsafe { w.bits(0xffff) });
+                    <$TIM as super::Instance>::latch();
+                    // Clear CCxIF and CCxOF, both write 0 to clear
+                    tim.sts().write(|w| unsafe { w.bits(!((1 << (c as u32)) | (1 << (c as u32 + 8)))) });
+                }
+
+                fn capture_address(c: u8) -> u32 {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ccr1().as_ptr() as u32 + 4 * (c as u32 - 1)
+                }
+
+                fn start(c: u8) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // CCxDE
+                    tim.dinten().modify(|r, w| unsafe { w.bits(r.bits() | (1 << (c as u32 + 8))) });
+                    // CCxE
+                    tim.ccen().modify(|r, w| unsafe { w.bits(r.bits() | (1 << (4 * (c as u32 - 1)))) });
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                }
+
+                fn stop(c: u8) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    tim.ccen().modify(|r, w| unsafe { w.bits(r.bits() & !(1 << (4 * (c as u32 - 1)))) });
+                    tim.dinten().modify(|r, w| unsafe { w.bits(r.bits() & !(1 << (c as u32 + 8))) });
+                }
+
+                fn overcapture(c: u8) -> bool {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    let flag = 1 << (c as u32 + 8);
+                    if tim.sts().read().bits() & flag != 0 {
+                        tim.sts().write(|w| unsafe { w.bits(!flag) });
+                        true
+                    } else {
+                        false
+                    }
+                }
+            }
+        )+
+    };
+}
+
+capture_timer! {
+    crate::pac::Tim1,
+    crate::pac::Tim2,
+    crate::pac::Tim3,
+    crate::pac::Tim4,
+    crate::pac::Tim5,
+    crate::pac::Tim8,
+}
+
+/// Edges of channel `C` (1..=4) of `TIM` captured into a circular buffer of `N` values
+pub struct CaptureDma<TIM, CH, const C: u8, const N: usize> {
+    timer: Timer<TIM>,
+    channel: CH,
+    buffer: &'static mut [u16; N],
+    tick: Hertz,
+    /// Values read since the start
+    read: u64,
+    /// Laps of the DMA around the buffer
+    laps: u64,
+    lost: u32,
+    /// Last capture read
+    last: Option<u16>,
+}
+
+impl<TIM, CH, const C: u8, const N: usize> CaptureDma<TIM, CH, C, N>
+where
+    TIM: CaptureTimer,
+    CH: CompatibleChannel<CaptureCompareRequest<TIM, C>, R> + DMAChannel,
+{
+    /// Captures the `edge`s of channel `C` into `buffer`, counting at `tick`
+    ///
+    /// The pin of the channel has to be configured as input. `filter` is the input filter
+    /// setting (0 to 15), which requires that many stable samples before an edge is accepted.
+    /// Only the channels with a DMA request exist as [CompatibleChannel]s, so `C` is 1 to 4.
+    ///
+    /// Returns [InvalidFilter](Error::InvalidFilter) if `filter` is above 15, and
+    /// [InvalidRate](Error::InvalidRate) if `tick` is zero, above the timer clock or below the
+    /// timer clock divided by 65536.
+    pub fn new(
+        timer: Timer<TIM>,
+        mut channel: CH,
+        buffer: &'static mut [u16; N],
+        edge: PulseEdge,
+        filter: u8,
+        tick: Hertz,
+    ) -> Result<Self, Error> {
+        if filter > 15 {
+            return Err(Error::InvalidFilter);
+        }
+        let div = match timer.clk.raw().checked_div(tick.raw()) {
+            Some(div) if (1..=1 << 16).contains(&div) => div,
+            _ => return Err(Error::InvalidRate),
+        };
+        let tick = Hertz::from_raw(timer.clk.raw() / div);
+
+        channel.stop();
+        channel.configure_channel();
+        channel.try_set_transfer_length(N)?;
+
+        TIM::configure(C, (div - 1) as u16, edge, filter);
+
+        channel.set_peripheral_address(TIM::capture_address(C), false);
+        channel.set_memory_address(buffer.as_ptr() as u32, true);
+        channel.set_word_size(WordSize::Bits16, WordSize::Bits16);
+        let priority = channel.priority_or(crate::dma::Priority::High) as u8;
+        channel.st().chcfg().modify(|_, w| {
+            w
+                // memory to memory mode disabled
+                .mem2mem()
+                .disabled()
+                // channel priority level, high unless set with DMAChannel::set_priority
+                .priolvl()
+                .bits(priority)
+                .circ()
+                .enabled()
+                // read from the peripheral
+                .dir()
+                .from_peripheral()
+        });
+        channel.clear_flags(Flag::TransferComplete | Flag::HalfTransfer);
+
+        atomic::compiler_fence(Ordering::Release);
+        channel.start();
+        TIM::start(C);
+
+        Ok(Self {
+            timer,
+            channel,
+            buffer,
+            tick,
+            read: 0,
+            laps: 0,
+            lost: 0,
+            last: None,
+        })
+    }
+
+    /// Counts the laps of the DMA around the buffer
+    ///
+    /// Call it from the half and complete transfer interrupts of the channel when the stream
+    /// isn't read at least once per lap, to keep [lost](Self::lost) exact.
+    pub fn poll(&mut self) {
+        self.written();
+    }
+
+    /// Returns the oldest capture not read yet
+    pub fn pop(&mut self) -> Option<u16> {
+        if TIM::overcapture(C) {
+            self.lost += 1;
+        }
+        let written = self.written();
+        let pending = written - self.read;
+        // The slot the DMA writes next may be overwritten while being read
+        if pending >= N as u64 {
+            let skip = pending - (N as u64 - 1);
+            self.lost = self.lost.saturating_add(skip as u32);
+            self.read += skip;
+        } else if pending == 0 {
+            return None;
+        }
+        let i = (self.read % N as u64) as usize;
+        atomic::compiler_fence(Ordering::Acquire);
+        // NOTE(unsafe) the DMA is writing other slots of the buffer
+        let value = unsafe { core::ptr::read_volatile(self.buffer.as_ptr().add(i)) };
+        self.read += 1;
+        self.last = Some(value);
+        Some(value)
+    }
+
+    /// Returns the ticks between the oldest capture not read yet and the one before it
+    ///
+    /// The first capture, and the first after lost edges, only start a new period.
+    pub fn pop_period(&mut self) -> Option<u16> {
+        loop {
+            let (last, lost) = (self.last, self.lost);
+            let value = self.pop()?;
+            match last {
+                Some(last) if self.lost == lost => return Some(value.wrapping_sub(last)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Number of captures waiting to be read, up to `N - 1`
+    pub fn pending(&mut self) -> usize {
+        let pending = self.written() - self.read;
+        pending.min(N as u64 - 1) as usize
+    }
+
+    /// Number of edges lost since the start, see the [module documentation](self)
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+
+    /// Frequency the captures count at
+    pub fn tick(&self) -> Hertz {
+        self.tick
+    }
+
+    /// Stops capturing and releases the timer, the DMA channel and the buffer
+    pub fn stop(mut self) -> (Timer<TIM>, CH, &'static mut [u16; N]) {
+        TIM::stop(C);
+        self.channel.stop();
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.timer, self.channel, self.buffer)
+    }
+
+    /// Number of captures written since the start
+    fn written(&mut self) -> u64 {
+        self.take_lap();
+        let mut position = N as u64 - self.channel.get_txnum() as u64;
+        // The DMA wrapped around between the flag and the counter
+        if self.take_lap() {
+            position = N as u64 - self.channel.get_txnum() as u64;
+        }
+        self.laps * N as u64 + position
+    }
+
+    fn take_lap(&mut self) -> bool {
+        let flags = self.channel.flags();
+        if flags.contains(Flag::TransferComplete) {
+            self.channel.clear_flags(Flag::TransferComplete | Flag::HalfTransfer);
+            self.laps += 1;
+            true
+        } else {
+            if flags.contains(Flag::HalfTransfer) {
+                self.channel.clear_flags(Flag::HalfTransfer.into());
+            }
+            false
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! External trigger input (ETR) of the advanced timers
+//!
+//! TIM1 and TIM8 have a dedicated external trigger pin, which goes through its own polarity
+//! selection, prescaler and digital filter before reaching the slave mode controller as ETRF.
+//! The prescaler divides the ETR frequency, the signal has to stay below a quarter of the
+//! timer clock after it.
+//!
+//! ETRF can clock the counter (external clock mode 2), here with [Timer::clock_from_etr] so
+//! that the count down periods are computed from the external frequency:
+//!
+//! ```rust
+//! let (timer, etr) = Timer::new(dp.Tim1, &clocks)
+//!     .clock_from_etr(gpioa.pa12, EtrConfig::default().prescaler(EtrPrescaler::Div4), 10.MHz());
+//! let mut timer = timer.start_count_down(1.millis());
+//! ```
+//!
+//! or reset and gate the counter of a PWM timer, see [PwmControl::set_etr](crate::pwm::PwmControl).
+
+use crate::gpio::alt::TimEtr;
+use crate::pac::{Tim1, Tim8};
+use crate::time::Hertz;
+
+use super::{Error, Timer};
+
+/// Polarity of the ETR input
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtrPolarity {
+    /// Active high or on rising edges
+    NonInverted,
+    /// Active low or on falling edges
+    Inverted,
+}
+
+/// Division of the ETR input before the filter
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtrPrescaler {
+    Div1 = 0b00,
+    Div2 = 0b01,
+    Div4 = 0b10,
+    Div8 = 0b11,
+}
+
+impl EtrPrescaler {
+    /// Division factor
+    pub fn divisor(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+/// Use of the ETR input, see `PwmControl::set_etr`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtrMode {
+    /// The counter counts the active edges of ETR (external clock mode 2)
+    Clock,
+    /// Each active edge restarts the period (reset slave mode)
+    Reset,
+    /// The counter only runs while ETR is active (gated slave mode)
+    Gate,
+}
+
+/// Conditioning of the ETR input
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EtrConfig {
+    pub polarity: EtrPolarity,
+    pub prescaler: EtrPrescaler,
+    /// Input filter setting (0 to 15), set with [filter](Self::filter)
+    filter: u8,
+}
+
+impl Default for EtrConfig {
+    /// Rising edges, no division and no filter
+    fn default() -> Self {
+        Self {
+            polarity: EtrPolarity::NonInverted,
+            prescaler: EtrPrescaler::Div1,
+            filter: 0,
+        }
+    }
+}
+
+impl EtrConfig {
+    pub fn polarity(mut self, polarity: EtrPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    pub fn prescaler(mut self, prescaler: EtrPrescaler) -> Self {
+        self.prescaler = prescaler;
+        self
+    }
+
+    /// Input filter setting (0 to 15), which requires that many stable samples before an
+    /// edge is accepted
+    ///
+    /// Returns [InvalidFilter](Error::InvalidFilter) if `filter` is above 15.
+    pub fn filter(mut self, filter: u8) -> Result<Self, Error> {
+        if filter > 15 {
+            return Err(Error::InvalidFilter);
+        }
+        self.filter = filter;
+        Ok(self)
+    }
+
+    /// Input filter setting
+    pub fn filter_setting(&self) -> u8 {
+        self.filter
+    }
+}
+
+/// Writes SMCTRL with the ETR conditioning of `config`, after `$mode` set the slave mode or
+/// the external clock enable on the writer `$w`
+macro_rules! write_etr {
+    ($tim:expr, $config:expr, |$w:ident| $mode:expr) => {
+        $tim.smctrl().write(|$w| unsafe {
+            $mode
+                .extp().bit($config.polarity == $crate::timer::EtrPolarity::Inverted)
+                .extps().bits($config.prescaler as u8)
+                .extf().bits($config.filter_setting())
+        })
+    };
+}
+pub(crate) use write_etr;
+
+macro_rules! etr_clock {
+    ($($TIM:ty,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Clocks the counter from the ETR pin (external clock mode 2)
+                ///
+                /// `freq` is the frequency of the signal on the pin, the clock of the timer
+                /// becomes `freq` divided by the prescaler of `config`. The periods of
+                /// [start_count_down](Timer::start_count_down) are computed from it. Returns the
+                /// ETR pin with the timer, it has to stay in its alternate function mode.
+                pub fn clock_from_etr(
+                    mut self,
+                    pin: impl Into<<$TIM as TimEtr>::Etr>,
+                    config: EtrConfig,
+                    freq: Hertz,
+                ) -> (Self, <$TIM as TimEtr>::Etr) {
+                    let pin = pin.into();
+                    self.tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    write_etr!(self.tim, config, |w| w.excen().set_bit());
+                    self.clk = Hertz::from_raw(freq.raw() / config.prescaler.divisor());
+                    (self, pin)
+                }
+            }
+        )+
+    }
+}
+
+etr_clock! {
+    Tim1,
+    Tim8,
+}
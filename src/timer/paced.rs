@@ -0,0 +1,276 @@
+//! DMA writes paced by the update event of a timer
+//!
+//! Every update event of the timer requests a DMA transfer of the next word of a buffer to
+//! a peripheral register, e.g. the output data register of a GPIO port or a DAC holding
+//! register, which gives a fixed sample rate without any CPU involvement.
+//!
+//! ```rust
+//! static WAVE: [u16; 4] = [0, 0x0f, 0xf0, 0xff];
+//!
+//! let dma = dp.Dma1.split();
+//! let timer = Timer::new(dp.Tim2, &clocks);
+//! let pod = unsafe { (*pac::Gpiob::ptr()).pod().as_ptr() } as u32;
+//! let transfer = paced_write_circular(timer, dma.2, pod, &WAVE, 8.kHz())?;
+//! ```
+
+use core::sync::atomic::{self, Ordering};
+
+use embedded_dma::ReadBuffer;
+use enumflags2::BitFlags;
+
+use super::{Timer, UpdateRequest};
+use crate::dma::{CompatibleChannel, DMAChannel, DmaWord, Error, Flag, WordSize, W};
+use crate::time::Hertz;
+
+/// Timer that can request DMA transfers on its update event
+///
+/// Also paces the [8080 bus](crate::display) of the `display` feature.
+pub trait UpdateDma: crate::Sealed {
+    /// Stops the timer, sets its period and loads it without requesting a transfer
+    #[doc(hidden)]
+    fn configure(psc: u16, arr: u16);
+    #[doc(hidden)]
+    fn set_period(psc: u16, arr: u16);
+    /// Stops the timer and loads the preloaded registers without requesting a transfer
+    #[doc(hidden)]
+    fn prepare();
+    #[doc(hidden)]
+    fn start();
+    #[doc(hidden)]
+    fn clear_update();
+    #[doc(hidden)]
+    fn is_updated() -> bool;
+    #[doc(hidden)]
+    fn stop();
+}
+
+macro_rules! update_dma {
+    ($($TIM:ty,)+) => {
+        $(
+            impl UpdateDma for $TIM {
+                fn configure(psc: u16, arr: u16) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    tim.psc().write(|w| unsafe { w.psc().bits(psc) });
+                    tim.ar().write(|w| unsafe { w.bits(arr as u32) });
+                    Self::prepare();
+                }
+
+                fn set_period(psc: u16, arr: u16) {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    // The prescaler is loaded on the next update event, the auto-reload
+                    // right away, so make sure the counter doesn't run past it
+                    tim.psc().write(|w| unsafe { w.psc().bits(psc) });
+                    tim.ar().write(|w| unsafe { w.bits(arr as u32) });
+                    if tim.cnt().read().bits() as u16 >= arr {
+                        tim.cnt().reset();
+                    }
+                }
+
+                fn prepare() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    tim.dinten().modify(|_, w| w.udeen().clear_bit());
+                    <$TIM as crate::timer::Instance>::latch();
+                    tim.sts().write(|w| w.uditf().clear_bit());
+                }
+
+                fn start() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.dinten().modify(|_, w| w.udeen().set_bit());
+                    tim.ctrl1().modify(|_, w| w.cnten().set_bit());
+                }
+
+                fn clear_update() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.sts().write(|w| w.uditf().clear_bit());
+                }
+
+                fn is_updated() -> bool {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.sts().read().uditf().bit_is_set()
+                }
+
+                fn stop() {
+                    let tim = unsafe { &*<$TIM>::ptr() };
+                    tim.ctrl1().modify(|_, w| w.cnten().clear_bit());
+                    tim.dinten().modify(|_, w| w.udeen().clear_bit());
+                }
+            }
+        )+
+    };
+}
+
+update_dma! {
+    crate::pac::Tim1,
+    crate::pac::Tim2,
+    crate::pac::Tim3,
+    crate::pac::Tim4,
+    crate::pac::Tim5,
+    crate::pac::Tim6,
+    crate::pac::Tim8,
+}
+#[cfg(not(any(feature = "n32g401",feature = "n32g430")))]
+update_dma! {
+    crate::pac::Tim7,
+}
+
+/// Prescaler and auto-reload values giving update events at `rate`
+fn period(clk: Hertz, rate: Hertz) -> Result<(u16, u16), Error> {
+    if rate.raw() == 0 {
+        return Err(Error::InvalidRate);
+    }
+    let ticks = (clk.raw() / rate.raw()).max(1);
+    let psc = (ticks - 1) / (1 << 16);
+    Ok((psc as u16, (ticks / (psc + 1) - 1) as u16))
+}
+
+/// Transfer of a buffer to a peripheral register, one word per update event of `TIM`
+pub struct PacedWrite<TIM, CH, BUF> {
+    timer: Timer<TIM>,
+    channel: CH,
+    buffer: BUF,
+}
+
+/// Writes `buffer` to the register at `address`, one word per period of `rate`
+///
+/// The words are zero extended to the 32-bit register. The transfer starts right away,
+/// use [PacedWrite::wait] to wait for its completion.
+pub fn paced_write<TIM, CH, BUF>(
+    timer: Timer<TIM>,
+    channel: CH,
+    address: u32,
+    buffer: BUF,
+    rate: Hertz,
+) -> Result<PacedWrite<TIM, CH, BUF>, Error>
+where
+    TIM: UpdateDma,
+    CH: CompatibleChannel<UpdateRequest<TIM>, W> + DMAChannel,
+    BUF: ReadBuffer,
+    BUF::Word: DmaWord,
+{
+    PacedWrite::start(timer, channel, address, buffer, rate, false)
+}
+
+/// Writes `buffer` to the register at `address` over and over, one word per period of `rate`
+///
+/// Runs until [PacedWrite::stop] is called.
+pub fn paced_write_circular<TIM, CH, BUF>(
+    timer: Timer<TIM>,
+    channel: CH,
+    address: u32,
+    buffer: BUF,
+    rate: Hertz,
+) -> Result<PacedWrite<TIM, CH, BUF>, Error>
+where
+    TIM: UpdateDma,
+    CH: CompatibleChannel<UpdateRequest<TIM>, W> + DMAChannel,
+    BUF: ReadBuffer,
+    BUF::Word: DmaWord,
+{
+    PacedWrite::start(timer, channel, address, buffer, rate, true)
+}
+
+impl<TIM, CH, BUF> PacedWrite<TIM, CH, BUF>
+where
+    TIM: UpdateDma,
+    CH: CompatibleChannel<UpdateRequest<TIM>, W> + DMAChannel,
+    BUF: ReadBuffer,
+    BUF::Word: DmaWord,
+{
+    fn start(
+        timer: Timer<TIM>,
+        mut channel: CH,
+        address: u32,
+        buffer: BUF,
+        rate: Hertz,
+        circular: bool,
+    ) -> Result<Self, Error> {
+        // NOTE(unsafe) the buffer is owned by the transfer until it is stopped
+        let (ptr, len) = unsafe { buffer.read_buffer() };
+        // The register is written with 32-bit words
+        WordSize::Bits32.check(address)?;
+
+        channel.stop();
+        channel.configure_channel();
+        channel.try_set_transfer_length(len)?;
+
+        let (psc, arr) = period(timer.clk, rate)?;
+        TIM::configure(psc, arr);
+
+        channel.set_peripheral_address(address, false);
+        channel.set_memory_address(ptr as u32, true);
+        channel.set_word_size(<BUF::Word as DmaWord>::SIZE, WordSize::Bits32);
+        let priority = channel.priority_or(crate::dma::Priority::High) as u8;
+        channel.st().chcfg().modify(|_, w| {
+            let w = w
+                // memory to memory mode disabled
+                .mem2mem()
+                .disabled()
+                // channel priority level, high unless set with DMAChannel::set_priority
+                .priolvl()
+                .bits(priority);
+            let w = if circular { w.circ().enabled() } else { w.circ().disabled() };
+            // read from memory
+            w.dir().from_memory()
+        });
+
+        atomic::compiler_fence(Ordering::Release);
+        channel.start();
+        TIM::start();
+
+        Ok(Self {
+            timer,
+            channel,
+            buffer,
+        })
+    }
+
+    /// Changes the rate of the transfer while it runs, from the next word on
+    ///
+    /// Fails with [InvalidRate](Error::InvalidRate) if `rate` is zero.
+    pub fn set_rate(&mut self, rate: Hertz) -> Result<(), Error> {
+        let (psc, arr) = period(self.timer.clk, rate)?;
+        TIM::set_period(psc, arr);
+        Ok(())
+    }
+
+    /// Buffer being transferred
+    ///
+    /// The DMA keeps reading it while the transfer runs, writes must be volatile.
+    pub(crate) fn buffer_mut(&mut self) -> &mut BUF {
+        &mut self.buffer
+    }
+
+    /// Returns `true` once every word was written, after the first pass for circular transfers
+    pub fn is_done(&self) -> bool {
+        self.channel.flags().contains(Flag::TransferComplete)
+    }
+
+    /// Waits for the transfer to complete, or its first pass if circular, and releases the timer,
+    /// the channel and the buffer
+    pub fn wait(self) -> Result<(Timer<TIM>, CH, BUF), Error> {
+        #[cfg(feature = "profiling")]
+        let _scope = crate::profiling::probes::DMA_WAIT.scope();
+        let result = loop {
+            let flags = self.channel.flags();
+            if flags.contains(Flag::TransferError) {
+                break Err(Error::TransferError);
+            }
+            if flags.contains(Flag::TransferComplete) {
+                break Ok(());
+            }
+        };
+        let parts = self.stop();
+        result.map(|_| parts)
+    }
+
+    /// Stops the transfer and releases the timer, the channel and the buffer
+    pub fn stop(mut self) -> (Timer<TIM>, CH, BUF) {
+        TIM::stop();
+        self.channel.stop();
+        self.channel.clear_flags(BitFlags::ALL);
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.timer, self.channel, self.buffer)
+    }
+}
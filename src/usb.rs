@@ -12,6 +12,9 @@ use crate::gpio::gpioa::{PA11, PA12};
 use crate::gpio::{Floating, Input};
 pub use stm32_usbd::UsbBus;
 
+#[cfg(feature = "usb-serial")]
+pub mod cdc;
+
 pub struct Peripheral {
     pub usb: Usb,
     pub pin_dm: PA11<Input<Floating>>,
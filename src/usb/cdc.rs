@@ -0,0 +1,134 @@
+//! # Virtual COM port
+//!
+//! [UsbSerial] bundles the USB device and a CDC-ACM class, so a serial port over USB
+//! only needs the bus allocator and a periodic [UsbSerial::poll].
+//!
+//! Requires the `usb-serial` feature.
+//!
+//! ```rust
+//! let usb = Peripheral { usb: dp.Usb, pin_dm: gpioa.pa11, pin_dp: gpioa.pa12 };
+//! let bus = cortex_m::singleton!(: UsbBusAllocator<UsbBusType> = UsbBus::new(usb)).unwrap();
+//! let mut serial = UsbSerial::new(bus);
+//!
+//! loop {
+//!     serial.poll();
+//!     if serial.is_connected() {
+//!         writeln!(serial, "hello").ok();
+//!     }
+//! }
+//! ```
+
+use core::fmt;
+
+use usb_device::bus::UsbBusAllocator;
+use usb_device::device::{StringDescriptors, UsbDevice, UsbDeviceBuilder, UsbDeviceState, UsbVidPid};
+use usb_device::UsbError;
+use usbd_serial::SerialPort;
+
+use super::UsbBusType;
+
+/// Shared test VID/PID for CDC-ACM devices, replace it in products
+pub const DEFAULT_VID_PID: UsbVidPid = UsbVidPid(0x16c0, 0x27dd);
+
+/// CDC-ACM serial port over USB
+pub struct UsbSerial<'a> {
+    device: UsbDevice<'a, UsbBusType>,
+    serial: SerialPort<'a, UsbBusType>,
+}
+
+impl<'a> UsbSerial<'a> {
+    /// Creates the port with [DEFAULT_VID_PID] and generic descriptors
+    pub fn new(bus: &'a UsbBusAllocator<UsbBusType>) -> Self {
+        Self::with_ids(bus, DEFAULT_VID_PID, "n32g4xx-hal", "Serial port", "0")
+    }
+
+    /// Creates the port with the given IDs and descriptor strings
+    pub fn with_ids(
+        bus: &'a UsbBusAllocator<UsbBusType>,
+        vid_pid: UsbVidPid,
+        manufacturer: &'a str,
+        product: &'a str,
+        serial_number: &'a str,
+    ) -> Self {
+        // The class has to be allocated before the device is built
+        let serial = SerialPort::new(bus);
+        let device = UsbDeviceBuilder::new(bus, vid_pid)
+            .strings(&[StringDescriptors::default()
+                .manufacturer(manufacturer)
+                .product(product)
+                .serial_number(serial_number)])
+            .expect("too many string descriptors")
+            .device_class(usbd_serial::USB_CLASS_CDC)
+            .build();
+        Self { device, serial }
+    }
+
+    /// Handles pending USB events, call this from the USB interrupts or at least every 10 ms
+    ///
+    /// Returns true if new data may be available for [read](Self::read).
+    pub fn poll(&mut self) -> bool {
+        self.device.poll(&mut [&mut self.serial])
+    }
+
+    /// Returns true if the device is configured and a terminal opened the port (DTR set)
+    pub fn is_connected(&self) -> bool {
+        self.device.state() == UsbDeviceState::Configured && self.serial.dtr()
+    }
+
+    /// Reads available bytes into `buf` and returns their count, 0 if none are pending
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, UsbError> {
+        match self.serial.read(buf) {
+            Err(UsbError::WouldBlock) => Ok(0),
+            result => result,
+        }
+    }
+
+    /// Queues as many bytes of `data` as fit in the transmit buffer and returns their count
+    pub fn write(&mut self, data: &[u8]) -> Result<usize, UsbError> {
+        match self.serial.write(data) {
+            Err(UsbError::WouldBlock) => Ok(0),
+            result => result,
+        }
+    }
+
+    /// Writes all of `data`, polling the device while the transmit buffer is full
+    ///
+    /// Fails with [UsbError::InvalidState] if the host closes the port meanwhile.
+    pub fn write_all(&mut self, mut data: &[u8]) -> Result<(), UsbError> {
+        while !data.is_empty() {
+            if !self.is_connected() {
+                return Err(UsbError::InvalidState);
+            }
+            let count = self.write(data)?;
+            data = &data[count..];
+            if count == 0 {
+                self.poll();
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the buffered data
+    pub fn flush(&mut self) -> Result<(), UsbError> {
+        match self.serial.flush() {
+            Err(UsbError::WouldBlock) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Gives access to the CDC-ACM class, e.g. to read the line coding
+    pub fn serial_port(&mut self) -> &mut SerialPort<'a, UsbBusType> {
+        &mut self.serial
+    }
+
+    /// Gives access to the USB device
+    pub fn device(&mut self) -> &mut UsbDevice<'a, UsbBusType> {
+        &mut self.device
+    }
+}
+
+impl fmt::Write for UsbSerial<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}